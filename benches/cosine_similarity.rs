@@ -0,0 +1,40 @@
+// Chunked cosine similarity vs the naive three-pass loop on 768-dim vectors.
+// Run with `cargo bench --bench cosine_similarity`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use embed_search::utils::cosine_similarity;
+
+const DIMENSION: usize = 768;
+const ITERATIONS: u32 = 200_000;
+
+fn naive(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    dot / (norm_a * norm_b)
+}
+
+fn time(name: &str, mut f: impl FnMut() -> f32) -> Duration {
+    // Warm up caches and the branch predictor
+    for _ in 0..ITERATIONS / 10 {
+        black_box(f());
+    }
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(f());
+    }
+    let elapsed = start.elapsed();
+    println!("{:<8} {:>8.1} ns/op", name, elapsed.as_nanos() as f64 / ITERATIONS as f64);
+    elapsed
+}
+
+fn main() {
+    let a: Vec<f32> = (0..DIMENSION).map(|i| (i as f32 * 0.37).sin()).collect();
+    let b: Vec<f32> = (0..DIMENSION).map(|i| (i as f32 * 0.11).cos()).collect();
+
+    let naive_time = time("naive", || naive(black_box(&a), black_box(&b)));
+    let chunked_time = time("chunked", || cosine_similarity(black_box(&a), black_box(&b)).unwrap());
+    println!("speedup  {:>8.2}x", naive_time.as_secs_f64() / chunked_time.as_secs_f64());
+}
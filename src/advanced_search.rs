@@ -1,30 +1,30 @@
 use anyhow::Result;
-use tantivy::{Index, IndexWriter, schema::{Schema, Field, TEXT, STORED, Value}};
-use tantivy::query::QueryParser;
-use tantivy::collector::TopDocs;
 use std::collections::HashMap;
 
-use crate::simple_storage::{VectorStorage, SearchResult as VectorResult};
-use crate::gguf_embedder::{GGUFEmbedder, GGUFEmbedderConfig};
+use crate::simple_storage::{cosine_similarity, VectorStorage, SearchResult as VectorResult};
+use crate::gguf_embedder::{GGUFEmbedder, GGUFEmbedderConfig, CODE_MODEL_PATH, TEXT_MODEL_PATH};
+use crate::embedder::Embedder;
 use crate::embedding_prefixes::EmbeddingTask;
 use crate::search::bm25_fixed::{BM25Engine, BM25Match};
-use crate::search::fusion::FusionConfig;
+use crate::search::fusion::{dedup_similar, ComponentScores, FusionConfig, MatchType};
+use crate::search::snippet::first_match_line;
+use crate::markdown_metadata_extractor::{is_markdown_path, match_heading_path, HEADING_BREADCRUMB_SEPARATOR};
 use crate::symbol_extractor::{SymbolExtractor, Symbol};
+use crate::search::text_backend::TantivyText;
+use crate::config::SearchBackend;
+use crate::error::SearchError;
+use crate::utils::content_preview_with_marker;
 
 /// Advanced hybrid search combining all 5 technologies with parallel execution
 pub struct AdvancedHybridSearch {
     vector_storage: VectorStorage,
-    text_index: Index,
-    text_writer: IndexWriter,
-    text_embedder: GGUFEmbedder,
-    code_embedder: GGUFEmbedder,
+    /// Tantivy index; `None` when running with `SearchBackend::Bm25Only`
+    text_index: Option<TantivyText>,
+    text_embedder: Box<dyn Embedder>,
+    code_embedder: Box<dyn Embedder>,
     bm25_engine: BM25Engine,
     symbol_extractor: SymbolExtractor,
     fusion_config: FusionConfig,
-    
-    // Schema fields
-    content_field: Field,
-    path_field: Field,
 }
 
 #[derive(Debug, Clone)]
@@ -33,44 +33,56 @@ pub struct AdvancedSearchResult {
     pub file_path: String,
     pub score: f32,
     pub match_type: String,
+    /// 1-based line of the first query match within the indexed document
     pub line_number: Option<usize>,
     pub symbols: Vec<Symbol>,
+    /// Markdown only: headings enclosing the match, e.g. `Installation > Linux`
+    pub heading_path: Option<String>,
+    /// Each search type's weighted RRF contribution to `score` before the heading
+    /// boost and reranking; set only when `FusionConfig::explain` is
+    pub component_scores: Option<ComponentScores>,
 }
 
 impl AdvancedHybridSearch {
     pub async fn new(db_path: &str) -> Result<Self> {
-        // Initialize vector storage
-        let vector_storage = VectorStorage::new(db_path)?;
-        
-        // Initialize Tantivy for full-text search
-        let mut schema_builder = Schema::builder();
-        let content_field = schema_builder.add_text_field("content", TEXT | STORED);
-        let path_field = schema_builder.add_text_field("path", TEXT | STORED);
-        let schema = schema_builder.build();
-        
-        // Open existing index or create new persistent disk-based index
-        let index_path = format!("{}/tantivy_index", db_path);
-        std::fs::create_dir_all(&index_path)?;
-        let text_index = if std::path::Path::new(&format!("{}/meta.json", index_path)).exists() {
-            Index::open_in_dir(&index_path)?
-        } else {
-            Index::create_in_dir(&index_path, schema)?
-        };
-        let text_writer = text_index.writer(50_000_000)?; // 50MB heap
-        
+        Self::with_backend(db_path, SearchBackend::default()).await
+    }
+    
+    /// Create an advanced search; `Bm25Only` skips Tantivy and relies on `BM25Engine`
+    pub async fn with_backend(db_path: &str, backend: SearchBackend) -> Result<Self> {
         // Initialize text embedder for markdown
         let text_config = GGUFEmbedderConfig {
-            model_path: "./src/model/nomic-embed-text-v1.5.Q4_K_M.gguf".to_string(),
+            model_path: TEXT_MODEL_PATH.to_string(),
             ..Default::default()
         };
         let text_embedder = GGUFEmbedder::new(text_config)?;
         
         // Initialize code embedder for code files
         let code_config = GGUFEmbedderConfig {
-            model_path: "./src/model/nomic-embed-code.Q4_K_M.gguf".to_string(),
+            model_path: CODE_MODEL_PATH.to_string(),
             ..Default::default()
         };
         let code_embedder = GGUFEmbedder::new(code_config)?;
+        
+        Self::with_embedders(db_path, backend, Box::new(text_embedder), Box::new(code_embedder)).await
+    }
+    
+    /// Create an advanced search that embeds with the given models instead of loading the GGUF files
+    pub async fn with_embedders(
+        db_path: &str,
+        backend: SearchBackend,
+        text_embedder: Box<dyn Embedder>,
+        code_embedder: Box<dyn Embedder>,
+    ) -> Result<Self> {
+        // Initialize vector storage
+        let vector_storage = VectorStorage::new(db_path)?;
+        
+        // Initialize Tantivy for full-text search unless BM25 handles keywords alone
+        let text_index = match backend {
+            SearchBackend::Tantivy => Some(TantivyText::open(db_path)?),
+            SearchBackend::Bm25Only => None,
+        };
+        
         let bm25_engine = BM25Engine::new()?;
         let symbol_extractor = SymbolExtractor::new()?;
         let fusion_config = FusionConfig::default();
@@ -78,14 +90,11 @@ impl AdvancedHybridSearch {
         Ok(Self {
             vector_storage,
             text_index,
-            text_writer,
             text_embedder,
             code_embedder,
             bm25_engine,
             symbol_extractor,
             fusion_config,
-            content_field,
-            path_field,
         })
     }
 
@@ -94,20 +103,7 @@ impl AdvancedHybridSearch {
         // Generate embeddings with appropriate embedder for each file
         let mut embeddings = Vec::new();
         for (content, path) in contents.iter().zip(file_paths.iter()) {
-            // Determine embedder and task based on file extension
-            let (embedder, task) = if path.ends_with(".md") || path.ends_with(".markdown") {
-                (&self.text_embedder, EmbeddingTask::SearchDocument)
-            } else if path.ends_with(".rs") || path.ends_with(".py") || path.ends_with(".js") || 
-                      path.ends_with(".ts") || path.ends_with(".go") || path.ends_with(".java") || 
-                      path.ends_with(".cpp") || path.ends_with(".c") || path.ends_with(".h") || 
-                      path.ends_with(".jsx") || path.ends_with(".tsx") || path.ends_with(".cs") || 
-                      path.ends_with(".php") || path.ends_with(".rb") || path.ends_with(".swift") ||
-                      path.ends_with(".kt") || path.ends_with(".scala") || path.ends_with(".r") {
-                (&self.code_embedder, EmbeddingTask::CodeDefinition)
-            } else {
-                (&self.text_embedder, EmbeddingTask::SearchDocument)
-            };
-            
+            let (embedder, task) = self.embedder_for(path);
             let embedding = embedder.embed(content, task)?;
             embeddings.push(embedding);
         }
@@ -115,18 +111,15 @@ impl AdvancedHybridSearch {
         // Store in vector database
         self.vector_storage.store(contents.clone(), embeddings, file_paths.clone())?;
         
-        // Store in Tantivy text index and BM25 engine
+        // Store in Tantivy text index
+        if let Some(text_index) = self.text_index.as_mut() {
+            text_index.add(&contents, &file_paths)?;
+        }
+        
+        // Store in BM25 engine
         for (content, path) in contents.iter().zip(file_paths.iter()) {
-            // Tantivy index
-            let mut doc = tantivy::doc!();
-            doc.add_text(self.content_field, content);
-            doc.add_text(self.path_field, path);
-            self.text_writer.add_document(doc)?;
-            
-            // BM25 engine
             self.bm25_engine.index_document(path, content);
         }
-        self.text_writer.commit()?;
 
         Ok(())
     }
@@ -149,46 +142,104 @@ impl AdvancedHybridSearch {
         let symbol_results = self.symbol_search(query, search_limit).await?;
         
         // Advanced fusion with configurable weights
-        let fused_results = self.advanced_fusion(
+        let mut fused_results = self.advanced_fusion(
+            query,
             vector_results, 
             text_results, 
             bm25_results,
             symbol_results,
             limit
         );
+        for result in &mut fused_results {
+            if result.line_number.is_none() {
+                result.line_number = first_match_line(&result.content, query);
+            }
+        }
         
         Ok(fused_results)
     }
 
+    /// Fused search followed by a second pass over the top `rerank_top_n` results:
+    /// each candidate is re-embedded and its similarity to the query is blended with
+    /// the fused score using `FusionConfig::rerank_weight`. Results past
+    /// `rerank_top_n` keep their fused order and scores.
+    pub async fn search_reranked(&mut self, query: &str, limit: usize, rerank_top_n: usize) -> Result<Vec<AdvancedSearchResult>> {
+        let weight = self.fusion_config.rerank_weight;
+        if !(0.0..=1.0).contains(&weight) {
+            return Err(SearchError::InvalidParameter {
+                parameter: "rerank_weight".to_string(),
+                reason: format!("{} is outside 0.0..=1.0", weight),
+            }.into());
+        }
+        
+        let mut results = self.search(query, limit).await?;
+        let head_len = rerank_top_n.min(results.len());
+        if head_len == 0 || weight == 0.0 {
+            return Ok(results);
+        }
+        
+        // Compare each candidate with a query embedding from the same model
+        let mut text_query: Option<Vec<f32>> = None;
+        let mut code_query: Option<Vec<f32>> = None;
+        let mut similarities = Vec::with_capacity(head_len);
+        for result in &results[..head_len] {
+            let (embedder, task) = self.embedder_for(&result.file_path);
+            let query_slot = if task == EmbeddingTask::CodeDefinition { &mut code_query } else { &mut text_query };
+            if query_slot.is_none() {
+                *query_slot = Some(embedder.embed(query, EmbeddingTask::SearchQuery)?);
+            }
+            let candidate = embedder.embed(&result.content, task)?;
+            similarities.push(cosine_similarity(query_slot.as_ref().unwrap(), &candidate));
+        }
+        
+        rerank_head(&mut results, &similarities, weight);
+        Ok(results)
+    }
+    
+    pub fn fusion_config(&self) -> &FusionConfig {
+        &self.fusion_config
+    }
+    
+    pub fn set_fusion_config(&mut self, fusion_config: FusionConfig) {
+        self.fusion_config = fusion_config;
+    }
+    
+    /// Pick the embedder and task for a file based on its extension
+    fn embedder_for(&self, path: &str) -> (&dyn Embedder, EmbeddingTask) {
+        if path.ends_with(".md") || path.ends_with(".markdown") {
+            (self.text_embedder.as_ref(), EmbeddingTask::SearchDocument)
+        } else if path.ends_with(".rs") || path.ends_with(".py") || path.ends_with(".js") || 
+                  path.ends_with(".ts") || path.ends_with(".go") || path.ends_with(".java") || 
+                  path.ends_with(".cpp") || path.ends_with(".c") || path.ends_with(".h") || 
+                  path.ends_with(".jsx") || path.ends_with(".tsx") || path.ends_with(".cs") || 
+                  path.ends_with(".php") || path.ends_with(".rb") || path.ends_with(".swift") ||
+                  path.ends_with(".kt") || path.ends_with(".scala") || path.ends_with(".r") {
+            (self.code_embedder.as_ref(), EmbeddingTask::CodeDefinition)
+        } else {
+            (self.text_embedder.as_ref(), EmbeddingTask::SearchDocument)
+        }
+    }
+
     fn text_search(&self, query: &str, limit: usize) -> Result<Vec<AdvancedSearchResult>> {
-        let reader = self.text_index.reader()?;
-        let searcher = reader.searcher();
-        let query_parser = QueryParser::for_index(&self.text_index, vec![self.content_field]);
-        
-        let parsed_query = query_parser.parse_query(query)?;
-        let top_docs = searcher.search(&*parsed_query, &TopDocs::with_limit(limit))?;
-        
-        let mut results = Vec::new();
-        for (score, doc_address) in top_docs {
-            let doc: tantivy::TantivyDocument = searcher.doc(doc_address)?;
-            let content = doc.get_first(self.content_field)
-                .and_then(|v| v.as_str())
-                .unwrap_or("")
-                .to_string();
-            let path = doc.get_first(self.path_field)
-                .and_then(|v| v.as_str())
-                .unwrap_or("")
-                .to_string();
-            
-            results.push(AdvancedSearchResult {
-                content,
-                file_path: path,
-                score,
-                match_type: "text".to_string(),
+        let text_index = match &self.text_index {
+            Some(text_index) => text_index,
+            None => return Ok(Vec::new()),
+        };
+        
+        let results = text_index
+            .search(query, limit)?
+            .into_iter()
+            .map(|r| AdvancedSearchResult {
+                content: r.content,
+                file_path: r.file_path,
+                score: r.score,
+                match_type: r.match_type,
                 line_number: None,
                 symbols: vec![],
-            });
-        }
+                heading_path: None,
+                component_scores: None,
+            })
+            .collect();
         
         Ok(results)
     }
@@ -200,12 +251,17 @@ impl AdvancedHybridSearch {
         let results = bm25_matches
             .into_iter()
             .map(|m| AdvancedSearchResult {
+                // The snippet is an excerpt, so locate the match in the full document
+                line_number: m.line_number.or_else(|| {
+                    self.bm25_engine.get_document(&m.path).and_then(|doc| first_match_line(doc, query))
+                }),
                 content: m.snippet,
                 file_path: m.path,
                 score: m.score,
                 match_type: "bm25".to_string(),
-                line_number: m.line_number,
                 symbols: vec![],
+                heading_path: None,
+                component_scores: None,
             })
             .collect();
             
@@ -221,6 +277,7 @@ impl AdvancedHybridSearch {
     
     /// Advanced RRF fusion with configurable weights for all 4 search types
     fn advanced_fusion(&self,
+                      query: &str,
                       vector_results: Vec<VectorResult>,
                       text_results: Vec<AdvancedSearchResult>,
                       bm25_results: Vec<AdvancedSearchResult>,
@@ -239,7 +296,7 @@ impl AdvancedHybridSearch {
         
         // Add vector results with weighted RRF scoring
         for (rank, result) in vector_results.into_iter().enumerate() {
-            let key = format!("{}:{}", result.file_path, &result.content[..50.min(result.content.len())]);
+            let key = format!("{}:{}", result.file_path, content_preview_with_marker(&result.content, 50, ""));
             let rrf_score = vector_weight * (1.0 / (k + rank as f32 + 1.0));
             
             score_map.insert(key, (AdvancedSearchResult {
@@ -249,71 +306,175 @@ impl AdvancedHybridSearch {
                 match_type: "vector".to_string(),
                 line_number: None,
                 symbols: vec![],
+                heading_path: None,
+                component_scores: self.fusion_config.explain_component(&MatchType::Semantic, rrf_score),
             }, rrf_score));
         }
         
         // Add text results
         for (rank, result) in text_results.into_iter().enumerate() {
-            let key = format!("{}:{}", result.file_path, &result.content[..50.min(result.content.len())]);
+            let key = format!("{}:{}", result.file_path, content_preview_with_marker(&result.content, 50, ""));
             let rrf_score = text_weight * (1.0 / (k + rank as f32 + 1.0));
             
             if let Some((existing_result, existing_score)) = score_map.get_mut(&key) {
                 *existing_score += rrf_score;
                 existing_result.match_type = "hybrid".to_string();
                 existing_result.score = *existing_score;
+                if let Some(components) = existing_result.component_scores.as_mut() {
+                    components.add(&MatchType::Statistical, rrf_score);
+                }
             } else {
-                score_map.insert(key, (result, rrf_score));
+                let component_scores = self.fusion_config.explain_component(&MatchType::Statistical, rrf_score);
+                score_map.insert(key, (AdvancedSearchResult { component_scores, ..result }, rrf_score));
             }
         }
         
         // Add BM25 results
         for (rank, result) in bm25_results.into_iter().enumerate() {
-            let key = format!("{}:{}", result.file_path, &result.content[..50.min(result.content.len())]);
+            let key = format!("{}:{}", result.file_path, content_preview_with_marker(&result.content, 50, ""));
             let rrf_score = bm25_weight * (1.0 / (k + rank as f32 + 1.0));
             
             if let Some((existing_result, existing_score)) = score_map.get_mut(&key) {
                 *existing_score += rrf_score;
                 existing_result.match_type = "hybrid".to_string();
                 existing_result.score = *existing_score;
+                if let Some(components) = existing_result.component_scores.as_mut() {
+                    components.add(&MatchType::Statistical, rrf_score);
+                }
             } else {
-                score_map.insert(key, (result, rrf_score));
+                let component_scores = self.fusion_config.explain_component(&MatchType::Statistical, rrf_score);
+                score_map.insert(key, (AdvancedSearchResult { component_scores, ..result }, rrf_score));
             }
         }
         
         // Add symbol results
         for (rank, result) in symbol_results.into_iter().enumerate() {
-            let key = format!("{}:{}", result.file_path, &result.content[..50.min(result.content.len())]);
+            let key = format!("{}:{}", result.file_path, content_preview_with_marker(&result.content, 50, ""));
             let rrf_score = symbol_weight * (1.0 / (k + rank as f32 + 1.0));
             
             if let Some((existing_result, existing_score)) = score_map.get_mut(&key) {
                 *existing_score += rrf_score;
                 existing_result.match_type = "hybrid".to_string();
                 existing_result.score = *existing_score;
+                if let Some(components) = existing_result.component_scores.as_mut() {
+                    components.add(&MatchType::Symbol, rrf_score);
+                }
             } else {
-                score_map.insert(key, (result, rrf_score));
+                let component_scores = self.fusion_config.explain_component(&MatchType::Symbol, rrf_score);
+                score_map.insert(key, (AdvancedSearchResult { component_scores, ..result }, rrf_score));
             }
         }
         
         // Sort by combined score and return top results
         let mut final_results: Vec<_> = score_map.into_values().map(|(result, _)| result).collect();
+        for result in &mut final_results {
+            self.apply_heading_context(result, query);
+        }
         final_results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+        final_results.retain(|r| self.fusion_config.passes_min_score(r.score));
+        if let Some(threshold) = self.fusion_config.dedup_threshold {
+            final_results = dedup_similar(final_results, threshold, |r| {
+                self.vector_storage.embedding(&r.file_path, &r.content)
+            });
+        }
         
         final_results.into_iter().take(limit).collect()
     }
 
+    /// Breadcrumb and `FusionConfig::heading_weight` boost for markdown results,
+    /// matched against the whole document since BM25 results carry only a snippet
+    fn apply_heading_context(&self, result: &mut AdvancedSearchResult, query: &str) {
+        if !is_markdown_path(&result.file_path) {
+            return;
+        }
+        let document = self.bm25_engine.get_document(&result.file_path).unwrap_or(&result.content);
+        if let Some((path, share)) = match_heading_path(document, query) {
+            if !path.is_empty() {
+                result.heading_path = Some(path.join(HEADING_BREADCRUMB_SEPARATOR));
+            }
+            result.score += self.fusion_config.heading_weight * share;
+        }
+    }
+
     pub async fn clear(&mut self) -> Result<()> {
         self.vector_storage.clear()?;
-        self.text_writer.delete_all_documents()?;
-        self.text_writer.commit()?;
+        if let Some(text_index) = self.text_index.as_mut() {
+            text_index.clear()?;
+        }
         Ok(())
     }
 }
 
+/// Blend the first `similarities.len()` results' fused scores (scaled to the head's
+/// maximum) with their similarities and re-sort that head; the tail is untouched.
+fn rerank_head(results: &mut [AdvancedSearchResult], similarities: &[f32], weight: f32) {
+    let head = &mut results[..similarities.len()];
+    let max_score = head.iter().map(|r| r.score).fold(0.0f32, f32::max);
+    
+    for (result, similarity) in head.iter_mut().zip(similarities) {
+        let fused = if max_score > 0.0 { result.score / max_score } else { 0.0 };
+        result.score = (1.0 - weight) * fused + weight * similarity;
+    }
+    head.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::embedder::MinimalEmbedder;
     use tempfile::tempdir;
 
+    fn result(path: &str, score: f32) -> AdvancedSearchResult {
+        AdvancedSearchResult {
+            content: format!("content of {}", path),
+            file_path: path.to_string(),
+            score,
+            match_type: "hybrid".to_string(),
+            line_number: None,
+            symbols: vec![],
+            heading_path: None,
+            component_scores: None,
+        }
+    }
+
+    fn paths(results: &[AdvancedSearchResult]) -> Vec<&str> {
+        results.iter().map(|r| r.file_path.as_str()).collect()
+    }
+
+    #[test]
+    fn test_rerank_head_reorders_by_similarity() {
+        let mut results = vec![result("a.rs", 0.03), result("b.rs", 0.02), result("c.rs", 0.01)];
+        rerank_head(&mut results, &[0.1, 0.2, 0.9], 1.0);
+        assert_eq!(paths(&results), vec!["c.rs", "b.rs", "a.rs"]);
+        assert!((results[0].score - 0.9).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_rerank_head_zero_weight_keeps_order() {
+        let mut results = vec![result("a.rs", 0.03), result("b.rs", 0.02)];
+        rerank_head(&mut results, &[0.0, 1.0], 0.0);
+        assert_eq!(paths(&results), vec!["a.rs", "b.rs"]);
+    }
+
+    #[test]
+    fn test_rerank_head_blends_scores() {
+        let mut results = vec![result("a.rs", 0.04), result("b.rs", 0.02)];
+        rerank_head(&mut results, &[0.0, 1.0], 0.5);
+        // a: 0.5 * 1.0 + 0.5 * 0.0 = 0.5, b: 0.5 * 0.5 + 0.5 * 1.0 = 0.75
+        assert_eq!(paths(&results), vec!["b.rs", "a.rs"]);
+        assert!((results[0].score - 0.75).abs() < 1e-6);
+        assert!((results[1].score - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_rerank_head_leaves_tail_unchanged() {
+        let mut results = vec![result("a.rs", 0.03), result("b.rs", 0.02), result("c.rs", 0.01), result("d.rs", 0.005)];
+        rerank_head(&mut results, &[0.1, 0.9], 1.0);
+        assert_eq!(paths(&results), vec!["b.rs", "a.rs", "c.rs", "d.rs"]);
+        assert_eq!(results[2].score, 0.01);
+        assert_eq!(results[3].score, 0.005);
+    }
+
     #[tokio::test]
     async fn test_advanced_hybrid_search() -> Result<()> {
         let temp_dir = tempdir()?;
@@ -341,4 +502,69 @@ mod tests {
         
         Ok(())
     }
+    
+    #[tokio::test]
+    async fn test_advanced_search_with_minimal_embedders() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut search = AdvancedHybridSearch::with_embedders(
+            &db_path,
+            SearchBackend::Bm25Only,
+            Box::new(MinimalEmbedder::new(64)),
+            Box::new(MinimalEmbedder::new(64)),
+        ).await?;
+        
+        search.index(
+            vec!["impl BM25Engine { fn search() {} }".to_string(), "struct User { name: String }".to_string()],
+            vec!["bm25.rs".to_string(), "user.rs".to_string()],
+        ).await?;
+        
+        let results = search.search_reranked("BM25Engine search", 5, 2).await?;
+        assert_eq!(results[0].file_path, "bm25.rs");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_advanced_search_reports_markdown_heading_path() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut search = AdvancedHybridSearch::with_embedders(
+            &db_path,
+            SearchBackend::Bm25Only,
+            Box::new(MinimalEmbedder::new(64)),
+            Box::new(MinimalEmbedder::new(64)),
+        ).await?;
+        let guide = "# Guide\n\n## Installation\n\n### Linux\n\nUse the package manager.";
+        search.index(
+            vec![guide.to_string(), "fn install_linux() {}".to_string()],
+            vec!["guide.md".to_string(), "install.rs".to_string()],
+        ).await?;
+
+        let results = search.search("linux", 5).await?;
+        let markdown = results.iter().find(|r| r.file_path == "guide.md").unwrap();
+        assert_eq!(markdown.heading_path.as_deref(), Some("Guide > Installation > Linux"));
+        assert!(results.iter().filter(|r| r.file_path == "install.rs").all(|r| r.heading_path.is_none()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_advanced_search_results_carry_first_match_line() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut search = AdvancedHybridSearch::with_embedders(
+            &db_path,
+            SearchBackend::Bm25Only,
+            Box::new(MinimalEmbedder::new(64)),
+            Box::new(MinimalEmbedder::new(64)),
+        ).await?;
+        let parser = "use std::fmt;\n\nstruct Parser;\n\nfn tokenize(input: &str) {}";
+        search.index(vec![parser.to_string()], vec!["parser.rs".to_string()]).await?;
+
+        let results = search.search("tokenize", 5).await?;
+        assert!(!results.is_empty());
+        for result in &results {
+            assert_eq!(result.line_number, Some(5), "{} result", result.match_type);
+        }
+        Ok(())
+    }
 }
\ No newline at end of file
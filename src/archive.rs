@@ -0,0 +1,292 @@
+// Export and import of a whole index as a single tar archive
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::search::text_backend::{TantivyText, TextBackend, DEFAULT_BM25_INDEX_FILE};
+use crate::simple_search::{HybridSearch, DOCUMENT_METADATA_FILE};
+use crate::simple_storage::EmbeddingRecord;
+use crate::error::{SearchError, StorageError};
+use crate::config::SearchBackend;
+use crate::search::bm25_fixed::BM25Engine;
+
+/// Bumped whenever the layout of `HybridSearch::export_archive` output changes
+pub const ARCHIVE_FORMAT_VERSION: u32 = 1;
+
+/// Archive entry holding the `ArchiveManifest`
+const ARCHIVE_MANIFEST: &str = "manifest.json";
+
+/// Archive entry holding the vector store's records
+const ARCHIVE_VECTORS: &str = "vectors.json";
+
+/// Describes an index archive; `HybridSearch::import_archive` checks it against the
+/// importing index before touching anything
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ArchiveManifest {
+    pub format_version: u32,
+    /// Version of this crate that wrote the archive
+    pub created_by: String,
+    pub text_backend: SearchBackend,
+    /// Length of the archived vectors; `None` when there are none
+    pub dimension: Option<usize>,
+    /// Models that produced the vectors, when known
+    pub model: Option<String>,
+    /// Documents in the keyword index
+    pub documents: usize,
+    pub vectors: usize,
+}
+
+/// Keyword index read from an archive, ready to replace the current one
+enum ArchivedText {
+    Tantivy { contents: Vec<String>, file_paths: Vec<String> },
+    Bm25(Box<BM25Engine>),
+}
+
+fn append_json(archive: &mut tar::Builder<std::fs::File>, name: &str, value: &impl serde::Serialize) -> Result<()> {
+    let bytes = serde_json::to_vec(value)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(bytes.len() as u64);
+    header.set_mode(0o644);
+    archive.append_data(&mut header, name, bytes.as_slice())?;
+    Ok(())
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(dir: &Path, name: &str) -> Result<T> {
+    let bytes = std::fs::read(dir.join(name)).with_context(|| format!("archive has no readable {}", name))?;
+    Ok(serde_json::from_slice(&bytes)?)
+}
+
+impl HybridSearch {
+    /// Write the keyword index, the vectors and document metadata, with an
+    /// `ArchiveManifest`, to a single tar file that `import_archive` restores
+    pub fn export_archive(&self, path: &Path) -> Result<ArchiveManifest> {
+        let records = self.vector_storage.records();
+        let manifest = ArchiveManifest {
+            format_version: ARCHIVE_FORMAT_VERSION,
+            created_by: env!("CARGO_PKG_VERSION").to_string(),
+            text_backend: self.text_backend.kind(),
+            dimension: self.vector_storage.dimension(),
+            model: self.embedders.as_ref().and_then(|e| e.model.clone()),
+            documents: self.text_backend.len()?,
+            vectors: records.len(),
+        };
+
+        let mut archive = tar::Builder::new(std::fs::File::create(path)?);
+        append_json(&mut archive, ARCHIVE_MANIFEST, &manifest)?;
+        append_json(&mut archive, ARCHIVE_VECTORS, &records)?;
+        append_json(&mut archive, DOCUMENT_METADATA_FILE, &self.document_metadata)?;
+        match &self.text_backend {
+            TextBackend::Tantivy(tantivy) => {
+                for entry in std::fs::read_dir(&tantivy.index_path)? {
+                    let entry = entry?;
+                    let name = entry.file_name();
+                    // Lock files belong to this process's writer
+                    if entry.file_type()?.is_file() && !name.to_string_lossy().starts_with(".tantivy-") {
+                        archive.append_path_with_name(entry.path(), Path::new("tantivy_index").join(&name))?;
+                    }
+                }
+            }
+            TextBackend::Bm25 { engine, .. } => {
+                let snapshot = tempfile::NamedTempFile::new()?;
+                engine.save(snapshot.path())?;
+                archive.append_path_with_name(snapshot.path(), DEFAULT_BM25_INDEX_FILE)?;
+            }
+        }
+        archive.into_inner()?.sync_all()?;
+        Ok(manifest)
+    }
+
+    /// Replace the contents of this index with an archive written by `export_archive`.
+    /// The archive must match this index's archive format, keyword backend and
+    /// embedding model, and its vectors the embedder's dimension; otherwise
+    /// `SearchError::Storage(StorageError::SchemaMismatch)` or `SearchError::DimensionMismatch` is returned
+    /// and nothing changes. An index that already holds documents is only replaced
+    /// with `force`.
+    pub async fn import_archive(&mut self, path: &Path, force: bool) -> Result<ArchiveManifest> {
+        self.check_writable()?;
+        let unpacked = tempfile::tempdir()?;
+        tar::Archive::new(std::fs::File::open(path)?).unpack(unpacked.path())?;
+        let manifest: ArchiveManifest = read_json(unpacked.path(), ARCHIVE_MANIFEST)?;
+        self.check_archive(&manifest)?;
+
+        let existing = self.text_backend.len()?.max(self.vector_storage.len());
+        if existing > 0 && !force {
+            return Err(SearchError::InvalidParameter {
+                parameter: "force".to_string(),
+                reason: format!("the index already holds {} documents; import with force to replace them", existing),
+            }.into());
+        }
+
+        // Read everything before clearing, so a damaged archive leaves the index alone
+        let records: Vec<EmbeddingRecord> = read_json(unpacked.path(), ARCHIVE_VECTORS)?;
+        let document_metadata: HashMap<String, HashMap<String, String>> = read_json(unpacked.path(), DOCUMENT_METADATA_FILE)?;
+        let text = match manifest.text_backend {
+            SearchBackend::Tantivy => {
+                let unpacked_path = unpacked.path().to_str().context("temporary directory path is not UTF-8")?;
+                let (contents, file_paths) = TantivyText::open_read_only(unpacked_path)?.documents()?;
+                ArchivedText::Tantivy { contents, file_paths }
+            }
+            SearchBackend::Bm25Only => ArchivedText::Bm25(Box::new(BM25Engine::load(&unpacked.path().join(DEFAULT_BM25_INDEX_FILE))?)),
+        };
+
+        self.clear().await?;
+        match (&mut self.text_backend, text) {
+            (TextBackend::Tantivy(tantivy), ArchivedText::Tantivy { contents, file_paths }) => tantivy.add(&contents, &file_paths)?,
+            (TextBackend::Bm25 { engine, index_path }, ArchivedText::Bm25(archived)) => {
+                **engine = archived
+                    .with_text_processor(engine.text_processor().clone())
+                    .with_filename_boost(engine.filename_boost())
+                    .with_tf_variant(engine.tf_variant());
+                engine.save(index_path)?;
+            }
+            _ => unreachable!("check_archive compared the keyword backends"),
+        }
+        if !records.is_empty() {
+            let mut contents = Vec::with_capacity(records.len());
+            let mut embeddings = Vec::with_capacity(records.len());
+            let mut file_paths = Vec::with_capacity(records.len());
+            let mut metadata = Vec::with_capacity(records.len());
+            for record in records {
+                contents.push(record.content);
+                embeddings.push(record.embedding);
+                file_paths.push(record.file_path);
+                metadata.push(record.metadata);
+            }
+            self.vector_storage.insert_with_metadata(contents, embeddings, file_paths, metadata)?;
+        }
+        self.document_metadata = document_metadata;
+        self.save_document_metadata()?;
+        Ok(manifest)
+    }
+
+    fn check_archive(&self, manifest: &ArchiveManifest) -> Result<()> {
+        if manifest.format_version != ARCHIVE_FORMAT_VERSION {
+            return Err(SearchError::from(StorageError::SchemaMismatch {
+                expected: format!("archive format {}", ARCHIVE_FORMAT_VERSION),
+                actual: format!("archive format {}", manifest.format_version),
+            }).into());
+        }
+        let backend = self.text_backend.kind();
+        if manifest.text_backend != backend {
+            return Err(SearchError::from(StorageError::SchemaMismatch {
+                expected: format!("a {} keyword index", backend),
+                actual: format!("a {} keyword index", manifest.text_backend),
+            }).into());
+        }
+        if let (Some(actual), Some(embedders)) = (manifest.dimension, &self.embedders) {
+            let expected = embedders.text.dimension();
+            if actual != expected {
+                return Err(SearchError::DimensionMismatch { expected, actual }.into());
+            }
+        }
+        let model = self.embedders.as_ref().and_then(|e| e.model.as_ref());
+        if let (Some(archived), Some(current)) = (&manifest.model, model) {
+            if archived != current {
+                return Err(SearchError::from(StorageError::SchemaMismatch {
+                    expected: format!("vectors from {}", current),
+                    actual: format!("vectors from {}", archived),
+                }).into());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, SemanticMode};
+    use crate::embedder::MinimalEmbedder;
+    use crate::simple_search::tests::{ranking, search_with_tagged_documents};
+    
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_export_then_import_preserves_search_results() -> Result<()> {
+        let (_dir, mut original) = search_with_tagged_documents().await?;
+        let expected = ranking(&original.search("parse config", 10).await?);
+        let archive_dir = tempdir()?;
+        let archive = archive_dir.path().join("index.tar");
+        let manifest = original.export_archive(&archive)?;
+        assert_eq!((manifest.documents, manifest.vectors, manifest.dimension), (4, 4, Some(64)));
+
+        let target = tempdir()?;
+        let db_path = target.path().join("test.db").to_str().unwrap().to_string();
+        let mut restored = HybridSearch::with_embedders(
+            &db_path,
+            &Config::default(),
+            Box::new(MinimalEmbedder::new(64)),
+            Box::new(MinimalEmbedder::new(64)),
+        ).await?;
+        restored.import_archive(&archive, false).await?;
+        assert_eq!(ranking(&restored.search("parse config", 10).await?), expected);
+
+        // The restored index is no longer empty
+        let err = restored.import_archive(&archive, false).await.unwrap_err();
+        match err.downcast_ref::<SearchError>() {
+            Some(SearchError::InvalidParameter { parameter, .. }) => assert_eq!(parameter, "force"),
+            other => panic!("expected InvalidParameter, got {:?}", other),
+        }
+        restored.import_archive(&archive, true).await?;
+        assert_eq!(ranking(&restored.search("parse config", 10).await?), expected);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_export_and_import_round_trip_between_separate_instances() -> Result<()> {
+        // As the CLI runs them: `index`, `export`, `import` and a later `search` are
+        // each their own process opened on a db path, without embedding models
+        let mut keyword_only = Config::default();
+        keyword_only.search.semantic_mode = SemanticMode::Disabled;
+        let (_dir, original) = search_with_tagged_documents().await?;
+        let source_db = original.metadata_path.parent().unwrap().to_str().unwrap().to_string();
+        original.close().await?;
+
+        let archive_dir = tempdir()?;
+        let archive = archive_dir.path().join("index.tar");
+        let mut exporter = HybridSearch::open_read_only(&source_db, &keyword_only).await?;
+        let expected = ranking(&exporter.search("parse config", 10).await?);
+        let manifest = exporter.export_archive(&archive)?;
+        assert_eq!((manifest.documents, manifest.vectors, manifest.dimension), (4, 4, Some(64)));
+        drop(exporter);
+
+        let target = tempdir()?;
+        let target_db = target.path().join("test.db").to_str().unwrap().to_string();
+        let mut importer = HybridSearch::with_config(&target_db, &keyword_only).await?;
+        importer.import_archive(&archive, false).await?;
+        importer.close().await?;
+
+        let mut restored = HybridSearch::open_read_only(&target_db, &keyword_only).await?;
+        assert_eq!(restored.stats()?.documents, 4);
+        assert_eq!(restored.stats()?.vector_count, 4);
+        assert_eq!(ranking(&restored.search("parse config", 10).await?), expected);
+        let similar = restored.find_similar("config.rs", 5)?;
+        assert_eq!(similar.len(), 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_import_rejects_archive_of_another_dimension() -> Result<()> {
+        let (_dir, original) = search_with_tagged_documents().await?;
+        let archive_dir = tempdir()?;
+        let archive = archive_dir.path().join("index.tar");
+        original.export_archive(&archive)?;
+
+        let target = tempdir()?;
+        let db_path = target.path().join("test.db").to_str().unwrap().to_string();
+        let mut other = HybridSearch::with_embedders(
+            &db_path,
+            &Config::default(),
+            Box::new(MinimalEmbedder::new(32)),
+            Box::new(MinimalEmbedder::new(32)),
+        ).await?;
+        let err = other.import_archive(&archive, true).await.unwrap_err();
+        match err.downcast_ref::<SearchError>() {
+            Some(SearchError::DimensionMismatch { expected, actual }) => assert_eq!((*expected, *actual), (32, 64)),
+            other => panic!("expected DimensionMismatch, got {:?}", other),
+        }
+        assert_eq!(other.indexed_document_count()?, 0);
+        Ok(())
+    }
+}
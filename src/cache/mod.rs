@@ -1,3 +1,3 @@
 pub mod bounded_cache;
 
-pub use bounded_cache::BoundedCache;
\ No newline at end of file
+pub use bounded_cache::{BoundedCache, CacheStats};
\ No newline at end of file
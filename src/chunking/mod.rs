@@ -2,6 +2,6 @@ pub mod regex_chunker;
 pub mod line_validator;
 pub mod three_chunk;
 
-pub use regex_chunker::{SimpleRegexChunker, Chunk, MarkdownRegexChunker, MarkdownChunk, MarkdownChunkType};
+pub use regex_chunker::{SimpleRegexChunker, Chunk, ChunkBoundary, overlap_chunks, MarkdownRegexChunker, MarkdownChunk, MarkdownChunkType};
 pub use line_validator::{LineValidator, ValidationError};
 pub use three_chunk::{ThreeChunkExpander, ChunkContext, ExpansionError};
\ No newline at end of file
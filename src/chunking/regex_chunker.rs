@@ -62,6 +62,7 @@ pub struct SimpleRegexChunker {
     function_patterns: Vec<Regex>,
     class_patterns: Vec<Regex>,
     chunk_size_target: usize,
+    overlap_lines: usize,
 }
 
 impl SimpleRegexChunker {
@@ -93,9 +94,26 @@ impl SimpleRegexChunker {
             function_patterns,
             class_patterns,
             chunk_size_target: chunk_size,
+            overlap_lines: 0,
         })
     }
     
+    /// Start each chunk `overlap_lines` lines before the previous one ends, so text
+    /// near a cut is indexed in both chunks. Must be smaller than the chunk size.
+    pub fn with_overlap(mut self, overlap_lines: usize) -> Result<Self, crate::error::EmbedError> {
+        if overlap_lines >= self.chunk_size_target {
+            return Err(crate::error::EmbedError::Configuration {
+                message: format!(
+                    "chunk_overlap ({}) must be smaller than chunk_size ({})",
+                    overlap_lines, self.chunk_size_target
+                ),
+                source: None,
+            });
+        }
+        self.overlap_lines = overlap_lines;
+        Ok(self)
+    }
+    
     pub fn chunk_file(&self, content: &str) -> Vec<Chunk> {
         let lines: Vec<&str> = content.lines().collect();
         let mut chunks = Vec::new();
@@ -109,6 +127,7 @@ impl SimpleRegexChunker {
                     content: chunk_content,
                     start_line,
                     end_line: i - 1,
+                    boundary: ChunkBoundary::Lines,
                 });
                 current_chunk_lines.clear();
                 start_line = i;
@@ -122,6 +141,7 @@ impl SimpleRegexChunker {
                     content: chunk_content,
                     start_line,
                     end_line: i,
+                    boundary: ChunkBoundary::Lines,
                 });
                 current_chunk_lines.clear();
                 start_line = i + 1;
@@ -135,10 +155,11 @@ impl SimpleRegexChunker {
                 content: chunk_content,
                 start_line,
                 end_line,
+                boundary: ChunkBoundary::Lines,
             });
         }
         
-        chunks
+        overlap_chunks(chunks, &lines, self.overlap_lines)
     }
     
     /// Build chunk content that exactly matches the original file's line structure
@@ -162,6 +183,46 @@ pub struct Chunk {
     pub content: String,
     pub start_line: usize,
     pub end_line: usize,
+    /// How the chunk's edges were chosen
+    #[serde(default)]
+    pub boundary: ChunkBoundary,
+}
+
+/// Whether a chunk is a clean code unit or a mechanical cut
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChunkBoundary {
+    /// Cut by line count without regard for code structure
+    #[default]
+    Lines,
+    /// One whole top-level definition (function, class, ...) with its leading comments
+    Unit,
+    /// Code before the first definition, e.g. imports and module docs
+    Preamble,
+    /// A slice of a definition too large to keep in one chunk
+    PartialUnit,
+}
+
+/// Pull the start of every chunk after the first back by up to `overlap_lines`
+/// lines, so consecutive chunks share that much context. Whole definitions
+/// (`Unit`, `Preamble`) keep their edges so they stay clean units.
+pub fn overlap_chunks(mut chunks: Vec<Chunk>, lines: &[&str], overlap_lines: usize) -> Vec<Chunk> {
+    if overlap_lines == 0 {
+        return chunks;
+    }
+    for i in 1..chunks.len() {
+        let previous_start = chunks[i - 1].start_line;
+        let chunk = &mut chunks[i];
+        if !matches!(chunk.boundary, ChunkBoundary::Lines | ChunkBoundary::PartialUnit) {
+            continue;
+        }
+        let start = chunk.start_line.saturating_sub(overlap_lines).max(previous_start + 1);
+        if start < chunk.start_line {
+            chunk.start_line = start;
+            chunk.content = lines[start..=chunk.end_line].join("\n");
+        }
+    }
+    chunks
 }
 
 /// Specialized chunker for Markdown files that preserves document structure
@@ -456,6 +517,31 @@ mod tests {
         assert_eq!(chunks[0].start_line, 0);
     }
     
+    #[test]
+    fn test_overlap_shares_boundary_lines() {
+        let chunker = SimpleRegexChunker::with_chunk_size(100)
+            .and_then(|c| c.with_overlap(10))
+            .expect("Failed to create chunker");
+        let content: Vec<String> = (0..300)
+            .map(|i| if i == 95 { "needle here".to_string() } else { format!("line {}", i) })
+            .collect();
+        let chunks = chunker.chunk_file(&content.join("\n"));
+
+        assert!(chunks.len() >= 3);
+        assert_eq!(chunks[0].start_line, 0);
+        assert_eq!(chunks[1].start_line, chunks[0].end_line + 1 - 10);
+        assert_eq!(chunks.iter().filter(|c| c.content.contains("needle")).count(), 2);
+        for chunk in &chunks {
+            assert_eq!(chunk.content.lines().count(), chunk.end_line - chunk.start_line + 1);
+        }
+    }
+
+    #[test]
+    fn test_overlap_must_be_smaller_than_chunk_size() {
+        let chunker = SimpleRegexChunker::with_chunk_size(100).expect("Failed to create chunker");
+        assert!(chunker.with_overlap(100).is_err());
+    }
+
     #[test]
     fn test_chunk_size_limit() {
         init();
@@ -1,4 +1,4 @@
-use crate::chunking::Chunk;
+use crate::chunking::{Chunk, ChunkBoundary};
 
 /// Expands a target chunk to include surrounding context (above/target/below)
 pub struct ThreeChunkExpander;
@@ -14,6 +14,9 @@ pub struct ChunkContext {
     pub below: Option<Chunk>,
     /// Original index of target chunk in the chunks array
     pub target_index: usize,
+    /// How the target chunk was cut; `ChunkBoundary::Unit` means a whole definition
+    #[serde(default)]
+    pub boundary: ChunkBoundary,
 }
 
 /// Errors that can occur during chunk expansion
@@ -53,12 +56,12 @@ impl ThreeChunkExpander {
     /// 
     /// # Examples
     /// ```
-    /// use embed_search::chunking::{ThreeChunkExpander, Chunk};
+    /// use embed_search::chunking::{ThreeChunkExpander, Chunk, ChunkBoundary};
     /// 
     /// let chunks = vec![
-    ///     Chunk { content: "chunk1".to_string(), start_line: 0, end_line: 0 },
-    ///     Chunk { content: "chunk2".to_string(), start_line: 1, end_line: 1 },
-    ///     Chunk { content: "chunk3".to_string(), start_line: 2, end_line: 2 },
+    ///     Chunk { content: "chunk1".to_string(), start_line: 0, end_line: 0, boundary: ChunkBoundary::Lines },
+    ///     Chunk { content: "chunk2".to_string(), start_line: 1, end_line: 1, boundary: ChunkBoundary::Lines },
+    ///     Chunk { content: "chunk3".to_string(), start_line: 2, end_line: 2, boundary: ChunkBoundary::Lines },
     /// ];
     /// 
     /// let context = ThreeChunkExpander::expand(&chunks, 1).unwrap();
@@ -94,11 +97,13 @@ impl ThreeChunkExpander {
             None
         };
         
+        let boundary = target.boundary;
         Ok(ChunkContext {
             above,
             target,
             below,
             target_index,
+            boundary,
         })
     }
     
@@ -215,10 +220,10 @@ mod tests {
     
     fn create_test_chunks() -> Vec<Chunk> {
         vec![
-            Chunk { content: "chunk0".to_string(), start_line: 0, end_line: 2 },
-            Chunk { content: "chunk1".to_string(), start_line: 3, end_line: 5 },
-            Chunk { content: "chunk2".to_string(), start_line: 6, end_line: 8 },
-            Chunk { content: "chunk3".to_string(), start_line: 9, end_line: 11 },
+            Chunk { content: "chunk0".to_string(), start_line: 0, end_line: 2, boundary: ChunkBoundary::Lines },
+            Chunk { content: "chunk1".to_string(), start_line: 3, end_line: 5, boundary: ChunkBoundary::Lines },
+            Chunk { content: "chunk2".to_string(), start_line: 6, end_line: 8, boundary: ChunkBoundary::Lines },
+            Chunk { content: "chunk3".to_string(), start_line: 9, end_line: 11, boundary: ChunkBoundary::Lines },
         ]
     }
     
@@ -239,7 +244,7 @@ mod tests {
     #[test]
     fn test_single_chunk() {
         let chunks = vec![
-            Chunk { content: "only".to_string(), start_line: 0, end_line: 2 }
+            Chunk { content: "only".to_string(), start_line: 0, end_line: 2, boundary: ChunkBoundary::Lines }
         ];
         
         let context = ThreeChunkExpander::expand(&chunks, 0).unwrap();
@@ -286,6 +291,14 @@ mod tests {
         assert_eq!(context.target_index, 1);
     }
     
+    #[test]
+    fn test_context_reports_target_boundary() {
+        let mut chunks = create_test_chunks();
+        chunks[1].boundary = ChunkBoundary::Unit;
+        assert_eq!(ThreeChunkExpander::expand(&chunks, 1).unwrap().boundary, ChunkBoundary::Unit);
+        assert_eq!(ThreeChunkExpander::expand(&chunks, 0).unwrap().boundary, ChunkBoundary::Lines);
+    }
+
     #[test]
     fn test_line_range() {
         let chunks = create_test_chunks();
@@ -316,9 +329,9 @@ mod tests {
     #[test]
     fn test_get_full_content() {
         let chunks = vec![
-            Chunk { content: "line1".to_string(), start_line: 0, end_line: 0 },
-            Chunk { content: "line2".to_string(), start_line: 1, end_line: 1 },
-            Chunk { content: "line3".to_string(), start_line: 2, end_line: 2 },
+            Chunk { content: "line1".to_string(), start_line: 0, end_line: 0, boundary: ChunkBoundary::Lines },
+            Chunk { content: "line2".to_string(), start_line: 1, end_line: 1, boundary: ChunkBoundary::Lines },
+            Chunk { content: "line3".to_string(), start_line: 2, end_line: 2, boundary: ChunkBoundary::Lines },
         ];
         
         let context = ThreeChunkExpander::expand(&chunks, 1).unwrap();
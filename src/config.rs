@@ -1,20 +1,102 @@
 // Configuration management - simple but flexible
 
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+
+use crate::content_filter::ContentFilter;
+use crate::embedding_prefixes::default_embedding_languages;
+use crate::error::ConfigError;
+use crate::gguf_embedder::NOMIC_EMBEDDING_DIMENSION;
+use crate::search::bm25_fixed::{TfVariant, DEFAULT_FILENAME_BOOST};
+use crate::search::fusion::FusionWeights;
+use crate::semantic_chunker::ChunkingStrategy;
+use crate::utils::retry::RetryConfig;
+
+/// The process-wide config and the file it was loaded from, installed by `Config::init`
+/// and swapped by `Config::reload`
+static CONFIG: Lazy<RwLock<(Option<String>, Arc<Config>)>> = Lazy::new(|| RwLock::new((None, Arc::new(Config::default()))));
+/// Carries every config `init` or `reload` installs to `Config::subscribe` receivers
+static INSTALLED: Lazy<watch::Sender<Arc<Config>>> = Lazy::new(|| watch::channel(CONFIG.read().1.clone()).0);
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub storage: StorageConfig,
     pub search: SearchConfig,
     pub indexing: IndexingConfig,
+    #[serde(default)]
+    pub workspace: WorkspaceConfig,
+    #[serde(default)]
+    pub embedding_retry: EmbeddingRetryConfig,
+    #[serde(default)]
+    pub watch: WatchConfig,
+    /// Workers reading and embedding files in parallel during `index`; 0 uses one
+    /// per logical core
+    #[serde(default)]
+    pub index_concurrency: usize,
+    /// Leave out of `index` the paths ignored by global, repository and nested
+    /// `.gitignore` files
+    #[serde(default = "default_respect_gitignore")]
+    pub respect_gitignore: bool,
+    /// What indexing does with a file that isn't valid UTF-8
+    #[serde(default)]
+    pub on_invalid_utf8: InvalidUtf8Policy,
+    /// What indexing does with a file that looks minified or generated, see
+    /// `is_generated_source`
+    #[serde(default)]
+    pub generated_file_policy: GeneratedFilePolicy,
+    /// How the simple vector store holds embeddings in memory
+    #[serde(default)]
+    pub vector_quantization: VectorQuantization,
+    /// Length of the vectors the embedding models must produce; a model with a
+    /// different embedding length fails to load
+    #[serde(default = "default_embedding_dimensions")]
+    pub embedding_dimensions: usize,
+    /// Most verbose level `init_logging` lets through: off, error, warn, info, debug
+    /// or trace
+    #[serde(default = "default_log_level")]
+    pub log_level: String,
+    /// Make searches for an empty or whitespace-only query fail with
+    /// `SearchError::EmptyQuery` instead of returning no results
+    #[serde(default)]
+    pub error_on_empty_query: bool,
+}
+
+fn default_respect_gitignore() -> bool {
+    true
+}
+
+fn default_log_level() -> String {
+    "warn".to_string()
+}
+
+fn default_embedding_dimensions() -> usize {
+    NOMIC_EMBEDDING_DIMENSION
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StorageConfig {
     pub db_path: PathBuf,
+    /// Maximum number of cached embeddings
     pub cache_size: usize,
+    /// Upper bound on the bytes of cached embedding vectors (a 768-dim vector is
+    /// 3 KB); whichever of this and `cache_size` is reached first triggers eviction
+    #[serde(default)]
+    pub embedding_cache_max_bytes: Option<usize>,
     pub batch_size: usize,
+    /// Where the BM25 backend persists its index; defaults to `<db_path>/bm25_index.json`
+    #[serde(default)]
+    pub bm25_index_path: Option<PathBuf>,
+    /// Vector store implementation behind `HybridSearch`
+    #[serde(default)]
+    pub backend: StorageBackend,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,15 +107,446 @@ pub struct SearchConfig {
     pub semantic_weight: f32,
     pub keyword_weight: f32,
     pub enable_fuzzy: bool,
+    #[serde(default)]
+    pub backend: SearchBackend,
+    /// Extra BM25 stop words, added to the built-in English list. Used whenever
+    /// no language-specific set applies.
+    #[serde(default)]
+    pub bm25_stop_words: Vec<String>,
+    /// Language assumed when none is detected, e.g. "es" for Spanish docs
+    #[serde(default)]
+    pub bm25_language: Option<String>,
+    /// Stop word sets keyed by language or file extension; a matching set
+    /// replaces the default list
+    #[serde(default)]
+    pub bm25_stop_words_by_lang: HashMap<String, Vec<String>>,
+    /// How much a query term in a file's name counts relative to one in its
+    /// content, for the BM25 backend; 0 ignores file names
+    #[serde(default = "default_bm25_filename_boost")]
+    pub bm25_filename_boost: f32,
+    /// How repeats of a term in a document add to its BM25 score, e.g.
+    /// `bm25_tf_variant = "log_tf"` or `bm25_tf_variant = { bm25_plus = { delta = 1.0 } }`
+    #[serde(default)]
+    pub bm25_tf_variant: TfVariant,
+    /// Index the parts of compound identifiers (`getUserName` -> `get`, `user`,
+    /// `name`) alongside the identifier itself
+    #[serde(default = "default_split_identifiers")]
+    pub split_identifiers: bool,
+    /// Also index character n-grams of each term for the BM25 backend, so a query
+    /// for part of an identifier (`parse` in `tokenparser`) finds it at a reduced score
+    #[serde(default)]
+    pub enable_ngrams: bool,
+    /// Shortest and longest indexed n-gram, in characters
+    #[serde(default = "default_min_ngram_size")]
+    pub min_ngram_size: usize,
+    #[serde(default = "default_max_ngram_size")]
+    pub max_ngram_size: usize,
+    /// Match BM25 query words by their English stem (`parsing` finds `parse`);
+    /// `SearchOptions::stemming` overrides it per search
+    #[serde(default)]
+    pub enable_stemming: bool,
+    /// Whether search needs the GGUF embedding models
+    #[serde(default)]
+    pub semantic_mode: SemanticMode,
+    /// Number of recent result lists `HybridSearch` keeps for repeated queries;
+    /// 0 disables the cache
+    #[serde(default = "default_search_cache_size")]
+    pub search_cache_size: usize,
+    /// Weights of exact, BM25, semantic and symbol matches in weighted fusion;
+    /// they must sum to 1.0 unless `normalize_fusion_weights` is set
+    #[serde(default = "default_fusion_exact_weight")]
+    pub fusion_exact_weight: f32,
+    #[serde(default = "default_fusion_bm25_weight")]
+    pub fusion_bm25_weight: f32,
+    #[serde(default = "default_fusion_semantic_weight")]
+    pub fusion_semantic_weight: f32,
+    #[serde(default = "default_fusion_symbol_weight")]
+    pub fusion_symbol_weight: f32,
+    /// Scale the four fusion weights to sum to 1.0 instead of rejecting other sums
+    #[serde(default)]
+    pub normalize_fusion_weights: bool,
+}
+
+/// How far the fusion weights may sum from 1.0 before `validate` rejects them
+pub const FUSION_WEIGHT_EPSILON: f32 = 1e-3;
+
+impl SearchConfig {
+    /// `(min, max)` n-gram lengths for `BM25Engine::set_ngram_sizes`; `None` unless `enable_ngrams`
+    pub fn ngram_sizes(&self) -> Option<(usize, usize)> {
+        self.enable_ngrams.then_some((self.min_ngram_size, self.max_ngram_size))
+    }
+
+    /// The fusion weights as configured, scaled to sum to 1.0 with `normalize_fusion_weights`
+    pub fn fusion_weights(&self) -> FusionWeights {
+        let weights = FusionWeights {
+            exact: self.fusion_exact_weight,
+            bm25: self.fusion_bm25_weight,
+            semantic: self.fusion_semantic_weight,
+            symbol: self.fusion_symbol_weight,
+        };
+        if self.normalize_fusion_weights {
+            weights.normalized()
+        } else {
+            weights
+        }
+    }
+}
+
+fn default_bm25_filename_boost() -> f32 {
+    DEFAULT_FILENAME_BOOST
+}
+
+fn default_split_identifiers() -> bool {
+    true
+}
+
+fn default_min_ngram_size() -> usize {
+    3
+}
+
+fn default_max_ngram_size() -> usize {
+    5
+}
+
+fn default_search_cache_size() -> usize {
+    100
+}
+
+fn default_fusion_exact_weight() -> f32 {
+    FusionWeights::default().exact
+}
+
+fn default_fusion_bm25_weight() -> f32 {
+    FusionWeights::default().bm25
+}
+
+fn default_fusion_semantic_weight() -> f32 {
+    FusionWeights::default().semantic
+}
+
+fn default_fusion_symbol_weight() -> f32 {
+    FusionWeights::default().symbol
+}
+
+/// Handling of source files that aren't valid UTF-8, e.g. Latin-1 text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InvalidUtf8Policy {
+    /// Leave the file out, with a warning
+    Skip,
+    /// Index the file with each invalid sequence replaced by U+FFFD
+    Lossy,
+    /// Report the file as failed with `SearchError::FileDecode`
+    #[default]
+    Error,
+}
+
+/// Handling of minified and generated source files, whose very long lines make poor
+/// chunks and embeddings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GeneratedFilePolicy {
+    /// Leave the file out
+    Skip,
+    /// Index the file whole as one chunk, without content filtering or
+    /// symbol-aware chunking, and tag it under `GENERATED_METADATA_KEY`
+    IndexAsOpaque,
+    /// Index the file like any other
+    #[default]
+    Normal,
+}
+
+/// How `VectorStorage` holds embeddings. `Int8` keeps one signed byte per dimension
+/// plus an f32 scale per vector, about a quarter of the memory of `None` (768
+/// dimensions: 772 bytes instead of 3072). Each component is rounded to the nearest
+/// 1/254 of the vector's largest magnitude, which moves cosine similarities by well
+/// under 0.01: clear winners keep their rank, but near-ties can swap, so recall@10
+/// against full precision is typically around 0.99 rather than 1.0. Archives and
+/// `VectorStore::records` always hold the vectors at full width.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VectorQuantization {
+    /// Full f32 precision
+    #[default]
+    None,
+    /// Scalar quantization to i8 with a per-vector scale
+    Int8,
+}
+
+/// Whether `HybridSearch` loads the embedding models and runs vector search
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SemanticMode {
+    /// Fail to start if a model can't be loaded
+    #[default]
+    Required,
+    /// Fall back to keyword-only search, with a warning, if a model can't be loaded
+    Optional,
+    /// Never load the models; keyword-only search
+    Disabled,
+}
+
+impl fmt::Display for SemanticMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SemanticMode::Required => write!(f, "required"),
+            SemanticMode::Optional => write!(f, "optional"),
+            SemanticMode::Disabled => write!(f, "disabled"),
+        }
+    }
+}
+
+/// Keyword search backend used alongside vector search
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchBackend {
+    /// Persistent Tantivy index under `<db_path>/tantivy_index`
+    #[default]
+    Tantivy,
+    /// In-memory `BM25Engine` only; no Tantivy files are created
+    Bm25Only,
+}
+
+impl fmt::Display for SearchBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SearchBackend::Tantivy => write!(f, "tantivy"),
+            SearchBackend::Bm25Only => write!(f, "bm25_only"),
+        }
+    }
+}
+
+impl FromStr for SearchBackend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "tantivy" => Ok(SearchBackend::Tantivy),
+            "bm25_only" | "bm25only" | "bm25" => Ok(SearchBackend::Bm25Only),
+            other => Err(anyhow::anyhow!("Unknown search backend '{}': expected 'tantivy' or 'bm25_only'", other)),
+        }
+    }
+}
+
+/// Backoff for transient embedding failures (context allocation, decode).
+/// Malformed input is never retried.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmbeddingRetryConfig {
+    /// Retries after the first attempt; 0 disables retrying
+    pub max_retries: usize,
+    pub initial_delay_ms: u64,
+    pub max_delay_ms: u64,
+    pub multiplier: f64,
+    pub jitter: bool,
+}
+
+impl Default for EmbeddingRetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 2,
+            initial_delay_ms: 50,
+            max_delay_ms: 1000,
+            multiplier: 2.0,
+            jitter: true,
+        }
+    }
+}
+
+impl EmbeddingRetryConfig {
+    pub fn to_retry_config(&self) -> RetryConfig {
+        RetryConfig::new(
+            self.max_retries,
+            Duration::from_millis(self.initial_delay_ms),
+            Duration::from_millis(self.max_delay_ms),
+            self.multiplier,
+            self.jitter,
+        )
+    }
+}
+
+/// How `embed-search watch` finds out about changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatchBackend {
+    /// Poll every `git_poll_interval_secs` and reconcile the whole tree
+    #[default]
+    GitPoll,
+    /// Filesystem events, debounced by `debounce_ms`; only the changed paths are re-indexed
+    Notify,
+}
+
+/// Change detection for `embed-search watch`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchConfig {
+    pub backend: WatchBackend,
+    /// Poll `git status` for changes; without it, or outside a git work tree,
+    /// the watcher compares file modification times instead
+    pub enable_git_watch: bool,
+    pub git_poll_interval_secs: u64,
+    /// With `Notify`, events closer together than this are handled as one batch
+    pub debounce_ms: u64,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        Self {
+            backend: WatchBackend::default(),
+            enable_git_watch: true,
+            git_poll_interval_secs: 5,
+            debounce_ms: 200,
+        }
+    }
+}
+
+impl WatchConfig {
+    pub fn poll_interval(&self) -> Duration {
+        Duration::from_secs(self.git_poll_interval_secs.max(1))
+    }
+
+    pub fn debounce(&self) -> Duration {
+        Duration::from_millis(self.debounce_ms)
+    }
+}
+
+/// Vector store used for semantic search. LanceDB is not offered while its
+/// dependency is disabled (see Cargo.toml).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageBackend {
+    /// In-memory `VectorStorage` with exact cosine search
+    #[default]
+    Simple,
+}
+
+impl fmt::Display for StorageBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageBackend::Simple => write!(f, "simple"),
+        }
+    }
+}
+
+impl FromStr for StorageBackend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "simple" => Ok(StorageBackend::Simple),
+            other => Err(anyhow::anyhow!("Unknown storage backend '{}': expected 'simple'", other)),
+        }
+    }
+}
+
+/// Repositories searched together by `MultiIndexSearch`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorkspaceConfig {
+    #[serde(default)]
+    pub roots: Vec<WorkspaceRoot>,
+}
+
+/// A single repository root with its own index and fusion weight
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceRoot {
+    pub name: String,
+    pub path: PathBuf,
+    /// Index location; defaults to `<path>/.embed_db` when absent
+    #[serde(default)]
+    pub db_path: Option<PathBuf>,
+    #[serde(default = "default_workspace_weight")]
+    pub weight: f32,
+}
+
+fn default_workspace_weight() -> f32 {
+    1.0
+}
+
+impl WorkspaceRoot {
+    pub fn index_path(&self) -> PathBuf {
+        self.db_path.clone().unwrap_or_else(|| self.path.join(".embed_db"))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IndexingConfig {
+    /// Target chunk length in lines
     pub chunk_size: usize,
+    /// Lines shared by consecutive chunks, so text near a cut lands in both;
+    /// must be smaller than `chunk_size`
     pub chunk_overlap: usize,
+    /// Largest file, in bytes, that will be indexed; 0 disables the limit
+    #[serde(alias = "max_file_size_bytes")]
     pub max_file_size: usize,
+    /// File extensions to index; `"*"` indexes every UTF-8 file
+    #[serde(alias = "indexable_extensions")]
     pub supported_extensions: Vec<String>,
     pub enable_incremental: bool,
+    /// How code files are split into chunks (markdown always uses its own chunker)
+    #[serde(default)]
+    pub chunking_strategy: ChunkingStrategy,
+    /// With `SemanticBoundary`, how far past `chunk_size` a definition may run
+    /// before it is split, e.g. 1.5 keeps units of up to 1.5x `chunk_size` lines whole
+    #[serde(default = "default_chunk_overshoot_factor")]
+    pub chunk_overshoot_factor: f32,
+    /// Gitignore-style patterns to leave out of the index, applied after the root's
+    /// `.embedignore`; `!pattern` re-includes
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// While indexing a directory, commit the Tantivy index after this many new
+    /// documents rather than after every batch; 0 commits once at the end
+    #[serde(default = "default_bulk_commit_every")]
+    pub bulk_commit_every: usize,
+    /// While indexing a directory, shrink batches and then hold new ones whenever the
+    /// system has less than this much memory available; 0 disables the check
+    #[serde(default)]
+    pub min_free_memory_mb: u64,
+    /// Index only the code of source files, only their comments, or both
+    #[serde(default)]
+    pub content_filter: ContentFilter,
+    /// Language each file extension is formatted as before it is embedded, e.g.
+    /// `py = "python"`; files with an extension not listed are embedded as is
+    #[serde(default = "default_embedding_languages")]
+    pub embedding_languages: BTreeMap<String, String>,
+}
+
+fn default_chunk_overshoot_factor() -> f32 {
+    1.5
+}
+
+fn default_bulk_commit_every() -> usize {
+    1000
+}
+
+impl Default for IndexingConfig {
+    fn default() -> Self {
+        Self {
+            chunk_size: 512,
+            chunk_overlap: 50,
+            max_file_size: 10_000_000, // 10MB
+            supported_extensions: vec![
+                "rs".to_string(),
+                "py".to_string(),
+                "js".to_string(),
+                "ts".to_string(),
+                "go".to_string(),
+                "java".to_string(),
+                "cpp".to_string(),
+                "c".to_string(),
+                "h".to_string(),
+                "md".to_string(),
+                "markdown".to_string(),
+            ],
+            enable_incremental: true,
+            chunking_strategy: ChunkingStrategy::default(),
+            chunk_overshoot_factor: default_chunk_overshoot_factor(),
+            exclude: Vec::new(),
+            bulk_commit_every: default_bulk_commit_every(),
+            min_free_memory_mb: 0,
+            content_filter: ContentFilter::default(),
+            embedding_languages: default_embedding_languages(),
+        }
+    }
 }
 
 impl Default for Config {
@@ -42,7 +555,10 @@ impl Default for Config {
             storage: StorageConfig {
                 db_path: PathBuf::from("./embed.db"),
                 cache_size: 1000,
+                embedding_cache_max_bytes: None,
                 batch_size: 50,
+                bm25_index_path: None,
+                backend: StorageBackend::default(),
             },
             search: SearchConfig {
                 max_results: 20,
@@ -51,40 +567,718 @@ impl Default for Config {
                 semantic_weight: 0.6,
                 keyword_weight: 0.4,
                 enable_fuzzy: true,
+                backend: SearchBackend::default(),
+                bm25_stop_words: Vec::new(),
+                bm25_language: None,
+                bm25_stop_words_by_lang: HashMap::new(),
+                bm25_filename_boost: default_bm25_filename_boost(),
+                bm25_tf_variant: TfVariant::default(),
+                split_identifiers: default_split_identifiers(),
+                enable_ngrams: false,
+                min_ngram_size: default_min_ngram_size(),
+                max_ngram_size: default_max_ngram_size(),
+                enable_stemming: false,
+                semantic_mode: SemanticMode::default(),
+                search_cache_size: default_search_cache_size(),
+                fusion_exact_weight: default_fusion_exact_weight(),
+                fusion_bm25_weight: default_fusion_bm25_weight(),
+                fusion_semantic_weight: default_fusion_semantic_weight(),
+                fusion_symbol_weight: default_fusion_symbol_weight(),
+                normalize_fusion_weights: false,
             },
-            indexing: IndexingConfig {
-                chunk_size: 512,
-                chunk_overlap: 50,
-                max_file_size: 10_000_000, // 10MB
-                supported_extensions: vec![
-                    "rs".to_string(),
-                    "py".to_string(),
-                    "js".to_string(),
-                    "ts".to_string(),
-                    "go".to_string(),
-                    "java".to_string(),
-                    "cpp".to_string(),
-                    "c".to_string(),
-                    "h".to_string(),
-                    "md".to_string(),
-                    "markdown".to_string(),
-                ],
-                enable_incremental: true,
-            },
+            indexing: IndexingConfig::default(),
+            workspace: WorkspaceConfig::default(),
+            embedding_retry: EmbeddingRetryConfig::default(),
+            watch: WatchConfig::default(),
+            index_concurrency: 0,
+            respect_gitignore: default_respect_gitignore(),
+            on_invalid_utf8: InvalidUtf8Policy::default(),
+            generated_file_policy: GeneratedFilePolicy::default(),
+            vector_quantization: VectorQuantization::default(),
+            embedding_dimensions: default_embedding_dimensions(),
+            log_level: default_log_level(),
+            error_on_empty_query: false,
         }
     }
 }
 
 impl Config {
-    pub fn from_file(path: &str) -> anyhow::Result<Self> {
-        let content = std::fs::read_to_string(path)?;
-        let config = toml::from_str(&content)?;
+    /// The config file at `path` (defaults when `None`), with environment overrides
+    /// (see `apply_env_overrides`) applied, validated
+    pub fn load(path: Option<&str>) -> Result<Self, ConfigError> {
+        let mut config = match path {
+            Some(path) => Self::from_file(path)?,
+            None => Self::default(),
+        };
+        config.apply_env_overrides(|variable| std::env::var(variable).ok())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// `load(path)`, installed as the process-wide config that `get` returns
+    pub fn init(path: Option<&str>) -> Result<Arc<Self>, ConfigError> {
+        let config = Arc::new(Self::load(path)?);
+        *CONFIG.write() = (path.map(str::to_string), config.clone());
+        INSTALLED.send_replace(config.clone());
         Ok(config)
     }
 
+    /// The config installed by `init` or `reload`; defaults before either ran
+    pub fn get() -> Arc<Self> {
+        CONFIG.read().1.clone()
+    }
+
+    /// Re-read the file and environment `init` loaded from and swap the result in,
+    /// notifying `subscribe` receivers. A config that fails to load or validate is
+    /// returned as the error and the live config stays in place.
+    pub fn reload() -> Result<(), ConfigError> {
+        let path = CONFIG.read().0.clone();
+        let config = Arc::new(Self::load(path.as_deref())?);
+        CONFIG.write().1 = config.clone();
+        INSTALLED.send_replace(config);
+        log::info!("Reloaded config from {}", path.as_deref().unwrap_or("defaults"));
+        Ok(())
+    }
+
+    /// Receiver marked changed whenever `init` or `reload` installs a config, for
+    /// long-running components that re-read their settings
+    pub fn subscribe() -> watch::Receiver<Arc<Self>> {
+        INSTALLED.subscribe()
+    }
+
+    /// Keep the installed config in step with its file for a long-running process:
+    /// `reload` on SIGHUP (Unix only) and whenever the modification time of the file
+    /// `init` loaded from changes, checked every `interval`. A failed reload is logged
+    /// and the live config kept. Runs until the returned task is aborted.
+    pub fn spawn_reloader(interval: Duration) -> tokio::task::JoinHandle<()> {
+        let modified = || {
+            let path = CONFIG.read().0.clone()?;
+            std::fs::metadata(path).and_then(|m| m.modified()).ok()
+        };
+        // Taken now, so an edit made right after this returns is still noticed
+        let mut last_modified = modified();
+        tokio::spawn(async move {
+            #[cfg(unix)]
+            let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+                .map_err(|e| log::warn!("Config reloads on SIGHUP are unavailable: {}", e))
+                .ok();
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                let hangup_received = async {
+                    #[cfg(unix)]
+                    if let Some(hangup) = hangup.as_mut() {
+                        return hangup.recv().await;
+                    }
+                    std::future::pending().await
+                };
+                tokio::select! {
+                    _ = ticker.tick() => {
+                        let now = modified();
+                        if now == last_modified {
+                            continue;
+                        }
+                        last_modified = now;
+                    }
+                    _ = hangup_received => {}
+                }
+                if let Err(e) = Self::reload() {
+                    log::warn!("Keeping the current config: {}", e);
+                }
+            }
+        })
+    }
+
+    /// Parse the TOML file at `path` without validating it
+    pub fn from_file(path: &str) -> Result<Self, ConfigError> {
+        let file = PathBuf::from(path);
+        let content = match std::fs::read_to_string(&file) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(ConfigError::FileNotFound { file }),
+            Err(source) => return Err(ConfigError::ReadFailed { file, source }),
+        };
+        toml::from_str(&content).map_err(|source| ConfigError::ParseError { file, source })
+    }
+
+    /// Override settings from `EMBED_SEARCH_BACKEND`, `EMBED_SEARCH_STORAGE_BACKEND`,
+    /// `EMBED_SEARCH_MAX_RESULTS`, `EMBED_SEARCH_EMBEDDING_DIMENSIONS` and
+    /// `EMBED_SEARCH_INDEX_CONCURRENCY`, looked up through `var`
+    pub fn apply_env_overrides(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<(), ConfigError> {
+        fn parse<T: FromStr>(var: &dyn Fn(&str) -> Option<String>, variable: &str) -> Result<Option<T>, ConfigError>
+        where
+            T::Err: fmt::Display,
+        {
+            var(variable)
+                .map(|value| {
+                    value.trim().parse().map_err(|e: T::Err| ConfigError::EnvParseError {
+                        variable: variable.to_string(),
+                        reason: e.to_string(),
+                        value,
+                    })
+                })
+                .transpose()
+        }
+
+        if let Some(backend) = parse(&var, "EMBED_SEARCH_BACKEND")? {
+            self.search.backend = backend;
+        }
+        if let Some(backend) = parse(&var, "EMBED_SEARCH_STORAGE_BACKEND")? {
+            self.storage.backend = backend;
+        }
+        if let Some(max_results) = parse(&var, "EMBED_SEARCH_MAX_RESULTS")? {
+            self.search.max_results = max_results;
+        }
+        if let Some(dimensions) = parse(&var, "EMBED_SEARCH_EMBEDDING_DIMENSIONS")? {
+            self.embedding_dimensions = dimensions;
+        }
+        if let Some(concurrency) = parse(&var, "EMBED_SEARCH_INDEX_CONCURRENCY")? {
+            self.index_concurrency = concurrency;
+        }
+        Ok(())
+    }
+
+    /// Reject settings that parse but can't work, naming the offending field
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let fail = |field: &str, reason: String| Err(ConfigError::ValidationFailed { field: field.to_string(), reason });
+
+        if self.storage.cache_size == 0 {
+            return fail("storage.cache_size", "must be greater than 0".to_string());
+        }
+        if self.storage.batch_size == 0 {
+            return fail("storage.batch_size", "must be greater than 0".to_string());
+        }
+        if self.embedding_dimensions == 0 {
+            return fail("embedding_dimensions", "must be greater than 0".to_string());
+        }
+        if self.search.max_results == 0 {
+            return fail("search.max_results", "must be greater than 0".to_string());
+        }
+        if !(self.search.bm25_k1.is_finite() && self.search.bm25_k1 >= 0.0) {
+            return fail("search.bm25_k1", format!("is {}, expected a non-negative number", self.search.bm25_k1));
+        }
+        if !(0.0..=1.0).contains(&self.search.bm25_b) {
+            return fail("search.bm25_b", format!("is {}, expected 0.0..=1.0", self.search.bm25_b));
+        }
+        if !(0.0..).contains(&self.search.bm25_filename_boost) {
+            return fail("search.bm25_filename_boost", format!("is {}, expected 0.0 or more", self.search.bm25_filename_boost));
+        }
+        if let TfVariant::Bm25Plus { delta } = self.search.bm25_tf_variant {
+            if !(0.0..).contains(&delta) {
+                return fail("search.bm25_tf_variant", format!("has delta {}, expected 0.0 or more", delta));
+            }
+        }
+        if self.search.min_ngram_size == 0 {
+            return fail("search.min_ngram_size", "must be greater than 0".to_string());
+        }
+        if self.search.min_ngram_size > self.search.max_ngram_size {
+            return fail(
+                "search.min_ngram_size",
+                format!("({}) must not exceed max_ngram_size ({})", self.search.min_ngram_size, self.search.max_ngram_size),
+            );
+        }
+        for (field, weight) in [
+            ("search.semantic_weight", self.search.semantic_weight),
+            ("search.keyword_weight", self.search.keyword_weight),
+        ] {
+            if !(0.0..=1.0).contains(&weight) {
+                return fail(field, format!("is {}, expected 0.0..=1.0", weight));
+            }
+        }
+        let fusion_weights = [
+            ("search.fusion_exact_weight", self.search.fusion_exact_weight),
+            ("search.fusion_bm25_weight", self.search.fusion_bm25_weight),
+            ("search.fusion_semantic_weight", self.search.fusion_semantic_weight),
+            ("search.fusion_symbol_weight", self.search.fusion_symbol_weight),
+        ];
+        for (field, weight) in fusion_weights {
+            if !(weight.is_finite() && weight >= 0.0) {
+                return fail(field, format!("is {}, expected a non-negative number", weight));
+            }
+        }
+        let sum: f32 = fusion_weights.iter().map(|(_, weight)| weight).sum();
+        let acceptable = if self.search.normalize_fusion_weights {
+            sum > 0.0
+        } else {
+            (sum - 1.0).abs() <= FUSION_WEIGHT_EPSILON
+        };
+        if !acceptable {
+            let terms: Vec<String> = fusion_weights.iter().map(|(field, weight)| format!("{} = {}", field, weight)).collect();
+            let expected = if self.search.normalize_fusion_weights {
+                "expected a positive sum to normalize".to_string()
+            } else {
+                "expected 1.0 (or set search.normalize_fusion_weights)".to_string()
+            };
+            return fail("fusion weights", format!("{} sum to {}, {}", terms.join(", "), sum, expected));
+        }
+        if self.indexing.chunk_size == 0 {
+            return fail("indexing.chunk_size", "must be greater than 0".to_string());
+        }
+        if self.indexing.chunk_overlap >= self.indexing.chunk_size {
+            return fail(
+                "indexing.chunk_overlap",
+                format!("({}) must be smaller than chunk_size ({})", self.indexing.chunk_overlap, self.indexing.chunk_size),
+            );
+        }
+        if !(1.0..).contains(&self.indexing.chunk_overshoot_factor) {
+            return fail(
+                "indexing.chunk_overshoot_factor",
+                format!("is {}, expected at least 1.0", self.indexing.chunk_overshoot_factor),
+            );
+        }
+        if let Some((ext, _)) = self.indexing.embedding_languages.iter().find(|(ext, language)| ext.is_empty() || ext.starts_with('.') || language.trim().is_empty()) {
+            return fail("indexing.embedding_languages", format!("entry {:?} needs an extension without a leading '.' and a language", ext));
+        }
+        for root in &self.workspace.roots {
+            if !(root.weight.is_finite() && root.weight > 0.0) {
+                return fail(&format!("workspace root '{}' weight", root.name), format!("is {}, expected a positive number", root.weight));
+            }
+        }
+        if self.log_level.parse::<log::LevelFilter>().is_err() {
+            return fail("log_level", format!("is {:?}, expected off, error, warn, info, debug or trace", self.log_level));
+        }
+        Ok(())
+    }
+
+    /// Build a config in code, starting from the defaults, rather than loading a file
+    /// or editing the global `CONFIG`
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+
+    pub fn search_backend(&self) -> SearchBackend {
+        self.search.backend
+    }
+
+    pub fn storage_backend(&self) -> StorageBackend {
+        self.storage.backend
+    }
+
     pub fn save(&self, path: &str) -> anyhow::Result<()> {
         let content = toml::to_string_pretty(self)?;
         std::fs::write(path, content)?;
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// Fluent construction of a `Config`, see `Config::builder`. Fields without a setter
+/// keep their defaults; `build` validates the result.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+impl ConfigBuilder {
+    pub fn db_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.storage.db_path = path.into();
+        self
+    }
+
+    pub fn bm25_index_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.storage.bm25_index_path = Some(path.into());
+        self
+    }
+
+    pub fn storage_backend(mut self, backend: StorageBackend) -> Self {
+        self.config.storage.backend = backend;
+        self
+    }
+
+    pub fn search_backend(mut self, backend: SearchBackend) -> Self {
+        self.config.search.backend = backend;
+        self
+    }
+
+    pub fn semantic_mode(mut self, mode: SemanticMode) -> Self {
+        self.config.search.semantic_mode = mode;
+        self
+    }
+
+    pub fn max_results(mut self, max_results: usize) -> Self {
+        self.config.search.max_results = max_results;
+        self
+    }
+
+    /// Weights of exact, BM25, semantic and symbol matches in weighted fusion
+    pub fn fusion_weights(mut self, weights: FusionWeights) -> Self {
+        self.config.search.fusion_exact_weight = weights.exact;
+        self.config.search.fusion_bm25_weight = weights.bm25;
+        self.config.search.fusion_semantic_weight = weights.semantic;
+        self.config.search.fusion_symbol_weight = weights.symbol;
+        self
+    }
+
+    pub fn normalize_fusion_weights(mut self, normalize: bool) -> Self {
+        self.config.search.normalize_fusion_weights = normalize;
+        self
+    }
+
+    pub fn chunk_size(mut self, lines: usize) -> Self {
+        self.config.indexing.chunk_size = lines;
+        self
+    }
+
+    pub fn chunk_overlap(mut self, lines: usize) -> Self {
+        self.config.indexing.chunk_overlap = lines;
+        self
+    }
+
+    pub fn supported_extensions<S: Into<String>>(mut self, extensions: impl IntoIterator<Item = S>) -> Self {
+        self.config.indexing.supported_extensions = extensions.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn embedding_dimensions(mut self, dimensions: usize) -> Self {
+        self.config.embedding_dimensions = dimensions;
+        self
+    }
+
+    pub fn log_level(mut self, level: impl Into<String>) -> Self {
+        self.config.log_level = level.into();
+        self
+    }
+
+    /// The config, or the first field `Config::validate` rejects
+    pub fn build(self) -> Result<Config, ConfigError> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+/// Install the process-wide logger, letting through messages up to
+/// `config.log_level`. `RUST_LOG`, when set, takes precedence. Only the first call in
+/// a process installs a logger; it returns false when one was already installed.
+/// Later calls still apply `config.log_level`, so a reloaded config can change it.
+pub fn init_logging(config: &Config) -> bool {
+    let from_env = std::env::var_os(env_logger::DEFAULT_FILTER_ENV).is_some();
+    // Without RUST_LOG the logger passes everything and the max level filters,
+    // which unlike the logger's own filter can be changed after installing it
+    let installed = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init()
+        .is_ok();
+    if !from_env {
+        log::set_max_level(config.log_level.parse().unwrap_or(log::LevelFilter::Info));
+    }
+    installed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_backend_round_trips_through_str() {
+        for backend in [SearchBackend::Tantivy, SearchBackend::Bm25Only] {
+            assert_eq!(backend.to_string().parse::<SearchBackend>().unwrap(), backend);
+        }
+        assert!("lucene".parse::<SearchBackend>().is_err());
+    }
+
+    #[test]
+    fn test_storage_backend_round_trips() {
+        assert_eq!(StorageBackend::Simple.to_string().parse::<StorageBackend>().unwrap(), StorageBackend::Simple);
+        assert_eq!(Config::default().storage_backend(), StorageBackend::Simple);
+        let parsed: Config = toml::from_str(&toml::to_string(&Config::default()).unwrap()).unwrap();
+        assert_eq!(parsed.storage_backend(), StorageBackend::Simple);
+
+        // No backend that can't be opened is accepted
+        assert!("lance_db".parse::<StorageBackend>().is_err());
+        let mut value = toml::Value::try_from(Config::default()).unwrap();
+        value["storage"]["backend"] = toml::Value::String("lance_db".to_string());
+        assert!(toml::from_str::<Config>(&toml::to_string(&value).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_embedding_retry_defaults_when_missing() {
+        let mut value = toml::Value::try_from(Config::default()).unwrap();
+        value.as_table_mut().unwrap().remove("embedding_retry");
+        let parsed: Config = toml::from_str(&toml::to_string(&value).unwrap()).unwrap();
+        assert_eq!(parsed.embedding_retry, EmbeddingRetryConfig::default());
+
+        let partial: EmbeddingRetryConfig = toml::from_str("max_retries = 5").unwrap();
+        assert_eq!(partial.max_retries, 5);
+        assert_eq!(partial.to_retry_config().initial_delay, Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_watch_config_defaults_when_missing() {
+        let mut value = toml::Value::try_from(Config::default()).unwrap();
+        value.as_table_mut().unwrap().remove("watch");
+        let parsed: Config = toml::from_str(&toml::to_string(&value).unwrap()).unwrap();
+        assert_eq!(parsed.watch, WatchConfig::default());
+
+        let partial: WatchConfig = toml::from_str("git_poll_interval_secs = 0").unwrap();
+        assert!(partial.enable_git_watch);
+        assert_eq!(partial.backend, WatchBackend::GitPoll);
+        assert_eq!(partial.poll_interval(), Duration::from_secs(1));
+
+        let notify: WatchConfig = toml::from_str("backend = \"notify\"\ndebounce_ms = 50").unwrap();
+        assert_eq!(notify.backend, WatchBackend::Notify);
+        assert_eq!(notify.debounce(), Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_validate_names_the_bad_field() {
+        assert!(Config::default().validate().is_ok());
+
+        let mut config = Config::default();
+        config.indexing.chunk_overlap = config.indexing.chunk_size;
+        let message = config.validate().unwrap_err().to_string();
+        assert!(message.contains("indexing.chunk_overlap"), "{}", message);
+
+        let mut config = Config::default();
+        config.search.bm25_b = 1.5;
+        assert!(config.validate().unwrap_err().to_string().contains("search.bm25_b"));
+    }
+
+    #[test]
+    fn test_log_level_is_validated_before_logging_starts() {
+        let mut config = Config { log_level: "verbose".to_string(), ..Default::default() };
+        match config.validate() {
+            Err(ConfigError::ValidationFailed { field, .. }) => assert_eq!(field, "log_level"),
+            other => panic!("expected ValidationFailed, got {:?}", other),
+        }
+
+        // Only the first call installs a logger; the rest must not panic either, and
+        // still apply their level unless RUST_LOG overrides it
+        for level in ["off", "error", "warn", "info", "debug", "trace", "INFO"] {
+            config.log_level = level.to_string();
+            assert!(config.validate().is_ok(), "{}", level);
+            init_logging(&config);
+            if std::env::var_os("RUST_LOG").is_none() {
+                assert_eq!(log::max_level(), level.parse::<log::LevelFilter>().unwrap());
+            }
+        }
+        assert!(!init_logging(&config));
+    }
+
+    fn with_fusion_weights(exact: f32, bm25: f32, semantic: f32, symbol: f32) -> Config {
+        let mut config = Config::default();
+        config.search.fusion_exact_weight = exact;
+        config.search.fusion_bm25_weight = bm25;
+        config.search.fusion_semantic_weight = semantic;
+        config.search.fusion_symbol_weight = symbol;
+        config
+    }
+
+    #[test]
+    fn test_fusion_weights_summing_to_one_are_valid() {
+        let config = with_fusion_weights(0.1, 0.2, 0.3, 0.4);
+        assert!(config.validate().is_ok());
+        assert_eq!(config.search.fusion_weights(), FusionWeights { exact: 0.1, bm25: 0.2, semantic: 0.3, symbol: 0.4 });
+    }
+
+    #[test]
+    fn test_fusion_weights_over_one_name_each_weight_and_the_sum() {
+        let config = with_fusion_weights(0.5, 0.5, 0.25, 0.125);
+        let message = config.validate().unwrap_err().to_string();
+        for expected in [
+            "search.fusion_exact_weight = 0.5",
+            "search.fusion_bm25_weight = 0.5",
+            "search.fusion_semantic_weight = 0.25",
+            "search.fusion_symbol_weight = 0.125",
+            "sum to 1.375",
+        ] {
+            assert!(message.contains(expected), "{} not in {}", expected, message);
+        }
+    }
+
+    #[test]
+    fn test_normalize_fusion_weights_scales_them_to_one() {
+        let mut config = with_fusion_weights(2.0, 1.0, 1.0, 0.0);
+        config.search.normalize_fusion_weights = true;
+        assert!(config.validate().is_ok());
+        let weights = config.search.fusion_weights();
+        assert!((weights.sum() - 1.0).abs() < 1e-6);
+        assert_eq!(weights, FusionWeights { exact: 0.5, bm25: 0.25, semantic: 0.25, symbol: 0.0 });
+
+        let mut all_zero = with_fusion_weights(0.0, 0.0, 0.0, 0.0);
+        all_zero.search.normalize_fusion_weights = true;
+        assert!(all_zero.validate().is_err());
+    }
+
+    #[test]
+    fn test_ngram_sizes_must_be_ordered() {
+        let mut config = Config::default();
+        assert_eq!(config.search.ngram_sizes(), None);
+        config.search.enable_ngrams = true;
+        assert_eq!(config.search.ngram_sizes(), Some((3, 5)));
+
+        config.search.min_ngram_size = 6;
+        assert!(config.validate().unwrap_err().to_string().contains("search.min_ngram_size"));
+        config.search.min_ngram_size = 0;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_builder_validates_what_it_builds() {
+        let config = Config::builder()
+            .db_path("/tmp/embedded.db")
+            .search_backend(SearchBackend::Bm25Only)
+            .semantic_mode(SemanticMode::Disabled)
+            .chunk_size(80)
+            .chunk_overlap(8)
+            .fusion_weights(FusionWeights { exact: 0.25, bm25: 0.25, semantic: 0.25, symbol: 0.25 })
+            .embedding_dimensions(384)
+            .build()
+            .unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.search_backend(), SearchBackend::Bm25Only);
+        assert_eq!(config.search_backend().to_string().parse::<SearchBackend>().unwrap(), config.search_backend());
+        assert_eq!(config.storage.db_path, PathBuf::from("/tmp/embedded.db"));
+        assert_eq!((config.indexing.chunk_size, config.embedding_dimensions), (80, 384));
+        assert_eq!(config.search.fusion_weights().bm25, 0.25);
+
+        match Config::builder().chunk_size(8).chunk_overlap(8).build() {
+            Err(ConfigError::ValidationFailed { field, .. }) => assert_eq!(field, "indexing.chunk_overlap"),
+            other => panic!("expected ValidationFailed, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_search_backend_defaults_to_tantivy() {
+        assert_eq!(Config::default().search_backend(), SearchBackend::Tantivy);
+    }
+
+    #[test]
+    fn test_search_backend_from_toml() {
+        let mut config = Config::default();
+        config.search.backend = SearchBackend::Bm25Only;
+        let parsed: Config = toml::from_str(&toml::to_string(&config).unwrap()).unwrap();
+        assert_eq!(parsed.search_backend(), SearchBackend::Bm25Only);
+    }
+
+    #[test]
+    fn test_semantic_mode_defaults_to_required() {
+        let mut value = toml::Value::try_from(Config::default()).unwrap();
+        value["search"].as_table_mut().unwrap().remove("semantic_mode");
+        let parsed: Config = toml::from_str(&toml::to_string(&value).unwrap()).unwrap();
+        assert_eq!(parsed.search.semantic_mode, SemanticMode::Required);
+
+        value["search"].as_table_mut().unwrap().insert("semantic_mode".to_string(), "optional".into());
+        let parsed: Config = toml::from_str(&toml::to_string(&value).unwrap()).unwrap();
+        assert_eq!(parsed.search.semantic_mode, SemanticMode::Optional);
+    }
+
+    #[test]
+    fn test_stop_words_by_lang_round_trip() {
+        let mut config = Config::default();
+        config.search.bm25_stop_words = vec!["todo".to_string()];
+        config.search.bm25_language = Some("es".to_string());
+        config.search.bm25_stop_words_by_lang.insert("es".to_string(), vec!["el".to_string(), "la".to_string()]);
+        let parsed: Config = toml::from_str(&toml::to_string(&config).unwrap()).unwrap();
+        assert_eq!(parsed.search.bm25_stop_words, vec!["todo".to_string()]);
+        assert_eq!(parsed.search.bm25_language.as_deref(), Some("es"));
+        assert_eq!(parsed.search.bm25_stop_words_by_lang["es"], vec!["el".to_string(), "la".to_string()]);
+    }
+
+    #[test]
+    fn test_load_reports_each_config_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.toml");
+        match Config::load(Some(missing.to_str().unwrap())) {
+            Err(ConfigError::FileNotFound { file }) => assert_eq!(file, missing),
+            other => panic!("expected FileNotFound, got {:?}", other),
+        }
+
+        let malformed = dir.path().join("malformed.toml");
+        std::fs::write(&malformed, "[search\nmax_results = ").unwrap();
+        match Config::from_file(malformed.to_str().unwrap()) {
+            Err(ConfigError::ParseError { file, .. }) => assert_eq!(file, malformed),
+            other => panic!("expected ParseError, got {:?}", other),
+        }
+
+        let mut config = Config::default();
+        config.storage.batch_size = 0;
+        match config.validate() {
+            Err(ConfigError::ValidationFailed { field, .. }) => assert_eq!(field, "storage.batch_size"),
+            other => panic!("expected ValidationFailed, got {:?}", other),
+        }
+
+        // Converts into anyhow for callers that don't match on it
+        let error: anyhow::Error = config.validate().unwrap_err().into();
+        assert!(matches!(error.downcast_ref::<ConfigError>(), Some(ConfigError::ValidationFailed { .. })));
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        let env = |pairs: &'static [(&'static str, &'static str)]| {
+            move |variable: &str| pairs.iter().find(|(name, _)| *name == variable).map(|(_, value)| value.to_string())
+        };
+
+        let mut config = Config::default();
+        config
+            .apply_env_overrides(env(&[("EMBED_SEARCH_BACKEND", "bm25"), ("EMBED_SEARCH_MAX_RESULTS", " 7")]))
+            .unwrap();
+        assert_eq!(config.search.backend, SearchBackend::Bm25Only);
+        assert_eq!(config.search.max_results, 7);
+
+        match config.apply_env_overrides(env(&[("EMBED_SEARCH_INDEX_CONCURRENCY", "many")])) {
+            Err(ConfigError::EnvParseError { variable, value, .. }) => {
+                assert_eq!((variable.as_str(), value.as_str()), ("EMBED_SEARCH_INDEX_CONCURRENCY", "many"));
+            }
+            other => panic!("expected EnvParseError, got {:?}", other),
+        }
+        match config.apply_env_overrides(env(&[("EMBED_SEARCH_STORAGE_BACKEND", "sqlite")])) {
+            Err(ConfigError::EnvParseError { variable, .. }) => assert_eq!(variable, "EMBED_SEARCH_STORAGE_BACKEND"),
+            other => panic!("expected EnvParseError, got {:?}", other),
+        }
+    }
+
+    /// `Config::init` swaps the process-wide config, so tests installing one take turns
+    static INSTALL_LOCK: parking_lot::Mutex<()> = parking_lot::Mutex::new(());
+
+    #[test]
+    fn test_reload_installs_the_edited_file() {
+        let _installing = INSTALL_LOCK.lock();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let path = path.to_str().unwrap();
+        let mut config = Config::default();
+        config.search.max_results = 5;
+        config.save(path).unwrap();
+
+        Config::init(Some(path)).unwrap();
+        assert_eq!(Config::get().search.max_results, 5);
+        let mut installed = Config::subscribe();
+
+        config.search.max_results = 9;
+        config.save(path).unwrap();
+        Config::reload().unwrap();
+        assert_eq!(Config::get().search.max_results, 9);
+        assert!(installed.has_changed().unwrap());
+        assert_eq!(installed.borrow_and_update().search.max_results, 9);
+
+        // A bad edit is rejected and the live config kept
+        config.search.max_results = 0;
+        config.save(path).unwrap();
+        assert!(matches!(Config::reload(), Err(ConfigError::ValidationFailed { .. })));
+        assert_eq!(Config::get().search.max_results, 9);
+        assert!(!installed.has_changed().unwrap());
+    }
+
+    #[test]
+    fn test_reloader_installs_the_file_once_it_changes() {
+        let _installing = INSTALL_LOCK.lock();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let path = path.to_str().unwrap();
+        let mut config = Config::default();
+        config.search.max_results = 5;
+        config.save(path).unwrap();
+        Config::init(Some(path)).unwrap();
+
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let mut installed = Config::subscribe();
+            let reloader = Config::spawn_reloader(Duration::from_millis(10));
+            config.search.max_results = 9;
+            config.save(path).unwrap();
+            // Filesystems with coarse timestamps could otherwise keep the old time
+            let later = std::time::SystemTime::now() + Duration::from_secs(5);
+            std::fs::File::options().write(true).open(path).unwrap().set_modified(later).unwrap();
+
+            tokio::time::timeout(Duration::from_secs(5), installed.changed()).await.unwrap().unwrap();
+            assert_eq!(installed.borrow_and_update().search.max_results, 9);
+            assert_eq!(Config::get().search.max_results, 9);
+            reloader.abort();
+        });
+    }
+}
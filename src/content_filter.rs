@@ -0,0 +1,164 @@
+// Index-time filtering of source files down to their code or their comments
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Key under which `HybridSearch` records the filter a file was indexed with, so a
+/// `MetadataFilter` can scope searches of a mixed index
+pub const CONTENT_FILTER_METADATA_KEY: &str = "content_filter";
+
+/// Which part of a source file is indexed. Comments are found with lightweight
+/// per-language rules (see `CommentSyntax`); files in a language without known comment
+/// syntax, such as markdown, are indexed whole whatever the filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentFilter {
+    /// Code and comments alike
+    #[default]
+    All,
+    /// Code with comments and docstrings removed
+    CodeOnly,
+    /// Comments and docstrings only
+    CommentsOnly,
+}
+
+impl ContentFilter {
+    /// The name used in config files and in `CONTENT_FILTER_METADATA_KEY` metadata
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContentFilter::All => "all",
+            ContentFilter::CodeOnly => "code_only",
+            ContentFilter::CommentsOnly => "comments_only",
+        }
+    }
+
+    /// The part of `content` this filter keeps. Line breaks are kept either way, so
+    /// line numbers in the result match the file.
+    pub fn apply(&self, content: &str, file_path: &str) -> String {
+        let syntax = match (self, CommentSyntax::for_path(file_path)) {
+            (ContentFilter::All, _) | (_, None) => return content.to_string(),
+            (_, Some(syntax)) => syntax,
+        };
+        let (code, comments) = syntax.split(content);
+        match self {
+            ContentFilter::CommentsOnly => comments,
+            _ => code,
+        }
+    }
+}
+
+/// How comments and string literals are written in a language. Strings are tracked
+/// only so comment markers inside them are not mistaken for comments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommentSyntax {
+    pub line: &'static [&'static str],
+    /// Opening and closing delimiters of block comments; Python's triple-quoted strings
+    /// are counted as docstrings here
+    pub blocks: &'static [(&'static str, &'static str)],
+    pub quotes: &'static [char],
+}
+
+const C_LIKE: CommentSyntax = CommentSyntax { line: &["//"], blocks: &[("/*", "*/")], quotes: &['"'] };
+const JAVASCRIPT: CommentSyntax = CommentSyntax { line: &["//"], blocks: &[("/*", "*/")], quotes: &['"', '\'', '`'] };
+const PYTHON: CommentSyntax = CommentSyntax { line: &["#"], blocks: &[("\"\"\"", "\"\"\""), ("'''", "'''")], quotes: &['"', '\''] };
+const HASH: CommentSyntax = CommentSyntax { line: &["#"], blocks: &[], quotes: &['"', '\''] };
+const SQL: CommentSyntax = CommentSyntax { line: &["--"], blocks: &[("/*", "*/")], quotes: &['\'', '"'] };
+const LUA: CommentSyntax = CommentSyntax { line: &["--"], blocks: &[("--[[", "]]")], quotes: &['\'', '"'] };
+
+impl CommentSyntax {
+    /// Comment syntax of the file's language, by extension
+    pub fn for_path(file_path: &str) -> Option<Self> {
+        let ext = Path::new(file_path).extension()?.to_str()?.to_lowercase();
+        match ext.as_str() {
+            "rs" | "c" | "h" | "cc" | "cpp" | "cxx" | "hpp" | "java" | "go" | "cs" | "swift" | "kt" | "scala" | "dart" => Some(C_LIKE),
+            "js" | "jsx" | "mjs" | "ts" | "tsx" => Some(JAVASCRIPT),
+            "py" | "pyi" => Some(PYTHON),
+            "rb" | "sh" | "bash" | "zsh" | "r" | "pl" | "yaml" | "yml" | "toml" => Some(HASH),
+            "sql" => Some(SQL),
+            "lua" => Some(LUA),
+            _ => None,
+        }
+    }
+
+    /// `content` split into its code and its comments, each keeping every line break
+    pub fn split(&self, content: &str) -> (String, String) {
+        let mut code = String::with_capacity(content.len());
+        let mut comments = String::new();
+        let mut rest = content;
+        while let Some(c) = rest.chars().next() {
+            let end = if let Some((open, close)) = self.blocks.iter().find(|(open, _)| rest.starts_with(open)) {
+                let end = rest[open.len()..].find(close).map_or(rest.len(), |i| open.len() + i + close.len());
+                comments.push_str(&rest[..end]);
+                code.extend(rest[..end].matches('\n'));
+                end
+            } else if self.line.iter().any(|marker| rest.starts_with(marker)) {
+                let end = rest.find('\n').unwrap_or(rest.len());
+                comments.push_str(&rest[..end]);
+                end
+            } else if self.quotes.contains(&c) {
+                let end = string_end(rest, c);
+                code.push_str(&rest[..end]);
+                comments.extend(rest[..end].matches('\n'));
+                end
+            } else {
+                code.push(c);
+                if c == '\n' {
+                    comments.push(c);
+                }
+                c.len_utf8()
+            };
+            rest = &rest[end..];
+        }
+        (code, comments)
+    }
+}
+
+/// Byte length of the string literal opening `rest` with `quote`, through its closing
+/// quote (or the end of `rest` if unterminated). Backslash escapes are skipped.
+fn string_end(rest: &str, quote: char) -> usize {
+    let mut chars = rest.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        if c == '\\' {
+            chars.next();
+        } else if c == quote {
+            return i + c.len_utf8();
+        }
+    }
+    rest.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<&str> {
+        text.lines().map(str::trim_end).collect()
+    }
+
+    #[test]
+    fn test_rust_line_and_block_comments() {
+        let source = "/* License header */\nfn main() { // entry point\n    let url = \"http://example.com\";\n}\n";
+        let code = ContentFilter::CodeOnly.apply(source, "src/main.rs");
+        assert_eq!(lines(&code), ["", "fn main() {", "    let url = \"http://example.com\";", "}"]);
+        let comments = ContentFilter::CommentsOnly.apply(source, "src/main.rs");
+        assert_eq!(lines(&comments), ["/* License header */", "// entry point", "", ""]);
+        assert_eq!(ContentFilter::All.apply(source, "src/main.rs"), source);
+    }
+
+    #[test]
+    fn test_python_hash_comments_and_docstrings() {
+        let source = "def load(path):\n    \"\"\"Read the settings\n    file.\"\"\"\n    return open(path)  # caller closes\nprint(\"# not a comment\")\n";
+        let code = ContentFilter::CodeOnly.apply(source, "settings.py");
+        assert_eq!(lines(&code), ["def load(path):", "", "", "    return open(path)", "print(\"# not a comment\")"]);
+        let comments = ContentFilter::CommentsOnly.apply(source, "settings.py");
+        assert_eq!(lines(&comments), ["", "\"\"\"Read the settings", "    file.\"\"\"", "# caller closes", ""]);
+    }
+
+    #[test]
+    fn test_files_without_comment_syntax_pass_through() {
+        let notes = "# Heading\nSome prose.";
+        assert_eq!(ContentFilter::CodeOnly.apply(notes, "README.md"), notes);
+        assert_eq!(ContentFilter::CommentsOnly.apply(notes, "README.md"), notes);
+        assert_eq!(serde_json::from_str::<ContentFilter>("\"comments_only\"").unwrap(), ContentFilter::CommentsOnly);
+    }
+}
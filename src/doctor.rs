@@ -0,0 +1,368 @@
+// Environment checks for `embed-search doctor`
+
+use anyhow::{bail, Result};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use crate::config::{Config, SemanticMode};
+use crate::gguf_embedder::{CODE_MODEL_PATH, TEXT_MODEL_PATH};
+
+/// Below this much free space on the index volume the check fails
+pub const MIN_FREE_DISK_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Below this much free space the check passes with a warning
+pub const LOW_FREE_DISK_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// Outcome of a single check; only `Fail` makes the report fail
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckStatus::Pass => write!(f, "PASS"),
+            CheckStatus::Warn => write!(f, "WARN"),
+            CheckStatus::Fail => write!(f, "FAIL"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// What to do about a warning or failure
+    pub remedy: Option<String>,
+}
+
+impl Check {
+    fn pass(name: &str, detail: String) -> Self {
+        Self { name: name.to_string(), status: CheckStatus::Pass, detail, remedy: None }
+    }
+
+    fn problem(name: &str, status: CheckStatus, detail: String, remedy: String) -> Self {
+        Self { name: name.to_string(), status, detail, remedy: Some(remedy) }
+    }
+}
+
+/// Every check that ran, in order
+#[derive(Debug, Clone, Default)]
+pub struct DoctorReport {
+    pub checks: Vec<Check>,
+}
+
+impl DoctorReport {
+    /// Whether no check failed; warnings don't count
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.status != CheckStatus::Fail)
+    }
+}
+
+impl fmt::Display for DoctorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            writeln!(f, "[{}] {}: {}", check.status, check.name, check.detail)?;
+            if let Some(remedy) = &check.remedy {
+                writeln!(f, "       fix: {}", remedy)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check the config at `config_path` (defaults when `None`), the GGUF models, and that
+/// `db_path` and the BM25 index location are writable with enough free space. A config
+/// that doesn't load is reported and the remaining checks run against the defaults.
+pub fn run(config_path: Option<&Path>, db_path: &Path) -> DoctorReport {
+    let mut report = DoctorReport::default();
+
+    let config = match config_path {
+        None => Config::default(),
+        Some(path) => match Config::from_file(&path.to_string_lossy()) {
+            Ok(config) => config,
+            Err(e) => {
+                report.checks.push(Check::problem(
+                    "config",
+                    CheckStatus::Fail,
+                    format!("{} does not load: {}", path.display(), e),
+                    "Fix the TOML syntax or field types, or drop --config to use the defaults".to_string(),
+                ));
+                Config::default()
+            }
+        },
+    };
+    if report.checks.is_empty() {
+        report.checks.push(match config.validate() {
+            Ok(()) => Check::pass("config", config_path.map_or("defaults".to_string(), |p| p.display().to_string())),
+            Err(e) => Check::problem(
+                "config",
+                CheckStatus::Fail,
+                e.to_string(),
+                "Change the named field in the config file".to_string(),
+            ),
+        });
+    }
+
+    // Without the models search still runs keyword-only unless they are required
+    let model_severity = match config.search.semantic_mode {
+        SemanticMode::Required => CheckStatus::Fail,
+        SemanticMode::Optional | SemanticMode::Disabled => CheckStatus::Warn,
+    };
+    if config.search.semantic_mode != SemanticMode::Disabled {
+        for (name, path) in [("text model", TEXT_MODEL_PATH), ("code model", CODE_MODEL_PATH)] {
+            report.checks.push(check_model(name, Path::new(path), config.embedding_dimensions, model_severity));
+        }
+    }
+
+    report.checks.push(check_writable("db path", db_path));
+    if let Some(bm25_dir) = config.storage.bm25_index_path.as_deref().and_then(Path::parent) {
+        report.checks.push(check_writable("bm25 index path", bm25_dir));
+    }
+    report.checks.push(check_disk_space(db_path));
+
+    report
+}
+
+fn check_model(name: &str, path: &Path, expected_dimension: usize, severity: CheckStatus) -> Check {
+    let remedy = format!(
+        "Download {} into {}, or set search.semantic_mode = \"optional\" to search by keyword only",
+        path.file_name().map_or_else(String::new, |f| f.to_string_lossy().into_owned()),
+        path.parent().map_or_else(String::new, |p| p.display().to_string()),
+    );
+    if !path.is_file() {
+        return Check::problem(name, severity, format!("{} not found", path.display()), remedy);
+    }
+    match gguf_embedding_length(path) {
+        Ok(dimension) if dimension == expected_dimension => {
+            Check::pass(name, format!("{} ({} dimensions)", path.display(), dimension))
+        }
+        Ok(dimension) => Check::problem(
+            name,
+            severity,
+            format!("{} has {} dimensions, expected {}", path.display(), dimension, expected_dimension),
+            remedy,
+        ),
+        Err(e) => Check::problem(name, severity, format!("{} is unreadable: {}", path.display(), e), remedy),
+    }
+}
+
+/// `dir` itself, or for a location that doesn't exist yet the ancestor it would be created in
+fn nearest_existing(dir: &Path) -> Option<PathBuf> {
+    let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    dir.ancestors().find(|p| p.exists()).map(Path::to_path_buf)
+}
+
+fn check_writable(name: &str, dir: &Path) -> Check {
+    let remedy = format!("Create {} or fix its permissions, or run from a directory you can write to", dir.display());
+    let Some(existing) = nearest_existing(dir) else {
+        return Check::problem(name, CheckStatus::Fail, format!("{} has no existing parent", dir.display()), remedy);
+    };
+    if !existing.is_dir() {
+        return Check::problem(name, CheckStatus::Fail, format!("{} is not a directory", existing.display()), remedy);
+    }
+    match tempfile::tempfile_in(&existing) {
+        Ok(_) => Check::pass(name, format!("{} is writable", dir.display())),
+        Err(e) => Check::problem(name, CheckStatus::Fail, format!("cannot write to {}: {}", existing.display(), e), remedy),
+    }
+}
+
+fn check_disk_space(dir: &Path) -> Check {
+    let name = "disk space";
+    let Some(existing) = nearest_existing(dir) else {
+        return Check::problem(name, CheckStatus::Warn, format!("cannot locate {}", dir.display()), "Check the db path".to_string());
+    };
+    let available = match fs4::available_space(&existing) {
+        Ok(available) => available,
+        Err(e) => {
+            return Check::problem(
+                name,
+                CheckStatus::Warn,
+                format!("cannot query free space for {}: {}", existing.display(), e),
+                "Check free space on that volume manually".to_string(),
+            )
+        }
+    };
+    let detail = format!("{} MiB free at {}", available / (1024 * 1024), existing.display());
+    let remedy = "Free up space on that volume or move the index elsewhere".to_string();
+    if available < MIN_FREE_DISK_BYTES {
+        Check::problem(name, CheckStatus::Fail, detail, remedy)
+    } else if available < LOW_FREE_DISK_BYTES {
+        Check::problem(name, CheckStatus::Warn, detail, remedy)
+    } else {
+        Check::pass(name, detail)
+    }
+}
+
+// GGUF metadata value types
+const GGUF_TYPE_UINT32: u32 = 4;
+const GGUF_TYPE_STRING: u32 = 8;
+const GGUF_TYPE_ARRAY: u32 = 9;
+const GGUF_TYPE_UINT64: u32 = 10;
+
+/// Read `<architecture>.embedding_length` from a GGUF (v2+) header without loading the model
+pub fn gguf_embedding_length(path: &Path) -> Result<usize> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != b"GGUF" {
+        bail!("not a GGUF file");
+    }
+    let version = read_u32(&mut reader)?;
+    if version < 2 {
+        bail!("GGUF version {} is not supported", version);
+    }
+    let _tensor_count = read_u64(&mut reader)?;
+    let metadata_count = read_u64(&mut reader)?;
+
+    for _ in 0..metadata_count {
+        let key = read_string(&mut reader)?;
+        let value_type = read_u32(&mut reader)?;
+        if key.ends_with(".embedding_length") {
+            return match value_type {
+                GGUF_TYPE_UINT32 => Ok(read_u32(&mut reader)? as usize),
+                GGUF_TYPE_UINT64 => Ok(read_u64(&mut reader)? as usize),
+                other => bail!("{} has unexpected type {}", key, other),
+            };
+        }
+        skip_value(&mut reader, value_type)?;
+    }
+    bail!("no embedding_length in the GGUF metadata")
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_string(reader: &mut impl Read) -> Result<String> {
+    let len = read_u64(reader)?;
+    // Metadata keys are short; anything huge means a corrupt header
+    if len > 1 << 16 {
+        bail!("metadata key of {} bytes", len);
+    }
+    let mut buf = vec![0u8; len as usize];
+    reader.read_exact(&mut buf)?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+fn skip_bytes(reader: &mut impl Read, n: u64) -> Result<()> {
+    if io::copy(&mut reader.take(n), &mut io::sink())? != n {
+        bail!("truncated GGUF header");
+    }
+    Ok(())
+}
+
+fn skip_value(reader: &mut impl Read, value_type: u32) -> Result<()> {
+    match value_type {
+        GGUF_TYPE_STRING => {
+            let len = read_u64(reader)?;
+            skip_bytes(reader, len)
+        }
+        GGUF_TYPE_ARRAY => {
+            let element_type = read_u32(reader)?;
+            let count = read_u64(reader)?;
+            match fixed_size(element_type) {
+                Some(size) => skip_bytes(reader, size * count),
+                None => (0..count).try_for_each(|_| skip_value(reader, element_type)),
+            }
+        }
+        other => match fixed_size(other) {
+            Some(size) => skip_bytes(reader, size),
+            None => bail!("unknown GGUF value type {}", other),
+        },
+    }
+}
+
+/// Byte width of scalar value types
+fn fixed_size(value_type: u32) -> Option<u64> {
+    match value_type {
+        0 | 1 | 7 => Some(1),  // u8, i8, bool
+        2 | 3 => Some(2),      // u16, i16
+        4..=6 => Some(4),      // u32, i32, f32
+        10..=12 => Some(8),    // u64, i64, f64
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn gguf_string(out: &mut Vec<u8>, s: &str) {
+        out.extend((s.len() as u64).to_le_bytes());
+        out.extend(s.as_bytes());
+    }
+
+    /// Header with an architecture string, a string array and the embedding length
+    fn write_gguf(path: &Path, dimension: u32) {
+        let mut out = b"GGUF".to_vec();
+        out.extend(3u32.to_le_bytes());
+        out.extend(0u64.to_le_bytes());
+        out.extend(3u64.to_le_bytes());
+        gguf_string(&mut out, "general.architecture");
+        out.extend(GGUF_TYPE_STRING.to_le_bytes());
+        gguf_string(&mut out, "nomic-bert");
+        gguf_string(&mut out, "tokenizer.ggml.tokens");
+        out.extend(GGUF_TYPE_ARRAY.to_le_bytes());
+        out.extend(GGUF_TYPE_STRING.to_le_bytes());
+        out.extend(2u64.to_le_bytes());
+        gguf_string(&mut out, "[CLS]");
+        gguf_string(&mut out, "hello");
+        gguf_string(&mut out, "nomic-bert.embedding_length");
+        out.extend(GGUF_TYPE_UINT32.to_le_bytes());
+        out.extend(dimension.to_le_bytes());
+        std::fs::write(path, out).unwrap();
+    }
+
+    #[test]
+    fn test_gguf_embedding_length_skips_other_metadata() -> Result<()> {
+        let dir = tempdir()?;
+        let model = dir.path().join("model.gguf");
+        write_gguf(&model, 768);
+        assert_eq!(gguf_embedding_length(&model)?, 768);
+        assert_eq!(check_model("text model", &model, 768, CheckStatus::Fail).status, CheckStatus::Pass);
+
+        write_gguf(&model, 384);
+        let check = check_model("text model", &model, 768, CheckStatus::Warn);
+        assert_eq!(check.status, CheckStatus::Warn);
+        assert!(check.detail.contains("384"));
+
+        std::fs::write(&model, b"not a model")?;
+        assert!(gguf_embedding_length(&model).is_err());
+        let missing = check_model("code model", &dir.path().join("missing.gguf"), 768, CheckStatus::Fail);
+        assert_eq!(missing.status, CheckStatus::Fail);
+        assert!(missing.remedy.unwrap().contains("missing.gguf"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_unloadable_config_fails_the_report() -> Result<()> {
+        let dir = tempdir()?;
+        let config_path = dir.path().join("bad.toml");
+        std::fs::write(&config_path, "[storage\ndb_path = 1")?;
+        let report = run(Some(&config_path), &dir.path().join("db"));
+        assert!(!report.passed());
+        assert_eq!(report.checks[0].name, "config");
+        assert_eq!(report.checks[0].status, CheckStatus::Fail);
+        // Not yet created, but its parent is writable
+        let db = report.checks.iter().find(|c| c.name == "db path").unwrap();
+        assert_eq!(db.status, CheckStatus::Pass);
+        assert!(report.to_string().contains("[FAIL] config"));
+        Ok(())
+    }
+}
@@ -0,0 +1,285 @@
+// Embedder abstraction shared by the search front ends
+
+use anyhow::Result;
+
+use crate::embedding_prefixes::EmbeddingTask;
+use crate::error::EmbeddingError;
+use crate::gguf_embedder::{l2_normalize, GGUFEmbedder, NOMIC_EMBEDDING_DIMENSION};
+
+/// Text-to-vector model used by `HybridSearch` and `AdvancedHybridSearch`
+pub trait Embedder: Send + Sync {
+    /// Embed a single text for the given task
+    fn embed(&self, text: &str, task: EmbeddingTask) -> Result<Vec<f32>>;
+
+    /// Embed several texts, in input order
+    fn embed_batch(&self, texts: Vec<String>, task: EmbeddingTask) -> Result<Vec<Vec<f32>>> {
+        texts.iter().map(|text| self.embed(text, task)).collect()
+    }
+
+    /// Length of the vectors this embedder produces
+    fn dimension(&self) -> usize;
+
+    /// Drop any cached embeddings; a no-op for embedders that don't cache
+    fn clear_cache(&self) {}
+}
+
+impl Embedder for GGUFEmbedder {
+    fn embed(&self, text: &str, task: EmbeddingTask) -> Result<Vec<f32>> {
+        GGUFEmbedder::embed(self, text, task)
+    }
+
+    fn embed_batch(&self, texts: Vec<String>, task: EmbeddingTask) -> Result<Vec<Vec<f32>>> {
+        GGUFEmbedder::embed_batch(self, texts, task)
+    }
+
+    fn dimension(&self) -> usize {
+        GGUFEmbedder::dimension(self)
+    }
+
+    fn clear_cache(&self) {
+        GGUFEmbedder::clear_cache(self)
+    }
+}
+
+/// Model-free embedder that hashes lowercase word tokens into a fixed number of
+/// signed buckets and L2-normalizes the result. Texts sharing words land close
+/// together, which is enough for tests and CI where no GGUF model is available.
+/// The task is ignored, so queries and documents share one space.
+///
+/// There is no randomness: a vector depends only on the text, the dimension and the
+/// seed (0 unless set with `with_seed`), so it is bit-for-bit identical across runs,
+/// processes and machines.
+#[derive(Debug, Clone)]
+pub struct MinimalEmbedder {
+    dimension: usize,
+    seed: u64,
+}
+
+impl MinimalEmbedder {
+    /// Matches the nomic text model so stores built with either are interchangeable
+    pub const DEFAULT_DIMENSION: usize = NOMIC_EMBEDDING_DIMENSION;
+
+    /// Panics if `dimension` is 0
+    pub fn new(dimension: usize) -> Self {
+        assert!(dimension > 0, "MinimalEmbedder dimension must be greater than 0");
+        Self { dimension, seed: 0 }
+    }
+
+    /// `DEFAULT_DIMENSION` embedder whose token hashes are keyed by `seed`. Equal seeds
+    /// give identical vectors; seed 0 is the same as `default()`.
+    pub fn with_seed(seed: u64) -> Self {
+        Self { seed, ..Self::default() }
+    }
+}
+
+impl Default for MinimalEmbedder {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_DIMENSION)
+    }
+}
+
+impl Embedder for MinimalEmbedder {
+    fn embed(&self, text: &str, _task: EmbeddingTask) -> Result<Vec<f32>> {
+        let mut embedding = vec![0.0f32; self.dimension];
+        for token in text
+            .split(|c: char| !c.is_alphanumeric() && c != '_')
+            .filter(|t| !t.is_empty())
+        {
+            let hash = fnv1a(self.seed, token.to_lowercase().as_bytes());
+            let bucket = (hash % self.dimension as u64) as usize;
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            embedding[bucket] += sign;
+        }
+        l2_normalize(&mut embedding);
+        Ok(embedding)
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+}
+
+/// How `MultiEmbedder` merges the vectors of its models
+#[derive(Debug, Clone, PartialEq)]
+pub enum Combination {
+    /// Models' vectors end to end; the dimension is the sum of theirs
+    Concat,
+    /// Per-model weighted sum of vectors of one shared dimension
+    WeightedAverage { weights: Vec<f32> },
+}
+
+/// Several models, e.g. a code and a general GGUF model, embedding every text and
+/// combined into one L2-normalized vector by a `Combination`. Usable wherever a single
+/// `Embedder` is, with `dimension` reporting the combined length.
+pub struct MultiEmbedder {
+    embedders: Vec<Box<dyn Embedder>>,
+    combination: Combination,
+}
+
+impl MultiEmbedder {
+    /// Fails without embedders, with a weight per embedder missing or negative, or with
+    /// `WeightedAverage` over models of different dimensions
+    pub fn new(embedders: Vec<Box<dyn Embedder>>, combination: Combination) -> Result<Self> {
+        let invalid = |message: String| EmbeddingError::InvalidInput { message, input_length: None };
+        if embedders.is_empty() {
+            return Err(invalid("MultiEmbedder needs at least one embedder".to_string()).into());
+        }
+        if let Combination::WeightedAverage { weights } = &combination {
+            if weights.len() != embedders.len() {
+                return Err(invalid(format!("{} weights for {} embedders", weights.len(), embedders.len())).into());
+            }
+            if !weights.iter().all(|w| w.is_finite() && *w >= 0.0) || weights.iter().sum::<f32>() <= 0.0 {
+                return Err(invalid(format!("weights {:?} must be non-negative with a positive sum", weights)).into());
+            }
+            let expected = embedders[0].dimension();
+            if let Some(other) = embedders.iter().find(|e| e.dimension() != expected) {
+                return Err(EmbeddingError::DimensionMismatch { expected, actual: other.dimension() }.into());
+            }
+        }
+        Ok(Self { embedders, combination })
+    }
+
+    fn combine(&self, vectors: Vec<Vec<f32>>) -> Result<Vec<f32>> {
+        for (embedder, vector) in self.embedders.iter().zip(&vectors) {
+            if vector.len() != embedder.dimension() {
+                return Err(EmbeddingError::DimensionMismatch { expected: embedder.dimension(), actual: vector.len() }.into());
+            }
+        }
+        let mut combined = match &self.combination {
+            Combination::Concat => vectors.concat(),
+            Combination::WeightedAverage { weights } => {
+                let mut sum = vec![0.0f32; self.dimension()];
+                for (vector, weight) in vectors.iter().zip(weights) {
+                    for (total, x) in sum.iter_mut().zip(vector) {
+                        *total += weight * x;
+                    }
+                }
+                sum
+            }
+        };
+        l2_normalize(&mut combined);
+        Ok(combined)
+    }
+}
+
+impl Embedder for MultiEmbedder {
+    fn embed(&self, text: &str, task: EmbeddingTask) -> Result<Vec<f32>> {
+        let vectors = self.embedders.iter().map(|embedder| embedder.embed(text, task)).collect::<Result<Vec<_>>>()?;
+        self.combine(vectors)
+    }
+
+    fn embed_batch(&self, texts: Vec<String>, task: EmbeddingTask) -> Result<Vec<Vec<f32>>> {
+        // Per model batches, then regrouped per text
+        let mut per_model = Vec::with_capacity(self.embedders.len());
+        for embedder in &self.embedders {
+            per_model.push(embedder.embed_batch(texts.clone(), task)?.into_iter());
+        }
+        (0..texts.len())
+            .map(|_| {
+                let vectors = per_model
+                    .iter_mut()
+                    .map(|batch| batch.next().ok_or_else(|| anyhow::anyhow!("embedder returned fewer vectors than texts")))
+                    .collect::<Result<Vec<_>>>()?;
+                self.combine(vectors)
+            })
+            .collect()
+    }
+
+    fn dimension(&self) -> usize {
+        match self.combination {
+            Combination::Concat => self.embedders.iter().map(|embedder| embedder.dimension()).sum(),
+            Combination::WeightedAverage { .. } => self.embedders[0].dimension(),
+        }
+    }
+
+    fn clear_cache(&self) {
+        for embedder in &self.embedders {
+            embedder.clear_cache();
+        }
+    }
+}
+
+/// 64-bit FNV-1a with `seed` folded into the offset basis, stable across platforms
+/// and Rust versions
+pub(crate) fn fnv1a(seed: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325 ^ seed, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gguf_embedder::l2_norm;
+    use crate::simple_storage::cosine_similarity;
+
+    #[test]
+    fn test_minimal_embedder_is_deterministic_and_normalized() -> Result<()> {
+        let embedder = MinimalEmbedder::default();
+        let a = embedder.embed("fn parse_config(path: &str)", EmbeddingTask::CodeDefinition)?;
+        let b = embedder.embed("fn parse_config(path: &str)", EmbeddingTask::SearchQuery)?;
+        assert_eq!(a.len(), MinimalEmbedder::DEFAULT_DIMENSION);
+        assert_eq!(a, b);
+        assert!((l2_norm(&a) - 1.0).abs() < 1e-5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_seeded_embedders_are_bit_identical() -> Result<()> {
+        let text = "fn parse_config(path: &str) -> Config";
+        let bits = |embedder: &MinimalEmbedder| -> Result<Vec<u32>> {
+            Ok(embedder.embed(text, EmbeddingTask::SearchDocument)?.iter().map(|x| x.to_bits()).collect())
+        };
+        assert_eq!(bits(&MinimalEmbedder::with_seed(42))?, bits(&MinimalEmbedder::with_seed(42))?);
+        assert_eq!(bits(&MinimalEmbedder::with_seed(0))?, bits(&MinimalEmbedder::default())?);
+        assert_ne!(bits(&MinimalEmbedder::with_seed(42))?, bits(&MinimalEmbedder::with_seed(7))?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_minimal_embedder_shared_words_score_higher() -> Result<()> {
+        let embedder = MinimalEmbedder::new(256);
+        let query = embedder.embed("parse config", EmbeddingTask::SearchQuery)?;
+        let related = embedder.embed("fn parse_config() reads the config file", EmbeddingTask::SearchDocument)?;
+        let unrelated = embedder.embed("render the html template", EmbeddingTask::SearchDocument)?;
+        assert!(cosine_similarity(&query, &related) > cosine_similarity(&query, &unrelated));
+        Ok(())
+    }
+
+    #[test]
+    fn test_embed_batch_default_keeps_order() -> Result<()> {
+        let embedder: Box<dyn Embedder> = Box::new(MinimalEmbedder::new(32));
+        let texts = vec!["alpha".to_string(), "beta".to_string()];
+        let batch = embedder.embed_batch(texts.clone(), EmbeddingTask::SearchDocument)?;
+        assert_eq!(batch[0], embedder.embed(&texts[0], EmbeddingTask::SearchDocument)?);
+        assert_eq!(batch[1], embedder.embed(&texts[1], EmbeddingTask::SearchDocument)?);
+        assert_eq!(embedder.dimension(), 32);
+        Ok(())
+    }
+
+    #[test]
+    fn test_multi_embedder_dimensions() -> Result<()> {
+        // Two 384-dim models that disagree, standing in for a code and a general model
+        let models = || -> Vec<Box<dyn Embedder>> {
+            vec![Box::new(MinimalEmbedder::new(384)), Box::new(MinimalEmbedder { dimension: 384, seed: 7 })]
+        };
+        let text = "fn parse_config(path: &str)";
+
+        let concat = MultiEmbedder::new(models(), Combination::Concat)?;
+        assert_eq!(concat.dimension(), 768);
+        let vector = concat.embed(text, EmbeddingTask::SearchQuery)?;
+        assert_eq!(vector.len(), 768);
+        assert!((l2_norm(&vector) - 1.0).abs() < 1e-5);
+
+        let average = MultiEmbedder::new(models(), Combination::WeightedAverage { weights: vec![0.7, 0.3] })?;
+        assert_eq!(average.dimension(), 384);
+        let batch = average.embed_batch(vec![text.to_string(), "render html".to_string()], EmbeddingTask::SearchDocument)?;
+        assert_eq!(batch[0], average.embed(text, EmbeddingTask::SearchDocument)?);
+        assert!(batch.iter().all(|vector| vector.len() == 384));
+
+        let mixed: Vec<Box<dyn Embedder>> = vec![Box::new(MinimalEmbedder::new(384)), Box::new(MinimalEmbedder::new(768))];
+        assert!(MultiEmbedder::new(mixed, Combination::WeightedAverage { weights: vec![0.5, 0.5] }).is_err());
+        assert!(MultiEmbedder::new(models(), Combination::WeightedAverage { weights: vec![1.0] }).is_err());
+        Ok(())
+    }
+}
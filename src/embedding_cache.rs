@@ -8,15 +8,64 @@ use std::hash::{Hash, Hasher};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use crate::config::StorageConfig;
+
 #[derive(Debug, Clone)]
 pub struct CachedEmbedding {
     pub embedding: Vec<f32>,
     pub timestamp: Instant,
+    /// Value of the cache's access clock when this entry was last read or written
+    pub last_used: u64,
+}
+
+impl CachedEmbedding {
+    /// Bytes counted against `max_bytes`: the vector payload only
+    pub fn size_bytes(&self) -> usize {
+        std::mem::size_of_val(self.embedding.as_slice())
+    }
+}
+
+/// Entries plus the bookkeeping that has to change under the same lock
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<u64, CachedEmbedding>,
+    bytes: usize,
+    clock: u64,
+    evicted_by_size: u64,
+    evicted_by_bytes: u64,
+    expired: u64,
+}
+
+impl CacheState {
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    fn remove(&mut self, key: u64) -> Option<CachedEmbedding> {
+        let removed = self.entries.remove(&key)?;
+        self.bytes -= removed.size_bytes();
+        Some(removed)
+    }
+
+    fn least_recently_used(&self) -> Option<u64> {
+        self.entries.iter().min_by_key(|(_, v)| v.last_used).map(|(&k, _)| k)
+    }
 }
 
+/// Why an entry left the cache before anyone asked for it to be cleared
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Eviction {
+    Size,
+    Bytes,
+}
+
+/// Least-recently-used cache of embeddings keyed by input text. Entries are evicted
+/// when either `max_size` entries or, if set, `max_bytes` of vector data is exceeded.
 pub struct EmbeddingCache {
-    cache: Arc<RwLock<HashMap<u64, CachedEmbedding>>>,
+    state: Arc<RwLock<CacheState>>,
     max_size: usize,
+    max_bytes: Option<usize>,
     ttl: Duration,
     hits: Arc<RwLock<u64>>,
     misses: Arc<RwLock<u64>>,
@@ -25,21 +74,35 @@ pub struct EmbeddingCache {
 impl EmbeddingCache {
     pub fn new(max_size: usize, ttl_seconds: u64) -> Self {
         Self {
-            cache: Arc::new(RwLock::new(HashMap::new())),
+            state: Arc::new(RwLock::new(CacheState::default())),
             max_size,
+            max_bytes: None,
             ttl: Duration::from_secs(ttl_seconds),
             hits: Arc::new(RwLock::new(0)),
             misses: Arc::new(RwLock::new(0)),
         }
     }
+
+    /// Entry and byte limits from `storage.cache_size` and `storage.embedding_cache_max_bytes`
+    pub fn from_config(storage: &StorageConfig, ttl_seconds: u64) -> Self {
+        Self::new(storage.cache_size, ttl_seconds).with_max_bytes(storage.embedding_cache_max_bytes)
+    }
+
+    /// Also cap the total size of cached vectors; `None` limits by entry count only
+    pub fn with_max_bytes(mut self, max_bytes: Option<usize>) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
     
     /// Get embedding from cache if available and not expired
     pub fn get(&self, text: &str) -> Option<Vec<f32>> {
         let key = self.compute_hash(text);
-        let cache = self.cache.read();
+        let mut state = self.state.write();
+        let now = state.tick();
         
-        if let Some(cached) = cache.get(&key) {
+        if let Some(cached) = state.entries.get_mut(&key) {
             if cached.timestamp.elapsed() < self.ttl {
+                cached.last_used = now;
                 *self.hits.write() += 1;
                 return Some(cached.embedding.clone());
             }
@@ -49,20 +112,46 @@ impl EmbeddingCache {
         None
     }
     
-    /// Store embedding in cache
+    /// Store embedding in cache, evicting least recently used entries until both
+    /// limits hold. A vector larger than `max_bytes` on its own is not cached.
     pub fn put(&self, text: &str, embedding: Vec<f32>) {
         let key = self.compute_hash(text);
-        let mut cache = self.cache.write();
-        
-        // Evict oldest entries if cache is full
-        if cache.len() >= self.max_size {
-            self.evict_oldest(&mut cache);
-        }
-        
-        cache.insert(key, CachedEmbedding {
+        let mut state = self.state.write();
+        let last_used = state.tick();
+        let entry = CachedEmbedding {
             embedding,
             timestamp: Instant::now(),
-        });
+            last_used,
+        };
+
+        // Replacing an entry frees its bytes before the limits are checked
+        state.remove(key);
+        if self.max_bytes.is_some_and(|max| entry.size_bytes() > max) {
+            return;
+        }
+
+        while let Some(reason) = self.over_limit(&state, entry.size_bytes()) {
+            let Some(oldest) = state.least_recently_used() else { break };
+            state.remove(oldest);
+            match reason {
+                Eviction::Size => state.evicted_by_size += 1,
+                Eviction::Bytes => state.evicted_by_bytes += 1,
+            }
+        }
+        
+        state.bytes += entry.size_bytes();
+        state.entries.insert(key, entry);
+    }
+
+    /// Which limit, if any, adding `incoming` bytes as one more entry would break
+    fn over_limit(&self, state: &CacheState, incoming: usize) -> Option<Eviction> {
+        if state.entries.len() >= self.max_size {
+            Some(Eviction::Size)
+        } else if self.max_bytes.is_some_and(|max| state.bytes + incoming > max) {
+            Some(Eviction::Bytes)
+        } else {
+            None
+        }
     }
     
     /// Batch get embeddings
@@ -90,10 +179,19 @@ impl EmbeddingCache {
     
     /// Clear expired entries
     pub fn clear_expired(&self) {
-        let mut cache = self.cache.write();
+        let mut state = self.state.write();
         let now = Instant::now();
+        let expired: Vec<u64> = state
+            .entries
+            .iter()
+            .filter(|(_, v)| now.duration_since(v.timestamp) >= self.ttl)
+            .map(|(&k, _)| k)
+            .collect();
         
-        cache.retain(|_, v| now.duration_since(v.timestamp) < self.ttl);
+        state.expired += expired.len() as u64;
+        for key in expired {
+            state.remove(key);
+        }
     }
     
     /// Get cache statistics
@@ -107,18 +205,24 @@ impl EmbeddingCache {
             0.0
         };
         
+        let state = self.state.read();
         CacheStats {
-            size: self.cache.read().len(),
+            size: state.entries.len(),
             max_size: self.max_size,
+            bytes: state.bytes,
+            max_bytes: self.max_bytes,
             hits,
             misses,
             hit_rate,
+            evictions_by_size: state.evicted_by_size,
+            evictions_by_bytes: state.evicted_by_bytes,
+            expired: state.expired,
         }
     }
     
-    /// Clear all cache entries
+    /// Clear all cache entries and reset the counters
     pub fn clear(&self) {
-        self.cache.write().clear();
+        *self.state.write() = CacheState::default();
         *self.hits.write() = 0;
         *self.misses.write() = 0;
     }
@@ -129,25 +233,24 @@ impl EmbeddingCache {
         text.hash(&mut hasher);
         hasher.finish()
     }
-    
-    fn evict_oldest(&self, cache: &mut HashMap<u64, CachedEmbedding>) {
-        // Find and remove the oldest entry
-        if let Some((&oldest_key, _)) = cache
-            .iter()
-            .min_by_key(|(_, v)| v.timestamp)
-        {
-            cache.remove(&oldest_key);
-        }
-    }
 }
 
 #[derive(Debug, Clone)]
 pub struct CacheStats {
     pub size: usize,
     pub max_size: usize,
+    /// Vector bytes currently held, as counted against `max_bytes`
+    pub bytes: usize,
+    pub max_bytes: Option<usize>,
     pub hits: u64,
     pub misses: u64,
     pub hit_rate: f64,
+    /// Entries evicted because the cache held `max_size` entries
+    pub evictions_by_size: u64,
+    /// Entries evicted to stay within `max_bytes`
+    pub evictions_by_bytes: u64,
+    /// Entries dropped by `clear_expired`
+    pub expired: u64,
 }
 
 /// Wrapper for embedder with caching
@@ -278,6 +381,41 @@ mod tests {
         assert_eq!(cache.get("text1"), None);
         assert_eq!(cache.get("text2"), Some(vec![0.2]));
         assert_eq!(cache.get("text3"), Some(vec![0.3]));
+        assert_eq!(cache.stats().evictions_by_size, 1);
+    }
+
+    #[test]
+    fn test_byte_budget_evicts_least_recently_used() {
+        // 768 f32s is 3072 bytes, so ten vectors fit in the budget
+        let vector = |i: usize| vec![i as f32; 768];
+        let cache = EmbeddingCache::new(1_000, 60).with_max_bytes(Some(10 * 3072));
+
+        for i in 0..10 {
+            cache.put(&format!("text{}", i), vector(i));
+        }
+        // Touch the oldest entry so text1 becomes the least recently used
+        assert!(cache.get("text0").is_some());
+        cache.put("text10", vector(10));
+        assert_eq!(cache.get("text1"), None);
+        assert!(cache.get("text0").is_some());
+
+        for i in 11..50 {
+            cache.put(&format!("text{}", i), vector(i));
+            assert!(cache.stats().bytes <= 10 * 3072);
+        }
+        assert_eq!(cache.get("text0"), None);
+        assert_eq!(cache.get("text49"), Some(vector(49)));
+
+        let stats = cache.stats();
+        assert_eq!(stats.size, 10);
+        assert_eq!(stats.bytes, 10 * 3072);
+        assert_eq!(stats.evictions_by_bytes, 40);
+        assert_eq!(stats.evictions_by_size, 0);
+
+        // Vectors over the whole budget are never cached
+        cache.put("huge", vec![0.0; 11 * 768]);
+        assert_eq!(cache.get("huge"), None);
+        assert_eq!(cache.stats().bytes, 10 * 3072);
     }
     
     #[test]
@@ -1,4 +1,6 @@
 use anyhow::Result;
+use std::collections::BTreeMap;
+use std::path::Path;
 
 /// Task types for nomic-embed-code model with correct prefixes
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -79,6 +81,22 @@ impl CodeFormatter {
         }
     }
 
+    /// `code` formatted for the language `languages` maps the file's extension to, so
+    /// each language reaches the embedder in its own wrapping. A language without a
+    /// built-in header is tagged with its name; an unmapped extension is left as is.
+    pub fn format_for_path(code: &str, file_path: &str, languages: &BTreeMap<String, String>) -> String {
+        let language = Path::new(file_path)
+            .extension()
+            .and_then(|ext| languages.get(&ext.to_string_lossy().to_lowercase()));
+        match language {
+            Some(language) => match Self::format_code(code, language) {
+                formatted if formatted == code => format!("[{}]\n{}", language, code),
+                formatted => formatted,
+            },
+            None => code.to_string(),
+        }
+    }
+
     /// Format code for definition task
     pub fn format_definition(code: &str, language: Option<&str>) -> String {
         match language {
@@ -96,6 +114,27 @@ impl CodeFormatter {
     }
 }
 
+/// Extension to language mapping `CodeFormatter::format_for_path` uses by default,
+/// covering the languages `CodeFormatter::detect_language` knows
+pub fn default_embedding_languages() -> BTreeMap<String, String> {
+    [
+        ("rs", "rust"),
+        ("py", "python"),
+        ("js", "javascript"),
+        ("ts", "typescript"),
+        ("go", "go"),
+        ("java", "java"),
+        ("cpp", "cpp"),
+        ("cc", "cpp"),
+        ("cxx", "cpp"),
+        ("c", "c"),
+        ("h", "c"),
+    ]
+    .into_iter()
+    .map(|(ext, language)| (ext.to_string(), language.to_string()))
+    .collect()
+}
+
 /// Batch processing utilities
 pub struct BatchProcessor;
 
@@ -167,6 +206,18 @@ mod tests {
         assert_eq!(py_formatted, "# Python\ndef main():");
     }
 
+    #[test]
+    fn test_format_for_path_follows_the_mapping() {
+        let mut languages = default_embedding_languages();
+        assert_eq!(CodeFormatter::format_for_path("x = 1", "lib/Util.PY", &languages), "# Python\nx = 1");
+        assert_eq!(CodeFormatter::format_for_path("x = 1", "notes.txt", &languages), "x = 1");
+
+        languages.insert("rb".to_string(), "ruby".to_string());
+        languages.remove("py");
+        assert_eq!(CodeFormatter::format_for_path("x = 1", "app.rb", &languages), "[ruby]\nx = 1");
+        assert_eq!(CodeFormatter::format_for_path("x = 1", "app.py", &languages), "x = 1");
+    }
+
     #[test]
     fn test_task_inference() {
         let definition = "fn calculate(x: i32) -> i32 { x * 2 }";
@@ -4,6 +4,8 @@
 use std::fmt;
 use std::error::Error as StdError;
 use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
 use thiserror::Error;
 
 /// Main error type for the embed-search system
@@ -183,6 +185,12 @@ pub enum StorageError {
         message: String,
         duration_ms: u64,
     },
+    
+    #[error("Dimension mismatch: expected {expected}, got {actual}")]
+    DimensionMismatch {
+        expected: usize,
+        actual: usize,
+    },
 }
 
 /// Embedding-specific error type
@@ -266,6 +274,100 @@ pub enum SearchError {
     CorruptedData {
         description: String,
     },
+    
+    #[error("Invalid parameter '{parameter}': {reason}")]
+    InvalidParameter {
+        parameter: String,
+        reason: String,
+    },
+    
+    #[error("Failed to read {path}: {reason}")]
+    FileRead {
+        path: String,
+        reason: String,
+    },
+    
+    #[error("{path} is not valid UTF-8: {reason}")]
+    FileDecode {
+        path: String,
+        reason: String,
+    },
+    
+    #[error("Failed to embed {path}: {reason}")]
+    FileEmbed {
+        path: String,
+        reason: String,
+    },
+    
+    #[error("Embedding dimension mismatch: configured for {expected}, but the model produces {actual}")]
+    DimensionMismatch {
+        expected: usize,
+        actual: usize,
+    },
+    
+    #[error("Search timed out after {timeout:?}")]
+    Timeout {
+        timeout: Duration,
+    },
+    
+    #[error("Index at {path} is open for writing elsewhere")]
+    IndexLocked {
+        path: String,
+    },
+    
+    #[error("Index at {path} was opened read-only")]
+    ReadOnly {
+        path: String,
+    },
+    
+    #[error("Resource exhausted: {resource} {reason}")]
+    ResourceExhausted {
+        resource: String,
+        reason: String,
+    },
+    
+    #[error("Query is empty")]
+    EmptyQuery,
+    
+    /// A vector or text store failure, kept whole so callers can match its kind
+    #[error("Storage error: {0}")]
+    Storage(#[from] StorageError),
+}
+
+/// Why `Config::load` or `Config::validate` rejected a configuration
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Config file {} not found", file.display())]
+    FileNotFound {
+        file: PathBuf,
+    },
+
+    #[error("Config file {} could not be read: {source}", file.display())]
+    ReadFailed {
+        file: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("Config file {} is not valid: {source}", file.display())]
+    ParseError {
+        file: PathBuf,
+        #[source]
+        source: toml::de::Error,
+    },
+
+    #[error("Invalid config: {field} {reason}")]
+    ValidationFailed {
+        field: String,
+        reason: String,
+    },
+
+    #[error("Environment variable {variable}={value:?} is invalid: {reason}")]
+    EnvParseError {
+        variable: String,
+        value: String,
+        reason: String,
+    },
 }
 
 /// Logging-specific error type
@@ -305,6 +407,15 @@ impl From<io::Error> for EmbedError {
     }
 }
 
+impl From<ConfigError> for EmbedError {
+    fn from(err: ConfigError) -> Self {
+        EmbedError::Configuration {
+            message: err.to_string(),
+            source: Some(Box::new(err)),
+        }
+    }
+}
+
 impl From<StorageError> for EmbedError {
     fn from(err: StorageError) -> Self {
         EmbedError::Storage {
@@ -539,6 +650,14 @@ mod tests {
         assert!(matches!(embed_err, EmbedError::Io { .. }));
     }
     
+    #[test]
+    fn test_storage_error_keeps_its_kind_as_search_error() {
+        let search_err: SearchError = StorageError::DimensionMismatch { expected: 768, actual: 384 }.into();
+        assert!(matches!(search_err, SearchError::Storage(StorageError::DimensionMismatch { expected: 768, actual: 384 })));
+        assert_eq!(search_err.to_string(), "Storage error: Dimension mismatch: expected 768, got 384");
+        assert!(std::error::Error::source(&search_err).is_some());
+    }
+    
     #[test]
     fn test_safe_unwrap() {
         let some_value: Option<i32> = Some(42);
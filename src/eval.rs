@@ -0,0 +1,169 @@
+// Search quality metrics over queries with known relevant files
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::error::SearchError;
+use crate::simple_search::HybridSearch;
+
+/// A query and the files a good search returns for it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LabeledQuery {
+    pub query: String,
+    pub relevant_paths: Vec<String>,
+}
+
+/// Metrics of one query's ranking
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QueryEval {
+    pub query: String,
+    /// 1 / rank of the first relevant file; 0 when none is in the top `k`
+    pub reciprocal_rank: f32,
+    pub ndcg_at_k: f32,
+    /// Share of the relevant files found in the top `k`
+    pub recall_at_k: f32,
+}
+
+/// Metrics averaged over a set of labeled queries, see `evaluate`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EvalReport {
+    pub k: usize,
+    pub mrr: f32,
+    pub ndcg_at_k: f32,
+    pub recall_at_k: f32,
+    pub per_query: Vec<QueryEval>,
+}
+
+/// Read a JSON array of `LabeledQuery`, such as `test_data/eval/labeled_queries.json`
+pub fn load_labeled_queries(path: &Path) -> Result<Vec<LabeledQuery>> {
+    Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+}
+
+/// Run every query through `search` and score the top `k` distinct files it returns
+/// against the labels. Relevance is binary and paths must match `SearchResult::file_path`
+/// exactly. Results are served from the result cache like any other search.
+pub async fn evaluate(search: &mut HybridSearch, queries: &[LabeledQuery], k: usize) -> Result<EvalReport> {
+    let mut rankings = Vec::with_capacity(queries.len());
+    for labeled in queries {
+        let results = search.search(&labeled.query, k).await?;
+        let mut seen = HashSet::new();
+        let ranking: Vec<String> = results
+            .into_iter()
+            .map(|result| result.file_path)
+            .filter(|path| seen.insert(path.clone()))
+            .collect();
+        rankings.push(ranking);
+    }
+    score_rankings(queries, &rankings, k)
+}
+
+/// `EvalReport` for rankings produced elsewhere, `rankings[i]` answering `queries[i]`.
+/// Fails on an unlabeled query or `k` of 0, which no metric is defined for.
+pub fn score_rankings(queries: &[LabeledQuery], rankings: &[Vec<String>], k: usize) -> Result<EvalReport> {
+    if k == 0 {
+        return Err(SearchError::InvalidParameter { parameter: "k".to_string(), reason: "must be greater than 0".to_string() }.into());
+    }
+    if queries.len() != rankings.len() {
+        return Err(SearchError::InvalidParameter {
+            parameter: "rankings".to_string(),
+            reason: format!("{} rankings for {} queries", rankings.len(), queries.len()),
+        }.into());
+    }
+    if let Some(unlabeled) = queries.iter().find(|labeled| labeled.relevant_paths.is_empty()) {
+        return Err(SearchError::InvalidParameter {
+            parameter: "queries".to_string(),
+            reason: format!("{:?} has no relevant paths", unlabeled.query),
+        }.into());
+    }
+
+    let per_query: Vec<QueryEval> = queries.iter().zip(rankings).map(|(labeled, ranking)| score_query(labeled, ranking, k)).collect();
+    let mean = |metric: fn(&QueryEval) -> f32| match per_query.len() {
+        0 => 0.0,
+        n => per_query.iter().map(metric).sum::<f32>() / n as f32,
+    };
+    Ok(EvalReport {
+        k,
+        mrr: mean(|q| q.reciprocal_rank),
+        ndcg_at_k: mean(|q| q.ndcg_at_k),
+        recall_at_k: mean(|q| q.recall_at_k),
+        per_query,
+    })
+}
+
+fn score_query(labeled: &LabeledQuery, ranking: &[String], k: usize) -> QueryEval {
+    let relevant: HashSet<&str> = labeled.relevant_paths.iter().map(String::as_str).collect();
+    let top_k = &ranking[..ranking.len().min(k)];
+    // Gain of rank i (0-based) is 1 / log2(i + 2)
+    let discount = |rank: usize| 1.0 / (rank as f32 + 2.0).log2();
+
+    let hits: Vec<usize> = top_k.iter().enumerate().filter(|(_, path)| relevant.contains(path.as_str())).map(|(rank, _)| rank).collect();
+    let dcg: f32 = hits.iter().map(|&rank| discount(rank)).sum();
+    let ideal: f32 = (0..relevant.len().min(k)).map(discount).sum();
+    let found: HashSet<&str> = hits.iter().map(|&rank| top_k[rank].as_str()).collect();
+
+    QueryEval {
+        query: labeled.query.clone(),
+        reciprocal_rank: hits.first().map_or(0.0, |&rank| 1.0 / (rank as f32 + 1.0)),
+        ndcg_at_k: dcg / ideal,
+        recall_at_k: found.len() as f32 / relevant.len() as f32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, SemanticMode};
+    use tempfile::tempdir;
+
+    fn fixture() -> Vec<LabeledQuery> {
+        load_labeled_queries(&Path::new(env!("CARGO_MANIFEST_DIR")).join("test_data/eval/labeled_queries.json")).unwrap()
+    }
+
+    #[test]
+    fn test_trivially_correct_ranking_scores_ideal_metrics() -> Result<()> {
+        let queries = fixture();
+        let rankings: Vec<Vec<String>> = queries.iter().map(|q| q.relevant_paths.clone()).collect();
+        let report = score_rankings(&queries, &rankings, 10)?;
+        assert_eq!(report.per_query.len(), queries.len());
+        assert_eq!((report.mrr, report.ndcg_at_k, report.recall_at_k), (1.0, 1.0, 1.0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_relevant_file_at_rank_two() -> Result<()> {
+        let queries = [LabeledQuery { query: "q".to_string(), relevant_paths: vec!["b.rs".to_string()] }];
+        let ranking = vec!["a.rs".to_string(), "b.rs".to_string()];
+        let report = score_rankings(&queries, std::slice::from_ref(&ranking), 10)?;
+        assert_eq!(report.mrr, 0.5);
+        assert!((report.ndcg_at_k - 1.0 / 3f32.log2()).abs() < 1e-6);
+        assert_eq!(report.recall_at_k, 1.0);
+
+        // Cut off by k
+        let report = score_rankings(&queries, std::slice::from_ref(&ranking), 1)?;
+        assert_eq!((report.mrr, report.ndcg_at_k, report.recall_at_k), (0.0, 0.0, 0.0));
+        assert!(score_rankings(&queries, &[], 1).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_keyword_search_over_fixture() -> Result<()> {
+        let data = Path::new(env!("CARGO_MANIFEST_DIR")).join("test_data");
+        let paths = ["user_auth.py", "api_controller.js", "cache_manager.rs", "markdown/table_heavy_document.md", "markdown/sample_document.md"];
+        let contents = paths.iter().map(|path| std::fs::read_to_string(data.join(path))).collect::<std::io::Result<Vec<_>>>()?;
+
+        let temp_dir = tempdir()?;
+        let mut config = Config::default();
+        config.search.semantic_mode = SemanticMode::Disabled;
+        let mut search = HybridSearch::with_config(temp_dir.path().to_str().unwrap(), &config).await?;
+        search.index(contents, paths.iter().map(|path| path.to_string()).collect()).await?;
+
+        let report = evaluate(&mut search, &fixture(), 5).await?;
+        for metric in [report.mrr, report.ndcg_at_k, report.recall_at_k] {
+            assert!((0.0..=1.0).contains(&metric), "{:?}", report);
+        }
+        assert!(report.recall_at_k > 0.0, "{:?}", report);
+        Ok(())
+    }
+}
@@ -5,6 +5,8 @@
 use serde::{Serialize, Deserialize};
 use std::collections::HashMap;
 
+use crate::utils::content_preview_with_marker;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FusionConfig {
     /// Weight for BM25/text search results (0.0 - 1.0)
@@ -278,7 +280,7 @@ impl FusionEngine {
             "{}:{}:{}",
             result.file_path,
             result.line_number.unwrap_or(0),
-            &result.content[..50.min(result.content.len())]
+            content_preview_with_marker(&result.content, 50, "")
         )
     }
 }
@@ -1,10 +1,24 @@
-use crate::llama_wrapper_working::{GGUFModel, GGUFContext};
+use crate::llama_wrapper_working::{GGUFModel, GGUFContext, MAX_SEQUENCE_TOKENS};
 use crate::embedding_prefixes::{EmbeddingTask, CodeFormatter, BatchProcessor};
-use anyhow::Result;
+use anyhow::{bail, Result};
 use std::sync::Arc;
 use parking_lot::Mutex;
 use lru::LruCache;
 use std::num::NonZeroUsize;
+use std::time::{Duration, Instant};
+use crate::config::EmbeddingRetryConfig;
+use crate::doctor::gguf_embedding_length;
+use crate::error::SearchError;
+use crate::utils::retry::{retry_blocking_with_backoff, RetryConfig};
+
+/// Text model `HybridSearch` loads for markdown and queries, relative to the working directory
+pub const TEXT_MODEL_PATH: &str = "./src/model/nomic-embed-text-v1.5.Q4_K_M.gguf";
+
+/// Code model `HybridSearch` loads for source files
+pub const CODE_MODEL_PATH: &str = "./src/model/nomic-embed-code.Q4_K_M.gguf";
+
+/// Embedding length of both nomic models; they share one vector store
+pub const NOMIC_EMBEDDING_DIMENSION: usize = 768;
 
 /// Configuration for GGUF embedder
 #[derive(Debug, Clone)]
@@ -16,6 +30,74 @@ pub struct GGUFEmbedderConfig {
     pub cache_size: usize,
     pub normalize: bool,
     pub threads: usize,
+    pub pooling: PoolingStrategy,
+    /// Backoff applied to each model call when it fails with a recoverable error
+    pub retry: RetryConfig,
+    /// Inputs longer than this many model tokens (task prefix included) are truncated.
+    /// Capped at `context_size` and `MAX_SEQUENCE_TOKENS`.
+    pub max_sequence_tokens: usize,
+    /// Embedding length the model must have; `new` reads it from the GGUF metadata
+    /// and fails with `SearchError::DimensionMismatch` before loading anything else.
+    /// `None` accepts any model.
+    pub expected_dimension: Option<usize>,
+}
+
+/// L2-normalize in place and return the norm measured before scaling.
+/// Near-zero vectors are left untouched.
+pub fn l2_normalize(embedding: &mut [f32]) -> f32 {
+    let norm = l2_norm(embedding);
+    if norm > 1e-8 {
+        for x in embedding.iter_mut() {
+            *x /= norm;
+        }
+    }
+    norm
+}
+
+/// Euclidean length of a vector
+pub fn l2_norm(embedding: &[f32]) -> f32 {
+    embedding.iter().map(|x| x * x).sum::<f32>().sqrt()
+}
+
+/// How token embeddings are reduced to a single vector
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PoolingStrategy {
+    /// Average over all tokens (nomic-style models)
+    #[default]
+    Mean,
+    /// First token only (BERT-style [CLS] models)
+    Cls,
+    /// Final token only (decoder-style models)
+    LastToken,
+}
+
+impl PoolingStrategy {
+    /// Reduce a row-major `[n_tokens x dim]` matrix of token embeddings to one vector
+    pub fn pool(&self, token_embeddings: &[f32], dim: usize) -> Result<Vec<f32>> {
+        if dim == 0 || token_embeddings.len() < dim || !token_embeddings.len().is_multiple_of(dim) {
+            bail!("Invalid embedding sequence length {} for dimension {}", token_embeddings.len(), dim);
+        }
+        let n_tokens = token_embeddings.len() / dim;
+        
+        let pooled = match self {
+            PoolingStrategy::Mean => {
+                let mut pooled = vec![0.0f32; dim];
+                for token in token_embeddings.chunks_exact(dim) {
+                    for (sum, value) in pooled.iter_mut().zip(token) {
+                        *sum += value;
+                    }
+                }
+                for value in &mut pooled {
+                    *value /= n_tokens as f32;
+                }
+                pooled
+            }
+            PoolingStrategy::Cls => token_embeddings[..dim].to_vec(),
+            PoolingStrategy::LastToken => token_embeddings[(n_tokens - 1) * dim..].to_vec(),
+        };
+        
+        Ok(pooled)
+    }
 }
 
 impl Default for GGUFEmbedderConfig {
@@ -25,17 +107,24 @@ impl Default for GGUFEmbedderConfig {
         let optimal_threads = std::cmp::max(1, (cpu_count * 3) / 4);  // Use 75% of cores
         
         Self {
-            model_path: "./src/model/nomic-embed-text-v1.5.Q4_K_M.gguf".to_string(),
+            model_path: TEXT_MODEL_PATH.to_string(),
             context_size: 8192,
             gpu_layers: 0,  // CPU-ONLY: No GPU layers
             batch_size: 16,  // Reduced for CPU efficiency
             cache_size: 2000,  // Increased cache for CPU compensation
             normalize: true,
             threads: optimal_threads,
+            pooling: PoolingStrategy::default(),
+            retry: EmbeddingRetryConfig::default().to_retry_config(),
+            max_sequence_tokens: MAX_SEQUENCE_TOKENS,
+            expected_dimension: None,
         }
     }
 }
 
+/// Number of recent embed latencies kept for percentile estimates
+pub const LATENCY_RESERVOIR_SIZE: usize = 1024;
+
 /// Statistics for embedder performance
 #[derive(Debug, Default)]
 pub struct EmbedderStats {
@@ -44,6 +133,11 @@ pub struct EmbedderStats {
     pub cache_misses: usize,
     pub batch_operations: usize,
     pub total_tokens_processed: usize,
+    /// L2 norm of the most recently computed embedding, before any normalization.
+    /// Values near zero point at a degenerate vector.
+    pub last_norm: f32,
+    /// Model latency of the last `LATENCY_RESERVOIR_SIZE` cache misses
+    latencies: LatencyReservoir,
 }
 
 impl EmbedderStats {
@@ -54,6 +148,108 @@ impl EmbedderStats {
             self.cache_hits as f64 / self.total_embeddings as f64
         }
     }
+
+    /// Embed latency at percentile `p` (0.0..=1.0) over the recent cache misses,
+    /// `None` until something has been embedded
+    pub fn latency_percentile(&self, p: f64) -> Option<Duration> {
+        self.latencies.percentile(p)
+    }
+
+    pub fn latency_p50(&self) -> Option<Duration> {
+        self.latency_percentile(0.50)
+    }
+
+    pub fn latency_p95(&self) -> Option<Duration> {
+        self.latency_percentile(0.95)
+    }
+
+    pub fn latency_p99(&self) -> Option<Duration> {
+        self.latency_percentile(0.99)
+    }
+
+    fn record_hit(&mut self) {
+        self.total_embeddings += 1;
+        self.cache_hits += 1;
+    }
+
+    fn record_miss(&mut self, tokens: usize, latency: Duration) {
+        self.total_embeddings += 1;
+        self.cache_misses += 1;
+        self.total_tokens_processed += tokens;
+        self.latencies.record(latency);
+    }
+}
+
+/// Fixed-size ring of latency samples; once full, new samples overwrite the oldest
+#[derive(Debug, Clone, Default)]
+struct LatencyReservoir {
+    samples: Vec<Duration>,
+    next: usize,
+}
+
+impl LatencyReservoir {
+    fn record(&mut self, latency: Duration) {
+        if self.samples.len() < LATENCY_RESERVOIR_SIZE {
+            self.samples.push(latency);
+        } else {
+            self.samples[self.next] = latency;
+        }
+        self.next = (self.next + 1) % LATENCY_RESERVOIR_SIZE;
+    }
+
+    /// Nearest-rank percentile
+    fn percentile(&self, p: f64) -> Option<Duration> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let mut sorted = self.samples.clone();
+        sorted.sort_unstable();
+        let rank = (p.clamp(0.0, 1.0) * sorted.len() as f64).ceil() as usize;
+        Some(sorted[rank.saturating_sub(1)])
+    }
+}
+
+/// Whether a failed model call is worth retrying: context creation, batch building
+/// and decode failures are transient (usually allocation), tokenization and
+/// degenerate-output failures are not
+pub fn is_recoverable_embedding_error(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        let message = cause.to_string().to_lowercase();
+        !message.contains("tokeniz")
+            && (message.contains("decode")
+                || message.contains("context")
+                || message.contains("alloc")
+                || message.contains("out of memory")
+                || message.contains("add token to batch"))
+    })
+}
+
+/// Run a model call, retrying recoverable failures per `retry`
+fn embed_with_retry<T>(retry: &RetryConfig, operation: impl FnMut() -> Result<T>) -> Result<T> {
+    retry_blocking_with_backoff("embed", retry, operation, is_recoverable_embedding_error)
+}
+
+/// Return the cached embedding for `key` or compute and cache it, recording a hit
+/// or a miss (with its latency and `tokens`) in `stats`
+fn embed_through_cache(
+    cache: &Mutex<LruCache<String, Vec<f32>>>,
+    stats: &Mutex<EmbedderStats>,
+    key: &str,
+    tokens: usize,
+    compute: impl FnOnce(&str) -> Result<Vec<f32>>,
+) -> Result<Vec<f32>> {
+    if let Some(cached) = cache.lock().get(key) {
+        stats.lock().record_hit();
+        return Ok(cached.clone());
+    }
+
+    let started = Instant::now();
+    let embedding = compute(key)?;
+    let latency = started.elapsed();
+
+    cache.lock().put(key.to_string(), embedding.clone());
+    stats.lock().record_miss(tokens, latency);
+    Ok(embedding)
 }
 
 /// Thread-safe GGUF embedder with caching and performance monitoring
@@ -68,15 +264,22 @@ pub struct GGUFEmbedder {
 impl GGUFEmbedder {
     /// Create new embedder with configuration
     pub fn new(config: GGUFEmbedderConfig) -> Result<Self> {
+        if let Some(expected) = config.expected_dimension {
+            let actual = gguf_embedding_length(std::path::Path::new(&config.model_path))?;
+            if actual != expected {
+                return Err(SearchError::DimensionMismatch { expected, actual }.into());
+            }
+        }
+        
         // Load GGUF model
         let model = Arc::new(GGUFModel::load_from_file(
             &config.model_path,
             config.gpu_layers,
         )?);
         
-        // Create context for embeddings
+        // Create context for embeddings, set up for the configured pooling
         let context = Arc::new(Mutex::new(
-            GGUFContext::new_with_model(&model, config.context_size)?
+            GGUFContext::new_with_pooling(&model, config.context_size, config.max_sequence_tokens, config.pooling)?
         ));
         
         // Initialize LRU cache
@@ -107,47 +310,17 @@ impl GGUFEmbedder {
     pub fn embed(&self, text: &str, task: EmbeddingTask) -> Result<Vec<f32>> {
         // Apply task-specific prefix
         let prefixed_text = task.apply_prefix(text);
+        let tokens = text.split_whitespace().count();
         
-        // Check cache first
-        {
-            let mut cache = self.cache.lock();
-            if let Some(cached) = cache.get(&prefixed_text) {
-                // Update statistics
-                let mut stats = self.stats.lock();
-                stats.total_embeddings += 1;
-                stats.cache_hits += 1;
-                return Ok(cached.clone());
-            }
-        }
-        
-        // Generate embedding using GGUF context
-        let embedding = {
-            let mut ctx = self.context.lock();
-            let result = ctx.embed(&prefixed_text)?;
+        embed_through_cache(&self.cache, &self.stats, &prefixed_text, tokens, |prefixed| {
+            // Generate embedding using GGUF context
+            let result = embed_with_retry(&self.config.retry, || {
+                self.context.lock().embed_with_pooling(prefixed, self.config.pooling)
+            })?;
             
-            // Apply L2 normalization if configured
-            if self.config.normalize {
-                self.normalize_embedding(result)
-            } else {
-                result
-            }
-        };
-        
-        // Cache the result
-        {
-            let mut cache = self.cache.lock();
-            cache.put(prefixed_text.clone(), embedding.clone());
-        }
-        
-        // Update statistics
-        {
-            let mut stats = self.stats.lock();
-            stats.total_embeddings += 1;
-            stats.cache_misses += 1;
-            stats.total_tokens_processed += text.split_whitespace().count();
-        }
-        
-        Ok(embedding)
+            // Record the raw norm and apply L2 normalization if configured
+            Ok(self.finish_embedding(result))
+        })
     }
     
     /// Generate embeddings for multiple texts with batch processing
@@ -181,17 +354,17 @@ impl GGUFEmbedder {
         // Process uncached texts in batches
         if !uncached_texts.is_empty() {
             for chunk in uncached_texts.chunks(self.config.batch_size) {
+                let started = Instant::now();
                 let mut ctx = self.context.lock();
-                let chunk_embeddings = ctx.embed_batch(chunk.to_vec())?;
+                let chunk_embeddings = embed_with_retry(&self.config.retry, || {
+                    ctx.embed_batch_with_pooling(chunk.to_vec(), self.config.pooling)
+                })?;
+                self.record_batch_latency(started.elapsed(), chunk.len());
                 
                 // Apply normalization if configured
-                let normalized_embeddings: Vec<Vec<f32>> = if self.config.normalize {
-                    chunk_embeddings.into_iter()
-                        .map(|emb| self.normalize_embedding(emb))
-                        .collect()
-                } else {
-                    chunk_embeddings
-                };
+                let normalized_embeddings: Vec<Vec<f32>> = chunk_embeddings.into_iter()
+                    .map(|emb| self.finish_embedding(emb))
+                    .collect();
                 
                 // Update results and cache
                 for (chunk_idx, embedding) in normalized_embeddings.into_iter().enumerate() {
@@ -273,14 +446,16 @@ impl GGUFEmbedder {
         // Process uncached
         if !uncached_texts.is_empty() {
             for chunk in uncached_texts.chunks(self.config.batch_size) {
+                let started = Instant::now();
                 let mut ctx = self.context.lock();
-                let embeddings = ctx.embed_batch(chunk.to_vec())?;
+                let embeddings = embed_with_retry(&self.config.retry, || {
+                    ctx.embed_batch_with_pooling(chunk.to_vec(), self.config.pooling)
+                })?;
+                self.record_batch_latency(started.elapsed(), chunk.len());
                 
-                let normalized: Vec<Vec<f32>> = if self.config.normalize {
-                    embeddings.into_iter().map(|e| self.normalize_embedding(e)).collect()
-                } else {
-                    embeddings
-                };
+                let normalized: Vec<Vec<f32>> = embeddings.into_iter()
+                    .map(|e| self.finish_embedding(e))
+                    .collect();
                 
                 for (chunk_idx, embedding) in normalized.into_iter().enumerate() {
                     if let Some(&result_idx) = uncached_indices.get(chunk_idx) {
@@ -302,11 +477,16 @@ impl GGUFEmbedder {
         self.model.embedding_dim
     }
     
-    /// Get performance statistics
+    /// Snapshot of the performance statistics
     pub fn stats(&self) -> EmbedderStats {
         self.stats.lock().clone()
     }
     
+    /// Zero all counters and drop the latency samples (the cache is kept)
+    pub fn reset_stats(&self) {
+        *self.stats.lock() = EmbedderStats::default();
+    }
+    
     /// Clear cache
     pub fn clear_cache(&self) {
         let mut cache = self.cache.lock();
@@ -319,14 +499,23 @@ impl GGUFEmbedder {
         (cache.len(), cache.cap().get())
     }
     
-    /// L2 normalization
-    fn normalize_embedding(&self, mut embedding: Vec<f32>) -> Vec<f32> {
-        let norm: f32 = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
-        if norm > 1e-8 {
-            for x in &mut embedding {
-                *x /= norm;
-            }
+    /// Spread a batch's model time evenly over its texts
+    fn record_batch_latency(&self, elapsed: Duration, texts: usize) {
+        let per_text = elapsed / texts.max(1) as u32;
+        let mut stats = self.stats.lock();
+        for _ in 0..texts {
+            stats.latencies.record(per_text);
         }
+    }
+    
+    /// Record the pre-normalization norm, then L2-normalize if configured
+    fn finish_embedding(&self, mut embedding: Vec<f32>) -> Vec<f32> {
+        let norm = if self.config.normalize {
+            l2_normalize(&mut embedding)
+        } else {
+            l2_norm(&embedding)
+        };
+        self.stats.lock().last_norm = norm;
         embedding
     }
 }
@@ -343,6 +532,8 @@ impl Clone for EmbedderStats {
             cache_misses: self.cache_misses,
             batch_operations: self.batch_operations,
             total_tokens_processed: self.total_tokens_processed,
+            last_norm: self.last_norm,
+            latencies: self.latencies.clone(),
         }
     }
 }
@@ -358,6 +549,161 @@ mod tests {
         GGUFEmbedder::new(config)
     }
 
+    /// GGUF header whose only metadata is `nomic-bert.embedding_length`
+    fn write_stub_model(path: &std::path::Path, dimension: u32) {
+        let key = "nomic-bert.embedding_length";
+        let mut out = b"GGUF".to_vec();
+        out.extend(3u32.to_le_bytes());
+        out.extend(0u64.to_le_bytes());
+        out.extend(1u64.to_le_bytes());
+        out.extend((key.len() as u64).to_le_bytes());
+        out.extend(key.as_bytes());
+        out.extend(4u32.to_le_bytes()); // uint32
+        out.extend(dimension.to_le_bytes());
+        std::fs::write(path, out).unwrap();
+    }
+
+    #[test]
+    fn test_dimension_mismatch_fails_before_loading_the_model() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let model_path = dir.path().join("model.gguf");
+        write_stub_model(&model_path, 384);
+
+        let config = GGUFEmbedderConfig {
+            model_path: model_path.to_string_lossy().into_owned(),
+            expected_dimension: Some(768),
+            ..Default::default()
+        };
+        // The stub has no tensors, so any other error would come from trying to load it
+        let err = GGUFEmbedder::new(config).err().expect("dimension mismatch");
+        match err.downcast_ref::<SearchError>() {
+            Some(SearchError::DimensionMismatch { expected, actual }) => assert_eq!((*expected, *actual), (768, 384)),
+            other => panic!("expected DimensionMismatch, got {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_pooling_strategies_differ() -> Result<()> {
+        // Three tokens with dimension 2
+        let tokens = [1.0, 0.0, 0.0, 1.0, 2.0, 2.0];
+        
+        let mean = PoolingStrategy::Mean.pool(&tokens, 2)?;
+        let cls = PoolingStrategy::Cls.pool(&tokens, 2)?;
+        let last = PoolingStrategy::LastToken.pool(&tokens, 2)?;
+        
+        assert_eq!(mean, vec![1.0, 1.0]);
+        assert_eq!(cls, vec![1.0, 0.0]);
+        assert_eq!(last, vec![2.0, 2.0]);
+        
+        let diff: f32 = mean.iter().zip(&cls).map(|(a, b)| (a - b).abs()).sum();
+        assert!(diff > 0.5, "Cls and Mean pooling should produce different vectors");
+        Ok(())
+    }
+
+    #[test]
+    fn test_pooling_rejects_ragged_input() {
+        assert!(PoolingStrategy::Mean.pool(&[1.0, 2.0, 3.0], 2).is_err());
+        assert!(PoolingStrategy::Cls.pool(&[], 2).is_err());
+    }
+
+    #[test]
+    fn test_default_pooling_is_mean() {
+        assert_eq!(GGUFEmbedderConfig::default().pooling, PoolingStrategy::Mean);
+    }
+
+    #[test]
+    fn test_normalize_toggle_norms() -> Result<()> {
+        let tokens = [3.0, 0.0, 1.0, 4.0, 1.0, 2.0];
+        let raw = PoolingStrategy::Mean.pool(&tokens, 3)?;
+        let raw_norm = l2_norm(&raw);
+        assert!((raw_norm - 1.0).abs() > 1e-3, "Raw pooled vector should not be unit length");
+        
+        let mut normalized = raw.clone();
+        let pre_norm = l2_normalize(&mut normalized);
+        assert!((pre_norm - raw_norm).abs() < 1e-6, "Pre-normalization norm should be reported");
+        assert!((l2_norm(&normalized) - 1.0).abs() < 1e-6, "Normalized vector should have unit norm");
+        
+        let mut zero = vec![0.0f32; 3];
+        assert_eq!(l2_normalize(&mut zero), 0.0);
+        assert_eq!(zero, vec![0.0; 3]);
+        Ok(())
+    }
+    
+    #[test]
+    fn test_cache_counters_track_unique_and_repeated_inputs() -> Result<()> {
+        let cache = Mutex::new(LruCache::new(NonZeroUsize::new(16).unwrap()));
+        let stats = Mutex::new(EmbedderStats::default());
+        let inputs = ["alpha", "beta", "alpha", "gamma", "beta", "alpha"];
+        let mut computed = 0;
+        
+        for input in inputs {
+            embed_through_cache(&cache, &stats, input, 1, |text| {
+                computed += 1;
+                Ok(vec![text.len() as f32])
+            })?;
+        }
+        
+        let snapshot = stats.lock().clone();
+        assert_eq!(snapshot.total_embeddings, inputs.len());
+        assert_eq!(snapshot.cache_misses, 3, "One miss per unique input");
+        assert_eq!(snapshot.cache_hits, 3, "One hit per repeat");
+        assert_eq!(computed, snapshot.cache_misses);
+        assert_eq!(snapshot.total_tokens_processed, 3);
+        assert!(snapshot.latency_p50().is_some());
+        assert!(snapshot.latency_p50() <= snapshot.latency_p99());
+        
+        *stats.lock() = EmbedderStats::default();
+        let reset = stats.lock().clone();
+        assert_eq!(reset.total_embeddings, 0);
+        assert!(reset.latency_p95().is_none());
+        Ok(())
+    }
+    
+    #[test]
+    fn test_embed_retry_recovers_after_transient_failures() -> Result<()> {
+        let retry = RetryConfig::new(3, Duration::from_millis(1), Duration::from_millis(5), 2.0, false);
+        let mut attempts = 0;
+        let embedding = embed_with_retry(&retry, || {
+            attempts += 1;
+            if attempts <= 2 {
+                Err(anyhow::anyhow!("llama_decode returned -1").context("Failed to decode token batch"))
+            } else {
+                Ok(vec![1.0, 0.0])
+            }
+        })?;
+        assert_eq!(embedding, vec![1.0, 0.0]);
+        assert_eq!(attempts, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_embed_retry_skips_malformed_input() {
+        let retry = RetryConfig::new(3, Duration::from_millis(1), Duration::from_millis(5), 2.0, false);
+        let mut attempts = 0;
+        let result: Result<Vec<f32>> = embed_with_retry(&retry, || {
+            attempts += 1;
+            bail!("Tokenization failed for input text")
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+        assert!(!is_recoverable_embedding_error(&anyhow::anyhow!("Embedding has zero norm - likely extraction failed")));
+        assert!(is_recoverable_embedding_error(&anyhow::anyhow!("Failed to create embedding context")));
+    }
+
+    #[test]
+    fn test_latency_reservoir_is_bounded() {
+        let mut reservoir = LatencyReservoir::default();
+        for ms in 1..=(LATENCY_RESERVOIR_SIZE as u64 * 2) {
+            reservoir.record(Duration::from_millis(ms));
+        }
+        assert_eq!(reservoir.samples.len(), LATENCY_RESERVOIR_SIZE);
+        // Only the newest half survives
+        assert_eq!(reservoir.percentile(0.0), Some(Duration::from_millis(LATENCY_RESERVOIR_SIZE as u64 + 1)));
+        assert_eq!(reservoir.percentile(1.0), Some(Duration::from_millis(LATENCY_RESERVOIR_SIZE as u64 * 2)));
+        assert_eq!(reservoir.percentile(0.5), Some(Duration::from_millis(LATENCY_RESERVOIR_SIZE as u64 * 3 / 2)));
+    }
+    
     #[test]
     fn test_embedder_creation() -> Result<()> {
         let embedder = create_test_embedder()?;
@@ -379,6 +725,48 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_query_and_document_tasks_embed_differently() -> Result<()> {
+        let embedder = create_test_embedder()?;
+        let query = embedder.embed("parse the config file", EmbeddingTask::SearchQuery)?;
+        let document = embedder.embed("parse the config file", EmbeddingTask::SearchDocument)?;
+        
+        let diff: f32 = query.iter().zip(&document).map(|(a, b)| (a - b).abs()).sum();
+        assert!(diff > 1e-3, "search_query: and search_document: prefixes should change the vector");
+        assert_eq!(embedder.stats().cache_misses, 2, "Each prefix is its own cache entry");
+        Ok(())
+    }
+
+    #[test]
+    fn test_overlong_input_is_truncated_to_a_valid_embedding() -> Result<()> {
+        let config = GGUFEmbedderConfig { cache_size: 10, max_sequence_tokens: 64, ..Default::default() };
+        let embedder = GGUFEmbedder::new(config)?;
+        
+        let long_text = "fn parse_config(path: &str) -> Config { load(path) }\n".repeat(2000);
+        let embedding = embedder.embed(&long_text, EmbeddingTask::SearchDocument)?;
+        assert_eq!(embedding.len(), 768);
+        assert!(embedding.iter().all(|x| x.is_finite()));
+        assert!((l2_norm(&embedding) - 1.0).abs() < 1e-5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cls_and_last_token_pooling_embed_through_the_model() -> Result<()> {
+        let mean = create_test_embedder()?.embed("fn parse_config(path: &str)", EmbeddingTask::SearchDocument)?;
+        for pooling in [PoolingStrategy::Cls, PoolingStrategy::LastToken] {
+            let config = GGUFEmbedderConfig { cache_size: 10, pooling, ..Default::default() };
+            let embedder = GGUFEmbedder::new(config)?;
+            
+            let embedding = embedder.embed("fn parse_config(path: &str)", EmbeddingTask::SearchDocument)?;
+            assert_eq!(embedding.len(), 768);
+            assert!(embedding.iter().all(|x| x.is_finite()));
+            assert!((l2_norm(&embedding) - 1.0).abs() < 1e-5, "{:?} embedding should be normalized", pooling);
+            let diff: f32 = embedding.iter().zip(&mean).map(|(a, b)| (a - b).abs()).sum();
+            assert!(diff > 1e-3, "{:?} pooling should not reproduce the Mean vector", pooling);
+        }
+        Ok(())
+    }
+
     #[test]
     fn test_batch_embedding() -> Result<()> {
         let embedder = create_test_embedder()?;
@@ -0,0 +1,1128 @@
+// Reading, embedding and storing files for `HybridSearch::index` and `index_directory`
+
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::simple_search::{storage_failure, Document, Embedders, HybridSearch, DOCUMENT_TASK, MTIME_METADATA_KEY};
+use crate::error::SearchError;
+use crate::config::{Config, GeneratedFilePolicy, IndexingConfig, InvalidUtf8Policy};
+use crate::content_filter::{ContentFilter, CONTENT_FILTER_METADATA_KEY};
+use crate::indexer::{
+    decode_source, exceeds_max_file_size, is_generated_source, is_indexable_extension, walk_source_files, IndexCheckpoint, IndexProgress,
+    ReconcileReport, GENERATED_METADATA_KEY, GENERATED_OPAQUE,
+};
+use crate::utils::memory_monitor::get_system_memory_info;
+
+/// Files per batch read and embedded by one `HybridSearch::index_directory` worker
+pub const INDEX_BATCH_SIZE: usize = 10;
+
+/// Checks of free memory, `MEMORY_RECOVERY_PAUSE` apart, that `index_directory` makes
+/// with no batch running before giving up on memory recovering
+const MEMORY_RECOVERY_CHECKS: u32 = 30;
+const MEMORY_RECOVERY_PAUSE: Duration = Duration::from_secs(1);
+
+/// Available system memory in MB, `None` where it can't be read
+pub(crate) fn available_memory_mb() -> Option<u64> {
+    get_system_memory_info().map(|info| info.available_mb)
+}
+
+/// Sizes `index_directory` batches by free memory: below `min_free_mb` the batch size
+/// halves per batch down to one file, then new batches wait for running ones to
+/// finish. Once memory recovers the size doubles back up to `INDEX_BATCH_SIZE`.
+struct MemoryThrottle {
+    /// 0 disables the check
+    min_free_mb: u64,
+    probe: fn() -> Option<u64>,
+    pause: Duration,
+    batch_size: usize,
+}
+
+impl MemoryThrottle {
+    fn new(min_free_mb: u64, probe: fn() -> Option<u64>) -> Self {
+        Self { min_free_mb, probe, pause: MEMORY_RECOVERY_PAUSE, batch_size: INDEX_BATCH_SIZE }
+    }
+
+    /// Files for the next batch, or 0 to wait for a running batch first. With nothing
+    /// running (`idle`) and memory short at one file per batch, waits for memory to
+    /// recover and returns `SearchError::ResourceExhausted` if it doesn't.
+    async fn next_batch_size(&mut self, idle: bool) -> Result<usize> {
+        // Platforms without a memory reading index at full size
+        let available = match (self.probe)() {
+            Some(available) if self.min_free_mb > 0 => available,
+            _ => return Ok(self.batch_size),
+        };
+        if available >= self.min_free_mb {
+            self.batch_size = (self.batch_size * 2).min(INDEX_BATCH_SIZE);
+            return Ok(self.batch_size);
+        }
+        if self.batch_size > 1 {
+            self.batch_size /= 2;
+            log::warn!(
+                "{} MB of memory available, below indexing.min_free_memory_mb ({}); indexing {} files per batch",
+                available, self.min_free_mb, self.batch_size
+            );
+            return Ok(self.batch_size);
+        }
+        if !idle {
+            return Ok(0);
+        }
+        let mut available = available;
+        for _ in 0..MEMORY_RECOVERY_CHECKS {
+            tokio::time::sleep(self.pause).await;
+            match (self.probe)() {
+                Some(now) if now < self.min_free_mb => available = now,
+                _ => return Ok(self.batch_size),
+            }
+        }
+        Err(SearchError::ResourceExhausted {
+            resource: "memory".to_string(),
+            reason: format!(
+                "stayed at {} MB available, below indexing.min_free_memory_mb ({}), with nothing left to shrink",
+                available, self.min_free_mb
+            ),
+        }.into())
+    }
+}
+
+/// Files read and embedded, ready to be stored
+struct EmbeddedBatch {
+    contents: Vec<String>,
+    file_paths: Vec<String>,
+    /// `None` when running keyword-only
+    embeddings: Option<Vec<Vec<f32>>>,
+    /// Files that couldn't be read or embedded, left out of the fields above
+    failed: Vec<(String, SearchError)>,
+    /// Files among `file_paths` kept whole under `GeneratedFilePolicy::IndexAsOpaque`
+    opaque: Vec<String>,
+    /// Modification time in Unix seconds of the files read, where the platform has one
+    modified: HashMap<String, u64>,
+    progress: IndexProgress,
+}
+
+/// Embed `contents`, keeping the files whose embedding succeeded and recording the
+/// others as failed. Without embedders every file is kept.
+fn embed_files(contents: Vec<String>, file_paths: Vec<String>, embedders: Option<&Embedders>) -> EmbeddedBatch {
+    let mut batch = EmbeddedBatch {
+        contents: Vec::new(),
+        file_paths: Vec::new(),
+        embeddings: None,
+        failed: Vec::new(),
+        opaque: Vec::new(),
+        modified: HashMap::new(),
+        progress: IndexProgress::default(),
+    };
+    let Some(embedders) = embedders else {
+        batch.contents = contents;
+        batch.file_paths = file_paths;
+        return batch;
+    };
+    let outcomes = embedders.embed_documents(&contents, &file_paths);
+    let mut embeddings = Vec::new();
+    for ((content, path), outcome) in contents.into_iter().zip(file_paths).zip(outcomes) {
+        match outcome {
+            Ok(embedding) => {
+                batch.contents.push(content);
+                batch.file_paths.push(path);
+                embeddings.push(embedding);
+            }
+            Err(e) => batch.failed.push((path, e)),
+        }
+    }
+    batch.embeddings = Some(embeddings);
+    batch
+}
+
+/// Modification time of the file at `path` in seconds since the epoch
+fn file_mtime(path: &Path) -> Option<u64> {
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_secs())
+}
+
+/// Read the indexable files among `files`, keep the part `indexing.content_filter`
+/// selects and embed it. Minified and generated files are skipped or kept whole per
+/// `generated`. Files that can't be read, decoded under `on_invalid_utf8` or embedded
+/// are reported as failed; they and files skipped for their size, encoding or being
+/// generated count as skipped.
+fn read_and_embed(
+    files: &[PathBuf],
+    indexing: &IndexingConfig,
+    on_invalid_utf8: InvalidUtf8Policy,
+    generated: GeneratedFilePolicy,
+    embedders: Option<&Embedders>,
+) -> EmbeddedBatch {
+    let mut contents = Vec::new();
+    let mut file_paths = Vec::new();
+    let mut failed = Vec::new();
+    let mut opaque = Vec::new();
+    let mut modified = HashMap::new();
+    for path in files.iter().filter(|path| is_indexable_extension(path, &indexing.supported_extensions)) {
+        let file_path = path.display().to_string();
+        let bytes = match std::fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                failed.push((file_path.clone(), SearchError::FileRead { path: file_path, reason: e.to_string() }));
+                continue;
+            }
+        };
+        if exceeds_max_file_size(bytes.len() as u64, indexing.max_file_size) {
+            log::warn!("Skipping {}: {} bytes exceeds max_file_size of {}", file_path, bytes.len(), indexing.max_file_size);
+            continue;
+        }
+        if let Some(mtime) = file_mtime(path) {
+            modified.insert(file_path.clone(), mtime);
+        }
+        match decode_source(path, bytes, on_invalid_utf8) {
+            Ok(Some(content)) => {
+                match generated {
+                    GeneratedFilePolicy::Normal => contents.push(indexing.content_filter.apply(&content, &file_path)),
+                    _ if !is_generated_source(&file_path, &content) => contents.push(indexing.content_filter.apply(&content, &file_path)),
+                    GeneratedFilePolicy::Skip => {
+                        log::info!("Skipping {}: minified or generated", file_path);
+                        continue;
+                    }
+                    GeneratedFilePolicy::IndexAsOpaque => {
+                        contents.push(content);
+                        opaque.push(file_path.clone());
+                    }
+                }
+                file_paths.push(file_path);
+            }
+            Ok(None) => {}
+            Err(e) => failed.push((file_path, e)),
+        }
+    }
+
+    let mut batch = embed_files(contents, file_paths, embedders);
+    failed.append(&mut batch.failed);
+    batch.failed = failed;
+    // Only those whose embedding succeeded were kept
+    batch.opaque = opaque.into_iter().filter(|path| batch.file_paths.contains(path)).collect();
+    batch.modified = modified;
+    batch.progress = IndexProgress {
+        files_seen: files.len(),
+        files_indexed: batch.contents.len(),
+        files_skipped: files.len() - batch.contents.len(),
+        bytes_processed: batch.contents.iter().map(|content| content.len() as u64).sum(),
+    };
+    batch
+}
+
+/// Per-file outcome of `HybridSearch::index` and `HybridSearch::index_directory`.
+/// Files skipped for their extension or size are in neither list.
+#[derive(Debug, Default)]
+pub struct IndexReport {
+    pub succeeded: Vec<String>,
+    pub failed: Vec<(String, SearchError)>,
+}
+
+impl IndexReport {
+    /// `Err` carrying the first failure when files failed and none succeeded
+    fn into_result(self) -> Result<Self> {
+        if !self.succeeded.is_empty() {
+            return Ok(self);
+        }
+        let count = self.failed.len();
+        match self.failed.into_iter().next() {
+            Some((_, first)) => Err(anyhow::Error::new(first).context(format!("all {} files failed to index", count))),
+            None => Ok(IndexReport::default()),
+        }
+    }
+}
+
+impl HybridSearch {
+    /// Index documents in both vector and text indices with appropriate embedders,
+    /// always under `EmbeddingTask::SearchDocument`. Without embedders only the text
+    /// index is updated. Files that fail to embed are reported and the rest are still
+    /// indexed; `Err` only when every file failed or the indexes can't be written.
+    pub async fn index(&mut self, contents: Vec<String>, file_paths: Vec<String>) -> Result<IndexReport> {
+        self.check_writable()?;
+        self.text_backend.commit_bulk()?;
+        // Generate embeddings with the appropriate embedder for each file
+        let batch = embed_files(contents, file_paths, self.embedders.as_ref());
+        let report = IndexReport { succeeded: batch.file_paths.clone(), failed: batch.failed };
+        if !batch.contents.is_empty() {
+            self.store(batch.contents, batch.file_paths, batch.embeddings)?;
+        }
+        report.into_result()
+    }
+
+    /// Index every indexable file under `root` through a pool of `config.index_concurrency`
+    /// workers (0 means one per logical core). Paths matched by the root's `.embedignore`
+    /// or `config.indexing.exclude`, and with `config.respect_gitignore` those ignored by
+    /// git, are not walked. Each worker reads and embeds a batch of
+    /// `INDEX_BATCH_SIZE` files, fewer while free memory is below
+    /// `config.indexing.min_free_memory_mb`; batches are stored in walk order, so the
+    /// resulting index is the same whatever the concurrency. `callback` gets the running totals after
+    /// each stored batch. As with `index`, failed files are reported rather than
+    /// stopping the run. The Tantivy index is committed every
+    /// `config.indexing.bulk_commit_every` documents and once at the end. If the
+    /// returned future is dropped mid-run, batches stored so far stay uncommitted until
+    /// the next `index`, `index_documents`, `index_directory` or `close`.
+    pub async fn index_directory<F: FnMut(IndexProgress)>(&mut self, root: &Path, config: &Config, callback: F) -> Result<IndexReport> {
+        self.check_writable()?;
+        let files = walk_source_files(root, &config.indexing, config.respect_gitignore)?;
+        self.index_walked(files, config, None, callback).await?.into_result()
+    }
+
+    /// `index_directory` that can pick up after an interruption. Files the checkpoint
+    /// at `checkpoint_path` records as indexed and unchanged since are skipped, and
+    /// neither counted in the progress nor reported. Every
+    /// `config.indexing.bulk_commit_every` files the Tantivy index is committed and the
+    /// checkpoint saved, so it never records files a crash could roll back. The
+    /// checkpoint is deleted once the run finishes.
+    pub async fn index_directory_resumable<F: FnMut(IndexProgress)>(
+        &mut self,
+        root: &Path,
+        config: &Config,
+        checkpoint_path: &Path,
+        callback: F,
+    ) -> Result<IndexReport> {
+        self.check_writable()?;
+        let mut checkpoint = IndexCheckpoint::load(checkpoint_path)?;
+        let files = walk_source_files(root, &config.indexing, config.respect_gitignore)?
+            .into_iter()
+            .filter(|file| !checkpoint.is_current(file))
+            .collect();
+        let report = self.index_walked(files, config, Some((&mut checkpoint, checkpoint_path)), callback).await?.into_result()?;
+        IndexCheckpoint::remove(checkpoint_path)?;
+        Ok(report)
+    }
+
+    /// Bring the index in line with the tree under `root`, as a watcher does after
+    /// the tree changed: files that are new, or whose modification time differs from
+    /// the one they were indexed with, are (re)indexed, and indexed files under `root`
+    /// that the walk no longer yields, deleted or newly excluded, are dropped. Files
+    /// that fail to index are logged and left out of the report.
+    pub async fn reconcile(&mut self, root: &Path, config: &Config) -> Result<ReconcileReport> {
+        self.sync_with_walk(root, config, |_| true, true).await
+    }
+
+    /// `reconcile` for just the files at or under `changed`, e.g. paths reported by
+    /// filesystem events. Those the walk of `root` yields are re-indexed whatever their
+    /// modification time; indexed ones it doesn't yield are dropped.
+    pub async fn reindex_paths(&mut self, root: &Path, changed: &[PathBuf], config: &Config) -> Result<ReconcileReport> {
+        self.sync_with_walk(root, config, |path| changed.iter().any(|c| path.starts_with(c)), false).await
+    }
+
+    async fn sync_with_walk(
+        &mut self,
+        root: &Path,
+        config: &Config,
+        touched: impl Fn(&Path) -> bool,
+        only_modified: bool,
+    ) -> Result<ReconcileReport> {
+        self.check_writable()?;
+        let indexed: HashSet<String> = self.indexed_paths()?.into_iter().collect();
+        let walked: Vec<PathBuf> = walk_source_files(root, &config.indexing, config.respect_gitignore)?
+            .into_iter()
+            .filter(|file| touched(file))
+            .collect();
+        let walked_paths: HashSet<String> = walked.iter().map(|file| file.display().to_string()).collect();
+
+        let mut removed: Vec<String> = indexed
+            .iter()
+            .filter(|path| Path::new(path).starts_with(root) && touched(Path::new(path)) && !walked_paths.contains(*path))
+            .cloned()
+            .collect();
+        removed.sort();
+        self.remove_files(&removed)?;
+
+        let files: Vec<PathBuf> = walked
+            .into_iter()
+            .filter(|file| !only_modified || self.indexed_mtime(&file.display().to_string()) != file_mtime(file))
+            .collect();
+        let indexed_report = if files.is_empty() {
+            IndexReport::default()
+        } else {
+            self.index_walked(files, config, None, |_| {}).await?
+        };
+        for (path, error) in &indexed_report.failed {
+            log::warn!("Failed to index {}: {}", path, error);
+        }
+
+        let mut report = ReconcileReport { removed: removed.into_iter().map(PathBuf::from).collect(), ..Default::default() };
+        for path in indexed_report.succeeded {
+            if indexed.contains(&path) {
+                report.updated.push(PathBuf::from(path));
+            } else {
+                report.added.push(PathBuf::from(path));
+            }
+        }
+        Ok(report)
+    }
+
+    /// Modification time, in seconds, `file_path` had when it was indexed
+    fn indexed_mtime(&self, file_path: &str) -> Option<u64> {
+        self.document_metadata.get(file_path)?.get(MTIME_METADATA_KEY)?.parse().ok()
+    }
+
+    /// Drop every entry of `file_paths` from the vectors, the keyword index and the
+    /// document metadata
+    fn remove_files(&mut self, file_paths: &[String]) -> Result<()> {
+        if file_paths.is_empty() {
+            return Ok(());
+        }
+        self.invalidate_result_cache();
+        self.text_backend.commit_bulk()?;
+        for file_path in file_paths {
+            self.vector_storage.remove(file_path)?;
+            self.document_metadata.remove(file_path);
+        }
+        self.text_backend.remove(file_paths)?;
+        self.save_document_metadata()
+    }
+
+    async fn index_walked<F: FnMut(IndexProgress)>(
+        &mut self,
+        files: Vec<PathBuf>,
+        config: &Config,
+        mut checkpoint: Option<(&mut IndexCheckpoint, &Path)>,
+        callback: F,
+    ) -> Result<IndexReport> {
+        self.text_backend.begin_bulk(config.indexing.bulk_commit_every);
+        let indexed = self.index_files(files, config, checkpoint.as_mut().map(|(c, path)| (&mut **c, *path)), callback).await;
+        // Commit even after a failure so the batches stored so far are kept
+        let committed = self.text_backend.commit_bulk();
+        self.save_document_metadata()?;
+        let saved = match (&committed, &checkpoint) {
+            (Ok(()), Some((checkpoint, path))) => checkpoint.save(path),
+            _ => Ok(()),
+        };
+        let report = indexed?;
+        committed?;
+        saved?;
+        Ok(report)
+    }
+
+    /// Index `files` in walk order. With a checkpoint, each stored file is recorded in
+    /// it, and it is saved after every `config.indexing.bulk_commit_every` files once
+    /// they are committed.
+    async fn index_files<F: FnMut(IndexProgress)>(
+        &mut self,
+        files: Vec<PathBuf>,
+        config: &Config,
+        mut checkpoint: Option<(&mut IndexCheckpoint, &Path)>,
+        mut callback: F,
+    ) -> Result<IndexReport> {
+        let workers = match config.index_concurrency {
+            0 => std::thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            n => n,
+        };
+        let mut files = files.into_iter().peekable();
+        let mut throttle = MemoryThrottle::new(config.indexing.min_free_memory_mb, self.memory_probe);
+        let mut next_position = 0;
+        let indexing = Arc::new(config.indexing.clone());
+        let on_invalid_utf8 = config.on_invalid_utf8;
+        let generated = config.generated_file_policy;
+
+        let mut running = tokio::task::JoinSet::new();
+        // Batches that finished ahead of an earlier one, keyed by walk position
+        let mut finished = BTreeMap::new();
+        let mut next_to_store = 0;
+        let mut progress = IndexProgress::default();
+        let mut report = IndexReport::default();
+        let mut unsaved = 0;
+        loop {
+            while running.len() < workers && files.peek().is_some() {
+                let size = throttle.next_batch_size(running.is_empty()).await?;
+                if size == 0 {
+                    break;
+                }
+                let batch: Vec<PathBuf> = files.by_ref().take(size).collect();
+                let position = next_position;
+                next_position += 1;
+                let embedders = self.embedders.clone();
+                let indexing = Arc::clone(&indexing);
+                running.spawn_blocking(move || (position, read_and_embed(&batch, &indexing, on_invalid_utf8, generated, embedders.as_ref())));
+            }
+            let Some(joined) = running.join_next().await else { break };
+            let (position, batch) = joined?;
+            finished.insert(position, batch);
+
+            while let Some(batch) = finished.remove(&next_to_store) {
+                next_to_store += 1;
+                progress.files_seen += batch.progress.files_seen;
+                progress.files_indexed += batch.progress.files_indexed;
+                progress.files_skipped += batch.progress.files_skipped;
+                progress.bytes_processed += batch.progress.bytes_processed;
+                report.failed.extend(batch.failed);
+                if !batch.contents.is_empty() {
+                    report.succeeded.extend(batch.file_paths.iter().cloned());
+                    let stored = batch.file_paths.len();
+                    self.store(batch.contents, batch.file_paths, batch.embeddings)?;
+                    let stored_paths = &report.succeeded[report.succeeded.len() - stored..];
+                    self.record_content_filter(stored_paths, config.indexing.content_filter);
+                    for file_path in stored_paths {
+                        let opaque = batch.opaque.contains(file_path);
+                        self.tag_document(file_path, GENERATED_METADATA_KEY, opaque.then_some(GENERATED_OPAQUE));
+                        let mtime = batch.modified.get(file_path).map(u64::to_string);
+                        self.tag_document(file_path, MTIME_METADATA_KEY, mtime.as_deref());
+                    }
+                    if let Some((checkpoint, _)) = checkpoint.as_mut() {
+                        for file_path in stored_paths {
+                            checkpoint.record(Path::new(file_path));
+                        }
+                        unsaved += stored;
+                    }
+                }
+                let commit_every = config.indexing.bulk_commit_every;
+                if let Some((checkpoint, path)) = checkpoint.as_ref().filter(|_| commit_every > 0 && unsaved >= commit_every) {
+                    self.text_backend.commit_bulk()?;
+                    self.text_backend.begin_bulk(commit_every);
+                    checkpoint.save(path)?;
+                    unsaved = 0;
+                }
+                callback(progress);
+            }
+        }
+        Ok(report)
+    }
+
+    /// Tag `file_paths` in the document metadata with the filter they were indexed
+    /// with, under `CONTENT_FILTER_METADATA_KEY`; `ContentFilter::All` removes the tag
+    fn record_content_filter(&mut self, file_paths: &[String], filter: ContentFilter) {
+        for file_path in file_paths {
+            let tag = (filter != ContentFilter::All).then_some(filter.as_str());
+            self.tag_document(file_path, CONTENT_FILTER_METADATA_KEY, tag);
+        }
+    }
+
+    /// Set `key` in the document metadata of `file_path` to `value`, or remove it
+    /// for `None`
+    fn tag_document(&mut self, file_path: &str, key: &str, value: Option<&str>) {
+        if let Some(value) = value {
+            self.document_metadata.entry(file_path.to_string()).or_default().insert(key.to_string(), value.to_string());
+        } else if let Some(metadata) = self.document_metadata.get_mut(file_path) {
+            metadata.remove(key);
+            if metadata.is_empty() {
+                self.document_metadata.remove(file_path);
+            }
+        }
+    }
+
+    /// Write embedded documents to the vector store and the text index; without
+    /// embeddings only the text index is updated
+    fn store(&mut self, contents: Vec<String>, file_paths: Vec<String>, embeddings: Option<Vec<Vec<f32>>>) -> Result<()> {
+        self.invalidate_result_cache();
+        if let Some(embeddings) = embeddings {
+            self.vector_storage.insert(contents.clone(), embeddings, file_paths.clone()).map_err(storage_failure)?;
+        }
+        self.text_backend.index(&contents, &file_paths)
+    }
+
+    /// Index documents keyed by `Document::id` rather than a file path. Documents whose
+    /// id is already indexed replace the stored version. Every document is embedded
+    /// with the text model, whatever its id looks like.
+    pub async fn index_documents(&mut self, docs: Vec<Document>) -> Result<()> {
+        self.check_writable()?;
+        let mut seen = HashMap::new();
+        for (position, doc) in docs.iter().enumerate() {
+            if let Some(first) = seen.insert(doc.id.as_str(), position) {
+                return Err(SearchError::InvalidParameter {
+                    parameter: "docs".to_string(),
+                    reason: format!("id {:?} appears at positions {} and {}", doc.id, first, position),
+                }.into());
+            }
+        }
+
+        let ids: Vec<String> = docs.iter().map(|doc| doc.id.clone()).collect();
+        let texts: Vec<String> = docs.iter().map(|doc| doc.text.clone()).collect();
+        let embeddings = match &self.embedders {
+            Some(embedders) => Some(
+                texts
+                    .iter()
+                    .map(|text| embedders.text.embed(text, DOCUMENT_TASK))
+                    .collect::<Result<Vec<_>>>()?,
+            ),
+            None => None,
+        };
+
+        // Embed before touching the indexes so a failure leaves the old versions in place
+        self.invalidate_result_cache();
+        self.text_backend.commit_bulk()?;
+        for id in &ids {
+            self.vector_storage.remove(id)?;
+            self.document_metadata.remove(id);
+        }
+        self.text_backend.remove(&ids)?;
+
+        if let Some(embeddings) = embeddings {
+            let metadata = docs.iter().map(|doc| doc.metadata.clone()).collect();
+            self.vector_storage.insert_with_metadata(texts.clone(), embeddings, ids.clone(), metadata)?;
+        }
+        self.text_backend.index(&texts, &ids)?;
+
+        for doc in docs {
+            if !doc.metadata.is_empty() {
+                self.document_metadata.insert(doc.id, doc.metadata);
+            }
+        }
+        self.save_document_metadata()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{SearchBackend, SemanticMode};
+    use crate::embedder::MinimalEmbedder;
+    use crate::search::text_backend::tests::numbered_files;
+    use crate::search::text_backend::TantivyText;
+    use crate::simple_search::tests::{document, PickyEmbedder};
+    use crate::simple_search::{MetadataFilter, SearchResult};
+    use crate::simple_storage::chunk_doc_id;
+    use std::time::SystemTime;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_index_documents_replaces_by_id_and_returns_metadata() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut search = HybridSearch::with_embedders(
+            &db_path,
+            &Config::default(),
+            Box::new(MinimalEmbedder::new(64)),
+            Box::new(MinimalEmbedder::new(64)),
+        ).await?;
+
+        search.index_documents(vec![
+            document("msg-1", "the deploy failed with a timeout", &[("channel", "ops")]),
+            document("msg-2", "lunch at noon", &[]),
+        ]).await?;
+        let results = search.search("deploy timeout", 5).await?;
+        assert_eq!(results[0].file_path, "msg-1");
+        assert_eq!(results[0].match_type, "hybrid");
+        assert_eq!(results[0].metadata.get("channel").map(String::as_str), Some("ops"));
+
+        search.index_documents(vec![document("msg-1", "rollback finished", &[("channel", "release")])]).await?;
+        assert!(search.search("deploy timeout", 5).await?.iter().all(|r| !r.content.contains("deploy")));
+        let results = search.search("rollback", 5).await?;
+        assert_eq!(results[0].file_path, "msg-1");
+        assert_eq!(results[0].metadata.get("channel").map(String::as_str), Some("release"));
+        assert_eq!(search.stats()?.vector_count, 2);
+
+        let duplicate = search.index_documents(vec![document("a", "x", &[]), document("a", "y", &[])]).await;
+        assert!(duplicate.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_document_metadata_survives_reopen() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        {
+            let mut search = HybridSearch::with_semantic_mode(&db_path, SemanticMode::Disabled).await?;
+            search.index_documents(vec![document("row-7", "invoice overdue", &[("table", "billing")])]).await?;
+        }
+        let mut search = HybridSearch::with_semantic_mode(&db_path, SemanticMode::Disabled).await?;
+        let results = search.search("invoice", 5).await?;
+        assert_eq!(results[0].metadata.get("table").map(String::as_str), Some("billing"));
+
+        search.clear().await?;
+        search.index_documents(vec![document("row-7", "invoice overdue", &[])]).await?;
+        assert!(search.search("invoice", 5).await?[0].metadata.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reindexing_a_modified_file_leaves_no_orphans() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut search = HybridSearch::with_embedders(
+            &db_path,
+            &Config::default(),
+            Box::new(MinimalEmbedder::new(64)),
+            Box::new(MinimalEmbedder::new(64)),
+        ).await?;
+        let paths = vec!["src/lib.rs".to_string(), "src/main.rs".to_string()];
+        search.index(vec!["fn old_name() {}".to_string(), "fn main() {}".to_string()], paths.clone()).await?;
+        search.index(vec!["fn new_name() {}".to_string()], paths[..1].to_vec()).await?;
+
+        assert_eq!(search.indexed_document_count()?, 2);
+        assert_eq!(search.vector_storage.len(), 2);
+        let record = search.vector_storage.records().into_iter().find(|r| r.file_path == paths[0]).unwrap();
+        assert_eq!((record.doc_id, record.content.as_str()), (chunk_doc_id(&paths[0], 0), "fn new_name() {}"));
+        assert!(search.search("old_name", 5).await?.iter().all(|r| !r.content.contains("old_name")));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_comments_only_indexing_leaves_code_unfindable() -> Result<()> {
+        let tree = tempdir()?;
+        std::fs::write(
+            tree.path().join("upload.rs"),
+            "// Retries with exponential backoff\nfn send_payload() {\n    /* jitter keeps clients apart */\n}\n",
+        )?;
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut config = Config::default();
+        config.search.semantic_mode = SemanticMode::Disabled;
+        config.indexing.content_filter = ContentFilter::CommentsOnly;
+        let mut search = HybridSearch::with_config(&db_path, &config).await?;
+        search.index_directory(tree.path(), &config, |_| {}).await?;
+
+        assert!(search.search("send_payload", 5).await?.is_empty());
+        assert_eq!(search.search("backoff", 5).await?.len(), 1);
+        assert_eq!(search.search("jitter", 5).await?.len(), 1);
+        let comments = MetadataFilter::new().equals(CONTENT_FILTER_METADATA_KEY, "comments_only");
+        assert_eq!(search.search_with_metadata_filter("backoff", 5, &comments).await?.len(), 1);
+
+        config.indexing.content_filter = ContentFilter::CodeOnly;
+        search.index_directory(tree.path(), &config, |_| {}).await?;
+        assert!(search.search("backoff", 5).await?.is_empty());
+        assert_eq!(search.search("send_payload", 5).await?.len(), 1);
+        assert!(search.search_with_metadata_filter("send_payload", 5, &comments).await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_generated_files_follow_the_configured_policy() -> Result<()> {
+        let tree = tempdir()?;
+        let minified = "var n=0;function debounceHandler(e){return setTimeout(e,n)}".repeat(40);
+        std::fs::write(tree.path().join("app.min.js"), minified)?;
+        std::fs::write(tree.path().join("bundle.js"), "function renderWidget(el) {\n  return el;\n}\n//# sourceMappingURL=bundle.js.map\n")?;
+        std::fs::write(tree.path().join("widget.rs"), "fn render_widget() {}\n")?;
+        let opaque = MetadataFilter::new().equals(GENERATED_METADATA_KEY, GENERATED_OPAQUE);
+
+        for policy in [GeneratedFilePolicy::Skip, GeneratedFilePolicy::IndexAsOpaque, GeneratedFilePolicy::Normal] {
+            let temp_dir = tempdir()?;
+            let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+            let mut config = Config::default();
+            config.search.semantic_mode = SemanticMode::Disabled;
+            config.generated_file_policy = policy;
+            let mut search = HybridSearch::with_config(&db_path, &config).await?;
+            search.index_directory(tree.path(), &config, |_| {}).await?;
+
+            let mut indexed: Vec<String> = search
+                .indexed_paths()?
+                .iter()
+                .map(|path| Path::new(path).file_name().unwrap().to_string_lossy().into_owned())
+                .collect();
+            indexed.sort();
+            let tagged = search.search_with_metadata_filter("debounceHandler", 5, &opaque).await?.len()
+                + search.search_with_metadata_filter("renderWidget", 5, &opaque).await?.len();
+            match policy {
+                GeneratedFilePolicy::Skip => assert_eq!(indexed, ["widget.rs"]),
+                _ => assert_eq!(indexed, ["app.min.js", "bundle.js", "widget.rs"]),
+            }
+            assert_eq!(tagged, if policy == GeneratedFilePolicy::IndexAsOpaque { 2 } else { 0 }, "{:?}", policy);
+        }
+        Ok(())
+    }
+
+    async fn index_tree_with_concurrency(root: &Path, concurrency: usize) -> Result<(tempfile::TempDir, HybridSearch)> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let config = Config { index_concurrency: concurrency, ..Default::default() };
+        let mut search = HybridSearch::with_embedders(
+            &db_path,
+            &config,
+            Box::new(MinimalEmbedder::new(64)),
+            Box::new(MinimalEmbedder::new(64)),
+        ).await?;
+        let mut progress = IndexProgress::default();
+        let report = search.index_directory(root, &config, |p| progress = p).await?;
+        assert_eq!(report.succeeded.len(), 35);
+        assert!(report.failed.is_empty());
+        assert_eq!(progress.files_indexed, 35);
+        assert_eq!(progress.files_skipped, 1);
+        Ok((temp_dir, search))
+    }
+
+    #[tokio::test]
+    async fn test_index_directory_result_does_not_depend_on_concurrency() -> Result<()> {
+        let tree = tempdir()?;
+        for i in 0..35 {
+            let (name, content) = if i % 3 == 0 {
+                (format!("notes_{}.md", i), format!("# Note {}\n\nparse the config for module {}", i, i))
+            } else {
+                (format!("module_{}.rs", i), format!("fn parse_config_{}() {{ load(\"module {}\") }}", i, i))
+            };
+            std::fs::write(tree.path().join(name), content)?;
+        }
+        std::fs::write(tree.path().join("image.bin"), [0u8, 159, 146, 150])?;
+
+        let (_dir1, mut sequential) = index_tree_with_concurrency(tree.path(), 1).await?;
+        let (_dir4, mut parallel) = index_tree_with_concurrency(tree.path(), 4).await?;
+
+        let (stats1, stats4) = (sequential.stats()?, parallel.stats()?);
+        assert_eq!(stats1.documents, stats4.documents);
+        assert_eq!(stats1.vector_count, stats4.vector_count);
+        assert_eq!(sequential.text_backend.paths()?, parallel.text_backend.paths()?);
+        for path in sequential.text_backend.paths()? {
+            let content = std::fs::read_to_string(&path)?;
+            let embedding = sequential.vector_storage.embedding(&path, &content);
+            assert!(embedding.is_some());
+            assert_eq!(embedding, parallel.vector_storage.embedding(&path, &content));
+        }
+        for query in ["parse config", "module 7", "note"] {
+            let ranked = |results: Vec<SearchResult>| -> Vec<(String, f32)> {
+                results.into_iter().map(|r| (r.file_path, r.score)).collect()
+            };
+            assert_eq!(ranked(sequential.search(query, 10).await?), ranked(parallel.search(query, 10).await?));
+        }
+        Ok(())
+    }
+
+    /// Index a tree of source files, with `embedignore` as its `.embedignore` when given,
+    /// and return the indexed paths relative to the tree
+    async fn indexed_with_excludes(embedignore: Option<&str>, exclude: &[&str]) -> Result<Vec<String>> {
+        let tree = tempdir()?;
+        for file in ["src/lib.rs", "src/app.min.js", "src/keep.min.js", "vendor/dep.rs", "node_modules/pkg/index.js", "README.md"] {
+            let path = tree.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap())?;
+            std::fs::write(path, "fn indexed() {}")?;
+        }
+        if let Some(embedignore) = embedignore {
+            std::fs::write(tree.path().join(crate::indexer::EMBED_IGNORE_FILE), embedignore)?;
+        }
+
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut config = Config::default();
+        config.search.semantic_mode = SemanticMode::Disabled;
+        config.indexing.exclude = exclude.iter().map(|p| p.to_string()).collect();
+        let mut search = HybridSearch::with_config(&db_path, &config).await?;
+        search.index_directory(tree.path(), &config, |_| {}).await?;
+        Ok(search
+            .indexed_paths()?
+            .iter()
+            .map(|path| Path::new(path).strip_prefix(tree.path()).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect())
+    }
+
+    #[tokio::test]
+    async fn test_low_memory_shrinks_index_batches() -> Result<()> {
+        // Memory is short for the first four checks, then recovers
+        static CHECKS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+        fn recovering_probe() -> Option<u64> {
+            match CHECKS.fetch_add(1, std::sync::atomic::Ordering::SeqCst) {
+                0..=3 => Some(10),
+                _ => Some(u64::MAX),
+            }
+        }
+
+        let tree = tempdir()?;
+        for i in 0..2 * INDEX_BATCH_SIZE {
+            std::fs::write(tree.path().join(format!("file_{:02}.rs", i)), format!("fn item_{}() {{}}", i))?;
+        }
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut config = Config::default();
+        config.search.semantic_mode = SemanticMode::Disabled;
+        config.index_concurrency = 2;
+        config.indexing.min_free_memory_mb = 512;
+        let mut search = HybridSearch::with_config(&db_path, &config).await?;
+        search.memory_probe = recovering_probe;
+
+        let mut batches = 0;
+        let report = search.index_directory(tree.path(), &config, |_| batches += 1).await?;
+        assert_eq!(report.succeeded.len(), 2 * INDEX_BATCH_SIZE);
+        assert!(batches > 2, "expected smaller batches, got {}", batches);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_resumed_index_only_indexes_files_after_the_interruption() -> Result<()> {
+        use futures_util::FutureExt;
+
+        let tree = tempdir()?;
+        let total = 3 * INDEX_BATCH_SIZE;
+        let paths: Vec<PathBuf> = (0..total).map(|i| tree.path().join(format!("file_{:02}.rs", i))).collect();
+        for (i, path) in paths.iter().enumerate() {
+            std::fs::write(path, format!("fn item_{}() {{}}", i))?;
+        }
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let checkpoint_path = temp_dir.path().join(crate::indexer::INDEX_CHECKPOINT_FILE);
+        let mut config = Config::default();
+        config.search.semantic_mode = SemanticMode::Disabled;
+        config.index_concurrency = 1;
+        config.indexing.bulk_commit_every = INDEX_BATCH_SIZE;
+
+        // Crash once two batches are stored, dropping the search without closing it
+        let mut search = HybridSearch::with_config(&db_path, &config).await?;
+        let interrupted = std::panic::AssertUnwindSafe(search.index_directory_resumable(tree.path(), &config, &checkpoint_path, |p| {
+            if p.files_indexed == 2 * INDEX_BATCH_SIZE {
+                panic!("simulated crash");
+            }
+        }))
+        .catch_unwind()
+        .await;
+        assert!(interrupted.is_err());
+        drop(search);
+        assert_eq!(IndexCheckpoint::load(&checkpoint_path)?.len(), 2 * INDEX_BATCH_SIZE);
+
+        let mut search = HybridSearch::with_config(&db_path, &config).await?;
+        let mut last = IndexProgress::default();
+        let report = search.index_directory_resumable(tree.path(), &config, &checkpoint_path, |p| last = p).await?;
+        let remaining: Vec<String> = paths[2 * INDEX_BATCH_SIZE..].iter().map(|path| path.display().to_string()).collect();
+        assert_eq!(report.succeeded, remaining);
+        assert_eq!(last.files_seen, INDEX_BATCH_SIZE);
+        assert_eq!(search.text_backend.len()?, total);
+        assert!(!checkpoint_path.exists());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_memory_throttle_gives_up_when_memory_never_recovers() {
+        let mut throttle = MemoryThrottle { pause: Duration::ZERO, ..MemoryThrottle::new(512, || Some(10)) };
+        let mut sizes = Vec::new();
+        let err = loop {
+            match throttle.next_batch_size(true).await {
+                Ok(size) => sizes.push(size),
+                Err(e) => break e,
+            }
+        };
+        assert_eq!(sizes, [5, 2, 1]);
+        assert!(matches!(err.downcast_ref::<SearchError>(), Some(SearchError::ResourceExhausted { .. })));
+
+        // Running batches are waited for rather than failing
+        let mut throttle = MemoryThrottle { batch_size: 1, ..MemoryThrottle::new(512, || Some(10)) };
+        assert_eq!(throttle.next_batch_size(false).await.unwrap(), 0);
+        let mut disabled = MemoryThrottle::new(0, || Some(10));
+        assert_eq!(disabled.next_batch_size(true).await.unwrap(), INDEX_BATCH_SIZE);
+    }
+
+    #[tokio::test]
+    async fn test_embedignore_excludes_directories() -> Result<()> {
+        let paths = indexed_with_excludes(Some("vendor/\nnode_modules\n"), &[]).await?;
+        assert_eq!(paths, vec!["README.md", "src/app.min.js", "src/keep.min.js", "src/lib.rs"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_exclude_patterns_match_globs() -> Result<()> {
+        let paths = indexed_with_excludes(None, &["*.min.js", "node_modules/"]).await?;
+        assert_eq!(paths, vec!["README.md", "src/lib.rs", "vendor/dep.rs"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_negated_exclude_pattern_reincludes_file() -> Result<()> {
+        // Command-line patterns come after the file, so they can undo its excludes
+        let paths = indexed_with_excludes(Some("*.js\n!keep.min.js\n"), &["!app.min.js", "vendor"]).await?;
+        assert_eq!(paths, vec!["README.md", "src/app.min.js", "src/keep.min.js", "src/lib.rs"]);
+        let paths = indexed_with_excludes(Some("*.js\n!keep.min.js\n"), &[]).await?;
+        assert_eq!(paths, vec!["README.md", "src/keep.min.js", "src/lib.rs", "vendor/dep.rs"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_index_directory_reports_failed_files_and_indexes_the_rest() -> Result<()> {
+        let tree = tempdir()?;
+        std::fs::write(tree.path().join("good.rs"), "fn parse_config() {}")?;
+        std::fs::write(tree.path().join("notes.md"), "# Parsing the config")?;
+        std::fs::write(tree.path().join("broken.rs"), [0x66u8, 0x6e, 0xff, 0xfe])?;
+
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let config = Config::default();
+        let mut search = HybridSearch::with_embedders(
+            &db_path,
+            &config,
+            Box::new(MinimalEmbedder::new(64)),
+            Box::new(MinimalEmbedder::new(64)),
+        ).await?;
+        let report = search.index_directory(tree.path(), &config, |_| {}).await?;
+
+        let file = |name: &str| tree.path().join(name).display().to_string();
+        assert_eq!(report.succeeded, vec![file("good.rs"), file("notes.md")]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, file("broken.rs"));
+        assert!(matches!(&report.failed[0].1, SearchError::FileDecode { path, .. } if *path == file("broken.rs")));
+        assert_eq!(search.indexed_paths()?, vec![file("good.rs"), file("notes.md")]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_invalid_utf8_policy_decides_what_happens_to_latin1_files() -> Result<()> {
+        let tree = tempdir()?;
+        std::fs::write(tree.path().join("good.rs"), "fn menu() {}")?;
+        // "café" in Latin-1: 0xE9 on its own is not valid UTF-8
+        std::fs::write(tree.path().join("latin1.rs"), b"// caf\xe9 menu\nfn order() {}")?;
+        let latin1 = tree.path().join("latin1.rs").display().to_string();
+
+        for policy in [InvalidUtf8Policy::Skip, InvalidUtf8Policy::Lossy, InvalidUtf8Policy::Error] {
+            let temp_dir = tempdir()?;
+            let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+            let mut config = Config::default();
+            config.search.semantic_mode = SemanticMode::Disabled;
+            config.on_invalid_utf8 = policy;
+            let mut search = HybridSearch::with_config(&db_path, &config).await?;
+            let report = search.index_directory(tree.path(), &config, |_| {}).await?;
+
+            let indexed = search.indexed_paths()?.contains(&latin1);
+            match policy {
+                InvalidUtf8Policy::Skip => {
+                    assert!(!indexed);
+                    assert!(report.failed.is_empty());
+                }
+                InvalidUtf8Policy::Lossy => {
+                    assert!(indexed);
+                    assert!(report.failed.is_empty());
+                    let results = search.search("order", 5).await?;
+                    assert_eq!(results[0].file_path, latin1);
+                    assert!(results[0].content.starts_with("// caf\u{FFFD} menu"));
+                }
+                InvalidUtf8Policy::Error => {
+                    assert!(!indexed);
+                    assert_eq!(report.failed.len(), 1);
+                    assert!(matches!(&report.failed[0].1, SearchError::FileDecode { path, .. } if *path == latin1));
+                }
+            }
+            assert_eq!(report.succeeded.len(), if indexed { 2 } else { 1 }, "{:?}", policy);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_index_keeps_files_that_embed_and_fails_only_when_none_do() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut search = HybridSearch::with_embedders(
+            &db_path,
+            &Config::default(),
+            Box::new(PickyEmbedder(MinimalEmbedder::new(64))),
+            Box::new(PickyEmbedder(MinimalEmbedder::new(64))),
+        ).await?;
+
+        let report = search.index(
+            vec!["fn parse() {}".to_string(), "unembeddable".to_string(), "# Parsing".to_string()],
+            vec!["parse.rs".to_string(), "odd.rs".to_string(), "parse.md".to_string()],
+        ).await?;
+        assert_eq!(report.succeeded, vec!["parse.rs", "parse.md"]);
+        assert_eq!(report.failed.len(), 1);
+        assert!(matches!(&report.failed[0].1, SearchError::FileEmbed { path, .. } if path == "odd.rs"));
+        assert_eq!(search.indexed_paths()?, vec!["parse.md", "parse.rs"]);
+
+        let err = search.index(vec!["unembeddable".to_string()], vec!["odd.rs".to_string()]).await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<SearchError>(), Some(SearchError::FileEmbed { .. })));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_index_directory_respects_gitignore_unless_disabled() -> Result<()> {
+        let tree = tempdir()?;
+        for file in ["src/lib.rs", "build/generated.rs", "build/nested/out.rs"] {
+            let path = tree.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap())?;
+            std::fs::write(path, "fn indexed() {}")?;
+        }
+        std::fs::write(tree.path().join(".gitignore"), "build/\n")?;
+
+        for (respect_gitignore, expected) in [
+            (true, vec!["src/lib.rs"]),
+            (false, vec!["build/generated.rs", "build/nested/out.rs", "src/lib.rs"]),
+        ] {
+            let temp_dir = tempdir()?;
+            let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+            let mut config = Config::default();
+            config.search.semantic_mode = SemanticMode::Disabled;
+            config.respect_gitignore = respect_gitignore;
+            let mut search = HybridSearch::with_config(&db_path, &config).await?;
+            search.index_directory(tree.path(), &config, |_| {}).await?;
+            let paths: Vec<String> = search
+                .indexed_paths()?
+                .iter()
+                .map(|path| Path::new(path).strip_prefix(tree.path()).unwrap().to_string_lossy().replace('\\', "/"))
+                .collect();
+            assert_eq!(paths, expected, "respect_gitignore = {}", respect_gitignore);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_watch_passes_update_the_configured_backend() -> Result<()> {
+        for backend in [SearchBackend::default(), SearchBackend::Bm25Only] {
+            let tree = tempdir()?;
+            let root = tree.path();
+            std::fs::write(root.join("a.rs"), "fn apple() {}")?;
+            std::fs::write(root.join("b.rs"), "fn banana() {}")?;
+            let temp_dir = tempdir()?;
+            let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+            let mut config = Config::default();
+            config.search.semantic_mode = SemanticMode::Disabled;
+            config.search.backend = backend;
+            let mut search = HybridSearch::with_config(&db_path, &config).await?;
+            search.index_directory(root, &config, |_| {}).await?;
+
+            let names = |paths: &[PathBuf]| -> Vec<String> {
+                paths.iter().map(|path| path.file_name().unwrap().to_string_lossy().into_owned()).collect()
+            };
+            let hits = |results: Vec<SearchResult>| -> Vec<String> {
+                results.iter().map(|r| Path::new(&r.file_path).file_name().unwrap().to_string_lossy().into_owned()).collect()
+            };
+
+            // Filesystem events: an edit, a delete and a new file
+            std::fs::write(root.join("a.rs"), "fn cherry() {}")?;
+            std::fs::remove_file(root.join("b.rs"))?;
+            std::fs::write(root.join("c.rs"), "fn durian() {}")?;
+            let changed = ["a.rs", "b.rs", "c.rs"].map(|file| root.join(file));
+            let report = search.reindex_paths(root, &changed, &config).await?;
+            assert_eq!((names(&report.added), names(&report.updated), names(&report.removed)), (vec!["c.rs".to_string()], vec!["a.rs".to_string()], vec!["b.rs".to_string()]), "{:?}", backend);
+            assert_eq!(hits(search.search("cherry", 5).await?), ["a.rs"], "{:?}", backend);
+            assert!(search.search("apple", 5).await?.is_empty(), "{:?}", backend);
+            assert!(search.search("banana", 5).await?.is_empty(), "{:?}", backend);
+
+            // Polling: only files whose modification time moved are re-indexed
+            std::fs::write(root.join("c.rs"), "fn elderberry() {}")?;
+            std::fs::File::options().write(true).open(root.join("c.rs"))?.set_modified(SystemTime::now() + Duration::from_secs(5))?;
+            std::fs::remove_file(root.join("a.rs"))?;
+            let report = search.reconcile(root, &config).await?;
+            assert_eq!((names(&report.added), names(&report.updated), names(&report.removed)), (vec![], vec!["c.rs".to_string()], vec!["a.rs".to_string()]), "{:?}", backend);
+            assert_eq!(hits(search.search("elderberry", 5).await?), ["c.rs"], "{:?}", backend);
+            assert!(search.search("cherry", 5).await?.is_empty(), "{:?}", backend);
+            assert_eq!(search.reconcile(root, &config).await?, ReconcileReport::default());
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_applies_the_indexing_settings_of_a_reloaded_config() -> Result<()> {
+        let tree = tempdir()?;
+        let root = tree.path();
+        std::fs::write(root.join("a.rs"), "fn apple() {}")?;
+        std::fs::write(root.join("b.rs"), "fn banana() {}")?;
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut config = Config::default();
+        config.search.semantic_mode = SemanticMode::Disabled;
+        let mut search = HybridSearch::with_config(&db_path, &config).await?;
+        search.index_directory(root, &config, |_| {}).await?;
+
+        // Neither file changed on disk, only which files the config lets in
+        let mut reloaded = config.clone();
+        reloaded.indexing.exclude = vec!["b.rs".to_string()];
+        let report = search.reconcile(root, &reloaded).await?;
+        assert_eq!(report.removed, vec![root.join("b.rs")]);
+        assert!(search.search("banana", 5).await?.is_empty());
+
+        let report = search.reconcile(root, &config).await?;
+        assert_eq!(report.added, vec![root.join("b.rs")]);
+        assert_eq!(search.search("banana", 5).await?.len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_index_after_cancelled_bulk_run_commits_its_batches() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().to_str().unwrap().to_string();
+        let (contents, paths) = numbered_files(3);
+
+        let mut search = HybridSearch::with_semantic_mode(&db_path, SemanticMode::Disabled).await?;
+        // What a dropped `index_directory` future leaves behind
+        search.text_backend.begin_bulk(0);
+        search.store(contents[..2].to_vec(), paths[..2].to_vec(), None)?;
+        search.index(contents[2..].to_vec(), paths[2..].to_vec()).await?;
+        search.close().await?;
+
+        let reopened = TantivyText::open(&db_path)?;
+        assert_eq!(reopened.paths()?, paths.iter().cloned().collect());
+        Ok(())
+    }
+}
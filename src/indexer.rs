@@ -1,32 +1,286 @@
 // Incremental indexing with change detection
 
 use anyhow::Result;
-use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 use ignore::WalkBuilder;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use parking_lot::Mutex;
 
-use crate::config::IndexingConfig;
-use crate::chunking::{Chunk, SimpleRegexChunker, MarkdownRegexChunker};
+use crate::config::{GeneratedFilePolicy, IndexingConfig, InvalidUtf8Policy};
+use crate::content_filter::CommentSyntax;
+use crate::error::SearchError;
+use crate::chunking::{Chunk, ChunkBoundary, SimpleRegexChunker, MarkdownRegexChunker};
+use crate::semantic_chunker::{BoundaryChunker, ChunkingStrategy};
 use crate::gguf_embedder::{GGUFEmbedder, GGUFEmbedderConfig};
+use crate::embedder::Embedder;
 use crate::embedding_prefixes::{EmbeddingTask, CodeFormatter};
 use crate::simple_storage::VectorStorage;
 use crate::search::bm25_fixed::BM25Engine;
 
+/// Entry in `supported_extensions` that matches any file, regardless of extension
+pub const ANY_EXTENSION: &str = "*";
+
+/// Check a path against the configured extensions. Shared by the CLI and
+/// `IncrementalIndexer` so both pick the same files.
+pub fn is_indexable_extension(path: &Path, extensions: &[String]) -> bool {
+    if extensions.iter().any(|ext| ext == ANY_EXTENSION) {
+        return true;
+    }
+
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext_str) => extensions.iter().any(|ext| ext.eq_ignore_ascii_case(ext_str)),
+        None => false,
+    }
+}
+
+/// Whether a file of `size` bytes is over the configured limit (0 means no limit)
+pub fn exceeds_max_file_size(size: u64, max_file_size: usize) -> bool {
+    max_file_size != 0 && size > max_file_size as u64
+}
+
+/// Text of the file at `path` read as `bytes`, with invalid UTF-8 handled per `policy`.
+/// `Ok(None)` means the file is skipped.
+pub fn decode_source(path: &Path, bytes: Vec<u8>, policy: InvalidUtf8Policy) -> std::result::Result<Option<String>, SearchError> {
+    let error = match String::from_utf8(bytes) {
+        Ok(text) => return Ok(Some(text)),
+        Err(error) => error,
+    };
+    match policy {
+        InvalidUtf8Policy::Skip => {
+            log::warn!("Skipping {}: {}", path.display(), error.utf8_error());
+            Ok(None)
+        }
+        InvalidUtf8Policy::Lossy => Ok(Some(String::from_utf8_lossy(error.as_bytes()).into_owned())),
+        InvalidUtf8Policy::Error => Err(SearchError::FileDecode {
+            path: path.display().to_string(),
+            reason: error.utf8_error().to_string(),
+        }),
+    }
+}
+
+/// Metadata key tagging files indexed under `GeneratedFilePolicy::IndexAsOpaque`
+pub const GENERATED_METADATA_KEY: &str = "generated";
+/// Value stored under `GENERATED_METADATA_KEY`
+pub const GENERATED_OPAQUE: &str = "opaque";
+
+/// Files shorter than this are never judged minified by their lines
+const MINIFIED_MIN_BYTES: usize = 1024;
+/// Mean line length, in characters, above which a file is minified whatever its spacing
+const MINIFIED_MEAN_LINE_CHARS: usize = 500;
+/// Mean line length above which a file with little whitespace is minified
+const DENSE_MEAN_LINE_CHARS: usize = 120;
+/// Share of non-whitespace characters that counts as little whitespace
+const DENSE_NON_WHITESPACE_SHARE: f32 = 0.85;
+
+/// Whether `content` looks minified or generated: it carries a source map comment
+/// (`//# sourceMappingURL=`), or it is source code in a language with known comment
+/// syntax whose lines are very long on average, or long and nearly free of
+/// whitespace. Prose such as markdown, where a paragraph is often one line, is only
+/// judged by the source map comment.
+pub fn is_generated_source(file_path: &str, content: &str) -> bool {
+    let has_source_map = content.lines().any(|line| {
+        let line = line.trim_start();
+        ["//# sourceMappingURL=", "//@ sourceMappingURL=", "/*# sourceMappingURL="].iter().any(|marker| line.starts_with(marker))
+    });
+    if has_source_map {
+        return true;
+    }
+    if content.len() < MINIFIED_MIN_BYTES || CommentSyntax::for_path(file_path).is_none() {
+        return false;
+    }
+    let chars = content.chars().count();
+    let mean_line_chars = chars / content.lines().count().max(1);
+    let non_whitespace_share = content.chars().filter(|c| !c.is_whitespace()).count() as f32 / chars as f32;
+    mean_line_chars > MINIFIED_MEAN_LINE_CHARS
+        || (mean_line_chars > DENSE_MEAN_LINE_CHARS && non_whitespace_share > DENSE_NON_WHITESPACE_SHARE)
+}
+
+/// Ignore file, in gitignore syntax, read from the root of an indexed tree
+pub const EMBED_IGNORE_FILE: &str = ".embedignore";
+
+/// Matcher for paths under `root` that must not be indexed: the root's
+/// `EMBED_IGNORE_FILE` followed by `patterns`, all in gitignore syntax. Later
+/// patterns win, so `!pattern` re-includes what an earlier one excluded.
+pub fn build_exclude_matcher(root: &Path, patterns: &[String]) -> Result<Gitignore> {
+    let invalid = |e: ignore::Error| SearchError::InvalidParameter {
+        parameter: "exclude".to_string(),
+        reason: e.to_string(),
+    };
+    let mut builder = GitignoreBuilder::new(root);
+    let ignore_file = root.join(EMBED_IGNORE_FILE);
+    if ignore_file.is_file() {
+        if let Some(e) = builder.add(&ignore_file) {
+            return Err(invalid(e).into());
+        }
+    }
+    for pattern in patterns {
+        builder.add_line(None, pattern).map_err(invalid)?;
+    }
+    Ok(builder.build().map_err(invalid)?)
+}
+
+/// Files under `root`, sorted by name within each directory, with the paths matched by
+/// `build_exclude_matcher` pruned. With `respect_gitignore`, paths ignored by global,
+/// repository and nested `.gitignore` files are pruned too, whether or not `root` is
+/// inside a git repository. Hidden files are walked like any other.
+pub fn walk_source_files(root: &Path, indexing: &IndexingConfig, respect_gitignore: bool) -> Result<Vec<PathBuf>> {
+    let excludes = build_exclude_matcher(root, &indexing.exclude)?;
+    let walk = WalkBuilder::new(root)
+        .standard_filters(false)
+        .git_ignore(respect_gitignore)
+        .git_global(respect_gitignore)
+        .git_exclude(respect_gitignore)
+        .parents(respect_gitignore)
+        .require_git(false)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .filter_entry(move |e| {
+            e.depth() == 0 || !excludes.matched(e.path(), e.file_type().is_some_and(|t| t.is_dir())).is_ignore()
+        })
+        .build();
+    Ok(walk
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_some_and(|t| t.is_file()))
+        .map(|e| e.into_path())
+        .collect())
+}
+
+/// File in the db directory that records an unfinished directory index
+pub const INDEX_CHECKPOINT_FILE: &str = "index_checkpoint.json";
+
+/// Size and modification time of a file when it was indexed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct FileStamp {
+    size: u64,
+    modified_secs: u64,
+    modified_nanos: u32,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(SystemTime::UNIX_EPOCH).ok()?;
+        Some(Self { size: metadata.len(), modified_secs: modified.as_secs(), modified_nanos: modified.subsec_nanos() })
+    }
+}
+
+/// Files a directory index has finished with, by path, size and modification time.
+/// A run that is interrupted leaves it behind so the next one can skip the files
+/// that have not changed since.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexCheckpoint {
+    files: BTreeMap<String, FileStamp>,
+}
+
+impl IndexCheckpoint {
+    /// The checkpoint saved at `path`, empty when there is none
+    pub fn load(path: &Path) -> Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(json) => Ok(serde_json::from_str(&json)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Write to a temp file and rename it over `path`, so a crash mid-save keeps the
+    /// previous checkpoint
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_string(self)?)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Delete the checkpoint at `path` once its run has finished; a missing one is fine
+    pub fn remove(path: &Path) -> Result<()> {
+        match std::fs::remove_file(path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Record `file` as indexed at its current size and modification time. A file
+    /// whose metadata can't be read is not recorded, so it is indexed again.
+    pub fn record(&mut self, file: &Path) {
+        if let Some(stamp) = FileStamp::of(file) {
+            self.files.insert(file.display().to_string(), stamp);
+        }
+    }
+
+    /// `file` was recorded and has the same size and modification time as then
+    pub fn is_current(&self, file: &Path) -> bool {
+        self.files.get(&file.display().to_string()).is_some_and(|stamp| FileStamp::of(file).as_ref() == Some(stamp))
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+/// Outcome of `IncrementalIndexer::reconcile` and `HybridSearch::reconcile`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReconcileReport {
+    pub added: Vec<PathBuf>,
+    pub updated: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+}
+
+/// One file's new index entries, computed by `IncrementalIndexer::prepare_reindex`
+/// without touching either index and swapped in by `IncrementalIndexer::apply_update`
+pub struct FileUpdate {
+    path: PathBuf,
+    /// Chunks and their embeddings plus the filtered content for BM25; `None` when
+    /// the file no longer exists and its entries are dropped
+    entries: Option<(Vec<String>, Vec<Vec<f32>>, String)>,
+    /// Indexed under `GeneratedFilePolicy::IndexAsOpaque`
+    opaque: bool,
+}
+
+impl FileUpdate {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Running totals emitted by `IncrementalIndexer::index_directory_with_progress`
+/// and `HybridSearch::index_directory`.
+/// Every file seen is counted as either indexed or skipped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IndexProgress {
+    pub files_seen: usize,
+    pub files_indexed: usize,
+    pub files_skipped: usize,
+    pub bytes_processed: u64,
+}
+
 pub struct IncrementalIndexer {
     config: IndexingConfig,
     indexed_files: HashSet<PathBuf>,
     last_index_time: SystemTime,
     regex_chunker: SimpleRegexChunker,
     markdown_chunker: MarkdownRegexChunker,
-    text_embedder: Option<GGUFEmbedder>,
-    code_embedder: Option<GGUFEmbedder>,
+    /// Set only for `ChunkingStrategy::SemanticBoundary`
+    boundary_chunker: Option<Mutex<BoundaryChunker>>,
+    text_embedder: Option<Box<dyn Embedder>>,
+    code_embedder: Option<Box<dyn Embedder>>,
+    /// Files `index_directory_with_progress` has indexed, including those of the run
+    /// it was resumed from
+    checkpoint: IndexCheckpoint,
+    generated_file_policy: GeneratedFilePolicy,
 }
 
 impl IncrementalIndexer {
     pub fn new(config: IndexingConfig) -> Result<Self> {
-        let regex_chunker = SimpleRegexChunker::with_chunk_size(config.chunk_size)?;
+        let regex_chunker = SimpleRegexChunker::with_chunk_size(config.chunk_size)?.with_overlap(config.chunk_overlap)?;
         let markdown_chunker = MarkdownRegexChunker::with_options(config.chunk_size, true)?;
+        let boundary_chunker = Self::boundary_chunker(&config)?;
         
         Ok(Self {
             config,
@@ -34,11 +288,51 @@ impl IncrementalIndexer {
             last_index_time: SystemTime::now(),
             regex_chunker,
             markdown_chunker,
+            boundary_chunker,
             text_embedder: None,
             code_embedder: None,
+            checkpoint: IndexCheckpoint::default(),
+            generated_file_policy: GeneratedFilePolicy::default(),
         })
     }
 
+    /// Resume an interrupted `index_directory_with_progress`: files `checkpoint` records
+    /// as indexed and unchanged since are skipped
+    pub fn with_checkpoint(mut self, checkpoint: IndexCheckpoint) -> Self {
+        self.checkpoint = checkpoint;
+        self
+    }
+
+    /// Handle minified and generated files per `policy`, normally
+    /// `Config::generated_file_policy`
+    pub fn with_generated_file_policy(mut self, policy: GeneratedFilePolicy) -> Self {
+        self.generated_file_policy = policy;
+        self
+    }
+
+    /// Embed with the given models instead of loading the GGUF files on first use,
+    /// e.g. `MinimalEmbedder` where no model is available
+    pub fn with_embedders(mut self, text_embedder: Box<dyn Embedder>, code_embedder: Box<dyn Embedder>) -> Self {
+        self.text_embedder = Some(text_embedder);
+        self.code_embedder = Some(code_embedder);
+        self
+    }
+
+    /// Files indexed so far, to save with `IndexCheckpoint::save` for a later resume
+    pub fn checkpoint(&self) -> &IndexCheckpoint {
+        &self.checkpoint
+    }
+
+    fn boundary_chunker(config: &IndexingConfig) -> Result<Option<Mutex<BoundaryChunker>>> {
+        match config.chunking_strategy {
+            ChunkingStrategy::FixedLines => Ok(None),
+            ChunkingStrategy::SemanticBoundary => Ok(Some(Mutex::new(
+                BoundaryChunker::new(config.chunk_size, config.chunk_overshoot_factor)?
+                    .with_overlap(config.chunk_overlap)?,
+            ))),
+        }
+    }
+
     /// Index only new or modified files
     pub fn init_embedders(&mut self) -> Result<()> {
         // Initialize text embedder for markdown files
@@ -46,41 +340,41 @@ impl IncrementalIndexer {
             model_path: "./src/model/nomic-embed-text-v1.5.Q4_K_M.gguf".to_string(),
             ..Default::default()
         };
-        self.text_embedder = Some(GGUFEmbedder::new(text_config)?);
+        self.text_embedder = Some(Box::new(GGUFEmbedder::new(text_config)?));
         
         // Initialize code embedder for all code files
         let code_config = GGUFEmbedderConfig {
             model_path: "./src/model/nomic-embed-code.Q4_K_M.gguf".to_string(),
             ..Default::default()
         };
-        self.code_embedder = Some(GGUFEmbedder::new(code_config)?);
+        self.code_embedder = Some(Box::new(GGUFEmbedder::new(code_config)?));
         
         Ok(())
     }
     
-    fn get_embedder_and_task(&self, file_path: &Path) -> (&GGUFEmbedder, EmbeddingTask) {
+    fn get_embedder_and_task(&self, file_path: &Path) -> (&dyn Embedder, EmbeddingTask) {
         // Determine which embedder and task to use based on file extension
         if let Some(ext) = file_path.extension() {
             if let Some(ext_str) = ext.to_str() {
                 match ext_str.to_lowercase().as_str() {
                     "md" | "markdown" => {
                         // Use text embedder for markdown files
-                        (self.text_embedder.as_ref().unwrap(), EmbeddingTask::SearchDocument)
+                        (self.text_embedder.as_deref().unwrap(), EmbeddingTask::SearchDocument)
                     },
                     "rs" | "py" | "js" | "ts" | "jsx" | "tsx" | "go" | "java" | "cpp" | "c" | "h" | "hpp" | "cc" | "cxx" | "cs" | "php" | "rb" | "swift" | "kt" | "scala" | "r" | "sh" | "bash" | "zsh" | "fish" | "ps1" | "psm1" | "lua" | "vim" | "el" => {
                         // Use code embedder for all code files
-                        (self.code_embedder.as_ref().unwrap(), EmbeddingTask::CodeDefinition)
+                        (self.code_embedder.as_deref().unwrap(), EmbeddingTask::CodeDefinition)
                     },
                     _ => {
                         // Default to text embedder for unknown file types
-                        (self.text_embedder.as_ref().unwrap(), EmbeddingTask::SearchDocument)
+                        (self.text_embedder.as_deref().unwrap(), EmbeddingTask::SearchDocument)
                     }
                 }
             } else {
-                (self.text_embedder.as_ref().unwrap(), EmbeddingTask::SearchDocument)
+                (self.text_embedder.as_deref().unwrap(), EmbeddingTask::SearchDocument)
             }
         } else {
-            (self.text_embedder.as_ref().unwrap(), EmbeddingTask::SearchDocument)
+            (self.text_embedder.as_deref().unwrap(), EmbeddingTask::SearchDocument)
         }
     }
 
@@ -96,96 +390,331 @@ impl IncrementalIndexer {
         }
         let mut indexed_count = 0;
         
-        // Use ignore crate to respect .gitignore and other ignore files
-        let walker = WalkBuilder::new(path)
-            .hidden(false)  // Don't process hidden files by default
-            .ignore(true)   // Respect .gitignore files
-            .git_ignore(true)  // Respect .gitignore
-            .git_global(true)  // Respect global gitignore
-            .git_exclude(true) // Respect .git/info/exclude
-            .parents(true)     // Respect parent .gitignore files
-            .build();
+        for file_path in self.collect_files(path) {
+            // Check if file is new or modified
+            if !self.needs_reindex(&file_path)? {
+                continue;
+            }
+            
+            if self.index_file(&file_path, storage, bm25)? {
+                indexed_count += 1;
+            }
+        }
         
-        // Collect files to index, respecting gitignore
-        let files_to_index: Vec<_> = walker
-            .filter_map(|e| e.ok())
-            .filter(|e| {
-                let path = e.path();
-                // Additional filtering for common directories to skip
-                if let Some(path_str) = path.to_str() {
-                    // Skip common build/dependency directories even if not in gitignore
-                    if path_str.contains("/target/") ||
-                       path_str.contains("/node_modules/") ||
-                       path_str.contains("/.git/") ||
-                       path_str.contains("/dist/") ||
-                       path_str.contains("/build/") ||
-                       path_str.contains("/.cache/") ||
-                       path_str.contains("/__pycache__/") {
-                        return false;
-                    }
-                }
-                self.should_index(path)
-            })
+        self.last_index_time = SystemTime::now();
+        Ok(indexed_count)
+    }
+    
+    /// Bring the index in line with the tree under `root`: index new files,
+    /// re-index modified ones and drop entries for files that no longer exist.
+    pub async fn reconcile(
+        &mut self,
+        root: &Path,
+        storage: &mut VectorStorage,
+        bm25: &mut BM25Engine,
+    ) -> Result<ReconcileReport> {
+        let mut report = ReconcileReport::default();
+        let current_files: HashSet<PathBuf> = self.collect_files(root).into_iter().collect();
+        
+        // Deleted files: indexed under this root but gone from the walk
+        let mut removed: Vec<PathBuf> = self.indexed_files
+            .iter()
+            .filter(|p| p.starts_with(root) && !current_files.contains(*p))
+            .cloned()
             .collect();
+        removed.sort();
+        for file_path in removed {
+            Self::remove_entries(&file_path, storage, bm25)?;
+            self.indexed_files.remove(&file_path);
+            report.removed.push(file_path);
+        }
         
-        for entry in files_to_index {
-            let file_path = entry.path();
-            
-            // Check if file is new or modified
-            if !self.needs_reindex(file_path)? {
+        let mut candidates: Vec<PathBuf> = current_files.into_iter().collect();
+        candidates.sort();
+        for file_path in candidates {
+            let is_new = !self.indexed_files.contains(&file_path);
+            if !is_new && !self.needs_reindex(&file_path)? {
                 continue;
             }
             
-            let content = std::fs::read_to_string(file_path)?;
-            
-            // Skip files that are too large
-            if content.len() > self.config.max_file_size {
+            if self.index_file(&file_path, storage, bm25)? {
+                if is_new {
+                    report.added.push(file_path);
+                } else {
+                    report.updated.push(file_path);
+                }
+            }
+        }
+        
+        self.last_index_time = SystemTime::now();
+        Ok(report)
+    }
+    
+    /// Like `reconcile`, but only for paths reported as changed (e.g. by filesystem
+    /// events). Existing files are re-indexed, directories are walked, and paths that
+    /// no longer exist drop whatever was indexed under them. A temp file that was
+    /// written and renamed away before this runs is simply gone, so an atomic save
+    /// re-indexes the rename target.
+    pub async fn reconcile_paths(
+        &mut self,
+        changed: &[PathBuf],
+        storage: &mut VectorStorage,
+        bm25: &mut BM25Engine,
+    ) -> Result<ReconcileReport> {
+        let mut report = ReconcileReport::default();
+        let mut candidates = std::collections::BTreeSet::new();
+        
+        for path in changed {
+            if path.is_dir() {
+                candidates.extend(self.collect_files(path));
+            } else if path.exists() {
+                if self.is_candidate(path) {
+                    candidates.insert(path.clone());
+                }
+            } else {
+                let mut removed: Vec<PathBuf> = self.indexed_files
+                    .iter()
+                    .filter(|p| p.starts_with(path))
+                    .cloned()
+                    .collect();
+                removed.sort();
+                for file_path in removed {
+                    Self::remove_entries(&file_path, storage, bm25)?;
+                    self.indexed_files.remove(&file_path);
+                    report.removed.push(file_path);
+                }
+            }
+        }
+        
+        // The event already says these changed, so skip the mtime check
+        for file_path in candidates {
+            let is_new = !self.indexed_files.contains(&file_path);
+            if self.index_file(&file_path, storage, bm25)? {
+                if is_new {
+                    report.added.push(file_path);
+                } else {
+                    report.updated.push(file_path);
+                }
+            }
+        }
+        
+        self.last_index_time = SystemTime::now();
+        Ok(report)
+    }
+    
+    /// Index every new or modified file under `root`, calling `callback` after each
+    /// file (indexed or skipped) so callers can render progress. Files are processed
+    /// as the walk yields them, and those the checkpoint records as unchanged are
+    /// skipped. Returns the final totals.
+    pub async fn index_directory_with_progress<F: FnMut(IndexProgress)>(
+        &mut self,
+        root: &Path,
+        storage: &mut VectorStorage,
+        bm25: &mut BM25Engine,
+        mut callback: F,
+    ) -> Result<IndexProgress> {
+        let mut progress = IndexProgress::default();
+        
+        for entry in Self::walk(root).filter_map(|e| e.ok()) {
+            if !entry.file_type().is_some_and(|t| t.is_file()) {
                 continue;
             }
+            let file_path = entry.path();
+            progress.files_seen += 1;
             
-            // Create chunks with overlap for better context
-            let chunks = self.create_chunks(&content, file_path)?;
+            let indexed = self.is_candidate(file_path)
+                && !self.checkpoint.is_current(file_path)
+                && self.needs_reindex(file_path)?
+                && self.index_file(file_path, storage, bm25)?;
             
-            // Process each chunk with appropriate embedder
-            for chunk in chunks {
-                // Get the appropriate embedder and task based on file type
-                let (embedder, task) = self.get_embedder_and_task(file_path);
-                
-                // For code files, optionally add language context
-                let content_to_embed = if task == EmbeddingTask::CodeDefinition {
-                    if let Some(lang) = CodeFormatter::detect_language(&file_path.to_string_lossy()) {
-                        CodeFormatter::format_code(&chunk.content, lang)
-                    } else {
-                        chunk.content.clone()
-                    }
-                } else {
-                    chunk.content.clone()
-                };
-                
-                // Generate embedding with appropriate task prefix
-                let embedding = embedder.embed(&content_to_embed, task)?;
-                
-                // Store original content in vector database (not the prefixed version)
-                storage.store(
-                    vec![chunk.content.clone()],
-                    vec![embedding],
-                    vec![file_path.display().to_string()],
-                )?;
-                
-                // Index in BM25
-                bm25.index_document(
-                    &file_path.display().to_string(),
-                    &chunk.content,
-                );
-                // Note: BM25 indexing returns void, no error handling needed
+            if indexed {
+                self.checkpoint.record(file_path);
+                progress.files_indexed += 1;
+                progress.bytes_processed += entry.metadata().map(|m| m.len()).unwrap_or(0);
+            } else {
+                progress.files_skipped += 1;
             }
-            
-            self.indexed_files.insert(file_path.to_path_buf());
-            indexed_count += 1;
+            callback(progress);
         }
         
         self.last_index_time = SystemTime::now();
-        Ok(indexed_count)
+        Ok(progress)
+    }
+    
+    /// Walk `path` respecting ignore files and return every indexable file
+    fn collect_files(&self, path: &Path) -> Vec<PathBuf> {
+        // Collect files to index, respecting gitignore
+        Self::walk(path)
+            .filter_map(|e| e.ok())
+            .filter(|e| self.is_candidate(e.path()))
+            .map(|e| e.into_path())
+            .collect()
+    }
+    
+    fn walk(path: &Path) -> ignore::Walk {
+        // Use ignore crate to respect .gitignore and other ignore files
+        WalkBuilder::new(path)
+            .hidden(false)  // Don't process hidden files by default
+            .ignore(true)   // Respect .gitignore files
+            .git_ignore(true)  // Respect .gitignore
+            .git_global(true)  // Respect global gitignore
+            .git_exclude(true) // Respect .git/info/exclude
+            .parents(true)     // Respect parent .gitignore files
+            .add_custom_ignore_filename(EMBED_IGNORE_FILE)
+            .build()
+    }
+    
+    fn is_candidate(&self, path: &Path) -> bool {
+        // Additional filtering for common directories to skip
+        if let Some(path_str) = path.to_str() {
+            // Skip common build/dependency directories even if not in gitignore
+            if path_str.contains("/target/") ||
+               path_str.contains("/node_modules/") ||
+               path_str.contains("/.git/") ||
+               path_str.contains("/dist/") ||
+               path_str.contains("/build/") ||
+               path_str.contains("/.cache/") ||
+               path_str.contains("/__pycache__/") {
+                return false;
+            }
+        }
+        self.should_index(path)
+    }
+    
+    /// Chunk, embed and store a single file, replacing any previous entries for it.
+    /// Returns `false` when the file is skipped.
+    fn index_file(&mut self, file_path: &Path, storage: &mut VectorStorage, bm25: &mut BM25Engine) -> Result<bool> {
+        match self.prepare_file(file_path)? {
+            Some(update) => {
+                self.apply_update(update, storage, bm25)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Bring one changed file's entries up to date, as editors and watchers need on
+    /// save: the file is read, chunked and embedded first, and only then are its old
+    /// chunks and BM25 document replaced, so the file is never missing from either
+    /// index. A file that no longer exists has its entries removed. Returns `false`
+    /// when the file is skipped (not UTF-8 or over `max_file_size`), leaving its
+    /// earlier entries in place.
+    pub fn reindex_file(&mut self, file_path: &Path, storage: &mut VectorStorage, bm25: &mut BM25Engine) -> Result<bool> {
+        match self.prepare_reindex(file_path)? {
+            Some(update) => {
+                self.apply_update(update, storage, bm25)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// The read, chunk and embed half of `reindex_file`, which needs no access to the
+    /// indexes. Callers sharing the indexes behind a lock run this outside it and hold
+    /// the lock only for `apply_update`.
+    pub fn prepare_reindex(&mut self, file_path: &Path) -> Result<Option<FileUpdate>> {
+        if !file_path.exists() {
+            return Ok(Some(FileUpdate { path: file_path.to_path_buf(), entries: None, opaque: false }));
+        }
+        self.prepare_file(file_path)
+    }
+
+    /// Swap `update` into both indexes: the file's old chunks are replaced by the new
+    /// ones in a single `VectorStorage::store` and its BM25 document in place
+    pub fn apply_update(&mut self, update: FileUpdate, storage: &mut VectorStorage, bm25: &mut BM25Engine) -> Result<()> {
+        let Some((contents, embeddings, content)) = update.entries else {
+            Self::remove_entries(&update.path, storage, bm25)?;
+            self.indexed_files.remove(&update.path);
+            return Ok(());
+        };
+        let path_str = update.path.display().to_string();
+        // All chunks in one call, which replaces the file's chunks from an earlier run.
+        // A file emptied since then has no chunks to replace them with.
+        if contents.is_empty() {
+            storage.remove_file(&path_str);
+        }
+        let paths = vec![path_str.clone(); contents.len()];
+        let mut tags = HashMap::new();
+        if update.opaque {
+            tags.insert(GENERATED_METADATA_KEY.to_string(), GENERATED_OPAQUE.to_string());
+        }
+        let metadata = vec![tags; paths.len()];
+        storage.store_with_metadata(contents, embeddings, paths, metadata)?;
+        
+        // One BM25 document per file, keyed by path, so it can be removed as a unit
+        bm25.index_document(&path_str, &content);
+        
+        self.indexed_files.insert(update.path);
+        Ok(())
+    }
+
+    /// Read, chunk and embed a single file; `None` when the file is skipped
+    fn prepare_file(&mut self, file_path: &Path) -> Result<Option<FileUpdate>> {
+        // Non-UTF-8 files are skipped, which is what makes "*" safe on binaries
+        let content = match std::fs::read_to_string(file_path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::InvalidData => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        
+        // Skip files that are too large
+        if exceeds_max_file_size(content.len() as u64, self.config.max_file_size) {
+            log::warn!("Skipping {}: {} bytes exceeds max_file_size of {}", file_path.display(), content.len(), self.config.max_file_size);
+            return Ok(None);
+        }
+        
+        // Only load the models when there is something to embed
+        if self.text_embedder.is_none() || self.code_embedder.is_none() {
+            self.init_embedders()?;
+        }
+        
+        let path_str = file_path.display().to_string();
+        let opaque = self.generated_file_policy != GeneratedFilePolicy::Normal && is_generated_source(&path_str, &content);
+        if opaque && self.generated_file_policy == GeneratedFilePolicy::Skip {
+            log::info!("Skipping {}: minified or generated", file_path.display());
+            return Ok(None);
+        }
+        
+        // Create chunks with overlap for better context; an opaque file is one chunk
+        let (content, chunks) = if opaque {
+            let end_line = content.lines().count().saturating_sub(1);
+            let chunk = Chunk { content: content.clone(), start_line: 0, end_line, boundary: ChunkBoundary::Lines };
+            (content, vec![chunk])
+        } else {
+            let content = self.config.content_filter.apply(&content, &path_str);
+            let chunks = self.create_chunks(&content, file_path)?;
+            (content, chunks)
+        };
+        let mut contents = Vec::with_capacity(chunks.len());
+        let mut embeddings = Vec::with_capacity(chunks.len());
+        
+        // Process each chunk with appropriate embedder
+        for chunk in chunks {
+            // Get the appropriate embedder and task based on file type
+            let (embedder, task) = self.get_embedder_and_task(file_path);
+            
+            // For code files, add the language context `embedding_languages` maps to
+            let content_to_embed = if task == EmbeddingTask::CodeDefinition {
+                CodeFormatter::format_for_path(&chunk.content, &path_str, &self.config.embedding_languages)
+            } else {
+                chunk.content.clone()
+            };
+            
+            // Generate embedding with appropriate task prefix
+            embeddings.push(embedder.embed(&content_to_embed, task)?);
+            // Store original content in vector database (not the prefixed version)
+            contents.push(chunk.content);
+        }
+        
+        Ok(Some(FileUpdate { path: file_path.to_path_buf(), entries: Some((contents, embeddings, content)), opaque }))
+    }
+    
+    /// Remove a file's chunks from vector storage and its BM25 document
+    fn remove_entries(file_path: &Path, storage: &mut VectorStorage, bm25: &mut BM25Engine) -> Result<()> {
+        let path_str = file_path.display().to_string();
+        storage.remove_file(&path_str);
+        bm25.remove_document(&path_str)?;
+        Ok(())
     }
     
     fn should_index(&self, path: &Path) -> bool {
@@ -195,7 +724,8 @@ impl IncrementalIndexer {
         
         // Skip files that are too large (e.g., generated files, binaries)
         if let Ok(metadata) = path.metadata() {
-            if metadata.len() > self.config.max_file_size as u64 {
+            if exceeds_max_file_size(metadata.len(), self.config.max_file_size) {
+                log::warn!("Skipping {}: {} bytes exceeds max_file_size of {}", path.display(), metadata.len(), self.config.max_file_size);
                 return false;
             }
         }
@@ -209,11 +739,10 @@ impl IncrementalIndexer {
                    ext_str == "log" || ext_str == "tmp" || ext_str == "bak" {
                     return false;
                 }
-                return self.config.supported_extensions.contains(&ext_str.to_string());
             }
         }
         
-        false
+        is_indexable_extension(path, &self.config.supported_extensions)
     }
     
     fn needs_reindex(&self, path: &Path) -> Result<bool> {
@@ -244,10 +773,14 @@ impl IncrementalIndexer {
                             content: mc.content,
                             start_line: mc.start_line,
                             end_line: mc.end_line,
+                            boundary: ChunkBoundary::Lines,
                         }).collect();
                         return Ok(chunks);
                     }
-                    _ => {
+                    ext => {
+                        if let Some(boundary_chunker) = &self.boundary_chunker {
+                            return Ok(boundary_chunker.lock().chunk(content, ext));
+                        }
                         // Use regex chunker for other supported files
                         return Ok(self.regex_chunker.chunk_file(content));
                     }
@@ -267,7 +800,8 @@ impl IncrementalIndexer {
             let chunk = Chunk {
                 content: chunk_lines.join("\n"),
                 start_line: i,
-                end_line: end,
+                end_line: end - 1,
+                boundary: ChunkBoundary::Lines,
             };
             
             chunks.push(chunk);
@@ -306,8 +840,9 @@ impl IncrementalIndexer {
         let last_index_secs = state["last_index_time"].as_u64().unwrap_or(0);
         let last_index_time = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(last_index_secs);
         
-        let regex_chunker = SimpleRegexChunker::with_chunk_size(config.chunk_size)?;
+        let regex_chunker = SimpleRegexChunker::with_chunk_size(config.chunk_size)?.with_overlap(config.chunk_overlap)?;
         let markdown_chunker = MarkdownRegexChunker::with_options(config.chunk_size, true)?;
+        let boundary_chunker = Self::boundary_chunker(&config)?;
         
         Ok(Self {
             config,
@@ -315,8 +850,307 @@ impl IncrementalIndexer {
             last_index_time,
             regex_chunker,
             markdown_chunker,
+            boundary_chunker,
             text_embedder: None,
             code_embedder: None,
+            checkpoint: IndexCheckpoint::default(),
+            generated_file_policy: GeneratedFilePolicy::default(),
         })
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn exts(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_is_indexable_extension_matches_configured_list() {
+        let extensions = exts(&["rs", "go", "java"]);
+        assert!(is_indexable_extension(Path::new("src/main.rs"), &extensions));
+        assert!(is_indexable_extension(Path::new("cmd/server.go"), &extensions));
+        assert!(is_indexable_extension(Path::new("App.JAVA"), &extensions));
+        assert!(!is_indexable_extension(Path::new("lib.cpp"), &extensions));
+        assert!(!is_indexable_extension(Path::new("Makefile"), &extensions));
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_paths_drops_vanished_paths_only() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let gone = dir.path().join("gone");
+        let notes = dir.path().join("notes.txt");
+        std::fs::write(&notes, "not an indexed extension")?;
+
+        let mut indexer = IncrementalIndexer::new(IndexingConfig::default())?;
+        let mut storage = VectorStorage::new("unused")?;
+        let mut bm25 = BM25Engine::new()?;
+        for file in ["a.rs", "b.rs"] {
+            let path = gone.join(file);
+            bm25.index_document(&path.display().to_string(), "fn main() {}");
+            indexer.indexed_files.insert(path);
+        }
+
+        // A renamed-away temp file was never indexed, so it just disappears
+        let changed = vec![dir.path().join(".lib.rs.tmp"), gone.clone(), notes];
+        let report = indexer.reconcile_paths(&changed, &mut storage, &mut bm25).await?;
+        assert_eq!(report.removed, vec![gone.join("a.rs"), gone.join("b.rs")]);
+        assert!(report.added.is_empty() && report.updated.is_empty());
+        assert!(indexer.indexed_files.is_empty());
+        assert_eq!(bm25.stats().total_documents, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_indexable_extension_wildcard() {
+        let extensions = exts(&[ANY_EXTENSION]);
+        assert!(is_indexable_extension(Path::new("lib.cpp"), &extensions));
+        assert!(is_indexable_extension(Path::new("Makefile"), &extensions));
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_removes_deleted_files() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let deleted = dir.path().join("deleted.rs");
+        let deleted_str = deleted.display().to_string();
+        
+        let mut indexer = IncrementalIndexer::new(Config::default().indexing)?;
+        let mut storage = VectorStorage::new("unused")?;
+        let mut bm25 = BM25Engine::new()?;
+        
+        // Simulate a previously indexed file that has since been deleted
+        storage.store(vec!["fn gone() {}".to_string()], vec![vec![1.0, 0.0]], vec![deleted_str.clone()])?;
+        bm25.index_document(&deleted_str, "fn gone() {}");
+        indexer.indexed_files.insert(deleted.clone());
+        
+        let report = indexer.reconcile(dir.path(), &mut storage, &mut bm25).await?;
+        
+        assert_eq!(report.removed, vec![deleted]);
+        assert!(report.added.is_empty());
+        assert!(report.updated.is_empty());
+        assert!(storage.is_empty());
+        assert!(bm25.search("gone", 10)?.is_empty());
+        assert!(indexer.indexed_files.is_empty());
+        Ok(())
+    }
+    
+    #[tokio::test]
+    async fn test_reconcile_ignores_paths_outside_root() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let other = PathBuf::from("/elsewhere/kept.rs");
+        
+        let mut indexer = IncrementalIndexer::new(Config::default().indexing)?;
+        let mut storage = VectorStorage::new("unused")?;
+        let mut bm25 = BM25Engine::new()?;
+        indexer.indexed_files.insert(other.clone());
+        
+        let report = indexer.reconcile(dir.path(), &mut storage, &mut bm25).await?;
+        
+        assert!(report.removed.is_empty());
+        assert!(indexer.indexed_files.contains(&other));
+        Ok(())
+    }
+    
+    #[tokio::test]
+    async fn test_progress_callback_fires_for_skipped_files() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::write(dir.path().join("notes.unknown"), "not indexed")?;
+        std::fs::write(dir.path().join("data.bin"), [0u8, 1, 2])?;
+        std::fs::write(dir.path().join("binary.rs"), [0xffu8, 0xfe, 0x00])?;
+        
+        let mut indexer = IncrementalIndexer::new(Config::default().indexing)?;
+        let mut storage = VectorStorage::new("unused")?;
+        let mut bm25 = BM25Engine::new()?;
+        let mut events = Vec::new();
+        
+        let totals = indexer
+            .index_directory_with_progress(dir.path(), &mut storage, &mut bm25, |p| events.push(p))
+            .await?;
+        
+        assert_eq!(events.len(), 3);
+        assert_eq!(events.last(), Some(&totals));
+        assert_eq!(totals.files_seen, 3);
+        assert_eq!(totals.files_skipped, 3);
+        assert_eq!(totals.files_indexed, 0);
+        assert_eq!(totals.bytes_processed, 0);
+        for (i, p) in events.iter().enumerate() {
+            assert_eq!(p.files_seen, i + 1);
+            assert_eq!(p.files_seen, p.files_indexed + p.files_skipped);
+        }
+        Ok(())
+    }
+    
+    #[test]
+    fn test_checkpoint_tracks_unchanged_files() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let checkpoint_path = dir.path().join(INDEX_CHECKPOINT_FILE);
+        assert!(IndexCheckpoint::load(&checkpoint_path)?.is_empty());
+        
+        let kept = dir.path().join("kept.rs");
+        let edited = dir.path().join("edited.rs");
+        std::fs::write(&kept, "fn kept() {}")?;
+        std::fs::write(&edited, "fn edited() {}")?;
+        let mut checkpoint = IndexCheckpoint::default();
+        checkpoint.record(&kept);
+        checkpoint.record(&edited);
+        checkpoint.record(&dir.path().join("missing.rs"));
+        checkpoint.save(&checkpoint_path)?;
+        
+        std::fs::write(&edited, "fn edited() { changed(); }")?;
+        let loaded = IndexCheckpoint::load(&checkpoint_path)?;
+        assert_eq!(loaded, checkpoint);
+        assert_eq!(loaded.len(), 2);
+        assert!(loaded.is_current(&kept));
+        assert!(!loaded.is_current(&edited));
+        
+        IndexCheckpoint::remove(&checkpoint_path)?;
+        IndexCheckpoint::remove(&checkpoint_path)?;
+        assert!(!checkpoint_path.exists());
+        Ok(())
+    }
+    
+    #[test]
+    fn test_rapid_reindex_never_leaves_the_file_unsearchable() -> Result<()> {
+        use crate::embedder::MinimalEmbedder;
+        use parking_lot::RwLock;
+        use std::sync::atomic::{AtomicBool, Ordering};
+        
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("session.rs");
+        let path_str = path.display().to_string();
+        std::fs::write(&path, "fn refresh_session() {}")?;
+        let mut indexer = IncrementalIndexer::new(IndexingConfig::default())?
+            .with_embedders(Box::new(MinimalEmbedder::new(32)), Box::new(MinimalEmbedder::new(32)));
+        let mut storage = VectorStorage::new("unused")?;
+        let mut bm25 = BM25Engine::new()?;
+        assert!(indexer.reindex_file(&path, &mut storage, &mut bm25)?);
+        
+        let indexes = RwLock::new((storage, bm25));
+        let done = AtomicBool::new(false);
+        std::thread::scope(|scope| -> Result<()> {
+            let reader = scope.spawn(|| -> Result<usize> {
+                let mut checks = 0;
+                loop {
+                    let finished = done.load(Ordering::Acquire);
+                    let guard = indexes.read();
+                    let (storage, bm25) = &*guard;
+                    assert!(!storage.is_empty(), "no chunks after {} checks", checks);
+                    assert!(bm25.search("refresh_session", 5)?.iter().any(|m| m.path == path_str));
+                    checks += 1;
+                    if finished {
+                        return Ok(checks);
+                    }
+                }
+            });
+            for version in 0..50 {
+                std::fs::write(&path, format!("fn refresh_session() {{}}\nfn version_{}() {{}}\n", version))?;
+                let update = indexer.prepare_reindex(&path)?.unwrap();
+                let mut guard = indexes.write();
+                let (storage, bm25) = &mut *guard;
+                indexer.apply_update(update, storage, bm25)?;
+            }
+            done.store(true, Ordering::Release);
+            reader.join().unwrap()?;
+            Ok(())
+        })?;
+        
+        let (mut storage, mut bm25) = indexes.into_inner();
+        assert!(bm25.get_document(&path_str).unwrap().contains("version_49"));
+        
+        // A deleted file leaves both indexes
+        std::fs::remove_file(&path)?;
+        assert!(indexer.reindex_file(&path, &mut storage, &mut bm25)?);
+        assert!(storage.is_empty());
+        assert!(bm25.search("refresh_session", 5)?.is_empty());
+        Ok(())
+    }
+    
+    #[test]
+    fn test_generated_files_are_detected_and_follow_the_policy() -> Result<()> {
+        use crate::embedder::MinimalEmbedder;
+        
+        let minified = "var n=0;function debounce(e){return setTimeout(e,n)}".repeat(40);
+        let functions: String = (0..40).map(|i| format!("function step{}(x) {{\n  return x + {};\n}}\n", i, i)).collect();
+        let mapped = format!("{}//# sourceMappingURL=bundle.js.map\n", functions);
+        assert!(is_generated_source("app.min.js", &minified));
+        assert!(is_generated_source("bundle.js", &mapped));
+        assert!(!is_generated_source("steps.js", &functions));
+        assert!(!is_generated_source("src/lib.rs", include_str!("lib.rs")));
+        // A paragraph per line is normal for prose
+        assert!(!is_generated_source("notes.md", &"A long paragraph of prose. ".repeat(100)));
+        
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("bundle.js");
+        std::fs::write(&path, &mapped)?;
+        let config = IndexingConfig { chunk_size: 10, chunk_overlap: 2, ..Default::default() };
+        for policy in [GeneratedFilePolicy::Skip, GeneratedFilePolicy::IndexAsOpaque, GeneratedFilePolicy::Normal] {
+            let mut indexer = IncrementalIndexer::new(config.clone())?
+                .with_embedders(Box::new(MinimalEmbedder::new(32)), Box::new(MinimalEmbedder::new(32)))
+                .with_generated_file_policy(policy);
+            let mut storage = VectorStorage::new("unused")?;
+            let mut bm25 = BM25Engine::new()?;
+            let indexed = indexer.reindex_file(&path, &mut storage, &mut bm25)?;
+            let chunks = storage.search_similar(&[1.0; 32], usize::MAX)?;
+            match policy {
+                GeneratedFilePolicy::Skip => assert!(!indexed && chunks.is_empty()),
+                GeneratedFilePolicy::IndexAsOpaque => {
+                    assert_eq!(chunks.len(), 1);
+                    assert_eq!(chunks[0].0.content, mapped);
+                    assert_eq!(chunks[0].0.metadata.get(GENERATED_METADATA_KEY).map(String::as_str), Some(GENERATED_OPAQUE));
+                }
+                GeneratedFilePolicy::Normal => {
+                    assert!(chunks.len() > 1);
+                    assert!(chunks.iter().all(|(chunk, _)| chunk.metadata.is_empty()));
+                }
+            }
+        }
+        Ok(())
+    }
+    
+    #[test]
+    fn test_exceeds_max_file_size() {
+        assert!(!exceeds_max_file_size(40_000, 1_048_576));
+        assert!(!exceeds_max_file_size(1_048_576, 1_048_576));
+        assert!(exceeds_max_file_size(1_048_577, 1_048_576));
+    }
+
+    #[test]
+    fn test_zero_max_file_size_means_no_limit() {
+        assert!(!exceeds_max_file_size(u64::MAX, 0));
+    }
+
+    #[test]
+    fn test_create_chunks_semantic_boundary_strategy() -> Result<()> {
+        let code = "fn one() {\n    1\n}\n\nfn two() {\n    2\n}\n";
+        let path = Path::new("lib.rs");
+
+        let config = IndexingConfig { chunking_strategy: ChunkingStrategy::SemanticBoundary, chunk_size: 10, chunk_overlap: 2, ..Default::default() };
+        let chunks = IncrementalIndexer::new(config)?.create_chunks(code, path)?;
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks.iter().all(|c| c.boundary == ChunkBoundary::Unit));
+        assert!(chunks[1].content.starts_with("fn two()"));
+
+        let fixed = IncrementalIndexer::new(IndexingConfig { chunk_size: 10, chunk_overlap: 2, ..Default::default() })?.create_chunks(code, path)?;
+        assert!(fixed.iter().all(|c| c.boundary == ChunkBoundary::Lines));
+        Ok(())
+    }
+
+    #[test]
+    fn test_indexable_extensions_alias_in_config() {
+        let toml_src = r#"
+            chunk_size = 512
+            chunk_overlap = 50
+            max_file_size_bytes = 1000
+            indexable_extensions = ["go", "*"]
+            enable_incremental = true
+        "#;
+        let config: IndexingConfig = toml::from_str(toml_src).unwrap();
+        assert_eq!(config.supported_extensions, exts(&["go", "*"]));
+        assert_eq!(config.max_file_size, 1000);
+        assert_eq!(config.chunking_strategy, ChunkingStrategy::FixedLines);
+        assert_eq!(config.chunk_overshoot_factor, 1.5);
+    }
+}
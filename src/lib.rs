@@ -6,18 +6,27 @@ pub mod search;
 pub mod cache;
 pub mod utils;
 pub mod config;
+pub mod content_filter;
 pub mod indexer;
 pub mod symbol_extractor;
 pub mod semantic_chunker;
 pub mod fusion;
 pub mod embedding_cache;
+pub mod embedder;
+pub mod watcher;
+pub mod doctor;
+pub mod eval;
+pub mod query_parser;
 
 // Simple modules for core functionality
 // Enable working GGUF implementation
 pub mod llama_wrapper_working;
 pub mod simple_storage;
 pub mod simple_search;
+pub mod archive;
+pub mod index_pipeline;
 pub mod advanced_search;
+pub mod multi_index_search;
 pub mod markdown_metadata_extractor;
 
 // GGUF embedding modules - now enabled
@@ -25,18 +34,23 @@ pub mod embedding_prefixes;
 pub mod gguf_embedder;
 
 // Re-export key types
-pub use error::{SearchError, Result};
+pub use error::{ConfigError, SearchError, Result};
 pub use chunking::{Chunk, ChunkContext};
 pub use search::bm25_fixed::BM25Engine;
 pub use fusion::{FusionConfig, SearchResult};
 pub use cache::BoundedCache;
-pub use config::Config;
-pub use indexer::IncrementalIndexer;
+pub use config::{init_logging, Config, ConfigBuilder, StorageBackend, VectorQuantization};
+pub use content_filter::ContentFilter;
+pub use indexer::{FileUpdate, IncrementalIndexer, IndexCheckpoint};
 pub use symbol_extractor::{SymbolExtractor, Symbol, SymbolKind};
+pub use eval::{evaluate, EvalReport, LabeledQuery};
+pub use query_parser::{parse_query, ParsedQuery};
 
 // Main hybrid search interface
-pub use simple_search::HybridSearch;
+pub use simple_search::{HybridSearch, ArchiveManifest, ClearTarget, Document, GroupBy, GroupedResult, IndexReport, MetadataFilter, OpenOutcome, SearchFeedback, SearchTimings};
+pub use simple_storage::{chunk_doc_id, VectorStore, open_vector_store};
 pub use advanced_search::{AdvancedHybridSearch, AdvancedSearchResult};
+pub use multi_index_search::{FederatedSearcher, IndexMerge, MultiIndexSearch, MultiIndexResult};
 pub use markdown_metadata_extractor::{
     MarkdownMetadataExtractor, EnhancedChunkMetadata, MarkdownSymbol, 
     DocumentOutline, LinkInfo, ImageInfo, SymbolType as MarkdownSymbolType
@@ -44,5 +58,6 @@ pub use markdown_metadata_extractor::{
 
 // GGUF embedding interfaces - now enabled
 pub use embedding_prefixes::{EmbeddingTask, CodeFormatter, BatchProcessor};
-pub use gguf_embedder::{GGUFEmbedder, GGUFEmbedderConfig, EmbedderStats};
+pub use gguf_embedder::{GGUFEmbedder, GGUFEmbedderConfig, EmbedderStats, PoolingStrategy};
+pub use embedder::{Combination, Embedder, MinimalEmbedder, MultiEmbedder};
 pub use llama_wrapper_working::{GGUFModel, GGUFContext};
\ No newline at end of file
@@ -13,6 +13,8 @@ use std::sync::{Arc, Mutex};
 use std::num::NonZeroU32;
 use once_cell::sync::Lazy;
 
+use crate::gguf_embedder::{l2_normalize, PoolingStrategy};
+
 // Global backend - CRITICAL for FFI safety
 static BACKEND: Lazy<LlamaBackend> = Lazy::new(|| {
     LlamaBackend::init().expect("Failed to initialize llama backend")
@@ -26,11 +28,15 @@ pub struct GGUFModel {
 
 impl GGUFModel {
     pub fn load_from_file<P: AsRef<Path>>(path: P, gpu_layers: i32) -> Result<Self> {
+        let path = path.as_ref();
+        // llama-cpp-2 panics on a missing file instead of returning an error
+        if !path.is_file() {
+            bail!("GGUF model not found: {}", path.display());
+        }
+
         // Force backend initialization
         Lazy::force(&BACKEND);
         
-        let path = path.as_ref();
-        
         // CPU-optimized parameters
         let params = LlamaModelParams::default()
             .with_n_gpu_layers(gpu_layers.max(0) as u32);
@@ -56,26 +62,53 @@ impl GGUFModel {
     }
 }
 
+/// Most tokens one embedding call can decode. A non-causal embedding model must see
+/// the whole sequence in one micro-batch, and llama-cpp-2 0.1.54 can't raise that
+/// above llama.cpp's default of 512.
+pub const MAX_SEQUENCE_TOKENS: usize = 512;
+
 /// Working GGUF context with thread safety
 pub struct GGUFContext {
     // Use Arc<Mutex<>> to avoid lifetime issues completely
     context: Arc<Mutex<LlamaContext<'static>>>,
     model: Arc<LlamaModel>,
     embedding_dim: usize,
+    /// Inputs are truncated to this many tokens before decoding
+    max_tokens: usize,
+    /// The model's own pooling is off, so every token keeps its embedding
+    token_embeddings: bool,
 }
 
 impl GGUFContext {
     pub fn new_with_model(model: &GGUFModel, context_size: u32) -> Result<Self> {
+        Self::new_with_max_tokens(model, context_size, MAX_SEQUENCE_TOKENS)
+    }
+
+    /// Context that truncates every input to `max_tokens`, capped at `context_size`
+    /// and `MAX_SEQUENCE_TOKENS`
+    pub fn new_with_max_tokens(model: &GGUFModel, context_size: u32, max_tokens: usize) -> Result<Self> {
+        Self::new_with_pooling(model, context_size, max_tokens, PoolingStrategy::Mean)
+    }
+
+    /// Context set up for `pooling`. Mean uses the model's own sequence pooling;
+    /// Cls and LastToken switch it off so the per-token embeddings survive decoding.
+    pub fn new_with_pooling(model: &GGUFModel, context_size: u32, max_tokens: usize, pooling: PoolingStrategy) -> Result<Self> {
         let context_size = NonZeroU32::new(context_size.max(1))
             .context("Context size must be greater than 0")?;
+        if max_tokens == 0 {
+            bail!("max_sequence_tokens must be greater than 0");
+        }
+        let max_tokens = max_tokens.min(context_size.get() as usize).min(MAX_SEQUENCE_TOKENS);
         
         let params = LlamaContextParams::default()
             .with_n_ctx(Some(context_size))
-            .with_n_batch(256) // Conservative batch size
+            .with_n_batch(max_tokens as u32) // Every token of a truncated input fits one batch
             .with_n_threads(num_cpus::get() as u32)
             .with_embeddings(true) // ESSENTIAL for embedding extraction
             .with_rope_scaling_type(RopeScalingType::Yarn) // CRITICAL: nomic models need Yarn scaling
             .with_rope_freq_scale(0.75); // CRITICAL: nomic-embed specific scaling factor
+        let token_embeddings = pooling != PoolingStrategy::Mean;
+        let params = if token_embeddings { without_model_pooling(params) } else { params };
         
         // Create context with proper error handling - need to fix lifetime issue
         let context = model.model()
@@ -92,28 +125,54 @@ impl GGUFContext {
             context: Arc::new(Mutex::new(static_context)),
             model: model.model().clone(),
             embedding_dim: model.embedding_dim(),
+            max_tokens,
+            token_embeddings,
         })
     }
+
+    /// Tokens an input is truncated to
+    pub fn max_tokens(&self) -> usize {
+        self.max_tokens
+    }
     
-    /// Generate embedding with proper error handling and memory safety
+    /// Generate an L2-normalized embedding with proper error handling and memory safety
     pub fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let mut embedding = self.embed_with_pooling(text, PoolingStrategy::Mean)?;
+        l2_normalize(&mut embedding);
+        Ok(embedding)
+    }
+    
+    /// Generate the raw (un-normalized) embedding, reducing the token embeddings
+    /// with the given pooling strategy
+    pub fn embed_with_pooling(&self, text: &str, pooling: PoolingStrategy) -> Result<Vec<f32>> {
         // Tokenize text
-        let tokens: Vec<LlamaToken> = self.model
+        let mut tokens: Vec<LlamaToken> = self.model
             .str_to_token(text, llama_cpp_2::model::AddBos::Never)?;
         
         if tokens.is_empty() {
             bail!("Tokenization failed for input text");
         }
+        let dropped = truncate_tokens(&mut tokens, self.max_tokens);
+        if dropped > 0 {
+            log::debug!("Embedding input truncated to {} tokens, {} dropped", self.max_tokens, dropped);
+        }
         
         // Lock context for thread safety
         let mut ctx = self.context.lock().unwrap();
         
+        // Cls/LastToken read per-token embeddings, which only exist once the model's
+        // pooling is off, and every token then needs outputs enabled
+        let per_token = self.token_embeddings;
+        if pooling != PoolingStrategy::Mean && !per_token {
+            bail!("{:?} pooling needs a context created with GGUFContext::new_with_pooling", pooling);
+        }
+        
         // Create batch with embedding-specific settings
         let mut batch = LlamaBatch::new(tokens.len(), 1);
         for (i, token) in tokens.iter().enumerate() {
             // CRITICAL: Set logits=true for the last token to enable embeddings
             let is_last = i == tokens.len() - 1;
-            batch.add(*token, i as i32, &[0], is_last)
+            batch.add(*token, i as i32, &[0], is_last || per_token)
                 .context("Failed to add token to batch")?;
         }
         
@@ -121,10 +180,19 @@ impl GGUFContext {
         ctx.decode(&mut batch)
             .context("Failed to decode token batch")?;
         
-        // FIXED: Proper mean pooling for nomic-embed models
-        // nomic-embed models require mean pooling over all sequence embeddings
-        let embedding_vec = {
-            // First try to get sequence embeddings (this is what nomic models output)
+        let embedding_vec = if per_token {
+            // Gather the per-token embeddings and reduce them ourselves
+            let mut token_embeddings = Vec::with_capacity(tokens.len() * self.embedding_dim);
+            for i in 0..tokens.len() {
+                let token_emb = ctx.embeddings_ith(i as i32).with_context(|| format!(
+                    "Failed to extract token {} embedding for {:?} pooling", i, pooling
+                ))?;
+                token_embeddings.extend_from_slice(token_emb);
+            }
+            pooling.pool(&token_embeddings, self.embedding_dim)?
+        } else {
+            // FIXED: Proper mean pooling for nomic-embed models
+            // nomic-embed models require mean pooling over all sequence embeddings
             let seq_emb = ctx.embeddings_seq_ith(0)
                 .context("Failed to extract sequence embeddings from nomic model")?;
             
@@ -132,61 +200,41 @@ impl GGUFContext {
                 bail!("No sequence embeddings returned from nomic model");
             }
             
-            // Calculate sequence length and validate
-            let seq_len = seq_emb.len() / self.embedding_dim;
-            if seq_len == 0 {
-                bail!("Invalid embedding sequence length: {}", seq_emb.len());
-            }
-            
             // CRITICAL: Mean pooling over the entire sequence for nomic-embed
-            let mut pooled = vec![0.0f32; self.embedding_dim];
-            
-            for i in 0..seq_len {
-                for j in 0..self.embedding_dim {
-                    let idx = i * self.embedding_dim + j;
-                    if idx < seq_emb.len() {
-                        pooled[j] += seq_emb[idx];
-                    }
-                }
-            }
-            
-            // Average the pooled embeddings
-            if seq_len > 0 {
-                for val in &mut pooled {
-                    *val /= seq_len as f32;
-                }
-            }
-            
-            pooled
+            PoolingStrategy::Mean.pool(seq_emb, self.embedding_dim)?
         };
         
-        // L2 normalization for cosine similarity (standard for nomic models)
-        let mut normalized_embedding = embedding_vec;
-        let norm = normalized_embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
-        if norm > 1e-8 {
-            for val in &mut normalized_embedding {
-                *val /= norm;
-            }
-        } else {
+        // Normalization is left to the caller; only reject degenerate vectors here
+        let norm = embedding_vec.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm <= 1e-8 {
             bail!("Embedding has zero norm - likely extraction failed");
         }
         
         // Verify we got a real embedding (not all zeros)
-        let non_zero_count = normalized_embedding.iter().filter(|&&x| x.abs() > 1e-8).count();
+        let non_zero_count = embedding_vec.iter().filter(|&&x| x.abs() > 1e-8).count();
         if non_zero_count < 10 {
             bail!("Embedding appears to be mostly zeros ({} non-zero values) - model may not be loaded properly", non_zero_count);
         }
         
-        Ok(normalized_embedding)
+        Ok(embedding_vec)
     }
     
-    /// Batch embedding generation
+    /// Batch embedding generation (L2-normalized)
     pub fn embed_batch(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        let mut embeddings = self.embed_batch_with_pooling(texts, PoolingStrategy::Mean)?;
+        for embedding in &mut embeddings {
+            l2_normalize(embedding);
+        }
+        Ok(embeddings)
+    }
+    
+    /// Raw batch embedding generation with an explicit pooling strategy
+    pub fn embed_batch_with_pooling(&self, texts: Vec<String>, pooling: PoolingStrategy) -> Result<Vec<Vec<f32>>> {
         let mut results = Vec::with_capacity(texts.len());
         
         // Process sequentially for memory safety
         for text in texts {
-            let embedding = self.embed(&text)?;
+            let embedding = self.embed_with_pooling(&text, pooling)?;
             results.push(embedding);
         }
         
@@ -194,6 +242,23 @@ impl GGUFContext {
     }
 }
 
+/// Turn off the model's built-in pooling so `embeddings_ith` has a row for every token.
+/// llama-cpp-2 0.1.54 has no setter for `pooling_type`, so this reaches through its wrapper.
+fn without_model_pooling(params: LlamaContextParams) -> LlamaContextParams {
+    // SAFETY: LlamaContextParams is a wrapper whose only field is the
+    // llama_context_params it hands to llama.cpp, and transmute checks the sizes match
+    let mut raw: llama_cpp_sys_2::llama_context_params = unsafe { std::mem::transmute(params) };
+    raw.pooling_type = llama_cpp_sys_2::LLAMA_POOLING_TYPE_NONE;
+    unsafe { std::mem::transmute(raw) }
+}
+
+/// Keep the first `max_tokens` tokens (prefix included), returning how many were dropped
+fn truncate_tokens<T>(tokens: &mut Vec<T>, max_tokens: usize) -> usize {
+    let dropped = tokens.len().saturating_sub(max_tokens);
+    tokens.truncate(max_tokens);
+    dropped
+}
+
 // Safety implementations
 unsafe impl Send for GGUFModel {}
 unsafe impl Sync for GGUFModel {}
@@ -219,6 +284,15 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_truncate_tokens_keeps_the_front() {
+        let mut tokens: Vec<u32> = (0..10).collect();
+        assert_eq!(truncate_tokens(&mut tokens, 4), 6);
+        assert_eq!(tokens, vec![0, 1, 2, 3]);
+        assert_eq!(truncate_tokens(&mut tokens, 8), 0);
+        assert_eq!(tokens.len(), 4);
+    }
+
     #[test] 
     fn test_context_creation() -> Result<()> {
         // Test context creation logic without actual model
@@ -1,15 +1,29 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
-use walkdir::WalkDir;
+use clap::{Parser, Subcommand, ValueEnum};
 use std::fs;
-// std::path::Path temporarily removed
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
 
-use embed_search::{simple_search::HybridSearch};
+use embed_search::{simple_search::{ClearTarget, GroupBy, HybridSearch, PathFilter, SearchResult, SearchTimings, DEFAULT_BM25_INDEX_FILE}, config::{init_logging, Config, WatchBackend}, search::{CodeTextProcessor, FusionConfig}, indexer::{IndexCheckpoint, IndexProgress, ReconcileReport, INDEX_CHECKPOINT_FILE}};
+use embed_search::{utils::content_preview, watcher::{NotifyWatcher, Watcher}, doctor, simple_storage::VECTOR_STORE_FILE};
+
+/// How often `watch` checks the config file for edits
+const CONFIG_CHECK_INTERVAL: Duration = Duration::from_secs(1);
 
 #[derive(Parser)]
 #[command(name = "embed-search")]
 #[command(about = "Simplified embedding search using real tech stack")]
 struct Cli {
+    /// Path to a TOML config file (defaults are used when omitted)
+    #[arg(long, global = true)]
+    config: Option<String>,
+
+    /// Embedding dimension the models must produce, overriding embedding_dimensions in the config
+    #[arg(long, global = true)]
+    dimension: Option<usize>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -20,103 +34,471 @@ enum Commands {
     Index {
         /// Directory to index
         path: String,
+        /// Leave out paths matching this gitignore-style pattern, after the directory's
+        /// .embedignore; repeatable, and "!pattern" re-includes
+        #[arg(long)]
+        exclude: Vec<String>,
+        /// Also index files that .gitignore excludes
+        #[arg(long)]
+        no_gitignore: bool,
+        /// Continue an interrupted index, skipping files it finished that are unchanged since
+        #[arg(long, conflicts_with = "force")]
+        resume: bool,
+        /// Start over, discarding what an interrupted index recorded
+        #[arg(long)]
+        force: bool,
     },
     /// Search for content
     Search {
         /// Search query
         query: String,
+        /// Drop results whose fused score is below this
+        #[arg(long)]
+        min_score: Option<f32>,
+        /// Only search indexed files whose path matches this glob, e.g. "src/search/**"
+        #[arg(long)]
+        path: Option<String>,
+        /// Collapse results: "file" lists each file once with its matching chunks
+        #[arg(long, default_value_t = GroupBy::None, conflicts_with = "path")]
+        group_by: GroupBy,
+        /// Print how long each phase of the search took, on stderr
+        #[arg(long)]
+        timings: bool,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// List indexed files whose embeddings are closest to an indexed file's
+    Similar {
+        /// Indexed file to compare against, as it was indexed
+        path: String,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Show the terms indexing derives from some text, and which are stop words
+    Analyze {
+        /// Text to analyze, such as a query or a line of code
+        text: String,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Report index health: document, term and vector counts and on-disk sizes
+    Stats {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
     },
+    /// Index a directory, then re-index changed files until interrupted (Ctrl-C)
+    Watch {
+        /// Directory to watch
+        path: String,
+    },
+    /// Check the config, models and index location, with a fix for each problem
+    Doctor,
     /// Clear all indexed data
-    Clear,
+    Clear {
+        /// Clear only this component: all, tantivy, bm25, vectors or embedding_cache
+        #[arg(long)]
+        only: Option<ClearTarget>,
+    },
+    /// Write the whole index to a single archive file
+    Export {
+        /// Archive to create
+        path: String,
+    },
+    /// Replace the index with an archive written by `export`
+    Import {
+        /// Archive to read
+        path: String,
+        /// Replace an index that already holds documents
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Human-readable listing
+    Text,
+    /// A single JSON array
+    Json,
+    /// One JSON object per line
+    Jsonl,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
     let db_path = "./simple_embed.db";
+    if let Commands::Doctor = cli.command {
+        init_logging(&Config::default());
+        // Runs before the config is loaded so a broken config is reported, not fatal
+        let report = doctor::run(cli.config.as_deref().map(Path::new), Path::new(db_path));
+        print!("{}", report);
+        if !report.passed() {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    let mut config = Config::clone(&*Config::init(cli.config.as_deref())?);
+    init_logging(&config);
+    if let Some(dimension) = cli.dimension {
+        config.embedding_dimensions = dimension;
+        config.validate()?;
+    }
 
     match cli.command {
-        Commands::Index { path } => {
+        Commands::Index { path, exclude, no_gitignore, resume, force } => {
             println!("Indexing files in: {}", path);
-            let mut search = HybridSearch::new(db_path).await?;
+            config.indexing.exclude.extend(exclude);
+            if no_gitignore {
+                config.respect_gitignore = false;
+            }
+            let checkpoint_path = Path::new(db_path).join(INDEX_CHECKPOINT_FILE);
+            let checkpoint = IndexCheckpoint::load(&checkpoint_path)?;
+            if force {
+                IndexCheckpoint::remove(&checkpoint_path)?;
+            } else if resume {
+                println!("Resuming: skipping {} files already indexed", checkpoint.len());
+            } else if !checkpoint.is_empty() {
+                anyhow::bail!(
+                    "an earlier index of {} was interrupted after {} files; pass --resume to continue it or --force to start over",
+                    db_path,
+                    checkpoint.len()
+                );
+            }
+            let mut search = HybridSearch::with_config(db_path, &config).await?;
+            let report = search
+                .index_directory_resumable(Path::new(&path), &config, &checkpoint_path, |p| render_progress(&p))
+                .await?;
             
-            let mut contents = Vec::new();
-            let mut file_paths = Vec::new();
+            println!();
+            if !report.failed.is_empty() {
+                println!("{} of {} files failed to index:", report.failed.len(), report.failed.len() + report.succeeded.len());
+                for (_, error) in &report.failed {
+                    println!("  {}", error);
+                }
+            }
+            println!("Indexing complete!");
+        },
+        
+        Commands::Search { query, min_score, path, group_by, timings, format } => {
+            if format == OutputFormat::Text {
+                println!("Searching for: {}", query);
+            }
+            // Read-only, so searches run alongside an `index` or `watch` of the same index
+            let mut search = HybridSearch::open_read_only(db_path, &config).await?;
+            if min_score.is_some() || timings {
+                let fusion_config = FusionConfig { min_score, collect_timings: timings, ..search.fusion_config().clone() };
+                search.set_fusion_config(fusion_config);
+            }
             
-            // Walk directory and collect files
-            for entry in WalkDir::new(&path)
-                .into_iter()
-                .filter_map(|e| e.ok())
-                .filter(|e| e.file_type().is_file())
-                .filter(|e| {
-                    if let Some(ext) = e.path().extension() {
-                        if let Some(ext_str) = ext.to_str() {
-                            // Use config's supported extensions - now includes markdown!
-                            let supported = vec!["rs", "py", "js", "ts", "go", "java", "cpp", "c", "h", "md", "markdown"];
-                            supported.contains(&ext_str)
-                        } else {
-                            false
-                        }
-                    } else {
-                        false
+            if group_by == GroupBy::File {
+                let groups = search.search_grouped(&query, 10, group_by).await?;
+                if let Some(timings) = search.last_search_timings() {
+                    print_timings(&timings);
+                }
+                if format == OutputFormat::Json {
+                    println!("{}", serde_json::to_string_pretty(&groups)?);
+                } else if format == OutputFormat::Jsonl {
+                    for group in &groups {
+                        println!("{}", serde_json::to_string(group)?);
                     }
-                }) {
-                
-                if let Ok(content) = fs::read_to_string(entry.path()) {
-                    if content.len() < 10000 { // Skip very large files
-                        contents.push(content);
-                        file_paths.push(entry.path().display().to_string());
+                } else if groups.is_empty() {
+                    println!("No results found");
+                } else {
+                    println!("Found {} files:", groups.len());
+                    for (i, group) in groups.iter().enumerate() {
+                        let chunks = if group.chunk_hits.len() == 1 { "chunk" } else { "chunks" };
+                        println!("\n{}. {} ({} matching {})", i + 1, group.file_path, group.chunk_hits.len(), chunks);
+                        println!("   Score: {:.3}", group.score);
+                        println!("   {}", preview(&group.chunk_hits[0]));
                     }
                 }
-                
-                // Process in batches
-                if contents.len() >= 10 {
-                    println!("Indexing batch of {} files", contents.len());
-                    search.index(contents.clone(), file_paths.clone()).await?;
-                    contents.clear();
-                    file_paths.clear();
-                }
+                return Ok(());
             }
             
-            // Process remaining files
-            if !contents.is_empty() {
-                println!("Indexing final batch of {} files", contents.len());
-                search.index(contents, file_paths).await?;
+            let results = match &path {
+                Some(glob) => {
+                    let filter = PathFilter { include: vec![glob.clone()], exclude: Vec::new() };
+                    if search.count_indexed_paths_matching(&filter)? == 0 {
+                        eprintln!("No indexed files match '{}'", glob);
+                        if format == OutputFormat::Json {
+                            println!("[]");
+                        }
+                        return Ok(());
+                    }
+                    search.search_filtered(&query, 10, &filter).await?
+                }
+                None => search.search(&query, 10).await?,
+            };
+            if let Some(timings) = search.last_search_timings() {
+                print_timings(&timings);
             }
             
-            println!("Indexing complete!");
-        },
-        
-        Commands::Search { query } => {
-            println!("Searching for: {}", query);
-            let mut search = HybridSearch::new(db_path).await?;
-            
-            let results = search.search(&query, 10).await?;
-            
-            if results.is_empty() {
+            if format == OutputFormat::Json {
+                println!("{}", serde_json::to_string_pretty(&results)?);
+            } else if format == OutputFormat::Jsonl {
+                for result in &results {
+                    println!("{}", serde_json::to_string(result)?);
+                }
+            } else if results.is_empty() {
                 println!("No results found");
             } else {
                 println!("Found {} results:", results.len());
                 for (i, result) in results.iter().enumerate() {
-                    println!("\n{}. {} ({})", i + 1, result.file_path, result.match_type);
+                    match result.line_number {
+                        Some(line) => println!("\n{}. {}:{} ({})", i + 1, result.file_path, line, result.match_type),
+                        None => println!("\n{}. {} ({})", i + 1, result.file_path, result.match_type),
+                    }
+                    if let Some(heading_path) = &result.heading_path {
+                        println!("   Section: {}", heading_path);
+                    }
                     println!("   Score: {:.3}", result.score);
-                    let preview = if result.content.len() > 100 {
-                        format!("{}...", &result.content[..100])
-                    } else {
-                        result.content.clone()
-                    };
-                    println!("   {}", preview.replace('\n', " "));
+                    println!("   {}", preview(result));
                 }
             }
         },
         
-        Commands::Clear => {
-            println!("Clearing all indexed data");
-            let mut search = HybridSearch::new(db_path).await?;
-            search.clear().await?;
+        Commands::Similar { path, format } => {
+            let search = HybridSearch::open_read_only(db_path, &config).await?;
+            let results = search.find_similar(&path, 10)?;
+            if format == OutputFormat::Json {
+                println!("{}", serde_json::to_string_pretty(&results)?);
+            } else if format == OutputFormat::Jsonl {
+                for result in &results {
+                    println!("{}", serde_json::to_string(result)?);
+                }
+            } else if results.is_empty() {
+                println!("No other indexed files");
+            } else {
+                println!("Files similar to {}:", path);
+                for (i, result) in results.iter().enumerate() {
+                    println!("\n{}. {}", i + 1, result.file_path);
+                    println!("   Similarity: {:.3}", result.score);
+                    println!("   {}", preview(result));
+                }
+            }
+        },
+        
+        Commands::Analyze { text, format } => {
+            let tokens = CodeTextProcessor::from_search_config(&config.search).analyze(&text);
+            if format == OutputFormat::Json {
+                println!("{}", serde_json::to_string_pretty(&tokens)?);
+            } else if format == OutputFormat::Jsonl {
+                for token in &tokens {
+                    println!("{}", serde_json::to_string(token)?);
+                }
+            } else {
+                for token in &tokens {
+                    let stop_word = if token.is_stop_word { "  (stop word)" } else { "" };
+                    println!("{}..{}\t{}\t{}{}", token.start, token.end, token.text, token.stemmed, stop_word);
+                }
+            }
+        },
+        
+        Commands::Stats { format } => {
+            // Read-only, so stats can be taken while an `index` or `watch` holds the writer
+            let search = HybridSearch::open_read_only(db_path, &config).await?;
+            let stats = search.stats()?;
+            
+            if format == OutputFormat::Json {
+                println!("{}", serde_json::to_string_pretty(&stats)?);
+            } else if format == OutputFormat::Jsonl {
+                println!("{}", serde_json::to_string(&stats)?);
+            } else {
+                println!("Index: {} ({} backend)", db_path, stats.backend);
+                println!("Documents: {}", stats.documents);
+                if let Some(bm25) = &stats.bm25 {
+                    println!("BM25 terms: {}", bm25.total_terms);
+                    println!("Average document length: {:.1} tokens", bm25.avg_doc_length);
+                    println!("BM25 size on disk: {} bytes", stats.bm25_disk_bytes.unwrap_or(0));
+                }
+                if let Some(tantivy) = &stats.tantivy {
+                    println!("Tantivy segments: {}", tantivy.segment_count);
+                    println!("Tantivy size on disk: {} bytes", tantivy.disk_bytes);
+                }
+                match stats.vector_dimension {
+                    Some(dim) => println!("Vectors: {} ({} dimensions)", stats.vector_count, dim),
+                    None => println!("Vectors: {}", stats.vector_count),
+                }
+                println!("Vector storage: {}", Path::new(db_path).join(VECTOR_STORE_FILE).display());
+            }
+        },
+        
+        Commands::Watch { path } => watch(Path::new(&path), db_path, &config).await?,
+        
+        Commands::Doctor => unreachable!("handled before the config is loaded"),
+        
+        Commands::Clear { only } => {
+            let target = only.unwrap_or(ClearTarget::All);
+            match target {
+                ClearTarget::All => println!("Clearing all indexed data"),
+                target => println!("Clearing {} data", target),
+            }
+            let mut search = HybridSearch::with_config(db_path, &config).await?;
+            search.clear_backend(target).await?;
             println!("Data cleared!");
         },
+        
+        Commands::Export { path } => {
+            let search = HybridSearch::open_read_only(db_path, &config).await?;
+            let manifest = search.export_archive(Path::new(&path))?;
+            println!("Exported {} documents and {} vectors to {}", manifest.documents, manifest.vectors, path);
+        },
+        
+        Commands::Import { path, force } => {
+            let mut search = HybridSearch::with_config(db_path, &config).await?;
+            let manifest = search.import_archive(Path::new(&path), force).await?;
+            println!("Imported {} documents and {} vectors from {}", manifest.documents, manifest.vectors, path);
+        },
     }
 
     Ok(())
+}
+
+/// Initial index of `root`, then re-index whatever the configured `WatchBackend`
+/// reports as changed. Everything goes through `HybridSearch` with the configured
+/// backend, so searches of the same index see each pass as soon as it is stored.
+/// The config is reloaded on SIGHUP or when its file changes. Its log level, fusion
+/// weights and indexing settings apply from then on, starting with a pass over the
+/// whole tree; the watch backend and its timings keep their startup values.
+async fn watch(root: &Path, db_path: &str, config: &Config) -> Result<()> {
+    // Absolute paths so walk results and filesystem events name files the same way
+    let root = fs::canonicalize(root)?;
+    let mut search = HybridSearch::with_config(db_path, config).await?;
+    let mut current = Arc::new(config.clone());
+    let mut reloads = Config::subscribe();
+    reloads.mark_unchanged();
+    let reloader = Config::spawn_reloader(CONFIG_CHECK_INTERVAL);
+
+    println!("Indexing files in: {}", root.display());
+    let report = search.index_directory(&root, &current, |p| render_progress(&p)).await?;
+    println!();
+    for (_, error) in &report.failed {
+        println!("  {}", error);
+    }
+    // Drop what was deleted while nobody was watching
+    log_reindex(&search.reconcile(&root, &current).await?);
+
+    let shutdown = tokio::signal::ctrl_c();
+    tokio::pin!(shutdown);
+    match config.watch.backend {
+        WatchBackend::GitPoll => {
+            let mut watcher = Watcher::new(&root, &config.watch)?;
+            let interval = config.watch.poll_interval();
+            println!("Watching {} ({} polling every {}s), Ctrl-C to stop", root.display(), watcher.source(), interval.as_secs());
+            loop {
+                tokio::select! {
+                    result = &mut shutdown => {
+                        result?;
+                        break;
+                    }
+                    changed = reloads.changed() => {
+                        changed?;
+                        current = reloads.borrow_and_update().clone();
+                        apply_reloaded_config(&mut search, &root, &current).await?;
+                    }
+                    _ = tokio::time::sleep(interval) => {
+                        if watcher.poll()? {
+                            log_reindex(&search.reconcile(&root, &current).await?);
+                        }
+                    }
+                }
+            }
+        }
+        WatchBackend::Notify => {
+            let bm25_path = config
+                .storage
+                .bm25_index_path
+                .clone()
+                .unwrap_or_else(|| Path::new(db_path).join(DEFAULT_BM25_INDEX_FILE));
+            let index_dir = fs::canonicalize(db_path)?;
+            let mut watcher = NotifyWatcher::new(&root, config.watch.debounce(), &[index_dir, bm25_path])?;
+            println!("Watching {} (filesystem events), Ctrl-C to stop", root.display());
+            loop {
+                tokio::select! {
+                    result = &mut shutdown => {
+                        result?;
+                        break;
+                    }
+                    changed = reloads.changed() => {
+                        changed?;
+                        current = reloads.borrow_and_update().clone();
+                        apply_reloaded_config(&mut search, &root, &current).await?;
+                    }
+                    batch = watcher.next_batch() => {
+                        let Some(changed) = batch else { break };
+                        log_reindex(&search.reindex_paths(&root, &changed, &current).await?);
+                    }
+                }
+            }
+        }
+    }
+
+    reloader.abort();
+    search.close().await?;
+    println!("Stopped watching, index saved to {}", db_path);
+    Ok(())
+}
+
+/// Apply the settings of a reloaded config that a running watch can take on, then
+/// reconcile `root` so changed indexing settings take effect on files left untouched
+async fn apply_reloaded_config(search: &mut HybridSearch, root: &Path, config: &Config) -> Result<()> {
+    init_logging(config);
+    search.apply_config(config);
+    println!("Config reloaded: log level, fusion weights and indexing settings applied");
+    log_reindex(&search.reconcile(root, config).await?);
+    Ok(())
+}
+
+/// Log what a watch pass changed
+fn log_reindex(report: &ReconcileReport) {
+    if report.added.is_empty() && report.updated.is_empty() && report.removed.is_empty() {
+        return;
+    }
+    for path in report.added.iter().chain(&report.updated) {
+        log::info!("Reindexed {}", path.display());
+    }
+    for path in &report.removed {
+        log::info!("Removed {}", path.display());
+    }
+    println!(
+        "Reindexed: {} added, {} updated, {} removed",
+        report.added.len(),
+        report.updated.len(),
+        report.removed.len()
+    );
+}
+
+/// One-line excerpt of a result: its snippet, or the start of its content
+fn preview(result: &SearchResult) -> String {
+    let preview = if let Some(snippet) = &result.snippet {
+        snippet.clone()
+    } else {
+        content_preview(&result.content, 100)
+    };
+    preview.replace('\n', " ")
+}
+
+/// Per-phase search latency, on stderr so it stays out of JSON output
+fn print_timings(timings: &SearchTimings) {
+    eprintln!("Search took {:.2?}:", timings.total);
+    for (phase, duration) in timings.phases() {
+        eprintln!("  {:<16} {:.2?}", phase, duration);
+    }
+}
+
+/// Redraw the single-line indexing progress indicator
+fn render_progress(progress: &IndexProgress) {
+    print!(
+        "\rFiles: {} seen, {} indexed, {} skipped ({} KB)",
+        progress.files_seen,
+        progress.files_indexed,
+        progress.files_skipped,
+        progress.bytes_processed / 1024
+    );
+    let _ = std::io::stdout().flush();
 }
\ No newline at end of file
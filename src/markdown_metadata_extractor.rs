@@ -2,10 +2,19 @@
 // Implements Phase 4 requirements: enhanced metadata and context extraction
 
 use anyhow::Result;
+use once_cell::sync::Lazy;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use crate::chunking::{MarkdownChunk, MarkdownChunkType};
+use crate::search::snippet::query_terms;
+
+/// ATX heading: level marker and text, without a trailing `{#anchor}`
+static HEADING_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(#{1,6})\s+(.+?)(?:\s*\{#([^}]+)\})?\s*$").expect("valid heading regex"));
+
+/// Separator used when a heading path is shown as a breadcrumb
+pub const HEADING_BREADCRUMB_SEPARATOR: &str = " > ";
 
 /// Enhanced markdown element with extracted metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -707,10 +716,108 @@ impl MarkdownMetadataExtractor {
     }
 }
 
+/// Whether `path` names a markdown file, the only kind heading context applies to
+pub fn is_markdown_path(path: &str) -> bool {
+    path.ends_with(".md") || path.ends_with(".markdown")
+}
+
+/// Headings enclosing 0-based `line` of a markdown document, outermost first.
+/// A heading line encloses itself; fenced code is skipped so `# comments` don't count.
+pub fn heading_path_at(content: &str, line: usize) -> Vec<String> {
+    let mut path: Vec<(usize, String)> = Vec::new();
+    let mut in_fence = false;
+    for text in content.lines().take(line + 1) {
+        if text.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        if let Some(captures) = HEADING_PATTERN.captures(text) {
+            let level = captures[1].len();
+            while path.last().is_some_and(|(parent, _)| *parent >= level) {
+                path.pop();
+            }
+            path.push((level, captures[2].trim().to_string()));
+        }
+    }
+    path.into_iter().map(|(_, heading)| heading).collect()
+}
+
+/// The heading path that best explains a match of `query` in `content`, with the
+/// share of distinct query terms found in it (0.0..=1.0). Headings mentioning the
+/// query win; otherwise the path enclosing the first line with any query term.
+/// `None` when no query term occurs in the document.
+pub fn match_heading_path(content: &str, query: &str) -> Option<(Vec<String>, f32)> {
+    let terms = query_terms(query);
+    if terms.is_empty() {
+        return None;
+    }
+
+    let mut best: Option<(Vec<String>, usize)> = None;
+    let mut first_match: Option<usize> = None;
+    let mut in_fence = false;
+    for (index, text) in content.lines().enumerate() {
+        if text.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        let lower = text.to_lowercase();
+        if !terms.iter().any(|t| lower.contains(t.as_str())) {
+            continue;
+        }
+        first_match.get_or_insert(index);
+        if !in_fence && HEADING_PATTERN.is_match(text) {
+            let path = heading_path_at(content, index);
+            let matched = count_terms(&path, &terms);
+            if best.as_ref().is_none_or(|(_, count)| matched > *count) {
+                best = Some((path, matched));
+            }
+        }
+    }
+
+    let (path, matched) = match best {
+        Some(best) => best,
+        None => {
+            let path = heading_path_at(content, first_match?);
+            let matched = count_terms(&path, &terms);
+            (path, matched)
+        }
+    };
+    Some((path, matched as f32 / terms.len() as f32))
+}
+
+fn count_terms(path: &[String], terms: &[String]) -> usize {
+    let joined = path.join(" ").to_lowercase();
+    terms.iter().filter(|t| joined.contains(t.as_str())).count()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::chunking::MarkdownRegexChunker;
+
+    const GUIDE: &str = "# Guide\n\n## Installation\n\nRun the installer.\n\n### Linux\n\n```sh\n# not a heading\napt install tool\n```\n\n## Usage\n\nRun tool on linux or mac.";
+
+    #[test]
+    fn test_heading_path_at_tracks_nesting() {
+        assert_eq!(heading_path_at(GUIDE, 4), vec!["Guide", "Installation"]);
+        assert_eq!(heading_path_at(GUIDE, 10), vec!["Guide", "Installation", "Linux"]);
+        assert_eq!(heading_path_at(GUIDE, 15), vec!["Guide", "Usage"]);
+        assert_eq!(heading_path_at("no headings", 0), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_match_heading_path_prefers_matching_heading() {
+        let (path, share) = match_heading_path(GUIDE, "linux install").unwrap();
+        assert_eq!(path.join(HEADING_BREADCRUMB_SEPARATOR), "Guide > Installation > Linux");
+        assert_eq!(share, 1.0);
+
+        let (path, share) = match_heading_path(GUIDE, "mac").unwrap();
+        assert_eq!(path, vec!["Guide", "Usage"]);
+        assert_eq!(share, 0.0);
+        assert!(match_heading_path(GUIDE, "windows").is_none());
+    }
     
     #[test]
     fn test_symbol_extraction() -> Result<()> {
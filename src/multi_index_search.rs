@@ -0,0 +1,328 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+
+use crate::config::WorkspaceConfig;
+use crate::search::fusion::{normalize_scores, ScoreNormalization};
+use crate::simple_search::{HybridSearch, SearchResult};
+use crate::utils::content_preview_with_marker;
+
+/// RRF constant shared with `HybridSearch`
+const RRF_K: f32 = 60.0;
+
+/// Workspace search across several repositories, one `HybridSearch` per repo
+pub struct MultiIndexSearch {
+    indexes: Vec<NamedIndex>,
+    merge: IndexMerge,
+}
+
+/// How `MultiIndexSearch::search` merges the rankings of its indexes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IndexMerge {
+    /// Weighted RRF over each index's ranks, see `fuse_weighted`
+    #[default]
+    Rrf,
+    /// Each index's scores min-max normalized onto `0.0..=1.0`, then multiplied by its
+    /// weight, so a close match in one index outranks a marginal one in another
+    /// whatever their ranks. An index's best result always scores its full weight.
+    NormalizedScore,
+}
+
+struct NamedIndex {
+    name: String,
+    weight: f32,
+    search: HybridSearch,
+}
+
+#[derive(Debug, Clone)]
+pub struct MultiIndexResult {
+    pub content: String,
+    pub file_path: String,
+    pub score: f32,
+    pub match_type: String,
+    /// Name of the index the result came from
+    pub source_index: String,
+}
+
+impl MultiIndexSearch {
+    pub fn new() -> Self {
+        Self { indexes: Vec::new(), merge: IndexMerge::default() }
+    }
+
+    /// Merge the per-index results with `merge` instead of weighted RRF
+    pub fn with_merge(mut self, merge: IndexMerge) -> Self {
+        self.merge = merge;
+        self
+    }
+
+    /// Open one `HybridSearch` per configured repository root
+    pub async fn from_config(config: &WorkspaceConfig) -> Result<Self> {
+        let mut multi = Self::new();
+        for root in &config.roots {
+            let db_path = root.index_path();
+            let db_path = db_path
+                .to_str()
+                .ok_or_else(|| anyhow!("Index path for '{}' is not valid UTF-8", root.name))?;
+            let search = HybridSearch::new(db_path).await?;
+            multi.add_index(&root.name, root.weight, search)?;
+        }
+        Ok(multi)
+    }
+
+    /// Register an index under a unique name with a positive fusion weight
+    pub fn add_index(&mut self, name: &str, weight: f32, search: HybridSearch) -> Result<()> {
+        if !weight.is_finite() || weight <= 0.0 {
+            return Err(anyhow!("Weight for index '{}' must be positive, got {}", name, weight));
+        }
+        if self.indexes.iter().any(|index| index.name == name) {
+            return Err(anyhow!("Index '{}' is already registered", name));
+        }
+        self.indexes.push(NamedIndex {
+            name: name.to_string(),
+            weight,
+            search,
+        });
+        Ok(())
+    }
+
+    pub fn index_names(&self) -> Vec<&str> {
+        self.indexes.iter().map(|index| index.name.as_str()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.indexes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indexes.is_empty()
+    }
+
+    /// Fan the query out to every index and merge the ranked lists into the top `limit`
+    pub async fn search(&mut self, query: &str, limit: usize) -> Result<Vec<MultiIndexResult>> {
+        let mut result_sets = Vec::with_capacity(self.indexes.len());
+        for index in &mut self.indexes {
+            let results = index.search.search(query, limit).await?;
+            result_sets.push((index.name.clone(), index.weight, results));
+        }
+        Ok(match self.merge {
+            IndexMerge::Rrf => fuse_weighted(result_sets, limit),
+            IndexMerge::NormalizedScore => merge_normalized(result_sets, limit),
+        })
+    }
+}
+
+impl Default for MultiIndexSearch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Federated query over separate indexes, e.g. one per repository: a `MultiIndexSearch`
+/// that merges by `IndexMerge::NormalizedScore`, so results rank by how well they match
+/// rather than by their rank within their own index
+pub struct FederatedSearcher {
+    indexes: MultiIndexSearch,
+}
+
+impl FederatedSearcher {
+    pub fn new() -> Self {
+        Self { indexes: MultiIndexSearch::new().with_merge(IndexMerge::NormalizedScore) }
+    }
+
+    /// Register an index under a unique name with a positive weight, the score its best
+    /// result is normalized to
+    pub fn add_index(&mut self, name: &str, weight: f32, search: HybridSearch) -> Result<()> {
+        self.indexes.add_index(name, weight, search)
+    }
+
+    pub fn index_names(&self) -> Vec<&str> {
+        self.indexes.index_names()
+    }
+
+    pub fn len(&self) -> usize {
+        self.indexes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indexes.is_empty()
+    }
+
+    /// Run the query against every index and return the top `limit` by normalized
+    /// score, each tagged with its `source_index`
+    pub async fn search(&mut self, query: &str, limit: usize) -> Result<Vec<MultiIndexResult>> {
+        self.indexes.search(query, limit).await
+    }
+}
+
+impl Default for FederatedSearcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Weighted RRF across result sets: each result scores `weight / (k + rank + 1)`
+/// within its own index, so the per-index rankings are kept and the weights
+/// decide how they interleave.
+fn fuse_weighted(result_sets: Vec<(String, f32, Vec<SearchResult>)>, limit: usize) -> Vec<MultiIndexResult> {
+    let mut score_map: HashMap<String, MultiIndexResult> = HashMap::new();
+
+    for (name, weight, results) in result_sets {
+        for (rank, result) in results.into_iter().enumerate() {
+            let key = format!("{}:{}:{}", name, result.file_path, content_preview_with_marker(&result.content, 50, ""));
+            let rrf_score = weight / (RRF_K + rank as f32 + 1.0);
+
+            score_map
+                .entry(key)
+                .and_modify(|existing| existing.score += rrf_score)
+                .or_insert(MultiIndexResult {
+                    content: result.content,
+                    file_path: result.file_path,
+                    score: rrf_score,
+                    match_type: result.match_type,
+                    source_index: name.clone(),
+                });
+        }
+    }
+
+    let mut final_results: Vec<_> = score_map.into_values().collect();
+    final_results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    final_results.into_iter().take(limit).collect()
+}
+
+/// Min-max normalize each result set's scores, weight them and keep the best `limit`.
+/// Equal scores are ordered by index name, then path.
+fn merge_normalized(result_sets: Vec<(String, f32, Vec<SearchResult>)>, limit: usize) -> Vec<MultiIndexResult> {
+    let mut merged = Vec::new();
+    for (name, weight, results) in result_sets {
+        let mut scores: Vec<f32> = results.iter().map(|result| result.score).collect();
+        normalize_scores(&mut scores, ScoreNormalization::MinMax);
+        merged.extend(results.into_iter().zip(scores).map(|(result, score)| MultiIndexResult {
+            content: result.content,
+            file_path: result.file_path,
+            score: weight * score,
+            match_type: result.match_type,
+            source_index: name.clone(),
+        }));
+    }
+    merged.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.source_index.cmp(&b.source_index))
+            .then_with(|| a.file_path.cmp(&b.file_path))
+    });
+    merged.truncate(limit);
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, SemanticMode};
+    use tempfile::tempdir;
+
+    fn result(path: &str, content: &str) -> SearchResult {
+        SearchResult {
+            content: content.to_string(),
+            file_path: path.to_string(),
+            score: 1.0,
+            match_type: "hybrid".to_string(),
+            snippet: None,
+            line_number: None,
+            heading_path: None,
+            component_scores: None,
+            metadata: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_fuse_weighted_labels_source_index() {
+        let sets = vec![
+            ("repo_a".to_string(), 1.0, vec![result("a.rs", "fn a() {}")]),
+            ("repo_b".to_string(), 1.0, vec![result("b.rs", "fn b() {}")]),
+        ];
+        let fused = fuse_weighted(sets, 10);
+        assert_eq!(fused.len(), 2);
+        assert!(fused.iter().any(|r| r.source_index == "repo_a" && r.file_path == "a.rs"));
+        assert!(fused.iter().any(|r| r.source_index == "repo_b" && r.file_path == "b.rs"));
+    }
+
+    #[test]
+    fn test_fuse_weighted_respects_weights() {
+        let sets = vec![
+            ("low".to_string(), 0.5, vec![result("low.rs", "low")]),
+            ("high".to_string(), 2.0, vec![result("high1.rs", "one"), result("high2.rs", "two")]),
+        ];
+        let fused = fuse_weighted(sets, 10);
+        assert_eq!(fused[0].source_index, "high");
+        assert_eq!(fused[1].source_index, "high");
+        assert_eq!(fused[2].source_index, "low");
+    }
+
+    #[test]
+    fn test_fuse_weighted_keeps_same_path_from_different_indexes() {
+        let sets = vec![
+            ("repo_a".to_string(), 1.0, vec![result("src/lib.rs", "same")]),
+            ("repo_b".to_string(), 1.0, vec![result("src/lib.rs", "same")]),
+        ];
+        let fused = fuse_weighted(sets, 10);
+        assert_eq!(fused.len(), 2);
+    }
+
+    #[test]
+    fn test_fuse_weighted_keys_multibyte_content() {
+        // Byte 50 falls inside a three-byte character
+        let content = format!("{}{}", "x".repeat(49), "漢字の説明");
+        let sets = vec![("repo".to_string(), 1.0, vec![result("doc.md", &content), result("doc.md", &content)])];
+        let fused = fuse_weighted(sets, 10);
+        assert_eq!(fused.len(), 1);
+        assert_eq!(fused[0].content, content);
+    }
+
+    #[test]
+    fn test_merge_normalized_scales_each_index_onto_its_weight() {
+        let scored = |path: &str, score: f32| SearchResult { score, ..result(path, path) };
+        let sets = vec![
+            ("small".to_string(), 1.0, vec![scored("s1.rs", 0.03), scored("s2.rs", 0.01)]),
+            ("large".to_string(), 1.0, vec![scored("l1.rs", 9.0), scored("l2.rs", 6.0), scored("l3.rs", 3.0)]),
+        ];
+        let merged = merge_normalized(sets, 4);
+        let order: Vec<(&str, f32)> = merged.iter().map(|r| (r.file_path.as_str(), r.score)).collect();
+        assert_eq!(order, [("l1.rs", 1.0), ("s1.rs", 1.0), ("l2.rs", 0.5), ("l3.rs", 0.0)]);
+    }
+
+    #[tokio::test]
+    async fn test_federated_search_merges_disjoint_indexes() -> Result<()> {
+        let mut federated = FederatedSearcher::new();
+        let mut dirs = Vec::new();
+        for (name, files) in [
+            ("billing", [("invoice.rs", "// send an invoice reminder\nfn remind() {}"), ("ledger.rs", "// post a ledger entry\nfn post() {}")]),
+            ("auth", [("session.rs", "// send a login reminder\nfn remind() {}"), ("token.rs", "// rotate the token\nfn rotate() {}")]),
+        ] {
+            let temp_dir = tempdir()?;
+            let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+            let mut config = Config::default();
+            config.search.semantic_mode = SemanticMode::Disabled;
+            let mut search = HybridSearch::with_config(&db_path, &config).await?;
+            let (paths, contents): (Vec<String>, Vec<String>) = files.iter().map(|(p, c)| (p.to_string(), c.to_string())).unzip();
+            search.index(contents, paths).await?;
+            federated.add_index(name, 1.0, search)?;
+            dirs.push(temp_dir);
+        }
+
+        let results = federated.search("send reminder", 10).await?;
+        let found: Vec<(&str, &str)> = results.iter().map(|r| (r.source_index.as_str(), r.file_path.as_str())).collect();
+        assert!(found.contains(&("billing", "invoice.rs")), "{:?}", found);
+        assert!(found.contains(&("auth", "session.rs")), "{:?}", found);
+        assert!(results.windows(2).all(|pair| pair[0].score >= pair[1].score));
+        Ok(())
+    }
+
+    #[test]
+    fn test_fuse_weighted_truncates_to_limit() {
+        let sets = vec![(
+            "repo".to_string(),
+            1.0,
+            (0..5).map(|i| result(&format!("{}.rs", i), "x")).collect(),
+        )];
+        assert_eq!(fuse_weighted(sets, 3).len(), 3);
+    }
+}
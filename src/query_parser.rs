@@ -0,0 +1,156 @@
+// Field-scoped queries: `parse symbol:Parser path:src/ lang:rust`
+
+use std::str::FromStr;
+
+use crate::embedding_prefixes::CodeFormatter;
+use crate::error::SearchError;
+use crate::simple_search::{MetadataFilter, PathFilter};
+
+/// Fields recognized before a `:` in a query
+pub const QUERY_FIELDS: [&str; 4] = ["symbol", "path", "lang", "meta"];
+
+/// A query split into its bare terms and `field:value` scopes, see `parse_query`.
+/// Values of a field repeated in one query are alternatives, except `symbol` and
+/// `meta`, which must all hold.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ParsedQuery {
+    /// Bare terms in query order, for the fused search
+    pub text: String,
+    /// `symbol:` identifiers a result's content must contain
+    pub symbols: Vec<String>,
+    /// `path:` globs; a value without glob characters is a path prefix
+    pub path: PathFilter,
+    /// `lang:` languages as named by `CodeFormatter::detect_language`
+    pub languages: Vec<String>,
+    /// `meta:key=value` conditions on `SearchResult::metadata`
+    pub metadata: MetadataFilter,
+}
+
+impl ParsedQuery {
+    /// Bare terms followed by the symbols, which the keyword and exact matchers rank on
+    pub fn search_text(&self) -> String {
+        let mut words: Vec<&str> = self.text.split_whitespace().collect();
+        words.extend(self.symbols.iter().map(String::as_str));
+        words.join(" ")
+    }
+
+    /// No field narrows the fused search
+    pub fn is_unscoped(&self) -> bool {
+        self.symbols.is_empty() && self.path.is_empty() && self.languages.is_empty() && self.metadata.is_empty()
+    }
+
+    /// `file_path` is in one of `languages`, or no language was asked for
+    pub fn matches_language(&self, file_path: &str) -> bool {
+        self.languages.is_empty()
+            || CodeFormatter::detect_language(file_path).is_some_and(|lang| self.languages.iter().any(|l| l == lang))
+    }
+
+    /// `content` contains every symbol as a whole identifier
+    pub fn matches_symbols(&self, content: &str) -> bool {
+        self.symbols.iter().all(|symbol| contains_identifier(content, symbol))
+    }
+}
+
+impl FromStr for ParsedQuery {
+    type Err = SearchError;
+
+    fn from_str(query: &str) -> Result<Self, SearchError> {
+        parse_query(query)
+    }
+}
+
+/// Split whitespace-separated `query` into bare terms and `field:value` scopes for
+/// the fields in `QUERY_FIELDS`. Words with `::` or `://` (`std::fs`, URLs) are bare
+/// terms. An unknown field, a missing field name (`:foo`) or an empty value is
+/// `SearchError::InvalidParameter`.
+pub fn parse_query(query: &str) -> Result<ParsedQuery, SearchError> {
+    let invalid = |reason: String| SearchError::InvalidParameter { parameter: "query".to_string(), reason };
+    let mut parsed = ParsedQuery::default();
+    let mut text = Vec::new();
+    for word in query.split_whitespace() {
+        let Some((field, value)) = word.split_once(':').filter(|_| !word.contains("::") && !word.contains("://")) else {
+            text.push(word);
+            continue;
+        };
+        if field.is_empty() {
+            return Err(invalid(format!("'{}' has no field name before ':'", word)));
+        }
+        if value.is_empty() {
+            return Err(invalid(format!("'{}' has no value after ':'", word)));
+        }
+        match field.to_lowercase().as_str() {
+            "symbol" => parsed.symbols.push(value.to_string()),
+            "path" => parsed.path.include.extend(path_globs(value)),
+            "lang" => parsed.languages.push(value.to_lowercase()),
+            "meta" => {
+                let (key, expected) = value
+                    .split_once('=')
+                    .filter(|(key, _)| !key.is_empty())
+                    .ok_or_else(|| invalid(format!("'{}' expected meta:key=value", word)))?;
+                parsed.metadata = std::mem::take(&mut parsed.metadata).equals(key, expected);
+            }
+            _ => {
+                return Err(invalid(format!("unknown field '{}' in '{}', expected one of {}", field, word, QUERY_FIELDS.join(", "))));
+            }
+        }
+    }
+    parsed.text = text.join(" ");
+    Ok(parsed)
+}
+
+/// A glob value as is; a plain path as itself and everything under it
+fn path_globs(value: &str) -> Vec<String> {
+    let value = value.strip_prefix("./").unwrap_or(value);
+    if value.contains(['*', '?', '[', '{']) {
+        vec![value.to_string()]
+    } else if value.ends_with('/') {
+        vec![format!("{}**", value)]
+    } else {
+        vec![value.to_string(), format!("{}/**", value)]
+    }
+}
+
+fn contains_identifier(content: &str, identifier: &str) -> bool {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    content.match_indices(identifier).any(|(start, _)| {
+        let end = start + identifier.len();
+        !content[..start].chars().next_back().is_some_and(is_ident) && !content[end..].chars().next().is_some_and(is_ident)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mixed_query_splits_into_scopes() {
+        let parsed = parse_query("parse symbol:Parser path:src/ tokens").unwrap();
+        assert_eq!(parsed.text, "parse tokens");
+        assert_eq!(parsed.symbols, ["Parser"]);
+        assert_eq!(parsed.path.include, ["src/**"]);
+        assert_eq!(parsed.search_text(), "parse tokens Parser");
+        assert!(!parsed.is_unscoped());
+
+        assert!(parsed.matches_symbols("pub struct Parser {}"));
+        assert!(!parsed.matches_symbols("pub struct ParserState {}"));
+
+        let parsed: ParsedQuery = "std::fs::read lang:rust meta:source=wiki".parse().unwrap();
+        assert_eq!(parsed.text, "std::fs::read");
+        assert!(parsed.matches_language("src/lib.rs"));
+        assert!(!parsed.matches_language("app.py"));
+        assert_eq!(parsed.metadata, MetadataFilter::new().equals("source", "wiki"));
+        assert!(parse_query("just words").unwrap().is_unscoped());
+    }
+
+    #[test]
+    fn test_malformed_fields_are_rejected() {
+        for query in [":foo", "parse author:me", "symbol:", "meta:source"] {
+            match parse_query(query) {
+                Err(SearchError::InvalidParameter { parameter, .. }) => assert_eq!(parameter, "query", "{}", query),
+                other => panic!("expected InvalidParameter for {:?}, got {:?}", query, other),
+            }
+        }
+        let message = parse_query("author:me").unwrap_err().to_string();
+        assert!(message.contains("author") && message.contains("symbol, path, lang, meta"), "{}", message);
+    }
+}
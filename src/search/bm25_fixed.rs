@@ -1,15 +1,62 @@
 // Fixed BM25 implementation with correct IDF calculation
 // Following TDD red-green-refactor methodology
 
-use anyhow::Result;
+use anyhow::{bail, Result};
+use once_cell::sync::Lazy;
+use rust_stemmers::{Algorithm, Stemmer};
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use rustc_hash::FxHashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use crate::search::text_processor::{character_ngrams, CodeTextProcessor};
+use crate::search::SearchOptions;
+
+static STEMMER: Lazy<Stemmer> = Lazy::new(|| Stemmer::create(Algorithm::English));
 
 /// BM25 parameters
 const K1: f32 = 1.2; // Term frequency saturation
 const B: f32 = 0.75; // Document length normalization
 
+/// Default weight of a query term in a document's file name relative to one in its content
+pub const DEFAULT_FILENAME_BOOST: f32 = 2.0;
+
+/// Weight of a query term found only inside a longer term, relative to a whole-term match
+pub const NGRAM_WEIGHT: f32 = 0.3;
+
+/// Bumped whenever the on-disk layout or tokenization changes; older files are rebuilt
+pub const BM25_INDEX_VERSION: u32 = 3;
+
+/// How a term's frequency `tf` in a document becomes the factor its IDF is multiplied
+/// by. `norm` is the length normalization `1 - B + B * dl / avgdl`.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TfVariant {
+    /// `tf * (K1 + 1) / (tf + K1 * norm)`
+    #[default]
+    Bm25Classic,
+    /// BM25+: the classic factor plus `delta`, so a match in a very long document keeps
+    /// at least `delta` of the IDF. The IDF itself is unchanged.
+    Bm25Plus { delta: f32 },
+    /// The classic factor of `ln(1 + tf)` instead of `tf`, so repeats add even less:
+    /// 50 occurrences weigh about as much as 4 do under `Bm25Classic`
+    LogTf,
+}
+
+impl TfVariant {
+    /// The factor for a term occurring `tf` times in a document `length_ratio` times
+    /// the average length
+    pub fn term_weight(&self, tf: f32, length_ratio: f32) -> f32 {
+        let norm = 1.0 - B + B * length_ratio;
+        let saturate = |tf: f32| tf * (K1 + 1.0) / (tf + K1 * norm);
+        match self {
+            TfVariant::Bm25Classic => saturate(tf),
+            TfVariant::Bm25Plus { delta } => saturate(tf) + delta,
+            TfVariant::LogTf => saturate(tf.ln_1p()),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct BM25Match {
     pub path: String,
@@ -18,6 +65,28 @@ pub struct BM25Match {
     pub line_number: Option<usize>,
 }
 
+/// Summary of the BM25 index, see `BM25Engine::stats`
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct BM25Stats {
+    pub total_documents: usize,
+    /// Distinct terms in the inverted index
+    pub total_terms: usize,
+    /// Average document length in tokens
+    pub avg_doc_length: f32,
+}
+
+/// On-disk form of `BM25Engine`, see `BM25Engine::save`
+#[derive(Serialize, Deserialize)]
+struct BM25Snapshot {
+    version: u32,
+    documents: FxHashMap<String, (String, usize)>,
+    inverted_index: FxHashMap<String, HashSet<String>>,
+    doc_frequencies: FxHashMap<String, usize>,
+    total_docs: usize,
+    total_doc_length: usize,
+    avg_doc_length: f32,
+}
+
 /// Fixed BM25 search engine with correct IDF calculation
 pub struct BM25Engine {
     /// Document collection: doc_id -> (content, token_count)
@@ -28,8 +97,30 @@ pub struct BM25Engine {
     doc_frequencies: FxHashMap<String, usize>,
     /// Total number of documents
     total_docs: usize,
+    /// Sum of all document lengths, kept so the average updates in O(1)
+    total_doc_length: usize,
     /// Average document length
     avg_doc_length: f32,
+    /// Filename field: term -> doc_ids whose file stem contains it. Derived from the
+    /// doc ids, so it is rebuilt on `load` rather than saved.
+    filename_index: FxHashMap<String, HashSet<String>>,
+    /// Weight of one filename occurrence relative to one in the content; 0 ranks by content alone
+    filename_boost: f32,
+    /// Derives the terms of documents, file stems and queries, splitting compound
+    /// identifiers and dropping stop words. Not saved; see `with_text_processor`.
+    processor: CodeTextProcessor,
+    /// `(min, max)` lengths of the character n-grams indexed per term; `None` disables them
+    ngram_sizes: Option<(usize, usize)>,
+    /// N-gram field: character n-gram -> doc_ids with a term containing it. Derived from
+    /// the content, so it is rebuilt by `set_ngram_sizes` rather than saved.
+    ngram_index: FxHashMap<String, HashSet<String>>,
+    /// Whether queries match by stem when `SearchOptions::stemming` is `None`
+    stemming: bool,
+    /// English stem -> indexed terms with that stem. Kept whatever `stemming` says, so
+    /// any search can turn stemming on; derived from the vocabulary and rebuilt on `load`.
+    stem_vocabulary: FxHashMap<String, HashSet<String>>,
+    /// Term frequency saturation; a scoring choice, so not saved
+    tf_variant: TfVariant,
 }
 
 impl BM25Engine {
@@ -39,45 +130,345 @@ impl BM25Engine {
             inverted_index: FxHashMap::default(),
             doc_frequencies: FxHashMap::default(),
             total_docs: 0,
+            total_doc_length: 0,
             avg_doc_length: 0.0,
+            filename_index: FxHashMap::default(),
+            filename_boost: DEFAULT_FILENAME_BOOST,
+            processor: CodeTextProcessor::new(),
+            ngram_sizes: None,
+            ngram_index: FxHashMap::default(),
+            stemming: false,
+            stem_vocabulary: FxHashMap::default(),
+            tf_variant: TfVariant::default(),
         })
     }
+
+    /// Count each occurrence of a query term in the file stem as `boost` content occurrences
+    pub fn with_filename_boost(mut self, boost: f32) -> Self {
+        self.filename_boost = boost;
+        self
+    }
+
+    pub fn set_filename_boost(&mut self, boost: f32) {
+        self.filename_boost = boost;
+    }
+
+    pub fn filename_boost(&self) -> f32 {
+        self.filename_boost
+    }
+
+    /// Match query terms by their English stem unless a search says otherwise
+    pub fn with_stemming(mut self, stemming: bool) -> Self {
+        self.stemming = stemming;
+        self
+    }
+
+    pub fn set_stemming(&mut self, stemming: bool) {
+        self.stemming = stemming;
+    }
+
+    /// Score term frequencies with `variant` instead of classic BM25
+    pub fn with_tf_variant(mut self, variant: TfVariant) -> Self {
+        self.tf_variant = variant;
+        self
+    }
+
+    pub fn set_tf_variant(&mut self, variant: TfVariant) {
+        self.tf_variant = variant;
+    }
+
+    pub fn tf_variant(&self) -> TfVariant {
+        self.tf_variant
+    }
+
+    /// Also index the character n-grams of every term, `min..=max` characters long, so a
+    /// query term that is only part of an indexed term (`parse` in `tokenparser`) still
+    /// matches, scored at `NGRAM_WEIGHT` of a whole-term match
+    pub fn with_ngram_sizes(mut self, min: usize, max: usize) -> Self {
+        self.set_ngram_sizes(Some((min, max)));
+        self
+    }
+
+    /// Change or, with `None`, disable n-gram indexing, re-deriving the n-grams of
+    /// everything already indexed
+    pub fn set_ngram_sizes(&mut self, sizes: Option<(usize, usize)>) {
+        if self.ngram_sizes == sizes {
+            return;
+        }
+        self.ngram_sizes = sizes;
+        self.ngram_index.clear();
+        let documents: Vec<(String, String)> = self
+            .documents
+            .iter()
+            .map(|(doc_id, (content, _))| (doc_id.clone(), content.clone()))
+            .collect();
+        for (doc_id, content) in documents {
+            let terms: HashSet<String> = self.document_terms(&doc_id, &content).into_iter().collect();
+            self.index_ngrams(&doc_id, &terms);
+        }
+    }
+
+    /// Derive terms with `processor`, e.g. `CodeTextProcessor::from_search_config`, so
+    /// its tokenizer, stop words and identifier splitting apply. Already indexed content keeps its
+    /// old terms, so set this before indexing, or right after `load` with the processor
+    /// the index was built with.
+    pub fn with_text_processor(mut self, processor: CodeTextProcessor) -> Self {
+        self.set_text_processor(processor);
+        self
+    }
+
+    pub fn set_text_processor(&mut self, processor: CodeTextProcessor) {
+        self.processor = processor;
+        // The filename field is derived, so it can follow the new processor
+        self.filename_index.clear();
+        let doc_ids: Vec<String> = self.documents.keys().cloned().collect();
+        for doc_id in doc_ids {
+            self.index_filename(&doc_id);
+        }
+    }
+
+    pub fn text_processor(&self) -> &CodeTextProcessor {
+        &self.processor
+    }
     
-    /// Index a document
+    /// Index a document, replacing any existing document with the same id
     pub fn index_document(&mut self, doc_id: &str, content: &str) {
-        println!("DEBUG INDEX: Indexing doc_id='{}', content='{}'", doc_id, content);
+        // Re-indexing must not count the old version's terms twice
+        self.remove_entry(doc_id);
         
         // Tokenize content
-        let tokens = self.tokenize(content);
+        let tokens = self.document_terms(doc_id, content);
         let token_count = tokens.len();
         
-        println!("DEBUG INDEX: Tokens: {:?}", tokens);
-        
         // Store document
         self.documents.insert(doc_id.to_string(), (content.to_string(), token_count));
         
         // Update inverted index and document frequencies
-        let unique_terms: HashSet<String> = tokens.iter().cloned().collect();
-        println!("DEBUG INDEX: Unique terms: {:?}", unique_terms);
-        
+        let unique_terms: HashSet<String> = tokens.into_iter().collect();
+        self.index_ngrams(doc_id, &unique_terms);
         for term in unique_terms {
+            self.stem_vocabulary.entry(stem(&term)).or_default().insert(term.clone());
             self.inverted_index
                 .entry(term.clone())
                 .or_insert_with(HashSet::new)
                 .insert(doc_id.to_string());
             
-            let old_freq = *self.doc_frequencies.get(&term).unwrap_or(&0);
-            *self.doc_frequencies.entry(term.clone()).or_insert(0) += 1;
-            let new_freq = *self.doc_frequencies.get(&term).unwrap();
-            println!("DEBUG INDEX: Term '{}' frequency: {} -> {}", term, old_freq, new_freq);
+            *self.doc_frequencies.entry(term).or_insert(0) += 1;
         }
+        self.index_filename(doc_id);
         
         // Update statistics
         self.total_docs += 1;
+        self.total_doc_length += token_count;
         self.update_avg_doc_length();
+    }
+    
+    /// Remove a document and its contribution to the term statistics.
+    /// Returns `false` if the document was not indexed.
+    /// Only the removed document's terms are touched, so this is O(document) rather than O(corpus).
+    pub fn remove_document(&mut self, doc_id: &str) -> Result<bool> {
+        Ok(self.remove_entry(doc_id))
+    }
+    
+    fn remove_entry(&mut self, doc_id: &str) -> bool {
+        let (content, token_count) = match self.documents.remove(doc_id) {
+            Some(doc) => doc,
+            None => return false,
+        };
+        
+        let unique_terms: HashSet<String> = self.document_terms(doc_id, &content).into_iter().collect();
+        for ngram in self.term_ngrams(&unique_terms) {
+            if let Some(doc_ids) = self.ngram_index.get_mut(&ngram) {
+                doc_ids.remove(doc_id);
+                if doc_ids.is_empty() {
+                    self.ngram_index.remove(&ngram);
+                }
+            }
+        }
+        for term in unique_terms {
+            if let Some(doc_ids) = self.inverted_index.get_mut(&term) {
+                doc_ids.remove(doc_id);
+                if doc_ids.is_empty() {
+                    self.inverted_index.remove(&term);
+                    let term_stem = stem(&term);
+                    if let Some(terms) = self.stem_vocabulary.get_mut(&term_stem) {
+                        terms.remove(&term);
+                        if terms.is_empty() {
+                            self.stem_vocabulary.remove(&term_stem);
+                        }
+                    }
+                }
+            }
+            if let Some(freq) = self.doc_frequencies.get_mut(&term) {
+                *freq = freq.saturating_sub(1);
+                if *freq == 0 {
+                    self.doc_frequencies.remove(&term);
+                }
+            }
+        }
         
-        println!("DEBUG INDEX: Total docs now: {}", self.total_docs);
-        println!("DEBUG INDEX: Doc frequencies: {:?}", self.doc_frequencies);
+        for term in self.filename_terms(doc_id) {
+            if let Some(doc_ids) = self.filename_index.get_mut(&term) {
+                doc_ids.remove(doc_id);
+                if doc_ids.is_empty() {
+                    self.filename_index.remove(&term);
+                }
+            }
+        }
+        
+        self.total_docs = self.total_docs.saturating_sub(1);
+        self.total_doc_length = self.total_doc_length.saturating_sub(token_count);
+        self.update_avg_doc_length();
+        
+        true
+    }
+
+    fn index_ngrams(&mut self, doc_id: &str, terms: &HashSet<String>) {
+        for ngram in self.term_ngrams(terms) {
+            self.ngram_index.entry(ngram).or_default().insert(doc_id.to_string());
+        }
+    }
+
+    /// Distinct character n-grams of `terms`; empty when n-grams are disabled
+    fn term_ngrams(&self, terms: &HashSet<String>) -> HashSet<String> {
+        let Some((min, max)) = self.ngram_sizes else {
+            return HashSet::new();
+        };
+        terms.iter().flat_map(|term| character_ngrams(term, min, max)).collect()
+    }
+
+    /// Documents with a term containing `term` that the n-gram field can vouch for:
+    /// those holding `term` itself as an n-gram or, for a term longer than the largest
+    /// n-gram, every one of its largest n-grams
+    fn ngram_matches(&self, term: &str) -> HashSet<&String> {
+        let Some((min, max)) = self.ngram_sizes else {
+            return HashSet::new();
+        };
+        let length = term.chars().count();
+        if length < min {
+            return HashSet::new();
+        }
+        if length <= max {
+            return self.ngram_index.get(term).into_iter().flatten().collect();
+        }
+        let mut matches: Option<HashSet<&String>> = None;
+        for ngram in character_ngrams(term, max, max) {
+            let docs: HashSet<&String> = self.ngram_index.get(&ngram).into_iter().flatten().collect();
+            matches = Some(match matches {
+                None => docs,
+                Some(previous) => previous.intersection(&docs).copied().collect(),
+            });
+        }
+        matches.unwrap_or_default()
+    }
+
+    fn index_filename(&mut self, doc_id: &str) {
+        for term in self.filename_terms(doc_id) {
+            self.filename_index.entry(term).or_default().insert(doc_id.to_string());
+        }
+    }
+
+    /// Tokens of the file stem, e.g. `src/query_parser.rs` -> `["query", "parser"]`
+    fn filename_terms(&self, doc_id: &str) -> Vec<String> {
+        let stem = Path::new(doc_id).file_stem().map(|s| s.to_string_lossy()).unwrap_or_default();
+        self.processor.index_terms(&stem, None)
+    }
+    
+    /// Write the term statistics, document lengths and corpus stats to `path`
+    /// so a later `load` can skip re-tokenizing the corpus
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let snapshot = BM25Snapshot {
+            version: BM25_INDEX_VERSION,
+            documents: self.documents.clone(),
+            inverted_index: self.inverted_index.clone(),
+            doc_frequencies: self.doc_frequencies.clone(),
+            total_docs: self.total_docs,
+            total_doc_length: self.total_doc_length,
+            avg_doc_length: self.avg_doc_length,
+        };
+        
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Write to a temporary file first so a crash never leaves a truncated index
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, serde_json::to_vec(&snapshot)?)?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+    
+    /// Load an engine written by `save`. Fails on a version mismatch or when the
+    /// stored statistics are inconsistent.
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path)?;
+        let snapshot: BM25Snapshot = serde_json::from_slice(&bytes)?;
+        
+        if snapshot.version != BM25_INDEX_VERSION {
+            bail!("BM25 index version {} does not match expected version {}", snapshot.version, BM25_INDEX_VERSION);
+        }
+        let total_length: usize = snapshot.documents.values().map(|(_, len)| len).sum();
+        if snapshot.total_docs != snapshot.documents.len() || snapshot.total_doc_length != total_length {
+            bail!("BM25 index at {} is inconsistent", path.display());
+        }
+        
+        let mut engine = Self {
+            documents: snapshot.documents,
+            inverted_index: snapshot.inverted_index,
+            doc_frequencies: snapshot.doc_frequencies,
+            total_docs: snapshot.total_docs,
+            total_doc_length: snapshot.total_doc_length,
+            avg_doc_length: snapshot.avg_doc_length,
+            filename_index: FxHashMap::default(),
+            filename_boost: DEFAULT_FILENAME_BOOST,
+            processor: CodeTextProcessor::new(),
+            ngram_sizes: None,
+            ngram_index: FxHashMap::default(),
+            stemming: false,
+            stem_vocabulary: FxHashMap::default(),
+            tf_variant: TfVariant::default(),
+        };
+        let doc_ids: Vec<String> = engine.documents.keys().cloned().collect();
+        for doc_id in doc_ids {
+            engine.index_filename(&doc_id);
+        }
+        for term in engine.inverted_index.keys() {
+            engine.stem_vocabulary.entry(stem(term)).or_default().insert(term.clone());
+        }
+        Ok(engine)
+    }
+    
+    /// Load from `path` if it holds a valid index, otherwise start empty so the
+    /// caller rebuilds by re-indexing
+    pub fn load_or_new(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Self::new();
+        }
+        match Self::load(path) {
+            Ok(engine) => Ok(engine),
+            Err(e) => {
+                log::warn!("Ignoring BM25 index at {}: {}; it will be rebuilt", path.display(), e);
+                Self::new()
+            }
+        }
+    }
+    
+    /// Document count, vocabulary size and average document length
+    pub fn stats(&self) -> BM25Stats {
+        BM25Stats {
+            total_documents: self.total_docs,
+            total_terms: self.inverted_index.len(),
+            avg_doc_length: self.avg_doc_length,
+        }
+    }
+    
+    /// Full content of an indexed document
+    pub fn get_document(&self, doc_id: &str) -> Option<&str> {
+        self.documents.get(doc_id).map(|(content, _)| content.as_str())
+    }
+    
+    /// Ids (file paths) of every indexed document, in no particular order
+    pub fn document_ids(&self) -> impl Iterator<Item = &str> {
+        self.documents.keys().map(String::as_str)
     }
     
     /// Calculate IDF (Inverse Document Frequency) - TRULY FIXED VERSION
@@ -85,10 +476,7 @@ impl BM25Engine {
         let term_lower = term.to_lowercase();
         let doc_freq = self.doc_frequencies.get(&term_lower).unwrap_or(&0);
         
-        println!("DEBUG IDF: term='{}', doc_freq={}, total_docs={}", term_lower, doc_freq, self.total_docs);
-        
         if *doc_freq == 0 {
-            println!("DEBUG IDF: Returning 0.0 for nonexistent term");
             return 0.0;
         }
         
@@ -100,8 +488,6 @@ impl BM25Engine {
         // Calculate the ratio first
         let ratio = (n - df + 0.5) / (df + 0.5);
         
-        println!("DEBUG IDF: n={}, df={}, ratio={}", n, df, ratio);
-        
         // Apply epsilon protection to ensure positive IDF values
         // For very common terms (high df), ratio approaches 0, so ln(ratio) becomes negative
         // We add smoothing to ensure all terms get positive IDF
@@ -109,43 +495,76 @@ impl BM25Engine {
         
         if ratio <= 0.0 {
             // If ratio is non-positive (edge case), return small positive value
-            println!("DEBUG IDF: Ratio <= 0, returning EPSILON: {}", EPSILON);
             EPSILON
         } else {
             // Standard case: ln(ratio), but ensure minimum positive value
-            let ln_ratio = ratio.ln();
-            let final_idf = ln_ratio.max(EPSILON);
-            println!("DEBUG IDF: ln({}) = {}, final_idf = {}", ratio, ln_ratio, final_idf);
-            final_idf
+            ratio.ln().max(EPSILON)
         }
     }
     
     /// Search documents using BM25 scoring
     pub fn search(&self, query: &str, limit: usize) -> Result<Vec<BM25Match>> {
+        self.search_with_options(query, limit, &SearchOptions::default())
+    }
+    
+    /// `search` with per-query overrides; `options.stemming` falls back to `with_stemming`
+    pub fn search_with_options(&self, query: &str, limit: usize, options: &SearchOptions) -> Result<Vec<BM25Match>> {
         if query.trim().is_empty() {
             return Ok(Vec::new());
         }
         
-        let query_terms = self.tokenize(query);
+        let stemming = options.stemming.unwrap_or(self.stemming);
+        let query_terms = self.processor.index_terms(query, None);
         let mut scores: FxHashMap<String, f32> = FxHashMap::default();
         
         for term in &query_terms {
-            let idf = self.calculate_idf(term);
+            let variants = self.term_variants(term, stemming);
+            let content_docs: HashSet<&String> = variants.iter().filter_map(|v| self.inverted_index.get(v)).flatten().collect();
+            let filename_docs: HashSet<&String> = variants
+                .iter()
+                .filter_map(|v| self.filename_index.get(v))
+                .flatten()
+                .filter(|_| self.filename_boost > 0.0)
+                .collect();
             
-            // Get documents containing this term
-            if let Some(doc_ids) = self.inverted_index.get(term) {
-                for doc_id in doc_ids {
-                    if let Some((content, doc_length)) = self.documents.get(doc_id) {
-                        // Calculate term frequency in document
-                        let tf = self.calculate_term_frequency(content, term);
-                        
-                        // BM25 formula
-                        let dl = *doc_length as f32;
-                        let numerator = tf * (K1 + 1.0);
-                        let denominator = tf + K1 * (1.0 - B + B * (dl / self.avg_doc_length));
-                        let bm25_score = idf * (numerator / denominator);
-                        
-                        *scores.entry(doc_id.clone()).or_insert(0.0) += bm25_score;
+            // Documents containing this term in either field
+            let doc_ids: HashSet<&String> = content_docs.union(&filename_docs).copied().collect();
+            let idf = if filename_docs.is_empty() && variants.len() == 1 {
+                self.calculate_idf(term)
+            } else {
+                idf_from_frequency(self.total_docs, doc_ids.len())
+            };
+            
+            for doc_id in doc_ids {
+                if let Some((content, doc_length)) = self.documents.get(doc_id) {
+                    // Calculate term frequency in document
+                    let mut tf = self.calculate_term_frequency(doc_id, content, &variants);
+                    if filename_docs.contains(&doc_id) {
+                        // BM25F: filename hits count as weighted extra occurrences,
+                        // so they saturate together with content hits
+                        let filename_tf = self.filename_terms(doc_id).iter().filter(|t| variants.contains(t)).count();
+                        tf += self.filename_boost * filename_tf as f32;
+                    }
+                    
+                    // BM25 formula
+                    let bm25_score = idf * self.tf_variant.term_weight(tf, self.length_ratio(*doc_length));
+                    
+                    *scores.entry(doc_id.clone()).or_insert(0.0) += bm25_score;
+                }
+            }
+            
+            // Partial matches, as if the term occurred once, for documents without it
+            let partial: Vec<&String> = self
+                .ngram_matches(term)
+                .into_iter()
+                .filter(|doc_id| !content_docs.contains(doc_id))
+                .collect();
+            if !partial.is_empty() {
+                let idf = idf_from_frequency(self.total_docs, partial.len());
+                for doc_id in partial {
+                    if let Some((_, doc_length)) = self.documents.get(doc_id) {
+                        let saturation = self.tf_variant.term_weight(1.0, self.length_ratio(*doc_length));
+                        *scores.entry(doc_id.clone()).or_insert(0.0) += NGRAM_WEIGHT * idf * saturation;
                     }
                 }
             }
@@ -205,20 +624,33 @@ impl BM25Engine {
         Ok(())
     }
     
-    /// Simple tokenization (lowercase and split on non-alphanumeric)
-    fn tokenize(&self, text: &str) -> Vec<String> {
-        text.to_lowercase()
-            .split(|c: char| !c.is_alphanumeric())
-            .filter(|s| !s.is_empty())
-            .map(|s| s.to_string())
-            .collect()
+    /// Terms of a document as derived by the text processor, with the stop words of
+    /// the language its extension names (e.g. a set keyed "md" for `README.md`)
+    fn document_terms(&self, doc_id: &str, content: &str) -> Vec<String> {
+        let extension = Path::new(doc_id).extension().map(|ext| ext.to_string_lossy());
+        self.processor.index_terms(content, extension.as_deref())
     }
     
-    /// Calculate term frequency in a document
-    fn calculate_term_frequency(&self, content: &str, term: &str) -> f32 {
-        let tokens = self.tokenize(content);
-        let term_lower = term.to_lowercase();
-        tokens.iter().filter(|t| *t == &term_lower).count() as f32
+    /// Calculate term frequency in a document, counting every one of `terms`
+    fn calculate_term_frequency(&self, doc_id: &str, content: &str, terms: &[String]) -> f32 {
+        let tokens = self.document_terms(doc_id, content);
+        tokens.iter().filter(|t| terms.contains(t)).count() as f32
+    }
+    
+    /// The indexed terms a query term matches: itself, plus with stemming every indexed
+    /// term sharing its stem
+    fn term_variants(&self, term: &str, stemming: bool) -> Vec<String> {
+        let mut variants = vec![term.to_string()];
+        if stemming {
+            let same_stem = self.stem_vocabulary.get(&stem(term)).into_iter().flatten();
+            variants.extend(same_stem.filter(|variant| variant.as_str() != term).cloned());
+        }
+        variants
+    }
+    
+    /// `doc_length` relative to the average; 1 for an empty index
+    fn length_ratio(&self, doc_length: usize) -> f32 {
+        if self.avg_doc_length > 0.0 { doc_length as f32 / self.avg_doc_length } else { 1.0 }
     }
     
     /// Update average document length
@@ -228,8 +660,7 @@ impl BM25Engine {
             return;
         }
         
-        let total_length: usize = self.documents.values().map(|(_, len)| len).sum();
-        self.avg_doc_length = total_length as f32 / self.total_docs as f32;
+        self.avg_doc_length = self.total_doc_length as f32 / self.total_docs as f32;
     }
     
     /// Create a snippet around query terms
@@ -268,9 +699,22 @@ impl BM25Engine {
     }
 }
 
+/// English stem of a lowercase term
+fn stem(term: &str) -> String {
+    STEMMER.stem(term).into_owned()
+}
+
+/// BM25 IDF, `ln((N - df + 0.5) / (df + 0.5))`, floored like `calculate_idf`
+fn idf_from_frequency(total_docs: usize, doc_freq: usize) -> f32 {
+    let (n, df) = (total_docs as f32, doc_freq as f32);
+    ((n - df + 0.5) / (df + 0.5)).ln().max(0.01)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::search::tokenizer::Tokenizer;
+    use std::sync::Arc;
     
     #[test]
     fn test_idf_calculation_fixed() {
@@ -298,6 +742,172 @@ mod tests {
         assert!(cat_idf > 0.0, "Common terms should still have positive IDF");
     }
     
+    #[test]
+    fn test_remove_document() {
+        let mut engine = BM25Engine::new().unwrap();
+        engine.index_document("keep", "user profile");
+        engine.index_document("drop", "authentication user");
+        
+        assert!(engine.remove_document("drop").unwrap());
+        assert!(!engine.remove_document("drop").unwrap());
+        
+        assert!(engine.search("authentication", 10).unwrap().is_empty());
+        let results = engine.search("user", 10).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].path, "keep");
+    }
+    
+    #[test]
+    fn test_remove_then_readd_matches_fresh_index() {
+        let docs = [
+            ("a", "user authentication token"),
+            ("b", "user profile settings page"),
+            ("c", "database connection pool"),
+        ];
+        
+        let mut engine = BM25Engine::new().unwrap();
+        for (id, content) in docs {
+            engine.index_document(id, content);
+        }
+        engine.index_document("extra", "temporary user scratch document with many words");
+        assert!(engine.remove_document("extra").unwrap());
+        assert!(engine.remove_document("b").unwrap());
+        engine.index_document("b", docs[1].1);
+        
+        let mut fresh = BM25Engine::new().unwrap();
+        for (id, content) in docs {
+            fresh.index_document(id, content);
+        }
+        
+        assert_eq!(engine.stats(), fresh.stats());
+        for query in ["user", "profile page", "database pool", "scratch"] {
+            let mut got: Vec<(String, f32)> = engine.search(query, 10).unwrap().into_iter().map(|m| (m.path, m.score)).collect();
+            let mut want: Vec<(String, f32)> = fresh.search(query, 10).unwrap().into_iter().map(|m| (m.path, m.score)).collect();
+            got.sort_by(|x, y| x.0.cmp(&y.0));
+            want.sort_by(|x, y| x.0.cmp(&y.0));
+            assert_eq!(got, want, "scores differ for query '{}'", query);
+        }
+    }
+    
+    #[test]
+    fn test_reindexing_same_id_replaces_document() {
+        let mut engine = BM25Engine::new().unwrap();
+        engine.index_document("a", "old content");
+        engine.index_document("a", "new content here");
+        
+        let stats = engine.stats();
+        assert_eq!(stats.total_documents, 1);
+        assert!((stats.avg_doc_length - 3.0).abs() < 1e-6);
+        assert!(engine.search("old", 10).unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_save_load_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bm25.json");
+        
+        let mut engine = BM25Engine::new().unwrap();
+        engine.index_document("a", "user authentication token");
+        engine.index_document("b", "user profile settings");
+        engine.save(&path).unwrap();
+        
+        let loaded = BM25Engine::load(&path).unwrap();
+        assert_eq!(loaded.stats(), engine.stats());
+        let scores = |e: &BM25Engine| e.search("user token", 10).unwrap().into_iter().map(|m| (m.path, m.score)).collect::<Vec<_>>();
+        assert_eq!(scores(&loaded), scores(&engine));
+    }
+    
+    #[test]
+    fn test_load_rejects_version_mismatch_and_corruption() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bm25.json");
+        let mut engine = BM25Engine::new().unwrap();
+        engine.index_document("a", "user");
+        engine.save(&path).unwrap();
+        
+        let stale = std::fs::read_to_string(&path).unwrap()
+            .replace(&format!("\"version\":{}", BM25_INDEX_VERSION), "\"version\":0");
+        std::fs::write(&path, stale).unwrap();
+        assert!(BM25Engine::load(&path).is_err());
+        assert_eq!(BM25Engine::load_or_new(&path).unwrap().stats().total_documents, 0);
+        
+        std::fs::write(&path, b"not json").unwrap();
+        assert!(BM25Engine::load(&path).is_err());
+        assert_eq!(BM25Engine::load_or_new(&path).unwrap().stats().total_documents, 0);
+        
+        assert_eq!(BM25Engine::load_or_new(&dir.path().join("missing.json")).unwrap().stats().total_documents, 0);
+    }
+    
+    #[test]
+    fn test_tf_variants_follow_their_formulas() {
+        let mut engine = BM25Engine::new().unwrap();
+        engine.index_document("d1", "foo foo foo bar");
+        engine.index_document("d2", "bar baz");
+        engine.index_document("d3", "baz qux");
+        let score = |engine: &BM25Engine| engine.search("foo", 10).unwrap()[0].score;
+
+        // "foo": N = 3, df = 1, tf = 3 in d1; dl = 4 against avgdl = 8 / 3
+        let idf = ((3.0f32 - 1.0 + 0.5) / (1.0 + 0.5)).ln(); // 0.5108
+        let norm = 1.0 - B + B * (4.0 / (8.0 / 3.0)); // 1.375
+        let classic = idf * (3.0 * (K1 + 1.0) / (3.0 + K1 * norm)); // 0.5108 * 1.4194 = 0.7250
+        let plus = idf * (3.0 * (K1 + 1.0) / (3.0 + K1 * norm) + 1.0); // 0.5108 * 2.4194 = 1.2358
+        let log_tf = 4.0f32.ln(); // ln(1 + 3) = 1.3863
+        let log = idf * (log_tf * (K1 + 1.0) / (log_tf + K1 * norm)); // 0.5108 * 1.0045 = 0.5131
+
+        assert!((score(&engine) - classic).abs() < 1e-5);
+        assert!((classic - 0.7250).abs() < 1e-4);
+        let engine = engine.with_tf_variant(TfVariant::Bm25Plus { delta: 1.0 });
+        assert!((score(&engine) - plus).abs() < 1e-5);
+        assert!((plus - 1.2358).abs() < 1e-4);
+        let engine = engine.with_tf_variant(TfVariant::LogTf);
+        assert!((score(&engine) - log).abs() < 1e-5);
+        assert!((log - 0.5131).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_filename_match_outranks_comment_mention() {
+        let mut engine = BM25Engine::new().unwrap();
+        engine.index_document("src/parser.rs", "pub fn parse(input: &str) -> Ast { todo!() }");
+        engine.index_document("src/lexer.rs", "// Tokens for the parser\npub fn lex(input: &str) -> Vec<Token> { todo!() }");
+        engine.index_document("src/main.rs", "fn main() { run() }");
+
+        let results = engine.search("parser", 10).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].path, "src/parser.rs");
+        assert_eq!(results[1].path, "src/lexer.rs");
+
+        // Survives a save/load, where the filename field is rebuilt from the ids
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bm25.json");
+        engine.save(&path).unwrap();
+        let loaded = BM25Engine::load(&path).unwrap();
+        assert_eq!(loaded.search("parser", 10).unwrap()[0].path, "src/parser.rs");
+
+        // Without the boost only the content mention matches
+        engine.set_filename_boost(0.0);
+        let results = engine.search("parser", 10).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].path, "src/lexer.rs");
+
+        assert!(engine.remove_document("src/parser.rs").unwrap());
+        engine.set_filename_boost(DEFAULT_FILENAME_BOOST);
+        assert_eq!(engine.search("parser", 10).unwrap().len(), 1);
+    }
+    
+    #[test]
+    fn test_stats() {
+        let mut engine = BM25Engine::new().unwrap();
+        assert_eq!(engine.stats().total_documents, 0);
+        
+        engine.index_document("a", "user profile page");
+        engine.index_document("b", "user login");
+        
+        let stats = engine.stats();
+        assert_eq!(stats.total_documents, 2);
+        assert_eq!(stats.total_terms, 4);
+        assert!((stats.avg_doc_length - 2.5).abs() < 1e-6);
+    }
+    
     #[test]
     fn test_relevance_scoring_fixed() {
         let mut engine = BM25Engine::new().unwrap();
@@ -321,4 +931,118 @@ mod tests {
                 "Results should be sorted by score");
         }
     }
+    
+    /// Splits on `~~` only, so spaces and underscores stay inside terms
+    struct TildeTokenizer;
+    
+    impl Tokenizer for TildeTokenizer {
+        fn tokenize(&self, text: &str) -> Vec<crate::search::tokenizer::Token> {
+            let mut start = 0;
+            text.split("~~")
+                .map(|part| {
+                    let token = crate::search::tokenizer::Token::new(part, start..start + part.len());
+                    start += part.len() + 2;
+                    token
+                })
+                .collect()
+        }
+    }
+    
+    #[test]
+    fn test_custom_tokenizer_drives_indexing_and_queries() {
+        let processor = CodeTextProcessor::new().with_tokenizer(Arc::new(TildeTokenizer));
+        let mut engine = BM25Engine::new().unwrap().with_text_processor(processor);
+        engine.index_document("doc1", "Alpha Beta~~gamma_delta");
+        
+        let mut terms: Vec<&String> = engine.inverted_index.keys().collect();
+        terms.sort();
+        assert_eq!(terms, ["alpha beta", "delta", "gamma", "gamma_delta"]);
+        
+        // Queries are split the same way, so a lone word of "Alpha Beta" no longer
+        // matches while the parts of an identifier still do
+        assert!(engine.search("alpha", 10).unwrap().is_empty());
+        assert_eq!(engine.search("gamma", 10).unwrap().len(), 1);
+        let results = engine.search("ALPHA BETA", 10).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].path, "doc1");
+        
+        let mut default_engine = BM25Engine::new().unwrap();
+        default_engine.index_document("doc1", "Alpha Beta~~gamma_delta");
+        assert_eq!(default_engine.stats().total_terms, 5);
+        assert_eq!(default_engine.search("alpha", 10).unwrap().len(), 1);
+    }
+    
+    #[test]
+    fn test_ngrams_find_part_of_an_identifier() {
+        let mut engine = BM25Engine::new().unwrap().with_ngram_sizes(3, 5);
+        engine.index_document("lexer.rs", "fn tokenparser() {}");
+        engine.index_document("parse.rs", "fn parse() {}");
+        engine.index_document("other.rs", "fn render() {}");
+        
+        let results = engine.search("parse", 10).unwrap();
+        let paths: Vec<&str> = results.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(paths, ["parse.rs", "lexer.rs"], "whole-term match ranks first");
+        
+        // Longer than the largest n-gram: every 5-gram of the query must be present
+        assert_eq!(engine.search("tokenpars", 10).unwrap()[0].path, "lexer.rs");
+        assert!(engine.search("tokenrender", 10).unwrap().iter().all(|r| r.path != "lexer.rs"));
+        
+        engine.remove_document("lexer.rs").unwrap();
+        assert!(!engine.ngram_index.values().any(|docs| docs.contains("lexer.rs")));
+        engine.set_ngram_sizes(None);
+        assert!(engine.ngram_index.is_empty());
+        assert_eq!(engine.search("pars", 10).unwrap().len(), 0);
+    }
+    
+    #[test]
+    fn test_documents_use_the_stop_words_of_their_extension() {
+        let processor = CodeTextProcessor::new()
+            .with_language_stop_words("md", vec!["section".to_string()]);
+        let mut engine = BM25Engine::new().unwrap().with_text_processor(processor);
+        engine.index_document("guide.md", "section about parsing the config");
+        engine.index_document("parser.rs", "fn section() { the_config() }");
+        
+        // "section" is a stop word only in markdown, while "the" is one only in the
+        // default list, which the markdown set replaces
+        assert_eq!(engine.search("section", 10).unwrap().iter().map(|m| m.path.as_str()).collect::<Vec<_>>(), ["parser.rs"]);
+        assert!(engine.inverted_index["the"].contains("guide.md"));
+        
+        // Removal derives the same terms, so nothing of the markdown file is left
+        assert!(engine.remove_document("guide.md").unwrap());
+        assert!(engine.inverted_index.values().all(|docs| !docs.contains("guide.md")));
+    }
+    
+    #[test]
+    fn test_stemming_override_per_query() {
+        let index = |engine: BM25Engine| {
+            let mut engine = engine.with_filename_boost(0.0);
+            engine.index_document("a.rs", "fn parsing() {}");
+            engine.index_document("b.rs", "fn parse() {}");
+            engine
+        };
+        let paths = |engine: &BM25Engine, query: &str, stemming: Option<bool>| {
+            let mut paths: Vec<String> = engine
+                .search_with_options(query, 10, &SearchOptions { stemming, ..Default::default() })
+                .unwrap()
+                .into_iter()
+                .map(|m| m.path)
+                .collect();
+            paths.sort();
+            paths
+        };
+        
+        // Stemming on by default, off for one exact-symbol search
+        let stemmed = index(BM25Engine::new().unwrap().with_stemming(true));
+        assert_eq!(paths(&stemmed, "parsing", None), ["a.rs", "b.rs"]);
+        assert_eq!(paths(&stemmed, "parsing", Some(false)), ["a.rs"]);
+        
+        // Stemming off by default, on for one search, with the same index
+        let exact = index(BM25Engine::new().unwrap());
+        assert_eq!(paths(&exact, "parse", None), ["b.rs"]);
+        assert_eq!(paths(&exact, "parse", Some(true)), ["a.rs", "b.rs"]);
+        
+        let mut exact = exact;
+        exact.remove_document("a.rs").unwrap();
+        assert_eq!(paths(&exact, "parse", Some(true)), ["b.rs"]);
+    }
 }
\ No newline at end of file
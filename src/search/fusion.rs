@@ -1,8 +1,9 @@
+use std::cmp::Ordering;
 use std::collections::HashSet;
 use serde::{Serialize, Deserialize};
 use crate::error::SearchError;
 use crate::search::bm25_fixed::BM25Match;
-use crate::simple_storage::SearchResult;
+use crate::simple_storage::{cosine_similarity, SearchResult};
 use crate::symbol_extractor::Symbol;
 
 // Define ExactMatch for now
@@ -27,6 +28,181 @@ pub struct FusionConfig {
     pub normalization_percentile: f32,
     /// Semantic match score multiplier to balance against exact matches
     pub semantic_score_factor: f32,
+    /// Record per-component scores on each result (off by default to keep fusion allocation-free)
+    pub explain: bool,
+    /// Share of the query/candidate similarity in reranked scores, in `0.0..=1.0`
+    /// (0 keeps the fused order, 1 ranks by similarity alone)
+    pub rerank_weight: f32,
+    /// Characters of context kept on either side of the best match in result snippets
+    /// (0 disables snippets)
+    pub snippet_window: usize,
+    /// Marker placed before and after each highlighted term in snippets
+    pub highlight_marker: String,
+    /// Drop results whose final fused score is below this, before the result limit is
+    /// applied. Compared against the score each result is returned with: the raw RRF
+    /// sum in `HybridSearch`/`AdvancedHybridSearch` (at most ~0.033 per component),
+    /// the normalized fused score here. `None` keeps every result.
+    pub min_score: Option<f32>,
+    /// In `HybridSearch`/`AdvancedHybridSearch`, drop a result whose stored embedding has
+    /// cosine similarity above this with a higher-ranked kept result, before the result
+    /// limit. Results without a stored embedding are always kept. `None` disables it.
+    pub dedup_threshold: Option<f32>,
+    /// Added to a markdown result's fused score in `HybridSearch`/`AdvancedHybridSearch`,
+    /// scaled by the share of query terms found in its enclosing heading path
+    /// (e.g. `Installation > Linux`). 0 disables the boost; other files never get it.
+    pub heading_weight: f32,
+    /// How each match type's scores are rescaled, across one query's candidates,
+    /// before the weighted sum in `fuse_all_results_with_bm25`
+    pub normalization: ScoreNormalization,
+    /// How grouped search ranks a file from its chunk hits
+    pub group_score: GroupScore,
+    /// Weight of each match type in `fuse_all_results_with_bm25`
+    pub weights: FusionWeights,
+    /// In `HybridSearch`, time each phase of a search for `last_search_timings`
+    /// (off by default; the tracing spans are emitted either way)
+    pub collect_timings: bool,
+    /// In `HybridSearch`, cut the `content` of returned results to this many characters.
+    /// Snippets and line numbers are still found in the full content. `None` returns
+    /// content whole.
+    pub max_result_content_chars: Option<usize>,
+    /// In `HybridSearch`, round fused scores to this many decimal places before results
+    /// are filtered by `min_score` and sorted, so runs over the same data return equal
+    /// scores and equal scores tie. `None` keeps full precision.
+    pub score_precision: Option<u32>,
+    /// How results with (nearly) equal fused scores are ordered
+    pub tie_break: TieBreak,
+    /// Fused scores at most this far apart count as tied for `tie_break`. Measured from
+    /// the highest score of a run of ties, so ties don't chain down the ranking. The
+    /// default of 1e-6 only catches float noise; raw RRF scores of neighbouring ranks
+    /// differ by about 2.5e-4.
+    pub tie_epsilon: f32,
+    /// In `HybridSearch`, multiply each fused score by `RecencyBoost::multiplier` of its
+    /// file's age, from the modification time recorded when the file was indexed from
+    /// disk. Results without one, e.g. from `index_documents`, keep their score.
+    /// `None` disables it.
+    pub recency_boost: Option<RecencyBoost>,
+}
+
+/// Time-decayed favour for recently modified files, see `FusionConfig::recency_boost`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RecencyBoost {
+    /// Age in days at which a file gets half of `weight`
+    pub half_life_days: f32,
+    /// Extra share of its score a file modified just now gets
+    pub weight: f32,
+}
+
+impl RecencyBoost {
+    /// `1 + weight * 0.5^(age_days / half_life_days)`: `1 + weight` for a file modified
+    /// just now, decaying towards 1 as it ages. A non-positive half-life gives 1.
+    pub fn multiplier(&self, age_days: f32) -> f32 {
+        if self.half_life_days.is_nan() || self.half_life_days <= 0.0 {
+            return 1.0;
+        }
+        1.0 + self.weight * 0.5f32.powf(age_days.max(0.0) / self.half_life_days)
+    }
+}
+
+/// Share of each match type in a weighted fused score; the four sum to 1.0
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FusionWeights {
+    pub exact: f32,
+    pub bm25: f32,
+    pub semantic: f32,
+    pub symbol: f32,
+}
+
+impl Default for FusionWeights {
+    fn default() -> Self {
+        Self { exact: 0.4, bm25: 0.25, semantic: 0.25, symbol: 0.1 }
+    }
+}
+
+impl FusionWeights {
+    pub fn sum(&self) -> f32 {
+        self.exact + self.bm25 + self.semantic + self.symbol
+    }
+
+    /// The same proportions scaled to sum to 1.0; unchanged when the sum is 0
+    pub fn normalized(self) -> Self {
+        let sum = self.sum();
+        if sum <= 0.0 {
+            return self;
+        }
+        Self {
+            exact: self.exact / sum,
+            bm25: self.bm25 / sum,
+            semantic: self.semantic / sum,
+            symbol: self.symbol / sum,
+        }
+    }
+}
+
+/// Per-component rescaling applied before weighted fusion, so raw BM25 scores
+/// (unbounded, often 5-30) don't swamp cosine similarities (at most 1.0)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScoreNormalization {
+    /// Scores are weighted as they are
+    #[default]
+    None,
+    /// `(s - min) / (max - min)`, mapping each component onto `0.0..=1.0`.
+    /// A component whose candidates all score the same maps to 1.0.
+    MinMax,
+    /// `(s - mean) / stddev` (population standard deviation), centering each
+    /// component on 0 with unit spread. Negative scores mean below that
+    /// component's average; a component with no spread maps to 0.0.
+    ZScore,
+}
+
+/// How `HybridSearch::search_grouped` scores a file from its chunk hits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GroupScore {
+    /// The best chunk's score
+    #[default]
+    Best,
+    /// The sum of every matching chunk's score, favouring files with many hits
+    Sum,
+}
+
+/// Order of results whose fused scores are within `FusionConfig::tie_epsilon` of each
+/// other. Exact, symbol and BM25 matches count as lexical; a result both kinds of
+/// search found sits between the two. Each variant falls back to document order
+/// (path, then chunk or content), so ties come out the same on every run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TieBreak {
+    /// Lexical matches first, which suits jumping to a known identifier
+    PreferExact,
+    /// Semantic matches first
+    PreferSemantic,
+    /// Document order alone, whatever matched
+    #[default]
+    ByDocId,
+}
+
+/// Rescale `scores` in place as described on `ScoreNormalization`
+pub fn normalize_scores(scores: &mut [f32], method: ScoreNormalization) {
+    if scores.is_empty() {
+        return;
+    }
+    match method {
+        ScoreNormalization::None => {}
+        ScoreNormalization::MinMax => {
+            let min = scores.iter().copied().fold(f32::INFINITY, f32::min);
+            let max = scores.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+            let range = max - min;
+            for score in scores.iter_mut() {
+                *score = if range > f32::EPSILON { (*score - min) / range } else { 1.0 };
+            }
+        }
+        ScoreNormalization::ZScore => {
+            let n = scores.len() as f32;
+            let mean = scores.iter().sum::<f32>() / n;
+            let stddev = (scores.iter().map(|s| (s - mean).powi(2)).sum::<f32>() / n).sqrt();
+            for score in scores.iter_mut() {
+                *score = if stddev > f32::EPSILON { (*score - mean) / stddev } else { 0.0 };
+            }
+        }
+    }
 }
 
 impl Default for FusionConfig {
@@ -37,8 +213,100 @@ impl Default for FusionConfig {
             bm25_min_threshold: 0.01,
             normalization_percentile: 0.95,
             semantic_score_factor: 0.8,
+            explain: false,
+            rerank_weight: 0.5,
+            snippet_window: 60,
+            highlight_marker: "**".to_string(),
+            min_score: None,
+            dedup_threshold: None,
+            heading_weight: 0.01,
+            normalization: ScoreNormalization::default(),
+            group_score: GroupScore::default(),
+            weights: FusionWeights::default(),
+            collect_timings: false,
+            max_result_content_chars: None,
+            score_precision: None,
+            tie_break: TieBreak::default(),
+            tie_epsilon: 1e-6,
+            recency_boost: None,
+        }
+    }
+}
+
+impl FusionConfig {
+    /// A breakdown holding just `score` for `match_type`, when `explain` is set
+    pub(crate) fn explain_component(&self, match_type: &MatchType, score: f32) -> Option<ComponentScores> {
+        self.explain.then(|| {
+            let mut components = ComponentScores::default();
+            components.add(match_type, score);
+            components
+        })
+    }
+
+    /// Whether a fused score clears `min_score`
+    pub fn passes_min_score(&self, score: f32) -> bool {
+        self.min_score.is_none_or(|min| score >= min)
+    }
+
+    /// `score` rounded to `score_precision` decimal places; unchanged when unset
+    pub fn round_score(&self, score: f32) -> f32 {
+        match self.score_precision {
+            Some(places) => {
+                // Past 15 places f64 has no digits left to round
+                let scale = 10f64.powi(places.min(15) as i32);
+                ((score as f64 * scale).round() / scale) as f32
+            }
+            None => score,
+        }
+    }
+
+    /// Reorder `results`, already sorted by descending `score`, so each run of tied
+    /// results (see `tie_epsilon`) is in `tie_break` order. `match_type` is `None` for a
+    /// result found by both lexical and semantic search; `doc_order` compares documents.
+    pub fn break_ties<T>(
+        &self,
+        results: &mut [T],
+        score: impl Fn(&T) -> f32,
+        match_type: impl Fn(&T) -> Option<MatchType>,
+        doc_order: impl Fn(&T, &T) -> Ordering,
+    ) {
+        let preference = |result: &T| match (self.tie_break, match_type(result)) {
+            (TieBreak::ByDocId, _) => 0,
+            (_, None) => 1,
+            (TieBreak::PreferExact, Some(MatchType::Semantic)) => 2,
+            (TieBreak::PreferSemantic, Some(kind)) if kind != MatchType::Semantic => 2,
+            _ => 0,
+        };
+        let mut start = 0;
+        while start < results.len() {
+            let leader = score(&results[start]);
+            let end = start + 1 + results[start + 1..].iter().take_while(|r| (leader - score(r)).abs() <= self.tie_epsilon).count();
+            results[start..end].sort_by(|a, b| preference(a).cmp(&preference(b)).then_with(|| doc_order(a, b)));
+            start = end;
+        }
+    }
+}
+
+/// Keep ranked `results` in order, dropping each one whose embedding is more similar
+/// than `threshold` to one already kept, so the highest-ranked copy represents a group
+/// of near-duplicates. `embedding` returns `None` for results that can't be compared.
+pub fn dedup_similar<T>(
+    results: Vec<T>,
+    threshold: f32,
+    mut embedding: impl FnMut(&T) -> Option<Vec<f32>>,
+) -> Vec<T> {
+    let mut kept_embeddings: Vec<Vec<f32>> = Vec::new();
+    let mut kept = Vec::with_capacity(results.len());
+    for result in results {
+        if let Some(vector) = embedding(&result) {
+            if kept_embeddings.iter().any(|other| cosine_similarity(other, &vector) > threshold) {
+                continue;
+            }
+            kept_embeddings.push(vector);
         }
+        kept.push(result);
     }
+    kept
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -49,6 +317,26 @@ pub enum MatchType {
     Statistical,  // BM25/TF-IDF matches
 }
 
+/// Unweighted contribution of each search type to a fused score
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ComponentScores {
+    pub exact: f32,
+    pub bm25: f32,
+    pub semantic: f32,
+    pub symbol: f32,
+}
+
+impl ComponentScores {
+    pub(crate) fn add(&mut self, match_type: &MatchType, score: f32) {
+        match match_type {
+            MatchType::Exact => self.exact += score,
+            MatchType::Statistical => self.bm25 += score,
+            MatchType::Semantic => self.semantic += score,
+            MatchType::Symbol => self.symbol += score,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct FusedResult {
     pub file_path: String,
@@ -59,6 +347,8 @@ pub struct FusedResult {
     pub content: String,
     pub start_line: usize,
     pub end_line: usize,
+    /// Per-component breakdown, populated only when `FusionConfig::explain` is set
+    pub component_scores: Option<ComponentScores>,
 }
 
 pub struct SimpleFusion {
@@ -146,6 +436,7 @@ impl SimpleFusion {
                     content: exact.content,
                     start_line: exact.line_number,
                     end_line: exact.line_number,
+                    component_scores: None,
                 });
             }
         }
@@ -174,6 +465,7 @@ impl SimpleFusion {
                         content: semantic.content,
                         start_line: 0,
                         end_line: 0,
+                        component_scores: None,
                     });
                 }
             }
@@ -188,6 +480,8 @@ impl SimpleFusion {
             }
         }
         
+        self.record_component_scores(&mut results);
+        
         // Sort by score descending with explicit error handling
         results.sort_by(|a, b| {
             b.score.partial_cmp(&a.score).ok_or_else(|| {
@@ -196,6 +490,7 @@ impl SimpleFusion {
                 }
             }).unwrap_or(std::cmp::Ordering::Equal)
         });
+        self.break_ties(&mut results);
         
         // Take top 20 results
         results.truncate(20);
@@ -225,6 +520,7 @@ impl SimpleFusion {
                     content: exact.content,
                     start_line: exact.line_number,
                     end_line: exact.line_number,
+                    component_scores: None,
                 });
             }
         }
@@ -242,6 +538,7 @@ impl SimpleFusion {
                     content: format!("{} ({:?}): {}", symbol.name, symbol.kind, symbol.definition),
                     start_line: symbol.line,
                     end_line: symbol.line,
+                    component_scores: None,
                 });
             }
         }
@@ -269,6 +566,7 @@ impl SimpleFusion {
                         content: semantic.content,
                         start_line: 0,
                         end_line: 0,
+                        component_scores: None,
                     });
                 }
             }
@@ -283,6 +581,8 @@ impl SimpleFusion {
             }
         }
         
+        self.record_component_scores(&mut results);
+        
         // Sort by score descending with explicit error handling
         results.sort_by(|a, b| {
             b.score.partial_cmp(&a.score).ok_or_else(|| {
@@ -291,6 +591,7 @@ impl SimpleFusion {
                 }
             }).unwrap_or(std::cmp::Ordering::Equal)
         });
+        self.break_ties(&mut results);
         
         // Take top 20 results
         results.truncate(20);
@@ -322,6 +623,7 @@ impl SimpleFusion {
                     content: exact.content,
                     start_line: exact.line_number,
                     end_line: exact.line_number,
+                    component_scores: None,
                 });
             }
         }
@@ -349,6 +651,7 @@ impl SimpleFusion {
                     content: format!("BM25 match (score: {:.2})", bm25.score),
                     start_line: chunk_idx,
                     end_line: chunk_idx,
+                    component_scores: None,
                 });
             }
         }
@@ -366,6 +669,7 @@ impl SimpleFusion {
                     content: format!("{} ({:?}): {}", symbol.name, symbol.kind, symbol.definition),
                     start_line: symbol.line,
                     end_line: symbol.line,
+                    component_scores: None,
                 });
             }
         }
@@ -395,6 +699,7 @@ impl SimpleFusion {
                         content: semantic.content,
                         start_line: 0,
                         end_line: 0,
+                        component_scores: None,
                     });
                 }
             }
@@ -403,8 +708,12 @@ impl SimpleFusion {
         // Apply dynamic BM25 normalization before weighted fusion
         self.apply_dynamic_bm25_normalization(&mut results)?;
         
+        // Record contributions before weighting
+        self.record_component_scores(&mut results);
+        
         // Apply weighted fusion scoring
-        self.apply_weighted_fusion(&mut results, 0.4, 0.25, 0.25, 0.1);
+        let weights = self.config.weights;
+        self.apply_weighted_fusion(&mut results, weights.exact, weights.bm25, weights.semantic, weights.symbol);
         
         // Validate all scores before sorting
         for result in &results {
@@ -428,6 +737,7 @@ impl SimpleFusion {
                 }
             }).unwrap_or(std::cmp::Ordering::Equal)
         });
+        self.break_ties(&mut results);
         
         // Take top 20 results
         results.truncate(20);
@@ -456,6 +766,7 @@ impl SimpleFusion {
                     content: exact.content,
                     start_line: exact.line_number,
                     end_line: exact.line_number,
+                    component_scores: None,
                 });
             }
         }
@@ -483,6 +794,7 @@ impl SimpleFusion {
                     content: format!("BM25 match (score: {:.2})", bm25.score),
                     start_line: chunk_idx,
                     end_line: chunk_idx,
+                    component_scores: None,
                 });
             }
         }
@@ -498,6 +810,7 @@ impl SimpleFusion {
         
         // Apply dynamic BM25 normalization
         self.apply_dynamic_bm25_normalization(&mut results)?;
+        self.record_component_scores(&mut results);
         
         // Sort by score descending with explicit error handling
         results.sort_by(|a, b| {
@@ -507,8 +820,10 @@ impl SimpleFusion {
                 }
             }).unwrap_or(std::cmp::Ordering::Equal)
         });
+        self.break_ties(&mut results);
         
-        // Take top results based on configuration
+        // Drop the low-score tail, then take top results based on configuration
+        results.retain(|r| self.config.passes_min_score(r.score));
         results.truncate(self.config.max_results);
         Ok(results)
     }
@@ -529,12 +844,16 @@ impl SimpleFusion {
         // Map to store aggregated RRF scores for each document
         let mut rrf_scores: HashMap<String, f32> = HashMap::new();
         let mut result_map: HashMap<String, FusedResult> = HashMap::new();
+        let mut components: HashMap<String, ComponentScores> = HashMap::new();
         
         // Process exact matches (rank 1 is best)
         for (rank, exact) in exact_results.iter().enumerate() {
             let key = format!("{}-{}", exact.file_path, exact.line_number);
             let rrf_score = 1.0 / (k + (rank as f32) + 1.0);
             *rrf_scores.entry(key.clone()).or_insert(0.0) += rrf_score;
+            if self.config.explain {
+                components.entry(key.clone()).or_default().add(&MatchType::Exact, rrf_score);
+            }
             
             result_map.entry(key).or_insert(FusedResult {
                 file_path: exact.file_path.clone(),
@@ -545,6 +864,7 @@ impl SimpleFusion {
                 content: exact.content.clone(),
                 start_line: exact.line_number,
                 end_line: exact.line_number,
+                component_scores: None,
             });
         }
         
@@ -553,6 +873,9 @@ impl SimpleFusion {
             let key = format!("{}", bm25.path);
             let rrf_score = 1.0 / (k + (rank as f32) + 1.0);
             *rrf_scores.entry(key.clone()).or_insert(0.0) += rrf_score;
+            if self.config.explain {
+                components.entry(key.clone()).or_default().add(&MatchType::Statistical, rrf_score);
+            }
             
             result_map.entry(key).or_insert(FusedResult {
                 file_path: bm25.path.clone(),
@@ -563,6 +886,7 @@ impl SimpleFusion {
                 content: bm25.snippet.clone(),
                 start_line: bm25.line_number.unwrap_or(0),
                 end_line: bm25.line_number.unwrap_or(0),
+                component_scores: None,
             });
         }
         
@@ -571,6 +895,9 @@ impl SimpleFusion {
             let key = format!("{}", semantic.file_path);
             let rrf_score = 1.0 / (k + (rank as f32) + 1.0);
             *rrf_scores.entry(key.clone()).or_insert(0.0) += rrf_score;
+            if self.config.explain {
+                components.entry(key.clone()).or_default().add(&MatchType::Semantic, rrf_score);
+            }
             
             result_map.entry(key).or_insert(FusedResult {
                 file_path: semantic.file_path.clone(),
@@ -581,6 +908,7 @@ impl SimpleFusion {
                 content: semantic.content.clone(),
                 start_line: 0,
                 end_line: 0,
+                component_scores: None,
             });
         }
         
@@ -589,6 +917,9 @@ impl SimpleFusion {
             let key = format!("symbol-{}-{}", symbol.name, symbol.line);
             let rrf_score = 1.0 / (k + (rank as f32) + 1.0);
             *rrf_scores.entry(key.clone()).or_insert(0.0) += rrf_score;
+            if self.config.explain {
+                components.entry(key.clone()).or_default().add(&MatchType::Symbol, rrf_score);
+            }
             
             result_map.entry(key).or_insert(FusedResult {
                 file_path: "unknown".to_string(),
@@ -599,6 +930,7 @@ impl SimpleFusion {
                 content: format!("{} ({:?}): {}", symbol.name, symbol.kind, symbol.definition),
                 start_line: symbol.line,
                 end_line: symbol.line,
+                component_scores: None,
             });
         }
         
@@ -606,20 +938,48 @@ impl SimpleFusion {
         let mut results: Vec<FusedResult> = result_map.into_iter()
             .map(|(key, mut result)| {
                 result.score = *rrf_scores.get(&key).unwrap_or(&0.0);
+                result.component_scores = components.remove(&key);
                 result
             })
             .collect();
         
         // Sort by RRF score descending
         results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+        self.break_ties(&mut results);
         
-        // Take top results
+        // Drop the low-score tail, then take top results
+        results.retain(|r| self.config.passes_min_score(r.score));
         results.truncate(self.config.max_results);
         
         Ok(results)
     }
     
-    /// Apply weighted fusion to combine scores from different search types
+    /// `FusionConfig::break_ties` over fused results, in path, chunk, line order
+    fn break_ties(&self, results: &mut [FusedResult]) {
+        self.config.break_ties(
+            results,
+            |r| r.score,
+            |r| Some(r.match_type.clone()),
+            |a, b| (&a.file_path, a.chunk_index, a.line_number).cmp(&(&b.file_path, b.chunk_index, b.line_number)),
+        );
+    }
+    
+    /// Capture each result's current score as its component contribution when explaining
+    fn record_component_scores(&self, results: &mut [FusedResult]) {
+        if !self.config.explain {
+            return;
+        }
+        for result in results.iter_mut() {
+            if result.component_scores.is_none() {
+                let mut components = ComponentScores::default();
+                components.add(&result.match_type, result.score);
+                result.component_scores = Some(components);
+            }
+        }
+    }
+    
+    /// Apply weighted fusion to combine scores from different search types,
+    /// after normalizing each type per `FusionConfig::normalization`
     #[allow(dead_code)]
     fn apply_weighted_fusion(
         &self,
@@ -629,6 +989,7 @@ impl SimpleFusion {
         semantic_weight: f32,
         symbol_weight: f32,
     ) {
+        self.normalize_components(results);
         for result in results.iter_mut() {
             let base_score = result.score;
             result.score = match result.match_type {
@@ -803,10 +1164,26 @@ impl SimpleFusion {
                 }
             }).unwrap_or(std::cmp::Ordering::Equal)
         });
+        self.break_ties(results);
         
         Ok(())
     }
     
+    /// Normalize scores separately within each match type's candidates
+    fn normalize_components(&self, results: &mut [FusedResult]) {
+        if self.config.normalization == ScoreNormalization::None {
+            return;
+        }
+        for match_type in [MatchType::Exact, MatchType::Statistical, MatchType::Semantic, MatchType::Symbol] {
+            let mut group: Vec<&mut FusedResult> = results.iter_mut().filter(|r| r.match_type == match_type).collect();
+            let mut scores: Vec<f32> = group.iter().map(|r| r.score).collect();
+            normalize_scores(&mut scores, self.config.normalization);
+            for (result, score) in group.iter_mut().zip(scores) {
+                result.score = score;
+            }
+        }
+    }
+    
     /// Apply dynamic normalization to BM25 scores based on their distribution
     fn apply_dynamic_bm25_normalization(&self, results: &mut Vec<FusedResult>) -> Result<(), SearchError> {
         // Collect all BM25 scores for normalization calculation
@@ -932,6 +1309,7 @@ mod tests {
                 file_path: "test.rs".to_string(),
                 content: "some other content".to_string(),
                 score: 0.8,
+                metadata: Default::default(),
             }
         ];
         
@@ -983,6 +1361,94 @@ mod tests {
         assert!(results[0].score > results[2].score, "Score ordering should be preserved");
     }
     
+    fn candidate(file_path: &str, match_type: MatchType, score: f32) -> FusedResult {
+        FusedResult {
+            file_path: file_path.to_string(),
+            line_number: None,
+            chunk_index: None,
+            score,
+            match_type,
+            content: String::new(),
+            start_line: 0,
+            end_line: 0,
+            component_scores: None,
+        }
+    }
+
+    fn ranked(fusion: &SimpleFusion, mut results: Vec<FusedResult>) -> Vec<String> {
+        fusion.apply_weighted_fusion(&mut results, 0.4, 0.25, 0.25, 0.1);
+        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        results.into_iter().map(|r| r.file_path).collect()
+    }
+
+    #[test]
+    fn test_normalization_lets_strong_semantic_match_outrank_modest_bm25() {
+        let candidates = vec![
+            candidate("top_bm25.rs", MatchType::Statistical, 12.0),
+            candidate("modest_bm25.rs", MatchType::Statistical, 3.0),
+            candidate("weak_bm25.rs", MatchType::Statistical, 1.0),
+            candidate("strong_semantic.rs", MatchType::Semantic, 0.95),
+            candidate("weak_semantic.rs", MatchType::Semantic, 0.2),
+        ];
+        let position = |order: &[String]| order.iter().position(|p| p == "strong_semantic.rs").unwrap();
+
+        // Raw BM25 dwarfs the similarity: 3.0 * 0.25 > 0.95 * 0.25
+        let raw = ranked(&SimpleFusion::new(), candidates.clone());
+        assert_eq!(position(&raw), 3);
+
+        for normalization in [ScoreNormalization::MinMax, ScoreNormalization::ZScore] {
+            let fusion = SimpleFusion::with_config(FusionConfig { normalization, ..Default::default() });
+            let order = ranked(&fusion, candidates.clone());
+            assert!(position(&order) <= 1, "{:?}: {:?}", normalization, order);
+            assert!(position(&order) < order.iter().position(|p| p == "modest_bm25.rs").unwrap());
+        }
+    }
+
+    #[test]
+    fn test_tie_break_orders_equal_scores_by_match_type() {
+        let tied = || vec![
+            candidate("a_semantic.rs", MatchType::Semantic, 0.5),
+            candidate("b_exact.rs", MatchType::Exact, 0.5 + 1e-7),
+            candidate("c_bm25.rs", MatchType::Statistical, 0.3),
+        ];
+        let order = |tie_break: TieBreak| {
+            let fusion = SimpleFusion::with_config(FusionConfig { tie_break, ..Default::default() });
+            let mut results = tied();
+            results.sort_by(|a, b| b.score.total_cmp(&a.score));
+            fusion.break_ties(&mut results);
+            results.into_iter().map(|r| r.file_path).collect::<Vec<_>>()
+        };
+        assert_eq!(order(TieBreak::PreferExact), ["b_exact.rs", "a_semantic.rs", "c_bm25.rs"]);
+        assert_eq!(order(TieBreak::PreferSemantic), ["a_semantic.rs", "b_exact.rs", "c_bm25.rs"]);
+        assert_eq!(order(TieBreak::ByDocId), ["a_semantic.rs", "b_exact.rs", "c_bm25.rs"]);
+
+        // Outside the epsilon the higher score wins whatever the preference
+        let fusion = SimpleFusion::with_config(FusionConfig { tie_break: TieBreak::PreferSemantic, tie_epsilon: 1e-9, ..Default::default() });
+        let mut results = tied();
+        results.sort_by(|a, b| b.score.total_cmp(&a.score));
+        fusion.break_ties(&mut results);
+        assert_eq!(results[0].file_path, "b_exact.rs");
+    }
+
+    #[test]
+    fn test_normalize_scores_math() {
+        let mut scores = vec![2.0, 4.0, 6.0];
+        normalize_scores(&mut scores, ScoreNormalization::MinMax);
+        assert_eq!(scores, vec![0.0, 0.5, 1.0]);
+
+        let mut scores = vec![2.0, 4.0, 6.0];
+        normalize_scores(&mut scores, ScoreNormalization::ZScore);
+        let expected = 2.0 / (8.0f32 / 3.0).sqrt();
+        assert!((scores[0] + expected).abs() < 1e-5 && scores[1].abs() < 1e-6 && (scores[2] - expected).abs() < 1e-5);
+
+        // No spread: not NaN
+        let mut flat = vec![3.0, 3.0];
+        normalize_scores(&mut flat, ScoreNormalization::MinMax);
+        assert_eq!(flat, vec![1.0, 1.0]);
+        normalize_scores(&mut flat, ScoreNormalization::ZScore);
+        assert_eq!(flat, vec![0.0, 0.0]);
+    }
+
     #[test]
     fn test_fusion_config_customization() {
         let config = FusionConfig {
@@ -991,6 +1457,9 @@ mod tests {
             bm25_min_threshold: 0.1,
             normalization_percentile: 0.90,
             semantic_score_factor: 0.9,
+            explain: false,
+            rerank_weight: 0.5,
+            ..Default::default()
         };
         let fusion = SimpleFusion::with_config(config);
         
@@ -1011,6 +1480,38 @@ mod tests {
         assert!(results[0].score <= 0.7, "Custom BM25 cap should be applied");
     }
     
+    #[test]
+    fn test_dedup_similar_keeps_first_of_each_group() {
+        let results = vec![("a", Some(vec![1.0, 0.0])), ("b", Some(vec![0.99, 0.05])), ("c", None), ("d", Some(vec![0.0, 1.0]))];
+        let kept = dedup_similar(results, 0.95, |(_, embedding)| embedding.clone());
+        let names: Vec<&str> = kept.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, vec!["a", "c", "d"]);
+    }
+
+    #[test]
+    fn test_min_score_drops_tail_and_allows_empty() {
+        let bm25_matches: Vec<BM25Match> = [100.0, 50.0, 1.0]
+            .iter()
+            .enumerate()
+            .map(|(i, score)| BM25Match {
+                path: format!("file{}.rs", i),
+                score: *score,
+                snippet: "test snippet".to_string(),
+                line_number: Some(1),
+            })
+            .collect();
+
+        let unfiltered = SimpleFusion::new().fuse_results_core(vec![], bm25_matches.clone()).unwrap();
+        let threshold = unfiltered[1].score;
+        let fusion = SimpleFusion::with_config(FusionConfig { min_score: Some(threshold), ..Default::default() });
+        let filtered = fusion.fuse_results_core(vec![], bm25_matches.clone()).unwrap();
+        assert_eq!(filtered.len(), 2);
+        assert!(filtered.iter().all(|r| r.score >= threshold));
+
+        let fusion = SimpleFusion::with_config(FusionConfig { min_score: Some(10.0), ..Default::default() });
+        assert!(fusion.fuse_results_core(vec![], bm25_matches).unwrap().is_empty());
+    }
+
     #[test]
     fn test_fusion_deduplicates_results() {
         let fusion = SimpleFusion::new();
@@ -1034,4 +1535,52 @@ mod tests {
         
         assert_eq!(results.len(), 1); // Duplicates removed
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_component_scores_only_when_explaining() {
+        let bm25_matches = || vec![
+            BM25Match {
+                path: "file1.rs".to_string(),
+                score: 12.0,
+                snippet: "test snippet".to_string(),
+                line_number: Some(10),
+            },
+        ];
+        
+        let quiet = SimpleFusion::new().fuse_results_core(vec![], bm25_matches()).unwrap();
+        assert!(quiet[0].component_scores.is_none());
+        
+        let config = FusionConfig { explain: true, ..FusionConfig::default() };
+        let explained = SimpleFusion::with_config(config).fuse_results_core(vec![], bm25_matches()).unwrap();
+        let components = explained[0].component_scores.clone().unwrap();
+        assert_eq!(components.bm25, explained[0].score);
+        assert_eq!(components.exact, 0.0);
+        assert_eq!(components.semantic, 0.0);
+        assert_eq!(components.symbol, 0.0);
+    }
+    
+    #[test]
+    fn test_rrf_component_scores_sum_to_fused_score() {
+        let config = FusionConfig { explain: true, ..FusionConfig::default() };
+        let fusion = SimpleFusion::with_config(config);
+        
+        let bm25 = vec![BM25Match {
+            path: "shared.rs".to_string(),
+            score: 3.0,
+            snippet: "fn shared()".to_string(),
+            line_number: None,
+        }];
+        let semantic = vec![
+            SearchResult { content: "other".to_string(), file_path: "other.rs".to_string(), score: 0.9, metadata: Default::default() },
+            SearchResult { content: "fn shared()".to_string(), file_path: "shared.rs".to_string(), score: 0.8, metadata: Default::default() },
+        ];
+        
+        let results = fusion.apply_rrf_fusion(vec![], bm25, semantic, vec![], 60.0).unwrap();
+        let shared = results.iter().find(|r| r.file_path == "shared.rs").unwrap();
+        let components = shared.component_scores.clone().unwrap();
+        
+        assert!((components.bm25 - 1.0 / 61.0).abs() < 1e-6);
+        assert!((components.semantic - 1.0 / 62.0).abs() < 1e-6);
+        assert!((components.bm25 + components.semantic - shared.score).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,215 @@
+// Typo-tolerant keyword search over the Tantivy index
+
+use anyhow::Result;
+use tantivy::query::{BooleanQuery, Occur, Query, TermQuery};
+use tantivy::schema::IndexRecordOption;
+use tantivy::Term;
+use tantivy::collector::TopDocs;
+use levenshtein_automata::{Distance, LevenshteinAutomatonBuilder, DFA, SINK_STATE};
+use once_cell::sync::Lazy;
+use tantivy_fst::Automaton;
+
+use crate::search::text_backend::TantivyText;
+use crate::simple_search::SearchResult;
+use crate::error::SearchError;
+
+/// Largest edit distance supported by Tantivy's Levenshtein automata
+pub const MAX_FUZZY_DISTANCE: u8 = 2;
+
+/// Tuning for fuzzy text matching
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzyConfig {
+    /// Maximum edit distance per query term (0..=MAX_FUZZY_DISTANCE)
+    pub max_distance: u8,
+    /// Count a swap of two adjacent characters as a single edit
+    pub transposition_cost_one: bool,
+    /// Number of leading characters that must match exactly
+    pub prefix_length: usize,
+}
+
+impl Default for FuzzyConfig {
+    fn default() -> Self {
+        Self {
+            max_distance: 1,
+            transposition_cost_one: true,
+            prefix_length: 0,
+        }
+    }
+}
+
+impl FuzzyConfig {
+    pub fn validate(&self) -> std::result::Result<(), SearchError> {
+        if self.max_distance > MAX_FUZZY_DISTANCE {
+            return Err(SearchError::InvalidParameter {
+                parameter: "max_distance".to_string(),
+                reason: format!("{} exceeds the supported maximum of {}", self.max_distance, MAX_FUZZY_DISTANCE),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Expand each query term into the indexed terms within the configured edit distance
+/// that share the required prefix, then run them as a disjunction.
+pub(crate) fn fuzzy_text_search(text: &TantivyText, query: &str, config: &FuzzyConfig, limit: usize) -> Result<Vec<SearchResult>> {
+    let reader = text.index.reader()?;
+    let searcher = reader.searcher();
+
+    let mut tokenizer = text.index.tokenizer_for_field(text.content_field)?;
+    let mut query_terms = Vec::new();
+    let mut stream = tokenizer.token_stream(query);
+    while stream.advance() {
+        query_terms.push(stream.token().text.clone());
+    }
+
+    let mut matched_terms = std::collections::BTreeSet::new();
+    for term in &query_terms {
+        for segment_reader in searcher.segment_readers() {
+            let inverted_index = segment_reader.inverted_index(text.content_field)?;
+            let automaton = FuzzyTermAutomaton::new(term, config);
+            let mut terms = inverted_index.terms().search(automaton).into_stream()?;
+            while terms.advance() {
+                if let Ok(candidate) = std::str::from_utf8(terms.key()) {
+                    matched_terms.insert(candidate.to_string());
+                }
+            }
+        }
+    }
+
+    if matched_terms.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let clauses: Vec<(Occur, Box<dyn Query>)> = matched_terms
+        .iter()
+        .map(|term_text| {
+            let term = Term::from_field_text(text.content_field, term_text);
+            (Occur::Should, Box::new(TermQuery::new(term, IndexRecordOption::WithFreqs)) as Box<dyn Query>)
+        })
+        .collect();
+    let fuzzy_query = BooleanQuery::new(clauses);
+
+    let top_docs = searcher.search(&fuzzy_query, &TopDocs::with_limit(limit))?;
+    text.collect_results(&searcher, top_docs, "fuzzy")
+}
+
+/// Levenshtein automaton builders for every supported distance, with and without
+/// transpositions. Building one is costly, so they are made once and shared.
+static LEVENSHTEIN_BUILDERS: Lazy<Vec<LevenshteinAutomatonBuilder>> = Lazy::new(|| {
+    (0..=MAX_FUZZY_DISTANCE)
+        .flat_map(|distance| [false, true].map(|transposition| LevenshteinAutomatonBuilder::new(distance, transposition)))
+        .collect()
+});
+
+/// Accepts the terms that start with the query term's first `prefix_length` characters
+/// and whose remainder is within `max_distance` edits of the query term's remainder
+struct FuzzyTermAutomaton {
+    prefix: Vec<u8>,
+    dfa: DFA,
+}
+
+#[derive(Clone)]
+enum FuzzyTermState {
+    /// Matched this many bytes of the required prefix
+    Prefix(usize),
+    /// Past the prefix, in this state of the Levenshtein DFA
+    Suffix(u32),
+}
+
+impl FuzzyTermAutomaton {
+    fn new(term: &str, config: &FuzzyConfig) -> Self {
+        let split = term.char_indices().nth(config.prefix_length).map_or(term.len(), |(i, _)| i);
+        let (prefix, suffix) = term.split_at(split);
+        let builder = &LEVENSHTEIN_BUILDERS[config.max_distance as usize * 2 + config.transposition_cost_one as usize];
+        Self { prefix: prefix.as_bytes().to_vec(), dfa: builder.build_dfa(suffix) }
+    }
+
+    fn suffix_start(&self) -> FuzzyTermState {
+        FuzzyTermState::Suffix(self.dfa.initial_state())
+    }
+}
+
+impl Automaton for FuzzyTermAutomaton {
+    type State = FuzzyTermState;
+
+    fn start(&self) -> FuzzyTermState {
+        if self.prefix.is_empty() { self.suffix_start() } else { FuzzyTermState::Prefix(0) }
+    }
+
+    fn is_match(&self, state: &FuzzyTermState) -> bool {
+        match state {
+            FuzzyTermState::Prefix(_) => false,
+            FuzzyTermState::Suffix(dfa_state) => matches!(self.dfa.distance(*dfa_state), Distance::Exact(_)),
+        }
+    }
+
+    fn can_match(&self, state: &FuzzyTermState) -> bool {
+        !matches!(state, FuzzyTermState::Suffix(SINK_STATE))
+    }
+
+    fn accept(&self, state: &FuzzyTermState, byte: u8) -> FuzzyTermState {
+        match state {
+            FuzzyTermState::Prefix(matched) if self.prefix[*matched] != byte => FuzzyTermState::Suffix(SINK_STATE),
+            FuzzyTermState::Prefix(matched) if matched + 1 == self.prefix.len() => self.suffix_start(),
+            FuzzyTermState::Prefix(matched) => FuzzyTermState::Prefix(matched + 1),
+            FuzzyTermState::Suffix(dfa_state) => FuzzyTermState::Suffix(self.dfa.transition(*dfa_state, byte)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    use crate::search::text_backend::tests::text_only_index;
+    
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_fuzzy_config_rejects_distance_above_max() {
+        let config = FuzzyConfig { max_distance: MAX_FUZZY_DISTANCE + 1, ..Default::default() };
+        match config.validate() {
+            Err(SearchError::InvalidParameter { parameter, .. }) => assert_eq!(parameter, "max_distance"),
+            other => panic!("expected InvalidParameter, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_fuzzy_text_search_transposition_cost() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let text = text_only_index(temp_dir.path().to_str().unwrap(), &[("a.rs", "fn parse indexer")])?;
+        let swap_is_one = FuzzyConfig { max_distance: 1, transposition_cost_one: true, prefix_length: 0 };
+        assert_eq!(fuzzy_text_search(&text, "prase", &swap_is_one, 10)?.len(), 1);
+        let swap_is_two = FuzzyConfig { transposition_cost_one: false, ..swap_is_one.clone() };
+        assert!(fuzzy_text_search(&text, "prase", &swap_is_two, 10)?.is_empty());
+        let distance_two = FuzzyConfig { max_distance: 2, ..swap_is_two };
+        assert_eq!(fuzzy_text_search(&text, "index", &distance_two, 10)?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_fuzzy_text_search_matches_within_distance() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let text = text_only_index(temp_dir.path().to_str().unwrap(), &[
+            ("a.rs", "fn tokenize input"),
+            ("b.rs", "struct Config"),
+        ])?;
+        let config = FuzzyConfig { max_distance: 1, ..Default::default() };
+        let results = fuzzy_text_search(&text, "tokenise", &config, 10)?;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].file_path, "a.rs");
+        assert_eq!(results[0].match_type, "fuzzy");
+        Ok(())
+    }
+
+    #[test]
+    fn test_fuzzy_text_search_prefix_length() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let text = text_only_index(temp_dir.path().to_str().unwrap(), &[("a.rs", "fn parse")])?;
+        let strict = FuzzyConfig { max_distance: 1, prefix_length: 1, ..Default::default() };
+        assert!(fuzzy_text_search(&text, "barse", &strict, 10)?.is_empty());
+        let loose = FuzzyConfig { max_distance: 1, prefix_length: 0, ..Default::default() };
+        assert_eq!(fuzzy_text_search(&text, "barse", &loose, 10)?.len(), 1);
+        Ok(())
+    }
+}
@@ -2,10 +2,28 @@
 
 pub mod bm25_fixed;
 pub mod fusion;
+pub mod fuzzy;
 pub mod preprocessing;
+pub mod snippet;
+pub mod text_backend;
 pub mod text_processor;
+pub mod tokenizer;
 
 // Re-export key types
-pub use bm25_fixed::{BM25Engine, BM25Match};
-pub use fusion::{FusionConfig, MatchType};
-pub use text_processor::CodeTextProcessor;
\ No newline at end of file
+pub use bm25_fixed::{BM25Engine, BM25Match, BM25Stats};
+pub use fusion::{FusionConfig, FusionWeights, MatchType, ComponentScores, GroupScore, RecencyBoost, ScoreNormalization, TieBreak};
+pub use text_processor::{AnalyzedToken, CodeTextProcessor};
+pub use tokenizer::{AlphanumericTokenizer, CodeTokenizer, Token, Tokenizer};
+
+use crate::symbol_extractor::SymbolKind;
+
+/// Per-search overrides of the configured keyword matching
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SearchOptions {
+    /// Match query words by their stem (`parsing` finds `parse`); `None` keeps the
+    /// configured default
+    pub stemming: Option<bool>,
+    /// Keep only results defining a symbol of one of these kinds named by a query
+    /// word, as found by `SymbolExtractor`; `None` keeps every result
+    pub symbol_kinds: Option<Vec<SymbolKind>>,
+}
\ No newline at end of file
@@ -0,0 +1,153 @@
+// Query-centred snippets with highlighted matches
+
+use regex::{Regex, RegexBuilder};
+use std::collections::HashSet;
+
+/// Builds a window of `window` characters on either side of the best match of the query
+/// terms in `content`, with every match inside it wrapped in `marker`.
+/// The best match is the one whose window covers the most distinct query terms,
+/// earliest first. Returns `None` when no term occurs or `window` is 0.
+pub fn highlight_snippet(content: &str, query: &str, window: usize, marker: &str) -> Option<String> {
+    if window == 0 {
+        return None;
+    }
+    let pattern = term_pattern(query)?;
+    let matches: Vec<(usize, usize)> = pattern.find_iter(content).map(|m| (m.start(), m.end())).collect();
+    if matches.is_empty() {
+        return None;
+    }
+
+    let mut best: Option<(usize, usize, usize)> = None;
+    for &(start, end) in &matches {
+        let (window_start, window_end) = window_bounds(content, start, end, window);
+        let distinct = matches
+            .iter()
+            .filter(|(s, e)| *s >= window_start && *e <= window_end)
+            .map(|(s, e)| content[*s..*e].to_lowercase())
+            .collect::<HashSet<_>>()
+            .len();
+        if best.is_none_or(|(count, _, _)| distinct > count) {
+            best = Some((distinct, window_start, window_end));
+        }
+    }
+    let (_, window_start, window_end) = best?;
+
+    let mut snippet = String::new();
+    if window_start > 0 {
+        snippet.push_str("...");
+    }
+    let mut cursor = window_start;
+    for &(start, end) in matches.iter().filter(|(s, e)| *s >= window_start && *e <= window_end) {
+        snippet.push_str(&content[cursor..start]);
+        snippet.push_str(marker);
+        snippet.push_str(&content[start..end]);
+        snippet.push_str(marker);
+        cursor = end;
+    }
+    snippet.push_str(&content[cursor..window_end]);
+    if window_end < content.len() {
+        snippet.push_str("...");
+    }
+    Some(snippet)
+}
+
+/// 1-based number of the first line of `content` containing a query term, so a
+/// match spanning several lines reports where it starts
+pub fn first_match_line(content: &str, query: &str) -> Option<usize> {
+    let pattern = term_pattern(query)?;
+    let start = pattern.find(content)?.start();
+    Some(content[..start].matches('\n').count() + 1)
+}
+
+/// Case-insensitive alternation of the query's word terms, longest first so
+/// overlapping terms highlight the full word
+fn term_pattern(query: &str) -> Option<Regex> {
+    let terms = query_terms(query);
+    if terms.is_empty() {
+        return None;
+    }
+
+    let alternation = terms.iter().map(|t| regex::escape(t)).collect::<Vec<_>>().join("|");
+    RegexBuilder::new(&alternation).case_insensitive(true).build().ok()
+}
+
+/// Distinct lowercase word terms of `query`, longest first
+pub(crate) fn query_terms(query: &str) -> Vec<String> {
+    let mut terms: Vec<String> = query
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_lowercase())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    terms.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+    terms
+}
+
+/// Byte range of `window` characters either side of `start..end`
+fn window_bounds(content: &str, start: usize, end: usize, window: usize) -> (usize, usize) {
+    let window_start = content[..start]
+        .char_indices()
+        .rev()
+        .take(window)
+        .last()
+        .map(|(i, _)| i)
+        .unwrap_or(start);
+    let window_end = content[end..]
+        .char_indices()
+        .nth(window)
+        .map(|(i, _)| end + i)
+        .unwrap_or(content.len());
+    (window_start, window_end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snippet_centres_on_match() {
+        let content = format!("{}fn authenticate(user) {{}}{}", "x ".repeat(100), " y".repeat(100));
+        let snippet = highlight_snippet(&content, "authenticate", 10, "**").unwrap();
+        assert!(snippet.starts_with("..."));
+        assert!(snippet.ends_with("..."));
+        assert!(snippet.contains("**authenticate**"));
+        assert!(snippet.len() < 60);
+    }
+
+    #[test]
+    fn test_snippet_prefers_window_with_most_terms() {
+        let content = format!("user here{}user session token here", " filler".repeat(30));
+        let snippet = highlight_snippet(&content, "user token", 20, "**").unwrap();
+        assert!(snippet.contains("**user** session **token**"));
+    }
+
+    #[test]
+    fn test_snippet_custom_marker_and_case() {
+        let snippet = highlight_snippet("Parse the CONFIG file", "config", 50, "[]").unwrap();
+        assert_eq!(snippet, "Parse the []CONFIG[] file");
+    }
+
+    #[test]
+    fn test_snippet_none_without_match() {
+        assert!(highlight_snippet("fn main() {}", "database", 50, "**").is_none());
+        assert!(highlight_snippet("fn main() {}", "main", 0, "**").is_none());
+        assert!(highlight_snippet("fn main() {}", "  ", 50, "**").is_none());
+    }
+
+    #[test]
+    fn test_first_match_line_points_at_term() {
+        let content = "use std::fmt;\n\nfn parse() {}\nfn Render() {}\nfn render_all() {}";
+        assert_eq!(first_match_line(content, "render"), Some(4));
+        assert_eq!(first_match_line(content, "render parse"), Some(3));
+        assert_eq!(first_match_line(content, "use"), Some(1));
+        assert_eq!(first_match_line(content, "database"), None);
+    }
+
+    #[test]
+    fn test_snippet_respects_char_boundaries() {
+        let content = "ééééé données ééééé";
+        let snippet = highlight_snippet(content, "données", 2, "**").unwrap();
+        assert_eq!(snippet, "...é **données** é...");
+    }
+}
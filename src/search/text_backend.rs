@@ -0,0 +1,911 @@
+// Keyword backends of the hybrid search: Tantivy and the in-memory BM25 engine
+
+use anyhow::Result;
+use tantivy::{Index, IndexWriter, schema::{Schema, Field, STRING, TEXT, STORED, Value}};
+use tantivy::query::{AllQuery, QueryParser};
+use tantivy::Term;
+use tantivy::collector::{Count, DocSetCollector, TopDocs};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use crate::simple_search::{IndexStats, OpenOutcome, SearchResult};
+use crate::simple_storage::VectorStore;
+use crate::error::{SearchError, StorageError};
+use crate::config::{Config, SearchBackend};
+use crate::indexer::{decode_source, exceeds_max_file_size, is_indexable_extension, walk_source_files};
+use crate::search::bm25_fixed::{BM25Engine, TfVariant};
+use crate::search::{CodeTextProcessor, SearchOptions};
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct TantivyStats {
+    pub num_docs: u64,
+    pub segment_count: usize,
+    /// Size of `<db_path>/tantivy_index` on disk
+    pub disk_bytes: u64,
+}
+
+/// Advisory lock next to the Tantivy index, held by its one writer
+const TANTIVY_LOCK_FILE: &str = "tantivy_index.lock";
+
+/// Tantivy full-text index persisted under `<db_path>/tantivy_index`.
+/// Dropping it rolls back documents pending in bulk mode, leaving the index on disk
+/// at its last commit; `close` commits them first.
+pub(crate) struct TantivyText {
+    pub(crate) index: Index,
+    /// `None` when opened with `open_read_only`
+    writer: Option<TantivyWriter>,
+    pub(crate) index_path: std::path::PathBuf,
+        
+    // Schema fields
+    pub(crate) content_field: Field,
+    path_field: Field,
+    /// Untokenized copy of the path for deletes; `None` for indexes created before it existed
+    id_field: Option<Field>,
+    /// `Some(commit_every)` between `begin_bulk` and `commit_bulk`
+    bulk_commit_every: Option<usize>,
+    /// Documents added since the last commit in bulk mode
+    uncommitted: usize,
+}
+
+/// The write side of a `TantivyText`
+struct TantivyWriter {
+    writer: IndexWriter,
+    /// Exclusive lock on `<db_path>/tantivy_index.lock`, released on drop
+    _lock: std::fs::File,
+}
+
+/// Take the lock that admits one writer per index, or fail with `SearchError::IndexLocked`
+fn lock_for_writing(db_path: &str) -> Result<std::fs::File> {
+    std::fs::create_dir_all(db_path)?;
+    let lock = std::fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(Path::new(db_path).join(TANTIVY_LOCK_FILE))?;
+    match fs4::FileExt::try_lock_exclusive(&lock) {
+        Ok(()) => Ok(lock),
+        Err(e) if e.kind() == fs4::lock_contended_error().kind() => {
+            Err(SearchError::IndexLocked { path: db_path.to_string() }.into())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+impl TantivyText {
+    /// Open the index for writing, creating it if needed. Only one writer may hold an
+    /// index at a time, across processes; a second gets `SearchError::IndexLocked`.
+    pub(crate) fn open(db_path: &str) -> Result<Self> {
+        // Lock before looking for the index, so two writers can't both create it
+        let lock = lock_for_writing(db_path)?;
+        let (schema, content_field, path_field) = Self::schema();
+
+        // Open existing index or create new persistent disk-based index
+        let index_path = format!("{}/tantivy_index", db_path);
+        std::fs::create_dir_all(&index_path)?;
+        let index = if std::path::Path::new(&format!("{}/meta.json", index_path)).exists() {
+            Index::open_in_dir(&index_path)?
+        } else {
+            Index::create_in_dir(&index_path, schema)?
+        };
+        // 50MB heap
+        let writer = index.writer(50_000_000).map_err(|e| match e {
+            tantivy::TantivyError::LockFailure(..) => SearchError::IndexLocked { path: db_path.to_string() }.into(),
+            e => anyhow::Error::from(e),
+        })?;
+        Ok(Self::with_index(index, index_path, content_field, path_field, Some(TantivyWriter { writer, _lock: lock })))
+    }
+
+    /// Open an existing index for searching only. Takes no lock, so any number can be
+    /// open beside the writer, and each search sees the writer's latest commit.
+    /// `add`, `remove` and `clear` fail with `SearchError::ReadOnly`.
+    pub(crate) fn open_read_only(db_path: &str) -> Result<Self> {
+        let (_, content_field, path_field) = Self::schema();
+        let index_path = format!("{}/tantivy_index", db_path);
+        if !Path::new(&index_path).join("meta.json").exists() {
+            return Err(SearchError::IndexNotReady { index_name: index_path }.into());
+        }
+        let index = Index::open_in_dir(&index_path)?;
+        Ok(Self::with_index(index, index_path, content_field, path_field, None))
+    }
+
+    fn schema() -> (Schema, Field, Field) {
+        let mut schema_builder = Schema::builder();
+        let content_field = schema_builder.add_text_field("content", TEXT | STORED);
+        let path_field = schema_builder.add_text_field("path", TEXT | STORED);
+        schema_builder.add_text_field("doc_id", STRING);
+        (schema_builder.build(), content_field, path_field)
+    }
+
+    fn with_index(index: Index, index_path: String, content_field: Field, path_field: Field, writer: Option<TantivyWriter>) -> Self {
+        let id_field = index.schema().get_field("doc_id").ok();
+        Self {
+            index,
+            writer,
+            index_path: index_path.into(),
+            content_field,
+            path_field,
+            id_field,
+            bulk_commit_every: None,
+            uncommitted: 0,
+        }
+    }
+
+    fn writer(&mut self) -> Result<&mut IndexWriter> {
+        match &mut self.writer {
+            Some(writer) => Ok(&mut writer.writer),
+            None => Err(SearchError::ReadOnly { path: self.index_path.display().to_string() }.into()),
+        }
+    }
+
+    /// Stop committing after every `add`; added documents are committed by
+    /// `commit_bulk`, or whenever `commit_every` of them are pending (0 never).
+    /// Uncommitted documents are not visible to searches. Documents still pending from
+    /// an earlier bulk run that was never committed stay pending.
+    pub(crate) fn begin_bulk(&mut self, commit_every: usize) {
+        self.bulk_commit_every = Some(commit_every);
+    }
+
+    /// Commit what `add` accumulated since `begin_bulk` and go back to committing every call
+    pub(crate) fn commit_bulk(&mut self) -> Result<()> {
+        if self.bulk_commit_every.take().is_some() && self.uncommitted > 0 {
+            self.uncommitted = 0;
+            self.writer()?.commit()?;
+        }
+        Ok(())
+    }
+
+    /// Commit pending bulk documents and release the writer. Dropping the index
+    /// instead rolls them back, so relying on `Drop` alone may lose the last
+    /// uncommitted batch.
+    pub(crate) fn close(mut self) -> Result<()> {
+        self.commit_bulk()
+    }
+
+    /// Open the index at `db_path` if it validates, otherwise delete it and re-add
+    /// every indexable file under `source_root`. An index locked by another writer is
+    /// reported, not rebuilt.
+    pub(crate) fn open_or_rebuild(db_path: &str, source_root: &Path, config: &Config) -> Result<(Self, OpenOutcome)> {
+        let reason = match Self::open(db_path) {
+            Ok(text) => match text.validate() {
+                Ok(()) => return Ok((text, OpenOutcome::Opened)),
+                Err(reason) => reason,
+            },
+            // Another writer's index is not ours to rebuild
+            Err(e) if matches!(e.downcast_ref::<SearchError>(), Some(SearchError::IndexLocked { .. })) => return Err(e),
+            Err(e) => format!("failed to open: {:#}", e),
+        };
+        log::warn!("Rebuilding Tantivy index under {} from {}: {}", db_path, source_root.display(), reason);
+
+        let index_path = Path::new(db_path).join("tantivy_index");
+        if index_path.exists() {
+            std::fs::remove_dir_all(&index_path)?;
+        }
+        let mut text = Self::open(db_path)?;
+        let (contents, file_paths) = read_source_files(source_root, Path::new(db_path), config)?;
+        text.add(&contents, &file_paths)?;
+        Ok((text, OpenOutcome::Rebuilt(reason)))
+    }
+
+    /// Check that the schema has our fields, every segment can be read, and the
+    /// per-segment, searcher and full-scan document counts agree
+    fn validate(&self) -> std::result::Result<(), String> {
+        let schema = self.index.schema();
+        for name in ["content", "path"] {
+            schema.get_field(name).map_err(|_| format!("schema has no '{}' field", name))?;
+        }
+        let metas = self.index.searchable_segment_metas().map_err(|e| format!("unreadable segment metadata: {}", e))?;
+        let searcher = self.index.reader().map_err(|e| format!("failed to open segments: {}", e))?.searcher();
+
+        let meta_docs: u64 = metas.iter().map(|meta| u64::from(meta.num_docs())).sum();
+        let reader_docs: u64 = searcher.segment_readers().iter().map(|segment| u64::from(segment.num_docs())).sum();
+        let scanned = searcher.search(&AllQuery, &Count).map_err(|e| format!("full scan failed: {}", e))? as u64;
+        if meta_docs != searcher.num_docs() || reader_docs != searcher.num_docs() || scanned != searcher.num_docs() {
+            return Err(format!(
+                "inconsistent document counts: {} in segment metadata, {} in segments, {} scanned, {} reported",
+                meta_docs, reader_docs, scanned, searcher.num_docs()
+            ));
+        }
+
+        // The doc store is only read when results are returned, so load one per segment
+        for (ordinal, segment) in searcher.segment_readers().iter().enumerate() {
+            if segment.max_doc() > 0 {
+                searcher
+                    .doc::<tantivy::TantivyDocument>(tantivy::DocAddress::new(ordinal as u32, 0))
+                    .map_err(|e| format!("unreadable document store in segment {}: {}", ordinal, e))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Add documents keyed by their path in the `doc_id` field. The documents given for
+    /// a path are all of its chunks: those indexed for it by an earlier call are replaced.
+    pub(crate) fn add(&mut self, contents: &[String], file_paths: &[String]) -> Result<()> {
+        if let Some(id_field) = self.id_field {
+            let replaced: BTreeSet<&String> = file_paths.iter().collect();
+            for path in replaced {
+                self.writer()?.delete_term(Term::from_field_text(id_field, path));
+            }
+        }
+        for (content, path) in contents.iter().zip(file_paths.iter()) {
+            let mut doc = tantivy::doc!();
+            doc.add_text(self.content_field, content);
+            doc.add_text(self.path_field, path);
+            if let Some(id_field) = self.id_field {
+                doc.add_text(id_field, path);
+            }
+            self.writer()?.add_document(doc)?;
+        }
+        match self.bulk_commit_every {
+            None => {
+                self.writer()?.commit()?;
+            }
+            Some(commit_every) => {
+                self.uncommitted += contents.len().min(file_paths.len());
+                if commit_every > 0 && self.uncommitted >= commit_every {
+                    self.uncommitted = 0;
+                    self.writer()?.commit()?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Delete every document whose path is one of `file_paths`
+    pub(crate) fn remove(&mut self, file_paths: &[String]) -> Result<()> {
+        let id_field = self.id_field.ok_or_else(|| {
+            SearchError::from(StorageError::SchemaMismatch {
+                expected: "a doc_id field".to_string(),
+                actual: format!("none in {}; clear and re-index to replace documents", self.index_path.display()),
+            })
+        })?;
+        for path in file_paths {
+            self.writer()?.delete_term(Term::from_field_text(id_field, path));
+        }
+        self.writer()?.commit()?;
+        Ok(())
+    }
+
+    pub(crate) fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchResult>> {
+        // Create reader without reload policy (not available in tantivy 0.22)
+        let reader = self.index.reader()?;
+
+        let searcher = reader.searcher();
+        let query_parser = QueryParser::for_index(&self.index, vec![self.content_field]);
+
+        // Try both exact and fuzzy search
+        let parsed_query = query_parser.parse_query(query)?;
+
+        let top_docs = searcher.search(&*parsed_query, &TopDocs::with_limit(limit))?;
+        self.collect_results(&searcher, top_docs, "text")
+    }
+
+    pub(crate) fn collect_results(&self, searcher: &tantivy::Searcher,
+                       top_docs: Vec<(f32, tantivy::DocAddress)>,
+                       match_type: &str) -> Result<Vec<SearchResult>> {
+        let mut results = Vec::new();
+        for (score, doc_address) in top_docs {
+            let doc: tantivy::TantivyDocument = searcher.doc(doc_address)?;
+            let content = doc.get_first(self.content_field)
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string();
+            let path = doc.get_first(self.path_field)
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string();
+
+            results.push(SearchResult {
+                content,
+                file_path: path,
+                score,
+                match_type: match_type.to_string(),
+                snippet: None,
+                line_number: None,
+                heading_path: None,
+                component_scores: None,
+                metadata: HashMap::new(),
+            });
+        }
+
+        Ok(results)
+    }
+
+    /// Content and path of every committed document, in index order
+    pub(crate) fn documents(&self) -> Result<(Vec<String>, Vec<String>)> {
+        let searcher = self.index.reader()?.searcher();
+        let mut addresses: Vec<_> = searcher.search(&AllQuery, &DocSetCollector)?.into_iter().collect();
+        addresses.sort();
+        let mut contents = Vec::with_capacity(addresses.len());
+        let mut file_paths = Vec::with_capacity(addresses.len());
+        for doc_address in addresses {
+            let doc: tantivy::TantivyDocument = searcher.doc(doc_address)?;
+            let text = |field: Field| doc.get_first(field).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+            contents.push(text(self.content_field));
+            file_paths.push(text(self.path_field));
+        }
+        Ok((contents, file_paths))
+    }
+
+    /// Distinct `path` values of every committed document
+    pub(crate) fn paths(&self) -> Result<BTreeSet<String>> {
+        let searcher = self.index.reader()?.searcher();
+        let mut paths = BTreeSet::new();
+        for doc_address in searcher.search(&AllQuery, &DocSetCollector)? {
+            let doc: tantivy::TantivyDocument = searcher.doc(doc_address)?;
+            if let Some(path) = doc.get_first(self.path_field).and_then(|v| v.as_str()) {
+                paths.insert(path.to_string());
+            }
+        }
+        Ok(paths)
+    }
+
+    pub(crate) fn clear(&mut self) -> Result<()> {
+        self.writer()?.delete_all_documents()?;
+        self.writer()?.commit()?;
+        Ok(())
+    }
+
+    pub(crate) fn get_index_stats(&self) -> Result<TantivyStats> {
+        let searcher = self.index.reader()?.searcher();
+        let disk_bytes = walkdir::WalkDir::new(&self.index_path)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter_map(|e| e.metadata().ok())
+            .filter(|m| m.is_file())
+            .map(|m| m.len())
+            .sum();
+
+        Ok(TantivyStats {
+            num_docs: searcher.num_docs(),
+            segment_count: self.index.searchable_segment_metas()?.len(),
+            disk_bytes,
+        })
+    }
+}
+
+impl Drop for TantivyText {
+    fn drop(&mut self) {
+        if self.uncommitted == 0 {
+            return;
+        }
+        log::warn!("Rolling back {} uncommitted documents in {}", self.uncommitted, self.index_path.display());
+        if let Some(TantivyWriter { writer, .. }) = &mut self.writer {
+            if let Err(e) = writer.rollback() {
+                log::warn!("Rollback of {} failed: {}", self.index_path.display(), e);
+            }
+        }
+    }
+}
+
+/// Whole-file contents and paths of the indexable UTF-8 files under `root`, skipping
+/// anything under `db_path`, with the same filters as `embed-search index`
+fn read_source_files(root: &Path, db_path: &Path, config: &Config) -> Result<(Vec<String>, Vec<String>)> {
+    let mut contents = Vec::new();
+    let mut file_paths = Vec::new();
+    let indexing = &config.indexing;
+    let files = walk_source_files(root, indexing, config.respect_gitignore)?
+        .into_iter()
+        .filter(|path| !path.starts_with(db_path))
+        .filter(|path| is_indexable_extension(path, &indexing.supported_extensions));
+    for path in files {
+        let Ok(bytes) = std::fs::read(&path) else {
+            continue;
+        };
+        let Ok(Some(content)) = decode_source(&path, bytes, config.on_invalid_utf8) else {
+            continue;
+        };
+        if exceeds_max_file_size(content.len() as u64, indexing.max_file_size) {
+            continue;
+        }
+        contents.push(content);
+        file_paths.push(path.display().to_string());
+    }
+    Ok((contents, file_paths))
+}
+
+/// Default location of the persisted BM25 index, relative to the db path
+pub const DEFAULT_BM25_INDEX_FILE: &str = "bm25_index.json";
+
+/// Keyword side of the hybrid search, selected by `SearchBackend`
+pub(crate) enum TextBackend {
+    Tantivy(Box<TantivyText>),
+    /// In-memory BM25, saved to `index_path` after every change
+    Bm25 { engine: Box<BM25Engine>, index_path: PathBuf },
+}
+
+impl TextBackend {
+    /// `read_only` opens Tantivy without its writer lock; BM25 is loaded either way
+    pub(crate) fn open(db_path: &str, backend: SearchBackend, bm25_index_path: Option<&Path>, read_only: bool) -> Result<Self> {
+        match backend {
+            SearchBackend::Tantivy if read_only => Ok(TextBackend::Tantivy(Box::new(TantivyText::open_read_only(db_path)?))),
+            SearchBackend::Tantivy => Ok(TextBackend::Tantivy(Box::new(TantivyText::open(db_path)?))),
+            SearchBackend::Bm25Only => {
+                let index_path = bm25_index_path
+                    .map(Path::to_path_buf)
+                    .unwrap_or_else(|| Path::new(db_path).join(DEFAULT_BM25_INDEX_FILE));
+                let engine = Box::new(BM25Engine::load_or_new(&index_path)?);
+                Ok(TextBackend::Bm25 { engine, index_path })
+            }
+        }
+    }
+
+    pub(crate) fn kind(&self) -> SearchBackend {
+        match self {
+            TextBackend::Tantivy(_) => SearchBackend::Tantivy,
+            TextBackend::Bm25 { .. } => SearchBackend::Bm25Only,
+        }
+    }
+
+    /// Filename weighting for BM25; Tantivy scores its single content field only
+    pub(crate) fn set_filename_boost(&mut self, boost: f32) {
+        if let TextBackend::Bm25 { engine, .. } = self {
+            engine.set_filename_boost(boost);
+        }
+    }
+
+    /// Term frequency saturation for BM25; Tantivy keeps its own scoring
+    pub(crate) fn set_tf_variant(&mut self, variant: TfVariant) {
+        if let TextBackend::Bm25 { engine, .. } = self {
+            engine.set_tf_variant(variant);
+        }
+    }
+
+    /// Term derivation for BM25, see `BM25Engine::with_text_processor`; Tantivy keeps
+    /// its own tokenization
+    pub(crate) fn set_text_processor(&mut self, processor: CodeTextProcessor) {
+        if let TextBackend::Bm25 { engine, .. } = self {
+            engine.set_text_processor(processor);
+        }
+    }
+
+    /// Stemmed query matching for BM25; Tantivy's tokenizer doesn't stem
+    pub(crate) fn set_stemming(&mut self, stemming: bool) {
+        if let TextBackend::Bm25 { engine, .. } = self {
+            engine.set_stemming(stemming);
+        }
+    }
+
+    /// Character n-grams for BM25; Tantivy keeps its own tokenization
+    pub(crate) fn set_ngram_sizes(&mut self, sizes: Option<(usize, usize)>) {
+        if let TextBackend::Bm25 { engine, .. } = self {
+            engine.set_ngram_sizes(sizes);
+        }
+    }
+
+    /// Batch Tantivy commits until `commit_bulk`; BM25 is saved after every `index` regardless
+    pub(crate) fn begin_bulk(&mut self, commit_every: usize) {
+        if let TextBackend::Tantivy(tantivy) = self {
+            tantivy.begin_bulk(commit_every);
+        }
+    }
+
+    pub(crate) fn commit_bulk(&mut self) -> Result<()> {
+        match self {
+            TextBackend::Tantivy(tantivy) => tantivy.commit_bulk(),
+            TextBackend::Bm25 { .. } => Ok(()),
+        }
+    }
+
+    pub(crate) fn close(self) -> Result<()> {
+        match self {
+            TextBackend::Tantivy(tantivy) => tantivy.close(),
+            TextBackend::Bm25 { .. } => Ok(()),
+        }
+    }
+
+    pub(crate) fn index(&mut self, contents: &[String], file_paths: &[String]) -> Result<()> {
+        match self {
+            TextBackend::Tantivy(tantivy) => tantivy.add(contents, file_paths),
+            TextBackend::Bm25 { engine, index_path } => {
+                for (content, path) in contents.iter().zip(file_paths.iter()) {
+                    engine.index_document(path, content);
+                }
+                engine.save(index_path)
+            }
+        }
+    }
+
+    pub(crate) fn search(&self, query: &str, limit: usize, options: &SearchOptions) -> Result<Vec<SearchResult>> {
+        match self {
+            TextBackend::Tantivy(tantivy) => tantivy.search(query, limit),
+            TextBackend::Bm25 { engine: bm25, .. } => {
+                let matches = bm25.search_with_options(query, limit, options)?;
+                Ok(matches
+                    .into_iter()
+                    .map(|m| SearchResult {
+                        // Use the full document so fusion keys line up with vector results
+                        content: bm25.get_document(&m.path).map(str::to_string).unwrap_or(m.snippet),
+                        file_path: m.path,
+                        score: m.score,
+                        match_type: "bm25".to_string(),
+                        snippet: None,
+                        line_number: None,
+                        heading_path: None,
+                        component_scores: None,
+                        metadata: HashMap::new(),
+                    })
+                    .collect())
+            }
+        }
+    }
+
+    /// Drop every document of `file_paths` from the keyword index
+    pub(crate) fn remove(&mut self, file_paths: &[String]) -> Result<()> {
+        match self {
+            TextBackend::Tantivy(tantivy) => tantivy.remove(file_paths),
+            TextBackend::Bm25 { engine, index_path } => {
+                for path in file_paths {
+                    engine.remove_document(path)?;
+                }
+                engine.save(index_path)
+            }
+        }
+    }
+
+    /// Distinct file paths in the keyword index
+    pub(crate) fn paths(&self) -> Result<BTreeSet<String>> {
+        match self {
+            TextBackend::Tantivy(tantivy) => tantivy.paths(),
+            TextBackend::Bm25 { engine, .. } => Ok(engine.document_ids().map(str::to_string).collect()),
+        }
+    }
+
+    /// Number of documents in the keyword index
+    pub(crate) fn len(&self) -> Result<usize> {
+        match self {
+            TextBackend::Tantivy(tantivy) => Ok(tantivy.index.reader()?.searcher().num_docs() as usize),
+            TextBackend::Bm25 { engine, .. } => Ok(engine.stats().total_documents),
+        }
+    }
+
+    pub(crate) fn stats(&self, vector_storage: &dyn VectorStore) -> Result<IndexStats> {
+        let mut stats = IndexStats {
+            backend: SearchBackend::Tantivy,
+            documents: 0,
+            vector_count: vector_storage.len(),
+            vector_dimension: vector_storage.dimension(),
+            tantivy: None,
+            bm25: None,
+            bm25_disk_bytes: None,
+        };
+        match self {
+            TextBackend::Tantivy(tantivy) => {
+                let tantivy_stats = tantivy.get_index_stats()?;
+                stats.documents = tantivy_stats.num_docs as usize;
+                stats.tantivy = Some(tantivy_stats);
+            }
+            TextBackend::Bm25 { engine, index_path } => {
+                let bm25_stats = engine.stats();
+                stats.backend = SearchBackend::Bm25Only;
+                stats.documents = bm25_stats.total_documents;
+                stats.bm25 = Some(bm25_stats);
+                // Nothing is saved until the first change
+                stats.bm25_disk_bytes = Some(std::fs::metadata(index_path).map_or(0, |m| m.len()));
+            }
+        }
+        Ok(stats)
+    }
+
+    pub(crate) fn clear(&mut self) -> Result<()> {
+        match self {
+            TextBackend::Tantivy(tantivy) => tantivy.clear(),
+            TextBackend::Bm25 { engine, index_path } => {
+                **engine = BM25Engine::new()?
+                    .with_text_processor(engine.text_processor().clone())
+                    .with_filename_boost(engine.filename_boost())
+                    .with_tf_variant(engine.tf_variant());
+                engine.save(index_path)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::sync::Arc;
+    
+    use crate::simple_storage::VectorStorage;
+    use tempfile::tempdir;
+
+    pub(crate) fn text_only_index(db_path: &str, docs: &[(&str, &str)]) -> Result<TantivyText> {
+        let mut text = TantivyText::open(db_path)?;
+        let contents: Vec<String> = docs.iter().map(|(_, content)| content.to_string()).collect();
+        let paths: Vec<String> = docs.iter().map(|(path, _)| path.to_string()).collect();
+        text.add(&contents, &paths)?;
+        Ok(text)
+    }
+
+    #[test]
+    fn test_bm25_only_backend_uses_bm25_engine() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().to_str().unwrap();
+        let mut backend = TextBackend::open(db_path, SearchBackend::Bm25Only, None, false)?;
+        backend.index(
+            &["fn authenticate(user: &User) {}".to_string(), "struct Config {}".to_string()],
+            &["auth.rs".to_string(), "config.rs".to_string()],
+        )?;
+
+        let results = backend.search("authenticate", 5, &SearchOptions::default())?;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].file_path, "auth.rs");
+        assert_eq!(results[0].match_type, "bm25");
+        assert_eq!(results[0].content, "fn authenticate(user: &User) {}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_tantivy_index_stats() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let text = text_only_index(temp_dir.path().to_str().unwrap(), &[
+            ("a.rs", "fn parse"),
+            ("b.rs", "fn render"),
+        ])?;
+        let stats = text.get_index_stats()?;
+        assert_eq!(stats.num_docs, 2);
+        assert!(stats.segment_count >= 1);
+        assert!(stats.disk_bytes > 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_or_rebuild_keeps_a_valid_index() -> Result<()> {
+        let source = tempdir()?;
+        let db = tempdir()?;
+        let db_path = db.path().to_str().unwrap();
+        drop(text_only_index(db_path, &[("a.rs", "fn parse"), ("b.rs", "fn render")])?);
+
+        let (text, outcome) = TantivyText::open_or_rebuild(db_path, source.path(), &Config::default())?;
+        assert_eq!(outcome, OpenOutcome::Opened);
+        assert_eq!(text.get_index_stats()?.num_docs, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_or_rebuild_replaces_corrupted_index() -> Result<()> {
+        let source = tempdir()?;
+        std::fs::write(source.path().join("auth.rs"), "fn authenticate() {}")?;
+        std::fs::write(source.path().join("notes.bin"), "fn ignored() {}")?;
+
+        // Missing segment files: meta.json still parses and claims three documents
+        let db = tempdir()?;
+        let db_path = db.path().to_str().unwrap();
+        drop(text_only_index(db_path, &[("a.rs", "fn a"), ("b.rs", "fn b"), ("c.rs", "fn c")])?);
+        for entry in std::fs::read_dir(db.path().join("tantivy_index"))? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "store") {
+                std::fs::remove_file(path)?;
+            }
+        }
+        let (text, outcome) = TantivyText::open_or_rebuild(db_path, source.path(), &Config::default())?;
+        assert!(matches!(outcome, OpenOutcome::Rebuilt(_)), "{:?}", outcome);
+        let results = text.search("authenticate", 5)?;
+        assert_eq!(results.len(), 1);
+        assert!(results[0].file_path.ends_with("auth.rs"));
+        assert_eq!(text.get_index_stats()?.num_docs, 1);
+        drop(text);
+
+        // Unparseable metadata
+        std::fs::write(db.path().join("tantivy_index").join("meta.json"), "{ not json")?;
+        let (_, outcome) = TantivyText::open_or_rebuild(db_path, source.path(), &Config::default())?;
+        match outcome {
+            OpenOutcome::Rebuilt(reason) => assert!(reason.contains("failed to open"), "{}", reason),
+            other => panic!("expected a rebuild, got {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_bm25_backend_stats() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let mut backend = TextBackend::open(temp_dir.path().to_str().unwrap(), SearchBackend::Bm25Only, None, false)?;
+        backend.index(&["fn main() {}".to_string()], &["main.rs".to_string()])?;
+        let storage = VectorStorage::new("unused")?;
+
+        let stats = backend.stats(&storage)?;
+        assert_eq!(stats.backend, SearchBackend::Bm25Only);
+        assert_eq!(stats.documents, 1);
+        assert_eq!(stats.vector_count, 0);
+        assert!(stats.tantivy.is_none());
+        assert_eq!(stats.bm25.map(|b| b.total_terms), Some(2));
+        let saved = std::fs::metadata(temp_dir.path().join(DEFAULT_BM25_INDEX_FILE))?.len();
+        assert!(saved > 0);
+        assert_eq!(stats.bm25_disk_bytes, Some(saved));
+        Ok(())
+    }
+
+    #[test]
+    fn test_bm25_backend_reloads_persisted_index() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().to_str().unwrap();
+        {
+            let mut backend = TextBackend::open(db_path, SearchBackend::Bm25Only, None, false)?;
+            backend.index(&["fn authenticate() {}".to_string()], &["auth.rs".to_string()])?;
+        }
+        assert!(temp_dir.path().join(DEFAULT_BM25_INDEX_FILE).exists());
+
+        let reopened = TextBackend::open(db_path, SearchBackend::Bm25Only, None, false)?;
+        let results = reopened.search("authenticate", 5, &SearchOptions::default())?;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].file_path, "auth.rs");
+        Ok(())
+    }
+
+    #[test]
+    fn test_bm25_backend_uses_configured_index_path() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let custom = temp_dir.path().join("custom").join("bm25.json");
+        let mut backend = TextBackend::open(temp_dir.path().to_str().unwrap(), SearchBackend::Bm25Only, Some(&custom), false)?;
+        backend.index(&["fn main() {}".to_string()], &["main.rs".to_string()])?;
+        assert!(custom.exists());
+        assert!(!temp_dir.path().join(DEFAULT_BM25_INDEX_FILE).exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_text_backend_len() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let mut backend = TextBackend::open(temp_dir.path().to_str().unwrap(), SearchBackend::Bm25Only, None, false)?;
+        assert_eq!(backend.len()?, 0);
+        backend.index(&["a".to_string(), "b".to_string()], &["a.rs".to_string(), "b.rs".to_string()])?;
+        assert_eq!(backend.len()?, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_bm25_only_backend_never_creates_tantivy_index() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().to_str().unwrap();
+        let mut backend = TextBackend::open(db_path, SearchBackend::Bm25Only, None, false)?;
+        backend.index(&["fn main() {}".to_string()], &["main.rs".to_string()])?;
+        backend.search("main", 5, &SearchOptions::default())?;
+        backend.clear()?;
+        assert!(!temp_dir.path().join("tantivy_index").exists());
+        Ok(())
+    }
+
+    pub(crate) fn numbered_files(count: usize) -> (Vec<String>, Vec<String>) {
+        (0..count)
+            .map(|i| (format!("fn handler_{}() {{ route(\"/api/{}\") }}", i, i), format!("src/handler_{}.rs", i)))
+            .unzip()
+    }
+
+    #[test]
+    fn test_bulk_mode_commits_every_n_documents_and_at_the_end() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let mut text = TantivyText::open(temp_dir.path().to_str().unwrap())?;
+        let (contents, paths) = numbered_files(5);
+
+        text.begin_bulk(3);
+        text.add(&contents[..2], &paths[..2])?;
+        assert_eq!(text.paths()?.len(), 0);
+        text.add(&contents[2..4], &paths[2..4])?;
+        assert_eq!(text.paths()?.len(), 4);
+        text.add(&contents[4..], &paths[4..])?;
+        assert_eq!(text.paths()?.len(), 4);
+        text.commit_bulk()?;
+        assert_eq!(text.paths()?.len(), 5);
+
+        // Back to committing every call
+        let (contents, paths) = numbered_files(6);
+        text.add(&contents[5..], &paths[5..])?;
+        assert_eq!(text.paths()?.len(), 6);
+        Ok(())
+    }
+
+    #[test]
+    fn test_close_commits_pending_documents_and_drop_rolls_them_back() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().to_str().unwrap();
+        let (contents, paths) = numbered_files(4);
+
+        let mut text = TantivyText::open(db_path)?;
+        text.begin_bulk(0);
+        text.add(&contents[..2], &paths[..2])?;
+        text.close()?;
+        assert_eq!(TantivyText::open(db_path)?.paths()?, paths[..2].iter().cloned().collect());
+
+        let mut text = TantivyText::open(db_path)?;
+        text.begin_bulk(0);
+        text.add(&contents[2..], &paths[2..])?;
+        drop(text);
+        let reopened = TantivyText::open(db_path)?;
+        assert_eq!(reopened.validate(), Ok(()));
+        assert_eq!(reopened.paths()?, paths[..2].iter().cloned().collect());
+        Ok(())
+    }
+
+    #[test]
+    fn test_concurrent_write_opens_admit_one_writer() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().to_str().unwrap().to_string();
+        let barrier = Arc::new(std::sync::Barrier::new(2));
+        let openers: Vec<_> = (0..2)
+            .map(|_| {
+                let (db_path, barrier) = (db_path.clone(), Arc::clone(&barrier));
+                std::thread::spawn(move || {
+                    barrier.wait();
+                    TantivyText::open(&db_path)
+                })
+            })
+            .collect();
+        // Join both before dropping either, so the winner still holds the lock
+        let results: Vec<Result<TantivyText>> = openers.into_iter().map(|opener| opener.join().unwrap()).collect();
+
+        assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 1);
+        let err = results.iter().find_map(|result| result.as_ref().err()).unwrap();
+        match err.downcast_ref::<SearchError>() {
+            Some(SearchError::IndexLocked { path }) => assert_eq!(path, &db_path),
+            other => panic!("expected IndexLocked, got {:?}", other),
+        }
+
+        // Readers don't contend for the lock, and the lock goes with the writer
+        assert!(TantivyText::open_read_only(&db_path)?.paths()?.is_empty());
+        drop(results);
+        TantivyText::open(&db_path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_only_searchers_query_in_parallel_beside_the_writer() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().to_str().unwrap().to_string();
+        let (contents, paths) = numbered_files(20);
+        let mut writer = TantivyText::open(&db_path)?;
+        writer.add(&contents, &paths)?;
+
+        let readers: Vec<_> = (0..8)
+            .map(|i| {
+                let db_path = db_path.clone();
+                std::thread::spawn(move || -> Result<usize> {
+                    let reader = TantivyText::open_read_only(&db_path)?;
+                    let mut answered = 0;
+                    for _ in 0..10 {
+                        if !reader.search(&format!("handler_{}", i), 5)?.is_empty() {
+                            answered += 1;
+                        }
+                    }
+                    Ok(answered)
+                })
+            })
+            .collect();
+        for reader in readers {
+            assert_eq!(reader.join().unwrap()?, 10);
+        }
+
+        let mut reader = TantivyText::open_read_only(&db_path)?;
+        let err = reader.add(&contents[..1], &paths[..1]).unwrap_err();
+        assert!(matches!(err.downcast_ref::<SearchError>(), Some(SearchError::ReadOnly { .. })), "{:?}", err);
+        Ok(())
+    }
+
+    #[test]
+    #[ignore] // Benchmark; run with: cargo test bulk_indexing -- --ignored --nocapture
+    fn bench_bulk_indexing_1000_files() -> Result<()> {
+        let (contents, paths) = numbered_files(1000);
+
+        let individual_dir = tempdir()?;
+        let mut individual = TantivyText::open(individual_dir.path().to_str().unwrap())?;
+        let started = std::time::Instant::now();
+        for (content, path) in contents.iter().zip(&paths) {
+            individual.add(std::slice::from_ref(content), std::slice::from_ref(path))?;
+        }
+        let individual_time = started.elapsed();
+
+        let bulk_dir = tempdir()?;
+        let mut bulk = TantivyText::open(bulk_dir.path().to_str().unwrap())?;
+        let started = std::time::Instant::now();
+        bulk.begin_bulk(Config::default().indexing.bulk_commit_every);
+        for (content, path) in contents.iter().zip(&paths) {
+            bulk.add(std::slice::from_ref(content), std::slice::from_ref(path))?;
+        }
+        bulk.commit_bulk()?;
+        let bulk_time = started.elapsed();
+
+        println!("1000 files: {:?} committing each, {:?} in bulk", individual_time, bulk_time);
+        assert_eq!(individual.paths()?.len(), 1000);
+        assert_eq!(bulk.paths()?.len(), 1000);
+        assert!(bulk_time < individual_time, "bulk {:?} vs individual {:?}", bulk_time, individual_time);
+        Ok(())
+    }
+}
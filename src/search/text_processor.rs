@@ -1,25 +1,45 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use once_cell::sync::Lazy;
+use regex::Regex;
 use rust_stemmers::{Algorithm, Stemmer};
 use unicode_normalization::UnicodeNormalization;
-use unicode_segmentation::UnicodeSegmentation;
 use serde::{Serialize, Deserialize};
 
+use crate::config::SearchConfig;
+use crate::search::tokenizer::{CodeTokenizer, Tokenizer};
+use crate::search::SearchOptions;
+
+/// Hyphen-joined words such as `get-user-name`; `unicode_words` only sees the parts
+static KEBAB_IDENTIFIER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\b[A-Za-z][A-Za-z0-9]*(?:-[A-Za-z][A-Za-z0-9]*)+\b").unwrap());
+
 /// Code-aware text processor for optimal BM25 performance
 pub struct CodeTextProcessor {
-    /// Stop words to filter out
+    /// Stop words to filter out when no language-specific set applies
     stop_words: HashSet<String>,
+    /// Stop word sets keyed by lowercase language or file extension
+    stop_words_by_lang: HashMap<String, HashSet<String>>,
+    /// Language used when the caller doesn't pass one (or passes one without a set)
+    default_language: Option<String>,
     /// Porter stemmer for natural language in comments
     stemmer: Stemmer,
     /// Whether to enable stemming
     enable_stemming: bool,
     /// Whether to generate n-grams
     enable_ngrams: bool,
+    /// Minimum n-gram size
+    min_ngram_size: usize,
     /// Maximum n-gram size
     max_ngram_size: usize,
     /// Minimum term length to index
     min_term_length: usize,
     /// Maximum term length to index
     max_term_length: usize,
+    /// Whether compound identifiers also yield their parts
+    split_identifiers: bool,
+    /// Splits each line into words before normalization
+    tokenizer: Arc<dyn Tokenizer>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,6 +52,21 @@ pub struct ProcessedToken {
     pub importance_weight: f32,
 }
 
+/// A term of `CodeTextProcessor::analyze`, with where it came from in the input
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnalyzedToken {
+    /// The term as indexed: NFC-normalized and lowercased
+    pub text: String,
+    /// `text` stemmed when stemming is enabled, otherwise `text` again
+    pub stemmed: String,
+    /// Stop words are reported but never indexed
+    pub is_stop_word: bool,
+    /// Byte range in the input of the word the term was taken from; parts of a
+    /// split identifier share the identifier's range
+    pub start: usize,
+    pub end: usize,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TokenType {
     Identifier,      // Variable/function names (high importance)
@@ -50,12 +85,16 @@ impl std::fmt::Debug for CodeTextProcessor {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("CodeTextProcessor")
             .field("stop_words", &self.stop_words)
+            .field("stop_words_by_lang", &self.stop_words_by_lang)
+            .field("default_language", &self.default_language)
             .field("enable_stemming", &self.enable_stemming)
             .field("enable_ngrams", &self.enable_ngrams)
+            .field("min_ngram_size", &self.min_ngram_size)
             .field("max_ngram_size", &self.max_ngram_size)
             .field("min_term_length", &self.min_term_length)
             .field("max_term_length", &self.max_term_length)
-            .finish()
+            .field("split_identifiers", &self.split_identifiers)
+            .finish_non_exhaustive()
     }
 }
 
@@ -63,12 +102,17 @@ impl Clone for CodeTextProcessor {
     fn clone(&self) -> Self {
         Self {
             stop_words: self.stop_words.clone(),
+            stop_words_by_lang: self.stop_words_by_lang.clone(),
+            default_language: self.default_language.clone(),
             stemmer: Stemmer::create(Algorithm::English), // Recreate stemmer since it may not be Clone
             enable_stemming: self.enable_stemming,
             enable_ngrams: self.enable_ngrams,
+            min_ngram_size: self.min_ngram_size,
             max_ngram_size: self.max_ngram_size,
             min_term_length: self.min_term_length,
             max_term_length: self.max_term_length,
+            split_identifiers: self.split_identifiers,
+            tokenizer: Arc::clone(&self.tokenizer),
         }
     }
 }
@@ -80,12 +124,17 @@ impl CodeTextProcessor {
         
         Self {
             stop_words,
+            stop_words_by_lang: HashMap::new(),
+            default_language: None,
             stemmer,
             enable_stemming: true,
             enable_ngrams: true,
+            min_ngram_size: 2,
             max_ngram_size: 3,
             min_term_length: 2,
             max_term_length: 50,
+            split_identifiers: true,
+            tokenizer: Arc::new(CodeTokenizer),
         }
     }
     
@@ -106,13 +155,80 @@ impl CodeTextProcessor {
         
         Self {
             stop_words,
+            stop_words_by_lang: HashMap::new(),
+            default_language: None,
             stemmer,
             enable_stemming,
             enable_ngrams,
+            min_ngram_size: 2,
             max_ngram_size,
             min_term_length,
             max_term_length,
+            split_identifiers: true,
+            tokenizer: Arc::new(CodeTokenizer),
+        }
+    }
+    
+    /// Build a processor from the BM25 stop word settings in `SearchConfig`
+    pub fn from_search_config(config: &SearchConfig) -> Self {
+        let mut processor = Self::with_config(true, true, 3, 2, 50, config.bm25_stop_words.clone());
+        for (language, words) in &config.bm25_stop_words_by_lang {
+            processor = processor.with_language_stop_words(language, words.clone());
+        }
+        processor.default_language = config.bm25_language.as_ref().map(|l| l.to_lowercase());
+        processor.with_split_identifiers(config.split_identifiers)
+    }
+
+    /// Whether `getUserName`, `get_user_name` and `get-user-name` also index
+    /// `get`, `user` and `name`. The whole identifier is always indexed.
+    pub fn with_split_identifiers(mut self, split_identifiers: bool) -> Self {
+        self.split_identifiers = split_identifiers;
+        self
+    }
+
+    /// N-gram lengths, in words for the n-grams `tokenize_code` adds and in characters
+    /// for `character_ngrams`. Word n-grams are never shorter than two words.
+    pub fn with_ngram_sizes(mut self, min_ngram_size: usize, max_ngram_size: usize) -> Self {
+        self.min_ngram_size = min_ngram_size;
+        self.max_ngram_size = max_ngram_size;
+        self
+    }
+
+    /// Character n-grams of `term` between the configured sizes; empty when n-grams are disabled
+    pub fn character_ngrams(&self, term: &str) -> Vec<String> {
+        if !self.enable_ngrams {
+            return Vec::new();
         }
+        character_ngrams(term, self.min_ngram_size, self.max_ngram_size)
+    }
+
+    /// Split lines into words with `tokenizer` instead of `CodeTokenizer`.
+    /// Stop words, identifier splitting, stemming and n-grams still apply to its words.
+    pub fn with_tokenizer(mut self, tokenizer: Arc<dyn Tokenizer>) -> Self {
+        self.tokenizer = tokenizer;
+        self
+    }
+    
+    /// Use `words` instead of the default list for text in `language`
+    /// (a language name such as "python" or a file extension such as "md")
+    pub fn with_language_stop_words(mut self, language: &str, words: Vec<String>) -> Self {
+        let words = words.into_iter().map(|w| w.to_lowercase()).collect();
+        self.stop_words_by_lang.insert(language.to_lowercase(), words);
+        self
+    }
+    
+    /// Stop words for `language`, falling back to the configured default language
+    /// and then to the default list
+    pub fn stop_words_for(&self, language: Option<&str>) -> &HashSet<String> {
+        language
+            .map(str::to_lowercase)
+            .and_then(|lang| self.stop_words_by_lang.get(&lang))
+            .or_else(|| {
+                self.default_language
+                    .as_ref()
+                    .and_then(|lang| self.stop_words_by_lang.get(lang))
+            })
+            .unwrap_or(&self.stop_words)
     }
     
     /// Default stop words for code search
@@ -137,22 +253,30 @@ impl CodeTextProcessor {
     
     /// Tokenize code content with language awareness
     pub fn tokenize_code(&self, content: &str, language: Option<&str>) -> Vec<ProcessedToken> {
+        self.tokenize_code_with_options(content, language, &SearchOptions::default())
+    }
+    
+    /// `tokenize_code` with `options.stemming` in place of the configured stemming,
+    /// e.g. to tokenize a query for an exact symbol without stemming it
+    pub fn tokenize_code_with_options(&self, content: &str, language: Option<&str>, options: &SearchOptions) -> Vec<ProcessedToken> {
+        let stemming = options.stemming.unwrap_or(self.enable_stemming);
         let mut tokens = Vec::new();
         let mut position = 0;
         
         // Split content into lines for line number tracking
         let lines: Vec<&str> = content.lines().collect();
+        let stop_words = self.stop_words_for(language);
         
         for (line_num, line) in lines.iter().enumerate() {
             // Simple tokenization for now - can be enhanced with language-specific parsers
-            let line_tokens = self.tokenize_line(line, line_num, language);
+            let line_tokens = self.tokenize_line(line, line_num, language, stop_words, stemming);
             
             for mut token in line_tokens {
                 token.position = position;
                 position += 1;
                 
                 // Apply filters
-                if self.should_index_token(&token) {
+                if self.should_index_token(&token, stop_words) {
                     tokens.push(token);
                 }
             }
@@ -167,22 +291,86 @@ impl CodeTextProcessor {
         tokens
     }
     
+    /// Every term `tokenize_code` would derive from `text`, in input order, for seeing
+    /// why a query does or doesn't match: each word followed by the parts it splits
+    /// into, then any kebab-case compounds of its line. Unlike `tokenize_code`, stop
+    /// words are kept (flagged) and no n-grams are added; terms outside the length
+    /// limits are left out as they are there.
+    pub fn analyze(&self, text: &str) -> Vec<AnalyzedToken> {
+        let mut analyzed = Vec::new();
+        self.for_each_term(text, None, |term, is_stop_word, start, end| {
+            let stemmed = if self.enable_stemming { self.stemmer.stem(&term).to_string() } else { term.clone() };
+            analyzed.push(AnalyzedToken { text: term, stemmed, is_stop_word, start, end });
+        });
+        analyzed
+    }
+
+    /// Terms a keyword index such as `BM25Engine` stores for `text`: the terms of
+    /// `analyze` without the stop words of `language` (see `stop_words_for`).
+    /// Stemming and n-grams are left to the index.
+    pub fn index_terms(&self, text: &str, language: Option<&str>) -> Vec<String> {
+        let mut terms = Vec::new();
+        self.for_each_term(text, language, |term, is_stop_word, _, _| {
+            if !is_stop_word {
+                terms.push(term);
+            }
+        });
+        terms
+    }
+
+    /// Walk the terms of `text` as `analyze` describes, calling
+    /// `push(term, is_stop_word, start, end)` for each
+    fn for_each_term(&self, text: &str, language: Option<&str>, mut push: impl FnMut(String, bool, usize, usize)) {
+        let stop_words = self.stop_words_for(language);
+        let in_length = |term: &str| term.len() >= self.min_term_length && term.len() <= self.max_term_length;
+        let mut line_start = 0;
+        for line in text.split_inclusive('\n') {
+            for token in self.tokenizer.tokenize(line) {
+                let composed = token.text.nfc().collect::<String>();
+                let normalized = composed.to_lowercase();
+                let (start, end) = (line_start + token.span.start, line_start + token.span.end);
+                if stop_words.contains(&normalized) {
+                    push(normalized, true, start, end);
+                    continue;
+                }
+                let parts = if self.split_identifiers { self.split_compound_identifier(&composed) } else { Vec::new() };
+                if in_length(&normalized) {
+                    push(normalized.clone(), false, start, end);
+                }
+                for part in parts.into_iter().filter(|part| *part != normalized && in_length(part)) {
+                    push(part, false, start, end);
+                }
+            }
+            if self.split_identifiers && !self.is_comment_line(line, None) {
+                for compound in KEBAB_IDENTIFIER.find_iter(line) {
+                    let term = compound.as_str().to_lowercase();
+                    if in_length(&term) {
+                        push(term, false, line_start + compound.start(), line_start + compound.end());
+                    }
+                }
+            }
+            line_start += line.len();
+        }
+    }
+
     /// Tokenize a single line of code
-    fn tokenize_line(&self, line: &str, line_number: usize, language: Option<&str>) -> Vec<ProcessedToken> {
+    fn tokenize_line(&self, line: &str, line_number: usize, language: Option<&str>, stop_words: &HashSet<String>, stemming: bool) -> Vec<ProcessedToken> {
         let mut tokens = Vec::new();
         
         // Check if line is a comment
         let is_comment = self.is_comment_line(line, language);
         
         // Split on word boundaries and common separators
-        let words = line.unicode_words();
+        let words = self.tokenizer.tokenize(line);
         
-        for word in words {
-            // Normalize the word
-            let normalized = word.nfc().collect::<String>().to_lowercase();
+        for token in words {
+            let word = token.text.as_str();
+            // Normalize the word; case is kept until the identifier has been split
+            let composed = word.nfc().collect::<String>();
+            let normalized = composed.to_lowercase();
             
             // Skip if it's a stop word
-            if self.stop_words.contains(&normalized) {
+            if stop_words.contains(&normalized) {
                 continue;
             }
             
@@ -194,7 +382,7 @@ impl CodeTextProcessor {
             };
             
             // Apply stemming if enabled and appropriate
-            let processed_text = if self.enable_stemming && token_type == TokenType::Comment {
+            let processed_text = if stemming && token_type == TokenType::Comment {
                 self.stemmer.stem(&normalized).to_string()
             } else {
                 normalized.clone()
@@ -212,7 +400,10 @@ impl CodeTextProcessor {
             };
             
             // Handle camelCase and snake_case splitting
-            let subtokens = self.split_compound_identifier(&processed_text);
+            let mut subtokens = vec![processed_text];
+            if self.split_identifiers {
+                subtokens.extend(self.split_compound_identifier(&composed).into_iter().filter(|part| *part != normalized));
+            }
             
             for subtoken in subtokens {
                 if subtoken.len() >= self.min_term_length && subtoken.len() <= self.max_term_length {
@@ -228,6 +419,23 @@ impl CodeTextProcessor {
             }
         }
         
+        // Kebab-case compounds, whose parts were already emitted as words above
+        if self.split_identifiers && !is_comment {
+            for compound in KEBAB_IDENTIFIER.find_iter(line) {
+                let text = compound.as_str().to_lowercase();
+                if text.len() >= self.min_term_length && text.len() <= self.max_term_length {
+                    tokens.push(ProcessedToken {
+                        text,
+                        original_text: compound.as_str().to_string(),
+                        token_type: TokenType::Identifier,
+                        position: 0,
+                        line_number,
+                        importance_weight: 1.0,
+                    });
+                }
+            }
+        }
+        
         tokens
     }
     
@@ -288,45 +496,39 @@ impl CodeTextProcessor {
         TokenType::Other
     }
     
-    /// Split compound identifiers (camelCase, snake_case, etc.)
+    /// Split compound identifiers (camelCase, PascalCase, snake_case, SCREAMING_SNAKE,
+    /// kebab-case) into lowercase parts, including the whole identifier lowercased.
+    /// `identifier` must still have its original case.
     fn split_compound_identifier(&self, identifier: &str) -> Vec<String> {
-        let mut tokens = Vec::new();
-        
-        // Always add the original identifier
-        tokens.push(identifier.to_string());
-        
-        // Split on underscores
-        if identifier.contains('_') {
-            let parts: Vec<String> = identifier.split('_')
-                .filter(|s| !s.is_empty())
-                .map(|s| s.to_string())
-                .collect();
-            tokens.extend(parts);
+        let mut tokens = vec![identifier.to_lowercase()];
+        for part in identifier.split(['_', '-']).filter(|s| !s.is_empty()) {
+            tokens.extend(self.split_camel_case(part));
         }
         
-        // Split camelCase - ALWAYS try to split
-        let camel_parts = self.split_camel_case(identifier);
-        tokens.extend(camel_parts);
-        
         // Remove duplicates and return
         tokens.sort();
         tokens.dedup();
         tokens
     }
     
-    /// Split camelCase identifiers
+    /// Split camelCase identifiers. A run of capitals is one part, except that its
+    /// last capital starts the next word: `HTTPServer` -> `http`, `server`.
     fn split_camel_case(&self, text: &str) -> Vec<String> {
+        let chars: Vec<char> = text.chars().collect();
         let mut parts = Vec::new();
         let mut current = String::new();
-        let mut prev_was_upper = false;
         
-        for ch in text.chars() {
-            if ch.is_uppercase() && !prev_was_upper && !current.is_empty() {
+        for (i, &ch) in chars.iter().enumerate() {
+            let prev = i.checked_sub(1).map(|p| chars[p]);
+            let next = chars.get(i + 1);
+            let starts_word = ch.is_uppercase()
+                && (prev.is_some_and(|p| p.is_lowercase() || p.is_numeric())
+                    || (prev.is_some_and(char::is_uppercase) && next.is_some_and(|n| n.is_lowercase())));
+            if starts_word && !current.is_empty() {
                 parts.push(current.to_lowercase());
                 current = String::new();
             }
             current.push(ch);
-            prev_was_upper = ch.is_uppercase();
         }
         
         if !current.is_empty() {
@@ -340,7 +542,7 @@ impl CodeTextProcessor {
     fn generate_ngrams(&self, tokens: &[ProcessedToken]) -> Vec<ProcessedToken> {
         let mut ngrams = Vec::new();
         
-        for n in 2..=self.max_ngram_size.min(tokens.len()) {
+        for n in self.min_ngram_size.max(2)..=self.max_ngram_size.min(tokens.len()) {
             for i in 0..tokens.len() - n + 1 {
                 let ngram_text = tokens[i..i + n]
                     .iter()
@@ -369,14 +571,14 @@ impl CodeTextProcessor {
     }
     
     /// Check if a token should be indexed
-    fn should_index_token(&self, token: &ProcessedToken) -> bool {
+    fn should_index_token(&self, token: &ProcessedToken, stop_words: &HashSet<String>) -> bool {
         // Check length constraints
         if token.text.len() < self.min_term_length || token.text.len() > self.max_term_length {
             return false;
         }
         
         // Check if it's a stop word
-        if self.stop_words.contains(&token.text) {
+        if stop_words.contains(&token.text) {
             return false;
         }
         
@@ -389,6 +591,23 @@ impl CodeTextProcessor {
     }
 }
 
+/// Every run of `min..=max` characters in `term`, shortest first, without duplicates.
+/// Lets a search for part of an identifier (`parse` in `tokenparser`) find it.
+pub fn character_ngrams(term: &str, min: usize, max: usize) -> Vec<String> {
+    let chars: Vec<char> = term.chars().collect();
+    let mut seen = HashSet::new();
+    let mut ngrams = Vec::new();
+    for n in min.max(1)..=max.min(chars.len()) {
+        for window in chars.windows(n) {
+            let ngram: String = window.iter().collect();
+            if seen.insert(ngram.clone()) {
+                ngrams.push(ngram);
+            }
+        }
+    }
+    ngrams
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -408,6 +627,28 @@ mod tests {
         assert!(token_texts.contains(&"sum".to_string()));
     }
     
+    #[test]
+    fn test_analyze_reports_parts_stems_and_stop_words() {
+        let processor = CodeTextProcessor::new();
+        let text = "parseHTTPRequests from\nthe_cache";
+        let analyzed = processor.analyze(text);
+        let summary: Vec<(&str, &str, bool)> =
+            analyzed.iter().map(|t| (t.text.as_str(), t.stemmed.as_str(), t.is_stop_word)).collect();
+        assert!(summary.contains(&("parsehttprequests", "parsehttprequest", false)), "{:?}", summary);
+        assert!(summary.contains(&("requests", "request", false)), "{:?}", summary);
+        assert!(summary.contains(&("from", "from", true)), "{:?}", summary);
+        assert!(summary.contains(&("cache", "cach", false)), "{:?}", summary);
+
+        for token in &analyzed {
+            let source = text[token.start..token.end].to_lowercase();
+            assert!(source.contains(&token.text), "{:?}", token);
+        }
+        let from = analyzed.iter().find(|t| t.text == "from").unwrap();
+        assert_eq!((from.start, from.end), (18, 22));
+        // `the_cache` is one word, so its `the` part is no stop word
+        assert!(analyzed.iter().any(|t| t.text == "the_cache" && t.start == 23));
+    }
+
     #[test]
     fn test_camel_case_splitting() {
         let processor = CodeTextProcessor::new();
@@ -428,6 +669,40 @@ mod tests {
         assert!(tokens.contains(&"name".to_string()));
     }
     
+    #[test]
+    fn test_split_identifiers_keeps_compound_and_parts() {
+        let processor = CodeTextProcessor::new();
+        let cases = [
+            ("getUserName", "getusername", &["get", "user", "name"][..]),
+            ("GetUserName", "getusername", &["get", "user", "name"][..]),
+            ("get_user_name", "get_user_name", &["get", "user", "name"][..]),
+            ("MAX_RETRY_COUNT", "max_retry_count", &["max", "retry", "count"][..]),
+            ("get-user-name", "get-user-name", &["get", "user", "name"][..]),
+            ("HTTPServer", "httpserver", &["http", "server"][..]),
+        ];
+        for (code, compound, parts) in cases {
+            let tokens = texts(&processor.tokenize_code(code, Some("rust")));
+            assert!(tokens.contains(&compound.to_string()), "{}: {:?}", code, tokens);
+            for part in parts {
+                assert!(tokens.contains(&part.to_string()), "{}: missing {} in {:?}", code, part, tokens);
+            }
+        }
+    }
+    
+    #[test]
+    fn test_split_identifiers_can_be_disabled() {
+        let processor = CodeTextProcessor::new().with_split_identifiers(false);
+        let tokens = texts(&processor.tokenize_code("getUserName", Some("rust")));
+        assert_eq!(tokens, vec!["getusername"]);
+        
+        let mut config = crate::config::Config::default().search;
+        assert!(config.split_identifiers);
+        config.split_identifiers = false;
+        let processor = CodeTextProcessor::from_search_config(&config);
+        let tokens = texts(&processor.tokenize_code("get_user_name", Some("rust")));
+        assert_eq!(tokens, vec!["get_user_name"]);
+    }
+    
     #[test]
     fn test_comment_detection() {
         let processor = CodeTextProcessor::new();
@@ -437,4 +712,109 @@ mod tests {
         assert!(processor.is_comment_line("/* C-style comment */", Some("c")));
         assert!(!processor.is_comment_line("let x = 5;", Some("rust")));
     }
-}
\ No newline at end of file
+    
+    fn texts(tokens: &[ProcessedToken]) -> Vec<String> {
+        tokens.iter().map(|t| t.text.clone()).collect()
+    }
+    
+    #[test]
+    fn test_language_stop_words_replace_default_list() {
+        let processor = CodeTextProcessor::new()
+            .with_language_stop_words("es", vec!["el".to_string(), "de".to_string()]);
+        
+        let spanish = texts(&processor.tokenize_code("el valor de la cuenta", Some("es")));
+        assert!(!spanish.contains(&"el".to_string()));
+        assert!(!spanish.contains(&"de".to_string()));
+        assert!(spanish.contains(&"valor".to_string()));
+        
+        // English stop words are not applied to Spanish text
+        let mixed = texts(&processor.tokenize_code("the cuenta", Some("es")));
+        assert!(mixed.contains(&"the".to_string()));
+    }
+    
+    #[test]
+    fn test_unknown_language_falls_back_to_default_list() {
+        let processor = CodeTextProcessor::new()
+            .with_language_stop_words("es", vec!["el".to_string()]);
+        let tokens = texts(&processor.tokenize_code("the el value", Some("rust")));
+        assert!(!tokens.contains(&"the".to_string()));
+        assert!(tokens.contains(&"el".to_string()));
+    }
+    
+    #[test]
+    fn test_from_search_config_uses_configured_language() {
+        let mut config = crate::config::Config::default().search;
+        config.bm25_stop_words = vec!["todo".to_string()];
+        config.bm25_stop_words_by_lang.insert("es".to_string(), vec!["los".to_string()]);
+        config.bm25_language = Some("ES".to_string());
+        let processor = CodeTextProcessor::from_search_config(&config);
+        
+        assert!(processor.stop_words_for(None).contains("los"));
+        assert!(processor.stop_words_for(Some("rust")).contains("los"));
+        
+        config.bm25_language = None;
+        let processor = CodeTextProcessor::from_search_config(&config);
+        let fallback = processor.stop_words_for(Some("rust"));
+        assert!(fallback.contains("todo"));
+        assert!(fallback.contains("the"));
+    }
+    
+    /// Treats each `|`-separated cell as one word
+    struct PipeTokenizer;
+    
+    impl Tokenizer for PipeTokenizer {
+        fn tokenize(&self, text: &str) -> Vec<crate::search::tokenizer::Token> {
+            let mut start = 0;
+            text.split('|')
+                .map(|cell| {
+                    let token = crate::search::tokenizer::Token::new(cell, start..start + cell.len());
+                    start += cell.len() + 1;
+                    token
+                })
+                .collect()
+        }
+    }
+    
+    #[test]
+    fn test_custom_tokenizer_changes_indexed_terms() {
+        let processor = CodeTextProcessor::with_config(false, false, 3, 2, 50, vec![])
+            .with_tokenizer(Arc::new(PipeTokenizer));
+        let tokens = texts(&processor.tokenize_code("user id|order total", None));
+        assert!(tokens.contains(&"user id".to_string()));
+        assert!(tokens.contains(&"order total".to_string()));
+        assert!(!tokens.contains(&"user".to_string()));
+        
+        let default_tokens = texts(&CodeTextProcessor::with_config(false, false, 3, 2, 50, vec![])
+            .tokenize_code("user id|order total", None));
+        assert!(default_tokens.contains(&"user".to_string()));
+        assert!(!default_tokens.contains(&"user id".to_string()));
+    }
+    
+    #[test]
+    fn test_ngram_sizes_bound_word_and_character_ngrams() {
+        let processor = CodeTextProcessor::new().with_ngram_sizes(3, 4);
+        let tokens = processor.tokenize_code("alpha beta gamma delta", None);
+        let ngrams: Vec<&str> = tokens.iter().filter(|t| t.original_text.starts_with("ngram_")).map(|t| t.text.as_str()).collect();
+        assert_eq!(ngrams, ["alpha_beta_gamma", "beta_gamma_delta", "alpha_beta_gamma_delta"]);
+        
+        assert_eq!(processor.character_ngrams("parse"), ["par", "ars", "rse", "pars", "arse"]);
+        assert_eq!(character_ngrams("ab", 3, 5), Vec::<String>::new());
+        let disabled = CodeTextProcessor::with_config(false, false, 3, 2, 50, vec![]);
+        assert!(disabled.character_ngrams("parse").is_empty());
+    }
+    
+    #[test]
+    fn test_stemming_override_per_call() {
+        let comment = "// parsing files";
+        let stemmed = CodeTextProcessor::new();
+        assert!(texts(&stemmed.tokenize_code(comment, Some("rust"))).contains(&"pars".to_string()));
+        let unstemmed = texts(&stemmed.tokenize_code_with_options(comment, Some("rust"), &SearchOptions { stemming: Some(false), ..Default::default() }));
+        assert!(unstemmed.contains(&"parsing".to_string()));
+        assert!(!unstemmed.contains(&"pars".to_string()));
+        
+        let exact = CodeTextProcessor::with_config(false, false, 3, 2, 50, vec![]);
+        assert!(texts(&exact.tokenize_code(comment, Some("rust"))).contains(&"parsing".to_string()));
+        let stemmed = texts(&exact.tokenize_code_with_options(comment, Some("rust"), &SearchOptions { stemming: Some(true), ..Default::default() }));
+        assert!(stemmed.contains(&"pars".to_string()));
+    }
+}
\ No newline at end of file
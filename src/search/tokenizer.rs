@@ -0,0 +1,81 @@
+// Pluggable word segmentation for CodeTextProcessor, and through it BM25
+
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// A word cut out of the input, with its byte range in that input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub text: String,
+    pub span: Range<usize>,
+}
+
+impl Token {
+    pub fn new(text: impl Into<String>, span: Range<usize>) -> Self {
+        Self { text: text.into(), span }
+    }
+}
+
+/// Splits text into words. Callers normalize the words (case, stop words,
+/// identifier splitting), so a tokenizer only decides where words start and end.
+pub trait Tokenizer: Send + Sync {
+    fn tokenize(&self, text: &str) -> Vec<Token>;
+}
+
+/// Unicode word boundaries, which keep `snake_case` identifiers whole.
+/// The default for `CodeTextProcessor`, and so for `BM25Engine`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CodeTokenizer;
+
+impl Tokenizer for CodeTokenizer {
+    fn tokenize(&self, text: &str) -> Vec<Token> {
+        text.unicode_word_indices()
+            .map(|(start, word)| Token::new(word, start..start + word.len()))
+            .collect()
+    }
+}
+
+/// Runs of alphanumeric characters; everything else separates words.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AlphanumericTokenizer;
+
+impl Tokenizer for AlphanumericTokenizer {
+    fn tokenize(&self, text: &str) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        let mut start = None;
+        for (i, c) in text.char_indices() {
+            match (c.is_alphanumeric(), start) {
+                (true, None) => start = Some(i),
+                (false, Some(s)) => {
+                    tokens.push(Token::new(&text[s..i], s..i));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(s) = start {
+            tokens.push(Token::new(&text[s..], s..text.len()));
+        }
+        tokens
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spans_point_back_into_the_input() {
+        let text = "let user_id = getUser(42);";
+        for tokenizer in [&CodeTokenizer as &dyn Tokenizer, &AlphanumericTokenizer] {
+            for token in tokenizer.tokenize(text) {
+                assert_eq!(&text[token.span.clone()], token.text);
+            }
+        }
+
+        let words: Vec<String> = CodeTokenizer.tokenize(text).into_iter().map(|t| t.text).collect();
+        assert_eq!(words, ["let", "user_id", "getUser", "42"]);
+        let words: Vec<String> = AlphanumericTokenizer.tokenize(text).into_iter().map(|t| t.text).collect();
+        assert_eq!(words, ["let", "user", "id", "getUser", "42"]);
+    }
+}
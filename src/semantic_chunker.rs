@@ -2,9 +2,152 @@
 // This is the REAL implementation for production use
 
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 use tree_sitter::{Parser, Node, Tree, TreeCursor};
 use std::collections::HashMap;
 
+use crate::chunking::{overlap_chunks, Chunk, ChunkBoundary};
+use crate::symbol_extractor::{SymbolExtractor, SymbolKind};
+
+/// How `IncrementalIndexer` splits code files into chunks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChunkingStrategy {
+    /// Regex-detected starts plus a hard cut every `chunk_size` lines
+    #[default]
+    FixedLines,
+    /// One chunk per top-level definition found by `SymbolExtractor`; only units
+    /// longer than `chunk_size * overshoot_factor` lines are cut by line count
+    SemanticBoundary,
+}
+
+/// Symbol kinds that start a new chunk under `ChunkingStrategy::SemanticBoundary`
+const UNIT_KINDS: &[SymbolKind] = &[
+    SymbolKind::Function,
+    SymbolKind::Class,
+    SymbolKind::Struct,
+    SymbolKind::Enum,
+    SymbolKind::Interface,
+    SymbolKind::Module,
+];
+
+/// Line prefixes that belong to the definition below them (doc comments, attributes, decorators)
+const ATTACHED_PREFIXES: &[&str] = &["//", "/*", "*", "#", "@"];
+
+/// Chunker that keeps top-level definitions whole
+pub struct BoundaryChunker {
+    extractor: SymbolExtractor,
+    chunk_size: usize,
+    overshoot_factor: f32,
+    overlap_lines: usize,
+}
+
+impl BoundaryChunker {
+    /// `chunk_size` is in lines; a unit may grow to `chunk_size * overshoot_factor`
+    /// lines before it is split
+    pub fn new(chunk_size: usize, overshoot_factor: f32) -> Result<Self> {
+        if chunk_size == 0 {
+            anyhow::bail!("chunk_size must be greater than 0");
+        }
+        if !overshoot_factor.is_finite() || overshoot_factor < 1.0 {
+            anyhow::bail!("overshoot_factor must be at least 1.0, got {}", overshoot_factor);
+        }
+        Ok(Self {
+            extractor: SymbolExtractor::new()?,
+            chunk_size,
+            overshoot_factor,
+            overlap_lines: 0,
+        })
+    }
+
+    /// Lines shared between consecutive line-cut pieces; whole units are never widened
+    pub fn with_overlap(mut self, overlap_lines: usize) -> Result<Self> {
+        if overlap_lines >= self.chunk_size {
+            anyhow::bail!("chunk_overlap ({}) must be smaller than chunk_size ({})", overlap_lines, self.chunk_size);
+        }
+        self.overlap_lines = overlap_lines;
+        Ok(self)
+    }
+
+    /// Split `content` at definition boundaries. Languages `SymbolExtractor` can't
+    /// parse, and files without definitions, are cut every `chunk_size` lines.
+    pub fn chunk(&mut self, content: &str, extension: &str) -> Vec<Chunk> {
+        let lines: Vec<&str> = content.lines().collect();
+        if lines.is_empty() {
+            return Vec::new();
+        }
+
+        let starts = match self.extractor.extract(content, extension) {
+            Ok(symbols) => unit_starts(&lines, symbols.iter().map(|s| (s.line - 1, &s.kind))),
+            Err(_) => Vec::new(),
+        };
+        if starts.is_empty() {
+            let chunks = self.split_lines(&lines, 0, lines.len(), ChunkBoundary::Lines);
+            return overlap_chunks(chunks, &lines, self.overlap_lines);
+        }
+
+        let max_unit_lines = (self.chunk_size as f32 * self.overshoot_factor).ceil() as usize;
+        let mut chunks = Vec::new();
+        if starts[0] > 0 {
+            chunks.extend(self.split_lines(&lines, 0, starts[0], ChunkBoundary::Preamble));
+        }
+        for (i, &start) in starts.iter().enumerate() {
+            let end = starts.get(i + 1).copied().unwrap_or(lines.len());
+            if end - start <= max_unit_lines {
+                chunks.push(make_chunk(&lines, start, end, ChunkBoundary::Unit));
+            } else {
+                chunks.extend(self.split_lines(&lines, start, end, ChunkBoundary::PartialUnit));
+            }
+        }
+        overlap_chunks(chunks, &lines, self.overlap_lines)
+    }
+
+    /// Cut `lines[start..end]` into pieces of at most `chunk_size` lines
+    fn split_lines(&self, lines: &[&str], start: usize, end: usize, boundary: ChunkBoundary) -> Vec<Chunk> {
+        (start..end)
+            .step_by(self.chunk_size)
+            .map(|piece_start| make_chunk(lines, piece_start, (piece_start + self.chunk_size).min(end), boundary))
+            .collect()
+    }
+}
+
+/// Chunk over `lines[start..end]`, with the inclusive 0-based end line `Chunk` uses
+fn make_chunk(lines: &[&str], start: usize, end: usize, boundary: ChunkBoundary) -> Chunk {
+    Chunk {
+        content: lines[start..end].join("\n"),
+        start_line: start,
+        end_line: end - 1,
+        boundary,
+    }
+}
+
+/// 0-based first lines of the top-level definitions, moved up over the comments,
+/// attributes and decorators directly above each one
+fn unit_starts<'a>(lines: &[&str], symbols: impl Iterator<Item = (usize, &'a SymbolKind)>) -> Vec<usize> {
+    let mut starts: Vec<usize> = symbols
+        .filter(|(line, kind)| {
+            UNIT_KINDS.contains(kind)
+                && lines.get(*line).is_some_and(|text| !text.starts_with(char::is_whitespace))
+        })
+        .map(|(line, _)| line)
+        .collect();
+    starts.sort_unstable();
+    starts.dedup();
+
+    let mut previous_start = 0;
+    for start in starts.iter_mut() {
+        while *start > previous_start {
+            let above = lines[*start - 1];
+            if !ATTACHED_PREFIXES.iter().any(|prefix| above.starts_with(prefix)) {
+                break;
+            }
+            *start -= 1;
+        }
+        previous_start = *start + 1;
+    }
+    starts
+}
+
 #[derive(Debug, Clone)]
 pub struct SemanticChunk {
     pub content: String,
@@ -761,4 +904,51 @@ More advanced content here.
         
         Ok(())
     }
+    
+    #[test]
+    fn test_boundary_chunker_keeps_definitions_whole() -> Result<()> {
+        let code = "use std::fmt;\n\n/// Adds numbers\n#[inline]\nfn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n\nstruct Point {\n    x: i32,\n}\n";
+        let mut chunker = BoundaryChunker::new(10, 1.5)?;
+        let chunks = chunker.chunk(code, "rs");
+        
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0].boundary, ChunkBoundary::Preamble);
+        assert_eq!(chunks[0].content, "use std::fmt;\n");
+        // The doc comment and attribute stay with the function
+        assert_eq!(chunks[1].boundary, ChunkBoundary::Unit);
+        assert!(chunks[1].content.starts_with("/// Adds numbers\n#[inline]\nfn add"));
+        assert_eq!((chunks[1].start_line, chunks[1].end_line), (2, 7));
+        assert_eq!(chunks[2].boundary, ChunkBoundary::Unit);
+        assert!(chunks[2].content.starts_with("struct Point"));
+        Ok(())
+    }
+    
+    #[test]
+    fn test_boundary_chunker_splits_oversized_units_only() -> Result<()> {
+        let body: String = (0..20).map(|i| format!("    x = {}\n", i)).collect();
+        let code = format!("@cache\ndef big():\n{}\ndef small():\n    return 1\n", body);
+        let mut chunker = BoundaryChunker::new(8, 1.5)?;
+        let chunks = chunker.chunk(&code, "py");
+        
+        let partial: Vec<_> = chunks.iter().filter(|c| c.boundary == ChunkBoundary::PartialUnit).collect();
+        assert_eq!(partial.len(), 3, "23 lines split into pieces of 8");
+        assert!(partial[0].content.starts_with("@cache\ndef big():"));
+        assert!(partial.iter().all(|c| c.end_line - c.start_line < 8));
+        let last = chunks.last().unwrap();
+        assert_eq!(last.boundary, ChunkBoundary::Unit);
+        assert!(last.content.starts_with("def small():"));
+        Ok(())
+    }
+    
+    #[test]
+    fn test_boundary_chunker_falls_back_to_lines() -> Result<()> {
+        let text: String = (0..25).map(|i| format!("line {}\n", i)).collect();
+        let mut chunker = BoundaryChunker::new(10, 1.5)?;
+        let chunks = chunker.chunk(&text, "txt");
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks.iter().all(|c| c.boundary == ChunkBoundary::Lines));
+        assert_eq!((chunks[2].start_line, chunks[2].end_line), (20, 24));
+        assert!(BoundaryChunker::new(10, 0.5).is_err());
+        Ok(())
+    }
 }
\ No newline at end of file
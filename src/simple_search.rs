@@ -1,222 +1,1561 @@
 use anyhow::Result;
-use tantivy::{Index, IndexWriter, schema::{Schema, Field, TEXT, STORED, Value}};
-use tantivy::query::QueryParser;
-use tantivy::collector::TopDocs;
-use std::collections::HashMap;
-
-use crate::simple_storage::{VectorStorage, SearchResult as VectorResult};
-use crate::gguf_embedder::{GGUFEmbedder, GGUFEmbedderConfig};
-use crate::embedding_prefixes::EmbeddingTask;
-// BM25Engine and BM25Match temporarily removed
-// FusionConfig and MatchType temporarily removed
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use futures_util::stream::{self, Stream, StreamExt};
+
+use crate::simple_storage::{cosine_similarity, open_vector_store, EmbeddingRecord, VectorStore, SearchResult as VectorResult};
+use crate::cache::{BoundedCache, CacheStats};
+use crate::gguf_embedder::{GGUFEmbedder, GGUFEmbedderConfig, CODE_MODEL_PATH, TEXT_MODEL_PATH};
+use crate::embedder::Embedder;
+use crate::embedding_prefixes::{CodeFormatter, EmbeddingTask};
+use crate::error::{SearchError, StorageError};
+use crate::config::{Config, SearchBackend, SemanticMode};
+use crate::indexer::GENERATED_METADATA_KEY;
+use crate::search::bm25_fixed::BM25Stats;
+use crate::search::fusion::{dedup_similar, ComponentScores, FusionConfig, GroupScore, MatchType};
+use crate::search::snippet::{first_match_line, highlight_snippet};
+use crate::search::{CodeTextProcessor, SearchOptions};
+use crate::markdown_metadata_extractor::{is_markdown_path, match_heading_path, HEADING_BREADCRUMB_SEPARATOR};
+use crate::query_parser::parse_query;
+use crate::utils::{content_preview_with_marker, is_blank};
+use crate::symbol_extractor::{SymbolExtractor, SymbolKind};
+use crate::search::text_backend::{TantivyText, TextBackend};
+use crate::search::fuzzy::fuzzy_text_search;
+use crate::index_pipeline::available_memory_mb;
+// MatchType temporarily removed
 // ChunkContext and Chunk temporarily removed
-// BoundedCache temporarily removed
+
+pub use crate::archive::{ArchiveManifest, ARCHIVE_FORMAT_VERSION};
+pub use crate::index_pipeline::{IndexReport, INDEX_BATCH_SIZE};
+pub use crate::search::fuzzy::{FuzzyConfig, MAX_FUZZY_DISTANCE};
+pub use crate::search::text_backend::{TantivyStats, DEFAULT_BM25_INDEX_FILE};
+
+/// Keeps the candidates whose file path (or document id) it accepts
+type CandidateFilter<'a> = &'a dyn Fn(&str) -> bool;
 
 /// Simple hybrid search combining LanceDB + Tantivy
 pub struct HybridSearch {
-    vector_storage: VectorStorage,
-    text_index: Index,
-    text_writer: IndexWriter,
-    text_embedder: GGUFEmbedder,
-    code_embedder: GGUFEmbedder,
-    
-    // Schema fields
-    content_field: Field,
-    path_field: Field,
+    pub(crate) vector_storage: Box<dyn VectorStore>,
+    pub(crate) text_backend: TextBackend,
+    /// `None` when running keyword-only
+    pub(crate) embedders: Option<Embedders>,
+    fusion_config: FusionConfig,
+    /// Metadata of documents added through `index_documents`, keyed by id
+    pub(crate) document_metadata: HashMap<String, HashMap<String, String>>,
+    pub(crate) metadata_path: PathBuf,
+    /// Results of recent searches; emptied whenever the indexes change.
+    /// `None` when `search_cache_size` is 0.
+    result_cache: Option<BoundedCache<ResultCacheKey, Vec<SearchResult>>>,
+    /// `Config::error_on_empty_query`
+    error_on_empty_query: bool,
+    /// `Some(db_path)` when opened with `open_read_only`
+    read_only: Option<String>,
+    /// Available memory in MB as seen by `index_directory`, `available_memory_mb`
+    /// outside tests
+    pub(crate) memory_probe: fn() -> Option<u64>,
+    /// Phase durations of the search in progress, with `FusionConfig::collect_timings`
+    timings: parking_lot::Mutex<Option<SearchTimings>>,
+    last_timings: parking_lot::Mutex<Option<SearchTimings>>,
+}
+
+/// What a cached result list was computed for
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ResultCacheKey {
+    /// The query with runs of whitespace collapsed to one space
+    query: String,
+    limit: usize,
+    /// Hash of the path or metadata filter; 0 for an unfiltered search
+    filter_hash: u64,
+}
+
+impl ResultCacheKey {
+    fn new(query: &str, limit: usize, filter_hash: u64) -> Self {
+        Self { query: query.split_whitespace().collect::<Vec<_>>().join(" "), limit, filter_hash }
+    }
+}
+
+/// A vector store failure as the `SearchError::Storage` the search API returns
+pub(crate) fn storage_failure(err: anyhow::Error) -> anyhow::Error {
+    match err.downcast::<StorageError>() {
+        Ok(storage) => SearchError::from(storage).into(),
+        Err(err) => err,
+    }
+}
+
+/// `filter_hash` for a `ResultCacheKey`; `kind` keeps path and metadata filters apart
+fn filter_hash(kind: &str, filter: &impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    kind.hash(&mut hasher);
+    filter.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// File under the db path holding `Document::metadata`
+pub const DOCUMENT_METADATA_FILE: &str = "document_metadata.json";
+
+/// Document metadata key holding the modification time, in seconds since the Unix
+/// epoch, of a file indexed from disk; read by `FusionConfig::recency_boost`
+pub const MTIME_METADATA_KEY: &str = "mtime";
+
+/// A piece of text with no file behind it, e.g. a database row or a chat message.
+/// `id` takes the place of the file path: it is the key re-indexing replaces and the
+/// `file_path` of its search results.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Document {
+    pub id: String,
+    pub text: String,
+    /// Stored with the document and returned on `SearchResult::metadata`
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+}
+
+/// Text model for markdown and queries, code model for source files
+#[derive(Clone)]
+pub(crate) struct Embedders {
+    pub(crate) text: Arc<dyn Embedder>,
+    pub(crate) code: Arc<dyn Embedder>,
+    /// Model files the embedders were loaded from, recorded in archives;
+    /// `None` for embedders passed to `with_embedders`
+    pub(crate) model: Option<String>,
+    /// `IndexingConfig::embedding_languages`, applied to documents before embedding
+    languages: BTreeMap<String, String>,
+}
+
+impl Embedders {
+    /// Document embeddings in input order, with one `embed_batch` call per model:
+    /// the code model for source files, the text model for everything else. Each file is
+    /// formatted for its language (`CodeFormatter::format_for_path`) first. When a
+    /// batch fails its files are embedded one at a time, so only the files that
+    /// can't be embedded end up with `SearchError::FileEmbed`.
+    pub(crate) fn embed_documents(&self, contents: &[String], file_paths: &[String]) -> Vec<std::result::Result<Vec<f32>, SearchError>> {
+        let inputs: Vec<String> = contents
+            .iter()
+            .zip(file_paths)
+            .map(|(content, path)| CodeFormatter::format_for_path(content, path, &self.languages))
+            .collect();
+        let mut embeddings: Vec<_> = contents.iter().map(|_| Ok(Vec::new())).collect();
+        for (embedder, code) in [(&self.code, true), (&self.text, false)] {
+            let positions: Vec<usize> = (0..contents.len())
+                .filter(|&i| is_code_path(&file_paths[i]) == code)
+                .collect();
+            if positions.is_empty() {
+                continue;
+            }
+            let texts = positions.iter().map(|&i| inputs[i].clone()).collect();
+            match embedder.embed_batch(texts, DOCUMENT_TASK) {
+                Ok(batch) => {
+                    for (i, embedding) in positions.into_iter().zip(batch) {
+                        embeddings[i] = Ok(embedding);
+                    }
+                }
+                Err(_) => {
+                    for i in positions {
+                        embeddings[i] = embedder.embed(&inputs[i], DOCUMENT_TASK).map_err(|e| SearchError::FileEmbed {
+                            path: file_paths[i].clone(),
+                            reason: format!("{:#}", e),
+                        });
+                    }
+                }
+            }
+        }
+        embeddings
+    }
+}
+
+fn is_code_path(path: &str) -> bool {
+    path.ends_with(".rs") || path.ends_with(".py") || path.ends_with(".js") ||
+    path.ends_with(".ts") || path.ends_with(".go") || path.ends_with(".java") ||
+    path.ends_with(".cpp") || path.ends_with(".c") || path.ends_with(".h")
+}
+
+const SECONDS_PER_DAY: f32 = 86_400.0;
+
+/// RRF weight of each side when both run; keyword-only search gives the text side
+/// both, so fused scores and `min_score` mean the same thing either way
+const VECTOR_RRF_WEIGHT: f32 = 1.0;
+const TEXT_RRF_WEIGHT: f32 = 1.0;
+
+/// What `HybridSearch::clear_backend` drops
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClearTarget {
+    /// Everything, as `HybridSearch::clear`
+    All,
+    /// The Tantivy index, when it is the keyword backend
+    Tantivy,
+    /// The BM25 index, when it is the keyword backend
+    Bm25,
+    /// Stored embeddings; search falls back to keyword results until re-indexed
+    Vectors,
+    /// The embedders' in-memory caches; nothing indexed is touched
+    EmbeddingCache,
 }
 
-#[derive(Debug, Clone)]
+impl std::fmt::Display for ClearTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClearTarget::All => write!(f, "all"),
+            ClearTarget::Tantivy => write!(f, "tantivy"),
+            ClearTarget::Bm25 => write!(f, "bm25"),
+            ClearTarget::Vectors => write!(f, "vectors"),
+            ClearTarget::EmbeddingCache => write!(f, "embedding_cache"),
+        }
+    }
+}
+
+impl std::str::FromStr for ClearTarget {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().replace('-', "_").as_str() {
+            "all" => Ok(ClearTarget::All),
+            "tantivy" => Ok(ClearTarget::Tantivy),
+            "bm25" => Ok(ClearTarget::Bm25),
+            "vectors" => Ok(ClearTarget::Vectors),
+            "embedding_cache" => Ok(ClearTarget::EmbeddingCache),
+            other => Err(anyhow::anyhow!(
+                "Unknown clear target '{}': expected 'all', 'tantivy', 'bm25', 'vectors' or 'embedding_cache'",
+                other
+            )),
+        }
+    }
+}
+
+/// Nomic models are trained asymmetrically: queries carry `search_query: ` and the
+/// indexed side `search_document: `, whichever of the two models embeds it
+const QUERY_TASK: EmbeddingTask = EmbeddingTask::SearchQuery;
+pub(crate) const DOCUMENT_TASK: EmbeddingTask = EmbeddingTask::SearchDocument;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct SearchResult {
     pub content: String,
     pub file_path: String,
     pub score: f32,
     pub match_type: String,
+    /// Excerpt of `content` around the best query match, matches wrapped in
+    /// `FusionConfig::highlight_marker`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+    /// 1-based line of the first query match; indexed documents are whole files,
+    /// so this is the line within `file_path`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line_number: Option<usize>,
+    /// Markdown only: headings enclosing the match, e.g. `Installation > Linux`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heading_path: Option<String>,
+    /// Each backend's RRF contribution to `score` before heading, feedback and
+    /// recency boosts; set only when `FusionConfig::explain` is
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub component_scores: Option<ComponentScores>,
+    /// `Document::metadata` for results from `HybridSearch::index_documents`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty", serialize_with = "serialize_sorted")]
+    pub metadata: HashMap<String, String>,
+}
+
+/// The `MatchType` a fused result's `match_type` stands for in `FusionConfig::break_ties`:
+/// vector results are semantic, keyword and fuzzy results lexical, and hybrid ones both
+fn fused_match_type(match_type: &str) -> Option<MatchType> {
+    match match_type {
+        "vector" => Some(MatchType::Semantic),
+        "hybrid" => None,
+        _ => Some(MatchType::Statistical),
+    }
+}
+
+/// A map serialized with its keys in order, so equal results serialize identically
+fn serialize_sorted<S: serde::Serializer>(map: &HashMap<String, String>, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    serializer.collect_map(map.iter().collect::<BTreeMap<_, _>>())
+}
+
+/// Glob patterns restricting search results by `file_path`.
+/// A result is kept when it matches any `include` pattern (or `include` is empty)
+/// and no `exclude` pattern.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct PathFilter {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl PathFilter {
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    fn compile(&self) -> std::result::Result<PathMatcher, SearchError> {
+        Ok(PathMatcher {
+            include: build_glob_set("include", &self.include)?,
+            exclude: build_glob_set("exclude", &self.exclude)?,
+            has_include: !self.include.is_empty(),
+        })
+    }
+}
+
+fn build_glob_set(parameter: &str, patterns: &[String]) -> std::result::Result<GlobSet, SearchError> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern).map_err(|e| SearchError::InvalidParameter {
+            parameter: parameter.to_string(),
+            reason: format!("invalid glob '{}': {}", pattern, e),
+        })?;
+        builder.add(glob);
+    }
+    builder.build().map_err(|e| SearchError::InvalidParameter {
+        parameter: parameter.to_string(),
+        reason: e.to_string(),
+    })
+}
+
+struct PathMatcher {
+    include: GlobSet,
+    exclude: GlobSet,
+    has_include: bool,
+}
+
+impl PathMatcher {
+    fn is_match(&self, file_path: &str) -> bool {
+        // Indexed paths are often relative to the working directory ("./src/lib.rs")
+        let path = file_path.strip_prefix("./").unwrap_or(file_path);
+        (!self.has_include || self.include.is_match(path)) && !self.exclude.is_match(path)
+    }
+}
+
+/// Conditions on `SearchResult::metadata` for `HybridSearch::search_with_metadata_filter`.
+/// A result passes when every condition holds; a missing key fails any condition on it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct MetadataFilter {
+    pub conditions: Vec<MetadataCondition>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetadataCondition {
+    /// `metadata[key] == value`
+    Equals { key: String, value: String },
+    /// `metadata[key]` is one of `values`
+    In { key: String, values: BTreeSet<String> },
+}
+
+impl MetadataFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also require `metadata[key] == value`
+    pub fn equals(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.conditions.push(MetadataCondition::Equals { key: key.into(), value: value.into() });
+        self
+    }
+
+    /// Also require `metadata[key]` to be one of `values`
+    pub fn one_of<I, S>(mut self, key: impl Into<String>, values: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let values = values.into_iter().map(Into::into).collect();
+        self.conditions.push(MetadataCondition::In { key: key.into(), values });
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.conditions.is_empty()
+    }
+
+    pub fn matches(&self, metadata: &HashMap<String, String>) -> bool {
+        self.conditions.iter().all(|condition| match condition {
+            MetadataCondition::Equals { key, value } => metadata.get(key) == Some(value),
+            MetadataCondition::In { key, values } => metadata.get(key).is_some_and(|v| values.contains(v)),
+        })
+    }
+}
+
+/// Results ranked per requested one by searches that remove results after fusion:
+/// `HybridSearch::search_query` with `symbol:` scopes and `SearchOptions::symbol_kinds`
+const SYMBOL_SCOPE_OVERFETCH: usize = 4;
+
+/// The `results` defining a symbol of one of `kinds` named by a word of `query`
+/// (case-insensitively), with `line_number` and `snippet` moved to that definition.
+/// Files `SymbolExtractor` can't parse define nothing.
+fn restrict_to_symbol_kinds(results: Vec<SearchResult>, query: &str, kinds: &[SymbolKind]) -> Result<Vec<SearchResult>> {
+    let words: Vec<String> = query
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    let mut extractor = SymbolExtractor::new()?;
+    let mut kept = Vec::new();
+    for mut result in results {
+        let extension = Path::new(&result.file_path).extension().and_then(|ext| ext.to_str()).unwrap_or("");
+        let Ok(symbols) = extractor.extract(&result.content, extension) else { continue };
+        let definition = symbols.into_iter().find(|symbol| {
+            // Methods are named `Owner.method`
+            let name = symbol.name.rsplit('.').next().unwrap_or(&symbol.name).to_lowercase();
+            kinds.contains(&symbol.kind) && words.contains(&name)
+        });
+        if let Some(symbol) = definition {
+            result.line_number = Some(symbol.line);
+            result.snippet = Some(symbol.definition);
+            kept.push(result);
+        }
+    }
+    Ok(kept)
+}
+
+/// Multiplier applied to the fused score of paths in `SearchFeedback::boosted_paths`
+pub const FEEDBACK_BOOST: f32 = 2.0;
+
+/// Relevance feedback from an interactive session: "more like this file" and
+/// "never show this one". Applies only to searches made through
+/// `HybridSearch::with_feedback` and is never persisted.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SearchFeedback {
+    /// Paths whose fused score is multiplied by `FEEDBACK_BOOST`
+    pub boosted_paths: Vec<String>,
+    /// Paths removed from the candidates before fusion and `limit`
+    pub suppressed_paths: Vec<String>,
+}
+
+impl SearchFeedback {
+    pub fn is_empty(&self) -> bool {
+        self.boosted_paths.is_empty() && self.suppressed_paths.is_empty()
+    }
+
+    fn is_boosted(&self, file_path: &str) -> bool {
+        contains_path(&self.boosted_paths, file_path)
+    }
+
+    fn is_suppressed(&self, file_path: &str) -> bool {
+        contains_path(&self.suppressed_paths, file_path)
+    }
+}
+
+/// Whether `paths` lists `file_path`, ignoring a leading "./" on either side
+fn contains_path(paths: &[String], file_path: &str) -> bool {
+    let file_path = file_path.strip_prefix("./").unwrap_or(file_path);
+    paths.iter().any(|path| path.strip_prefix("./").unwrap_or(path) == file_path)
+}
+
+/// A `HybridSearch` borrowed together with the `SearchFeedback` its searches apply
+pub struct FeedbackSearch<'a> {
+    search: &'a HybridSearch,
+    feedback: &'a SearchFeedback,
+}
+
+impl FeedbackSearch<'_> {
+    /// Hybrid search with suppressed paths filtered out before fusion, as in
+    /// `HybridSearch::search_filtered`, and boosted paths scored higher
+    pub async fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchResult>> {
+        if self.search.is_blank_query(query)? {
+            return Ok(Vec::new());
+        }
+        let query_embedding = self.search.embed_query(query)?;
+        let keep = |path: &str| !self.feedback.is_suppressed(path);
+        let filter: Option<CandidateFilter> = if self.feedback.suppressed_paths.is_empty() { None } else { Some(&keep) };
+        let mut results = self.search.search_embedded(query, query_embedding, limit, filter, Some(self.feedback))?;
+        self.search.cap_content(&mut results);
+        Ok(results)
+    }
+}
+
+/// Where the time of a search went, see `HybridSearch::last_search_timings`. Phases
+/// that didn't run, such as `embed_query` when keyword-only or the text backend not in
+/// use, stay zero.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchTimings {
+    pub embed_query: Duration,
+    pub bm25_search: Duration,
+    pub tantivy_search: Duration,
+    pub semantic_search: Duration,
+    /// RRF fusion and snippet annotation
+    pub fuse: Duration,
+    /// The whole search, including the phases, the result cache lookup and any
+    /// filtering after fusion. A search answered from the cache runs no phase.
+    pub total: Duration,
+}
+
+impl SearchTimings {
+    /// Each phase with the name of its tracing span
+    pub fn phases(&self) -> [(&'static str, Duration); 5] {
+        [
+            ("embed_query", self.embed_query),
+            ("bm25_search", self.bm25_search),
+            ("tantivy_search", self.tantivy_search),
+            ("semantic_search", self.semantic_search),
+            ("fuse", self.fuse),
+        ]
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum SearchPhase {
+    EmbedQuery,
+    Bm25Search,
+    TantivySearch,
+    SemanticSearch,
+    Fuse,
+}
+
+/// How `HybridSearch::search_grouped` collapses results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupBy {
+    /// Every chunk hit is an entry of its own
+    #[default]
+    None,
+    /// One entry per file holding all of its chunk hits
+    File,
+}
+
+impl std::fmt::Display for GroupBy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            GroupBy::None => "none",
+            GroupBy::File => "file",
+        })
+    }
+}
+
+impl std::str::FromStr for GroupBy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(GroupBy::None),
+            "file" => Ok(GroupBy::File),
+            other => Err(anyhow::anyhow!("Unknown grouping '{}': expected 'none' or 'file'", other)),
+        }
+    }
+}
+
+/// Chunk hits from one file, as returned by `HybridSearch::search_grouped`
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct GroupedResult {
+    pub file_path: String,
+    /// Score of the best chunk hit
+    pub best_score: f32,
+    /// What the groups are ranked by: `best_score`, or the sum of the hits' scores
+    /// with `GroupScore::Sum`
+    pub score: f32,
+    /// Best first
+    pub chunk_hits: Vec<SearchResult>,
+}
+
+/// Collapse `ranked` (best first) into groups per `group_by`, best group first
+fn group_results(ranked: Vec<SearchResult>, group_by: GroupBy, group_score: GroupScore) -> Vec<GroupedResult> {
+    let mut groups: Vec<GroupedResult> = Vec::new();
+    let mut group_of_path: HashMap<String, usize> = HashMap::new();
+    for result in ranked {
+        let existing = match group_by {
+            GroupBy::None => None,
+            GroupBy::File => group_of_path.get(&result.file_path).copied(),
+        };
+        match existing {
+            Some(index) => groups[index].chunk_hits.push(result),
+            None => {
+                group_of_path.insert(result.file_path.clone(), groups.len());
+                groups.push(GroupedResult {
+                    file_path: result.file_path.clone(),
+                    best_score: result.score,
+                    score: result.score,
+                    chunk_hits: vec![result],
+                });
+            }
+        }
+    }
+    if group_score == GroupScore::Sum {
+        for group in &mut groups {
+            group.score = group.chunk_hits.iter().map(|hit| hit.score).sum();
+        }
+    }
+    groups.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.file_path.cmp(&b.file_path))
+    });
+    groups
+}
+
+/// A page of results from `HybridSearch::search_paged`
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PagedResults {
+    pub results: Vec<SearchResult>,
+    /// Results in the whole ranking, after filtering and deduplication
+    pub total_matched: usize,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+/// Snapshot of index health, see `HybridSearch::stats`
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct IndexStats {
+    pub backend: SearchBackend,
+    /// Documents in the keyword index
+    pub documents: usize,
+    /// Embeddings in the vector store, saved under `<db_path>/vector_store.json`
+    pub vector_count: usize,
+    pub vector_dimension: Option<usize>,
+    /// Set when the keyword backend is Tantivy
+    pub tantivy: Option<TantivyStats>,
+    /// Set when the keyword backend is BM25
+    pub bm25: Option<BM25Stats>,
+    /// Size of the saved BM25 index file; set when the keyword backend is BM25
+    pub bm25_disk_bytes: Option<u64>,
+}
+
+/// How `HybridSearch::open_or_rebuild` got its Tantivy index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OpenOutcome {
+    /// The existing index passed validation
+    Opened,
+    /// The index failed to open or validate, for the given reason, and was rebuilt
+    /// from the source tree
+    Rebuilt(String),
 }
 
 impl HybridSearch {
     pub async fn new(db_path: &str) -> Result<Self> {
-        // Initialize vector storage
-        let vector_storage = VectorStorage::new(db_path)?;
-        
-        // Initialize Tantivy for full-text search
-        let mut schema_builder = Schema::builder();
-        let content_field = schema_builder.add_text_field("content", TEXT | STORED);
-        let path_field = schema_builder.add_text_field("path", TEXT | STORED);
-        let schema = schema_builder.build();
-        
-        // Open existing index or create new persistent disk-based index
-        let index_path = format!("{}/tantivy_index", db_path);
-        std::fs::create_dir_all(&index_path)?;
-        let text_index = if std::path::Path::new(&format!("{}/meta.json", index_path)).exists() {
-            Index::open_in_dir(&index_path)?
-        } else {
-            Index::create_in_dir(&index_path, schema)?
+        Self::with_backend(db_path, SearchBackend::default()).await
+    }
+
+    /// Create a hybrid search whose keyword side uses the given backend
+    pub async fn with_backend(db_path: &str, backend: SearchBackend) -> Result<Self> {
+        let mut config = Config::default();
+        config.search.backend = backend;
+        Self::with_config(db_path, &config).await
+    }
+
+    /// Create a hybrid search with the given `SemanticMode` and otherwise default config
+    pub async fn with_semantic_mode(db_path: &str, mode: SemanticMode) -> Result<Self> {
+        let mut config = Config::default();
+        config.search.semantic_mode = mode;
+        Self::with_config(db_path, &config).await
+    }
+
+    /// Create a hybrid search using the keyword backend, vector store, BM25 index location,
+    /// embedding retry policy and semantic mode from `config`
+    pub async fn with_config(db_path: &str, config: &Config) -> Result<Self> {
+        Self::open(db_path, config, Self::configured_embedders(config)?, false)
+    }
+
+    /// `with_config` for searching only. The Tantivy index must already exist and is
+    /// opened without its writer lock, so any number of read-only searches can share
+    /// an index while one process writes it. `index`, `index_directory`,
+    /// `index_documents`, `clear` and `import_archive` fail with `SearchError::ReadOnly`.
+    pub async fn open_read_only(db_path: &str, config: &Config) -> Result<Self> {
+        Self::open(db_path, config, Self::configured_embedders(config)?, true)
+    }
+
+    /// The embedders `config.search.semantic_mode` asks for
+    fn configured_embedders(config: &Config) -> Result<Option<Embedders>> {
+        let embedders = match config.search.semantic_mode {
+            SemanticMode::Required => Some(Self::load_embedders(config)?),
+            SemanticMode::Optional => match Self::load_embedders(config) {
+                Ok(embedders) => Some(embedders),
+                // A model of the wrong size is a config error, not an unavailable model
+                Err(e) if matches!(e.downcast_ref::<SearchError>(), Some(SearchError::DimensionMismatch { .. })) => return Err(e),
+                Err(e) => {
+                    log::warn!("Embedding model unavailable, searching by keyword only: {}", e);
+                    None
+                }
+            },
+            SemanticMode::Disabled => None,
         };
-        let text_writer = text_index.writer(50_000_000)?; // 50MB heap
-        
+        Ok(embedders)
+    }
+
+    /// `with_config`, but a Tantivy index that fails validation on open is deleted and
+    /// rebuilt from the files under `source_root` instead of serving wrong results.
+    /// Only the keyword index is rebuilt; `index` the tree again to restore vectors.
+    /// With the BM25 backend there is no Tantivy index and this always reports `Opened`.
+    pub async fn open_or_rebuild(db_path: &str, config: &Config, source_root: &Path) -> Result<(Self, OpenOutcome)> {
+        let outcome = match config.search_backend() {
+            SearchBackend::Tantivy => TantivyText::open_or_rebuild(db_path, source_root, config)?.1,
+            SearchBackend::Bm25Only => OpenOutcome::Opened,
+        };
+        // The probe above has dropped its writer, so the index lock is free again
+        Ok((Self::with_config(db_path, config).await?, outcome))
+    }
+
+    fn load_embedders(config: &Config) -> Result<Embedders> {
+        let retry = config.embedding_retry.to_retry_config();
+
         // Initialize text embedder for markdown
         let text_config = GGUFEmbedderConfig {
-            model_path: "./src/model/nomic-embed-text-v1.5.Q4_K_M.gguf".to_string(),
+            model_path: TEXT_MODEL_PATH.to_string(),
+            retry: retry.clone(),
+            expected_dimension: Some(config.embedding_dimensions),
             ..Default::default()
         };
         let text_embedder = GGUFEmbedder::new(text_config)?;
         
         // Initialize code embedder for code files
         let code_config = GGUFEmbedderConfig {
-            model_path: "./src/model/nomic-embed-code.Q4_K_M.gguf".to_string(),
+            model_path: CODE_MODEL_PATH.to_string(),
+            retry,
+            expected_dimension: Some(config.embedding_dimensions),
             ..Default::default()
         };
         let code_embedder = GGUFEmbedder::new(code_config)?;
 
+        let file_name = |path: &str| Path::new(path).file_name().map_or_else(String::new, |f| f.to_string_lossy().into_owned());
+        Ok(Embedders {
+            text: Arc::new(text_embedder),
+            code: Arc::new(code_embedder),
+            model: Some(format!("{} + {}", file_name(TEXT_MODEL_PATH), file_name(CODE_MODEL_PATH))),
+            languages: config.indexing.embedding_languages.clone(),
+        })
+    }
+
+    /// Create a hybrid search from `config` that embeds with the given models instead of
+    /// loading the GGUF files, e.g. `MinimalEmbedder` where no model is available
+    pub async fn with_embedders(
+        db_path: &str,
+        config: &Config,
+        text_embedder: Box<dyn Embedder>,
+        code_embedder: Box<dyn Embedder>,
+    ) -> Result<Self> {
+        let embedders = Embedders {
+            text: Arc::from(text_embedder),
+            code: Arc::from(code_embedder),
+            model: None,
+            languages: config.indexing.embedding_languages.clone(),
+        };
+        Self::open(db_path, config, Some(embedders), false)
+    }
+
+    fn open(db_path: &str, config: &Config, embedders: Option<Embedders>, read_only: bool) -> Result<Self> {
+        let bm25_index_path = config.storage.bm25_index_path.as_deref();
+
+        // Initialize vector storage
+        let vector_storage = open_vector_store(config.storage_backend(), config.vector_quantization, db_path).map_err(storage_failure)?;
+
+        // Initialize the keyword backend (Tantivy or BM25 reloaded from disk)
+        let mut text_backend = TextBackend::open(db_path, config.search_backend(), bm25_index_path, read_only)?;
+        text_backend.set_text_processor(CodeTextProcessor::from_search_config(&config.search));
+        text_backend.set_filename_boost(config.search.bm25_filename_boost);
+        text_backend.set_tf_variant(config.search.bm25_tf_variant);
+        text_backend.set_ngram_sizes(config.search.ngram_sizes());
+        text_backend.set_stemming(config.search.enable_stemming);
+
+        let metadata_path = Path::new(db_path).join(DOCUMENT_METADATA_FILE);
+        let document_metadata = if metadata_path.exists() {
+            serde_json::from_str(&std::fs::read_to_string(&metadata_path)?)?
+        } else {
+            HashMap::new()
+        };
+        let result_cache = match config.search.search_cache_size {
+            0 => None,
+            capacity => Some(BoundedCache::new(capacity)?),
+        };
+
         Ok(Self {
             vector_storage,
-            text_index,
-            text_writer,
-            text_embedder,
-            code_embedder,
-            content_field,
-            path_field,
+            text_backend,
+            embedders,
+            fusion_config: FusionConfig { weights: config.search.fusion_weights(), ..FusionConfig::default() },
+            document_metadata,
+            metadata_path,
+            result_cache,
+            error_on_empty_query: config.error_on_empty_query,
+            read_only: read_only.then(|| db_path.to_string()),
+            memory_probe: available_memory_mb,
+            timings: parking_lot::Mutex::new(None),
+            last_timings: parking_lot::Mutex::new(None),
         })
     }
 
-    /// Index documents in both vector and text indices with appropriate embedders
-    pub async fn index(&mut self, contents: Vec<String>, file_paths: Vec<String>) -> Result<()> {
-        // Generate embeddings with appropriate embedder for each file
-        let mut embeddings = Vec::new();
-        for (content, path) in contents.iter().zip(file_paths.iter()) {
-            // Determine embedder and task based on file extension
-            let (embedder, task) = if path.ends_with(".md") || path.ends_with(".markdown") {
-                (&self.text_embedder, EmbeddingTask::SearchDocument)
-            } else if path.ends_with(".rs") || path.ends_with(".py") || path.ends_with(".js") || 
-                      path.ends_with(".ts") || path.ends_with(".go") || path.ends_with(".java") || 
-                      path.ends_with(".cpp") || path.ends_with(".c") || path.ends_with(".h") {
-                (&self.code_embedder, EmbeddingTask::CodeDefinition)
-            } else {
-                (&self.text_embedder, EmbeddingTask::SearchDocument)
-            };
-            
-            let embedding = embedder.embed(content, task)?;
-            embeddings.push(embedding);
+    pub(crate) fn check_writable(&self) -> Result<()> {
+        match &self.read_only {
+            Some(path) => Err(SearchError::ReadOnly { path: path.clone() }.into()),
+            None => Ok(()),
         }
-        
-        // Store in vector database
-        self.vector_storage.store(contents.clone(), embeddings, file_paths.clone())?;
-        
-        // Store in text index
-        for (content, path) in contents.iter().zip(file_paths.iter()) {
-            let mut doc = tantivy::doc!();
-            doc.add_text(self.content_field, content);
-            doc.add_text(self.path_field, path);
-            self.text_writer.add_document(doc)?;
+    }
+
+    /// Whether the embedding models are loaded; `false` means keyword-only search
+    pub fn is_semantic_enabled(&self) -> bool {
+        self.embedders.is_some()
+    }
+
+    pub fn fusion_config(&self) -> &FusionConfig {
+        &self.fusion_config
+    }
+
+    pub fn set_fusion_config(&mut self, config: FusionConfig) {
+        self.invalidate_result_cache();
+        self.fusion_config = config;
+    }
+
+    /// Re-apply the settings of `config` that can change while the index is open, as
+    /// after `Config::reload`: the fusion weights. Backend, storage and tokenization
+    /// settings only take effect when the index is reopened.
+    pub fn apply_config(&mut self, config: &Config) {
+        let weights = config.search.fusion_weights();
+        if weights != self.fusion_config.weights {
+            self.set_fusion_config(FusionConfig { weights, ..self.fusion_config.clone() });
         }
-        self.text_writer.commit()?;
+    }
 
+    /// Commit anything still pending in the text index and release its writer. Dropping
+    /// a `HybridSearch` rolls pending documents back instead, so relying on `Drop` alone
+    /// may lose the last uncommitted batch of a cancelled `index_directory`.
+    pub async fn close(self) -> Result<()> {
+        self.text_backend.close()
+    }
+
+    pub(crate) fn save_document_metadata(&self) -> Result<()> {
+        if self.document_metadata.is_empty() && !self.metadata_path.exists() {
+            return Ok(());
+        }
+        std::fs::write(&self.metadata_path, serde_json::to_string(&self.document_metadata)?)?;
         Ok(())
     }
 
-    /// Hybrid search with simple RRF fusion (uses text embedder for queries)
+    /// Hybrid search with simple RRF fusion (uses text embedder for queries).
+    /// Repeating a search before the indexes change is answered from the result cache.
     pub async fn search(&mut self, query: &str, limit: usize) -> Result<Vec<SearchResult>> {
-        // Vector search - use text embedder for search queries
-        // We use text embedder as queries are natural language
-        let query_embedding = self.text_embedder.embed(query, EmbeddingTask::SearchQuery)?;
-        let vector_results = self.vector_storage.search(query_embedding, limit * 2)?;
-        
-        // Text search
-        let text_results = self.text_search(query, limit * 2)?;
-        
-        // Simple RRF fusion
-        let fused_results = self.simple_rrf_fusion(vector_results, text_results, limit);
-        
-        Ok(fused_results)
+        if self.is_blank_query(query)? {
+            return Ok(Vec::new());
+        }
+        self.cached_search(ResultCacheKey::new(query, limit, 0), |search| {
+            // Vector search - use text embedder for search queries
+            // We use text embedder as queries are natural language
+            let query_embedding = search.embed_query(query)?;
+            search.search_embedded(query, query_embedding, limit, None, None)
+        })
     }
 
-    fn text_search(&self, query: &str, limit: usize) -> Result<Vec<SearchResult>> {
-        // Create reader without reload policy (not available in tantivy 0.22)
-        let reader = self.text_index.reader()?;
-        
-        let searcher = reader.searcher();
-        let query_parser = QueryParser::for_index(&self.text_index, vec![self.content_field]);
-        
-        // Try both exact and fuzzy search
-        let parsed_query = query_parser.parse_query(query)?;
-        
-        let top_docs = searcher.search(&*parsed_query, &TopDocs::with_limit(limit))?;
-        
-        let mut results = Vec::new();
-        for (score, doc_address) in top_docs {
-            let doc: tantivy::TantivyDocument = searcher.doc(doc_address)?;
-            let content = doc.get_first(self.content_field)
-                .and_then(|v| v.as_str())
-                .unwrap_or("")
-                .to_string();
-            let path = doc.get_first(self.path_field)
-                .and_then(|v| v.as_str())
-                .unwrap_or("")
-                .to_string();
-            
-            results.push(SearchResult {
-                content,
-                file_path: path,
-                score,
-                match_type: "text".to_string(),
+    /// `search` as a stream of the same results in the same order. Fusion still ranks
+    /// every candidate before the first item, but snippets, line numbers and content
+    /// caps are worked out one result at a time as the consumer pulls them. Bypasses
+    /// the result cache. An error ends the stream after it is yielded.
+    pub fn search_stream<'a>(&'a self, query: &'a str, limit: usize) -> impl Stream<Item = Result<SearchResult>> + 'a {
+        stream::once(async move { self.ranked_unannotated(query, limit) }).flat_map(move |ranked| match ranked {
+            Ok(results) => stream::iter(results)
+                .map(move |mut result| {
+                    self.annotate(std::slice::from_mut(&mut result), query);
+                    self.cap_content(std::slice::from_mut(&mut result));
+                    Ok(result)
+                })
+                .left_stream(),
+            Err(e) => stream::iter(Some(Err(e))).right_stream(),
+        })
+    }
+
+    /// `search` with per-query overrides of the configured keyword matching. Stemming
+    /// only changes BM25 matching; Tantivy never stems. With `symbol_kinds`, results
+    /// point `line_number` and `snippet` at the matching definition.
+    pub async fn search_with_options(&mut self, query: &str, limit: usize, options: SearchOptions) -> Result<Vec<SearchResult>> {
+        if self.is_blank_query(query)? {
+            return Ok(Vec::new());
+        }
+        if options == SearchOptions::default() {
+            return self.search(query, limit).await;
+        }
+        self.cached_search(ResultCacheKey::new(query, limit, filter_hash("options", &options)), |search| {
+            let query_embedding = search.embed_query(query)?;
+            let Some(kinds) = &options.symbol_kinds else {
+                return search.search_embedded_with_options(query, query_embedding, limit, None, None, &options);
+            };
+            // Files without a matching definition are dropped after fusion
+            let candidates = limit.saturating_mul(SYMBOL_SCOPE_OVERFETCH);
+            let mut results = search.search_embedded_with_options(query, query_embedding, candidates, None, None, &options)?;
+            // Files kept opaque as generated are never parsed for symbols
+            results.retain(|result| {
+                search.document_metadata.get(&result.file_path).and_then(|tags| tags.get(GENERATED_METADATA_KEY)).is_none()
             });
+            let mut results = restrict_to_symbol_kinds(results, query, kinds)?;
+            results.truncate(limit);
+            Ok(results)
+        })
+    }
+
+    /// Whether `query` is blank (only whitespace and zero-width characters) and gets no
+    /// results, or `SearchError::EmptyQuery` with `Config::error_on_empty_query`. Every
+    /// search checks this first, so no backend ever sees a blank query.
+    fn is_blank_query(&self, query: &str) -> Result<bool> {
+        if !is_blank(query) {
+            return Ok(false);
         }
-        
-        Ok(results)
+        if self.error_on_empty_query {
+            return Err(SearchError::EmptyQuery.into());
+        }
+        Ok(true)
     }
 
-    /// Simple RRF fusion - not over-engineered
-    fn simple_rrf_fusion(&self, 
-                         vector_results: Vec<VectorResult>, 
-                         text_results: Vec<SearchResult>, 
-                         limit: usize) -> Vec<SearchResult> {
-        let mut score_map: HashMap<String, (SearchResult, f32)> = HashMap::new();
-        
-        // Add vector results with RRF scoring
-        for (rank, result) in vector_results.into_iter().enumerate() {
-            let key = format!("{}:{}", result.file_path, &result.content[..50.min(result.content.len())]);
-            let rrf_score = 1.0 / (60.0 + rank as f32 + 1.0);
-            
-            score_map.insert(key, (SearchResult {
-                content: result.content,
-                file_path: result.file_path,
-                score: rrf_score,
-                match_type: "vector".to_string(),
-            }, rrf_score));
+    /// The fused ranking `search` returns, before `annotate` and `cap_content`
+    fn ranked_unannotated(&self, query: &str, limit: usize) -> Result<Vec<SearchResult>> {
+        if self.is_blank_query(query)? {
+            return Ok(Vec::new());
         }
-        
-        // Add text results with RRF scoring
-        for (rank, result) in text_results.into_iter().enumerate() {
-            let key = format!("{}:{}", result.file_path, &result.content[..50.min(result.content.len())]);
-            let rrf_score = 1.0 / (60.0 + rank as f32 + 1.0);
-            
-            if let Some((existing_result, existing_score)) = score_map.get_mut(&key) {
-                *existing_score += rrf_score;
-                existing_result.match_type = "hybrid".to_string();
-                existing_result.score = *existing_score;
-            } else {
-                score_map.insert(key, (result, rrf_score));
+        let query_embedding = self.embed_query(query)?;
+        let (vector_results, text_results) = self.candidates(query, query_embedding.as_deref(), limit * 2, None, &SearchOptions::default())?;
+        let mut fused_results = self.simple_rrf_fusion(query, vector_results, text_results, None);
+        fused_results.truncate(limit);
+        Ok(fused_results)
+    }
+
+    /// Serve `key` from the result cache, or run `search` and cache its results
+    fn cached_search(&self, key: ResultCacheKey, search: impl FnOnce(&Self) -> Result<Vec<SearchResult>>) -> Result<Vec<SearchResult>> {
+        self.timed_search(|this| {
+            let Some(cache) = &this.result_cache else {
+                let mut results = search(this)?;
+                this.cap_content(&mut results);
+                return Ok(results);
+            };
+            if let Some(results) = cache.get(&key) {
+                return Ok(results);
             }
+            let mut results = search(this)?;
+            this.cap_content(&mut results);
+            cache.put(key, results.clone());
+            Ok(results)
+        })
+    }
+
+    /// Run a whole search, timing its phases when `FusionConfig::collect_timings` is set
+    fn timed_search<T>(&self, search: impl FnOnce(&Self) -> Result<T>) -> Result<T> {
+        if !self.fusion_config.collect_timings {
+            return search(self);
         }
-        
-        // Sort by combined score
-        let mut final_results: Vec<_> = score_map.into_values().map(|(result, _)| result).collect();
-        final_results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
-        
-        final_results.into_iter().take(limit).collect()
+        *self.timings.lock() = Some(SearchTimings::default());
+        let started = Instant::now();
+        let result = search(self);
+        let timings = self.timings.lock().take().map(|timings| SearchTimings { total: started.elapsed(), ..timings });
+        *self.last_timings.lock() = timings;
+        result
     }
 
-    pub async fn clear(&mut self) -> Result<()> {
-        self.vector_storage.clear()?;
-        self.text_writer.delete_all_documents()?;
-        self.text_writer.commit()?;
-        Ok(())
+    /// Run one phase of a search inside its tracing span, adding its duration to the
+    /// current search's timings when they are collected
+    fn timed<T>(&self, phase: SearchPhase, run: impl FnOnce() -> T) -> T {
+        let span = match phase {
+            SearchPhase::EmbedQuery => tracing::debug_span!("embed_query"),
+            SearchPhase::Bm25Search => tracing::debug_span!("bm25_search"),
+            SearchPhase::TantivySearch => tracing::debug_span!("tantivy_search"),
+            SearchPhase::SemanticSearch => tracing::debug_span!("semantic_search"),
+            SearchPhase::Fuse => tracing::debug_span!("fuse"),
+        };
+        let _entered = span.enter();
+        if !self.fusion_config.collect_timings {
+            return run();
+        }
+        let started = Instant::now();
+        let output = run();
+        if let Some(timings) = self.timings.lock().as_mut() {
+            let elapsed = started.elapsed();
+            match phase {
+                SearchPhase::EmbedQuery => timings.embed_query += elapsed,
+                SearchPhase::Bm25Search => timings.bm25_search += elapsed,
+                SearchPhase::TantivySearch => timings.tantivy_search += elapsed,
+                SearchPhase::SemanticSearch => timings.semantic_search += elapsed,
+                SearchPhase::Fuse => timings.fuse += elapsed,
+            }
+        }
+        output
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Per-phase durations of the last `search`, `search_with_options`,
+    /// `search_filtered`, `search_with_metadata_filter`, `search_query` or
+    /// `search_grouped`. `None` unless `FusionConfig::collect_timings` was set for it.
+    pub fn last_search_timings(&self) -> Option<SearchTimings> {
+        *self.last_timings.lock()
+    }
+
+    pub(crate) fn invalidate_result_cache(&self) {
+        if let Some(cache) = &self.result_cache {
+            cache.clear();
+        }
+    }
+
+    /// Hits and misses of the result cache behind `search`, `search_filtered` and
+    /// `search_with_metadata_filter`; `None` when `search_cache_size` is 0
+    pub fn result_cache_stats(&self) -> Option<CacheStats> {
+        self.result_cache.as_ref().map(BoundedCache::stats)
+    }
+
+    /// `search` bounded by `timeout` as a whole, embedding, retrieval and fusion together,
+    /// returning `SearchError::Timeout` once it elapses. The query is embedded on a
+    /// blocking thread that is abandoned on timeout; retrieval and fusion can't be
+    /// interrupted, so the deadline is checked before and after them.
+    pub async fn search_with_timeout(&mut self, query: &str, limit: usize, timeout: Duration) -> Result<Vec<SearchResult>> {
+        if self.is_blank_query(query)? {
+            return Ok(Vec::new());
+        }
+        let deadline = tokio::time::Instant::now() + timeout;
+        let timed_out = || SearchError::Timeout { timeout };
+
+        let query_embedding = match &self.embedders {
+            Some(embedders) => {
+                let embedder = Arc::clone(&embedders.text);
+                let query = query.to_string();
+                let embedding = tokio::task::spawn_blocking(move || embedder.embed(&query, QUERY_TASK));
+                match tokio::time::timeout_at(deadline, embedding).await {
+                    Ok(joined) => Some(joined??),
+                    Err(_) => return Err(timed_out().into()),
+                }
+            }
+            None => None,
+        };
+        if tokio::time::Instant::now() >= deadline {
+            return Err(timed_out().into());
+        }
+        let mut results = self.search_embedded(query, query_embedding, limit, None, None)?;
+        if tokio::time::Instant::now() >= deadline {
+            return Err(timed_out().into());
+        }
+        self.cap_content(&mut results);
+        Ok(results)
+    }
+
+    /// Hybrid search returning `limit` groups of results, collapsed per `group_by` and
+    /// ranked per `FusionConfig::group_score`. Up to `2 * limit` chunk hits are grouped,
+    /// as `search` considers for its `limit` results.
+    pub async fn search_grouped(&mut self, query: &str, limit: usize, group_by: GroupBy) -> Result<Vec<GroupedResult>> {
+        if self.is_blank_query(query)? {
+            return Ok(Vec::new());
+        }
+        self.timed_search(|search| {
+            let query_embedding = search.embed_query(query)?;
+            let (vector_results, text_results) =
+                search.candidates(query, query_embedding.as_deref(), limit * 2, None, &SearchOptions::default())?;
+            let ranked = search.timed(SearchPhase::Fuse, || {
+                let mut ranked = search.simple_rrf_fusion(query, vector_results, text_results, None);
+                search.annotate(&mut ranked, query);
+                search.cap_content(&mut ranked);
+                ranked
+            });
+
+            let mut groups = group_results(ranked, group_by, search.fusion_config.group_score);
+            groups.truncate(limit);
+            Ok(groups)
+        })
+    }
+
+    /// Hybrid search restricted to results whose `file_path` passes `filter`.
+    /// Filtering happens on the candidate lists before fusion and `limit`, so
+    /// excluded files never push out matching ones. Malformed globs return
+    /// `SearchError::InvalidParameter`.
+    pub async fn search_filtered(&mut self, query: &str, limit: usize, filter: &PathFilter) -> Result<Vec<SearchResult>> {
+        if self.is_blank_query(query)? {
+            return Ok(Vec::new());
+        }
+        let matcher = filter.compile()?;
+        if filter.is_empty() {
+            return self.search(query, limit).await;
+        }
+        self.cached_search(ResultCacheKey::new(query, limit, filter_hash("path", filter)), |search| {
+            let query_embedding = search.embed_query(query)?;
+            search.search_embedded(query, query_embedding, limit, Some(&|path: &str| matcher.is_match(path)), None)
+        })
+    }
+
+    /// Hybrid search restricted to results whose metadata passes `filter`. As with
+    /// `search_filtered`, candidates are filtered before fusion and `limit`. Only
+    /// documents from `index_documents` carry metadata, plus the
+    /// `CONTENT_FILTER_METADATA_KEY` tag of files indexed by `index_directory` with a
+    /// `ContentFilter` other than `All`.
+    pub async fn search_with_metadata_filter(&mut self, query: &str, limit: usize, filter: &MetadataFilter) -> Result<Vec<SearchResult>> {
+        if self.is_blank_query(query)? {
+            return Ok(Vec::new());
+        }
+        if filter.is_empty() {
+            return self.search(query, limit).await;
+        }
+        self.cached_search(ResultCacheKey::new(query, limit, filter_hash("metadata", filter)), |search| {
+            let query_embedding = search.embed_query(query)?;
+            let empty = HashMap::new();
+            let keep = |path: &str| filter.matches(search.document_metadata.get(path).unwrap_or(&empty));
+            search.search_embedded(query, query_embedding, limit, Some(&keep), None)
+        })
+    }
+
+    /// Hybrid search over a query with `field:value` scopes (see `parse_query`), e.g.
+    /// `parse symbol:Parser path:src/ lang:rust`. Path, language and `meta:` scopes
+    /// filter candidates like `search_filtered`; each `symbol:` joins the ranked terms
+    /// and must appear as an identifier in a result's content. Unknown fields return
+    /// `SearchError::InvalidParameter`.
+    pub async fn search_query(&mut self, query: &str, limit: usize) -> Result<Vec<SearchResult>> {
+        if self.is_blank_query(query)? {
+            return Ok(Vec::new());
+        }
+        let parsed = parse_query(query)?;
+        let text = parsed.search_text();
+        if text.is_empty() {
+            return Err(SearchError::InvalidParameter {
+                parameter: "query".to_string(),
+                reason: format!("'{}' has no search terms besides its scopes", query),
+            }.into());
+        }
+        if parsed.is_unscoped() {
+            return self.search(&text, limit).await;
+        }
+        let matcher = parsed.path.compile()?;
+        self.cached_search(ResultCacheKey::new(&text, limit, filter_hash("query", &parsed)), |search| {
+            let query_embedding = search.embed_query(&text)?;
+            let empty = HashMap::new();
+            let keep = |path: &str| {
+                matcher.is_match(path)
+                    && parsed.matches_language(path)
+                    && parsed.metadata.matches(search.document_metadata.get(path).unwrap_or(&empty))
+            };
+            // Symbols are checked after fusion, so rank extra results to drop from
+            let candidates = if parsed.symbols.is_empty() { limit } else { limit.saturating_mul(SYMBOL_SCOPE_OVERFETCH) };
+            let mut results = search.search_embedded(&text, query_embedding, candidates, Some(&keep), None)?;
+            results.retain(|result| parsed.matches_symbols(&result.content));
+            results.truncate(limit);
+            Ok(results)
+        })
+    }
+
+    /// Search under `feedback` for the lifetime of the returned handle. Nothing is
+    /// stored: other searches are unaffected.
+    pub fn with_feedback<'a>(&'a self, feedback: &'a SearchFeedback) -> FeedbackSearch<'a> {
+        FeedbackSearch { search: self, feedback }
+    }
+
+    /// Distinct file paths (or document ids) in the keyword index, sorted. Read from the
+    /// index itself, so it always agrees with what search can return.
+    pub fn indexed_paths(&self) -> Result<Vec<String>> {
+        Ok(self.text_backend.paths()?.into_iter().collect())
+    }
+
+    /// Number of documents in the keyword index
+    pub fn indexed_document_count(&self) -> Result<usize> {
+        self.text_backend.len()
+    }
+
+    /// Number of distinct indexed file paths that pass `filter`, so callers can tell a
+    /// filter that matches no files apart from a query that matches nothing
+    pub fn count_indexed_paths_matching(&self, filter: &PathFilter) -> Result<usize> {
+        let matcher = filter.compile()?;
+        Ok(self.text_backend.paths()?.iter().filter(|path| matcher.is_match(path)).count())
+    }
+
+    /// Hybrid search with a precomputed query vector: `query_embedding` drives the
+    /// semantic side and `query_text` only the keyword side and snippets. The vector
+    /// must match the stored dimension (or the text embedder's when nothing is stored),
+    /// otherwise `SearchError::Storage(StorageError::DimensionMismatch)` is returned.
+    pub async fn search_with_embedding(&mut self, query_text: &str, query_embedding: &[f32], limit: usize) -> Result<Vec<SearchResult>> {
+        if self.is_blank_query(query_text)? {
+            return Ok(Vec::new());
+        }
+        let expected = self
+            .vector_storage
+            .dimension()
+            .or_else(|| self.embedders.as_ref().map(|e| e.text.dimension()));
+        if let Some(expected) = expected.filter(|&expected| expected != query_embedding.len()) {
+            return Err(SearchError::from(StorageError::DimensionMismatch {
+                expected,
+                actual: query_embedding.len(),
+            }).into());
+        }
+        let mut results = self.search_embedded(query_text, Some(query_embedding.to_vec()), limit, None, None)?;
+        self.cap_content(&mut results);
+        Ok(results)
+    }
+
+    /// Run several queries with a single batched embedding call.
+    /// Results are in input order; one failing query doesn't abort the others.
+    pub async fn search_batch(&mut self, queries: &[String], limit: usize) -> Result<Vec<Result<Vec<SearchResult>>>> {
+        if queries.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Blank queries are answered here and never embedded
+        let blank: Vec<Result<bool>> = queries.iter().map(|query| self.is_blank_query(query)).collect();
+        let searched: Vec<String> = queries
+            .iter()
+            .zip(&blank)
+            .filter(|(_, blank)| matches!(blank, Ok(false)))
+            .map(|(query, _)| query.clone())
+            .collect();
+        let query_embeddings: Vec<Result<Option<Vec<f32>>>> = match &self.embedders {
+            Some(embedders) if !searched.is_empty() => match embedders.text.embed_batch(searched.clone(), QUERY_TASK) {
+                Ok(embeddings) => embeddings.into_iter().map(|embedding| Ok(Some(embedding))).collect(),
+                // A query the model rejects fails the whole batch; embed each query alone
+                // so only its own slot gets the error
+                Err(e) => {
+                    log::warn!("Batched query embedding failed, embedding {} queries one at a time: {}", searched.len(), e);
+                    searched.iter().map(|query| embedders.text.embed(query, QUERY_TASK).map(Some)).collect()
+                }
+            },
+            _ => searched.iter().map(|_| Ok(None)).collect(),
+        };
+
+        let mut query_embeddings = query_embeddings.into_iter();
+        Ok(queries
+            .iter()
+            .zip(blank)
+            .map(|(query, blank)| match blank {
+                Ok(false) => {
+                    let query_embedding = query_embeddings.next().unwrap_or(Ok(None))?;
+                    self.search_embedded(query, query_embedding, limit, None, None).map(|mut results| {
+                        self.cap_content(&mut results);
+                        results
+                    })
+                }
+                Ok(true) => Ok(Vec::new()),
+                Err(e) => Err(e),
+            })
+            .collect())
+    }
+
+    /// One page of the full ranking: `total_matched` counts every result that survives
+    /// `min_score` and deduplication, and `results` holds those at `offset..offset + limit`.
+    /// An `offset` past the end gives an empty page.
+    pub async fn search_paged(&mut self, query: &str, offset: usize, limit: usize) -> Result<PagedResults> {
+        if self.is_blank_query(query)? {
+            return Ok(PagedResults { results: Vec::new(), total_matched: 0, offset, limit });
+        }
+        let query_embedding = self.embed_query(query)?;
+        // Rank every document so the total doesn't depend on the page requested
+        let candidates = self.vector_storage.len().max(self.text_backend.len()?).max(offset.saturating_add(limit));
+        let (vector_results, text_results) = self.candidates(query, query_embedding.as_deref(), candidates, None, &SearchOptions::default())?;
+
+        let ranked = self.simple_rrf_fusion(query, vector_results, text_results, None);
+        let total_matched = ranked.len();
+        let mut results: Vec<SearchResult> = ranked.into_iter().skip(offset).take(limit).collect();
+        self.annotate(&mut results, query);
+        self.cap_content(&mut results);
+
+        Ok(PagedResults {
+            results,
+            total_matched,
+            offset,
+            limit,
+        })
+    }
+
+    /// Query vector from the text embedder under `EmbeddingTask::SearchQuery`, or
+    /// `None` when running keyword-only
+    fn embed_query(&self, query: &str) -> Result<Option<Vec<f32>>> {
+        let Some(embedders) = &self.embedders else {
+            return Ok(None);
+        };
+        self.timed(SearchPhase::EmbedQuery, || embedders.text.embed(query, QUERY_TASK)).map(Some)
+    }
+
+    /// Vector + text search and fusion for a query whose embedding is already computed;
+    /// text search alone when there is no embedding
+    fn search_embedded(&self, query: &str, query_embedding: Option<Vec<f32>>, limit: usize, filter: Option<CandidateFilter>, feedback: Option<&SearchFeedback>) -> Result<Vec<SearchResult>> {
+        self.search_embedded_with_options(query, query_embedding, limit, filter, feedback, &SearchOptions::default())
+    }
+
+    fn search_embedded_with_options(
+        &self,
+        query: &str,
+        query_embedding: Option<Vec<f32>>,
+        limit: usize,
+        filter: Option<CandidateFilter>,
+        feedback: Option<&SearchFeedback>,
+        options: &SearchOptions,
+    ) -> Result<Vec<SearchResult>> {
+        let (vector_results, text_results) = self.candidates(query, query_embedding.as_deref(), limit * 2, filter, options)?;
+        
+        // Simple RRF fusion
+        Ok(self.timed(SearchPhase::Fuse, || {
+            let mut fused_results = self.simple_rrf_fusion(query, vector_results, text_results, feedback);
+            fused_results.truncate(limit);
+            self.annotate(&mut fused_results, query);
+            fused_results
+        }))
+    }
+
+    /// The `candidates` best vector and text results, restricted to file paths that pass
+    /// `filter` when given. Vector results are empty without a query embedding.
+    fn candidates(
+        &self,
+        query: &str,
+        query_embedding: Option<&[f32]>,
+        candidates: usize,
+        filter: Option<CandidateFilter>,
+        options: &SearchOptions,
+    ) -> Result<(Vec<VectorResult>, Vec<SearchResult>)> {
+        let vector_search = |n: usize| match query_embedding {
+            Some(embedding) => self.timed(SearchPhase::SemanticSearch, || self.vector_storage.search_similar(embedding, n).map_err(storage_failure)),
+            None => Ok(Vec::new()),
+        };
+        let text_phase = match self.text_backend {
+            TextBackend::Tantivy(_) => SearchPhase::TantivySearch,
+            TextBackend::Bm25 { .. } => SearchPhase::Bm25Search,
+        };
+        let text_search = |n: usize| self.timed(text_phase, || self.text_backend.search(query, n, options));
+        Ok(match filter {
+            None => (vector_search(candidates)?, text_search(candidates)?),
+            Some(keep) => {
+                // Rank everything, then filter, so enough matching candidates survive
+                let mut vector_results = vector_search(self.vector_storage.len().max(candidates))?;
+                let mut text_results = text_search(self.text_backend.len()?.max(candidates))?;
+                vector_results.retain(|r| keep(&r.file_path));
+                text_results.retain(|r| keep(&r.file_path));
+                vector_results.truncate(candidates);
+                text_results.truncate(candidates);
+                (vector_results, text_results)
+            }
+        })
+    }
+
+    /// Fill in the snippet and first matched line of each returned result
+    fn annotate(&self, results: &mut [SearchResult], query: &str) {
+        for result in results {
+            result.snippet = highlight_snippet(
+                &result.content,
+                query,
+                self.fusion_config.snippet_window,
+                &self.fusion_config.highlight_marker,
+            );
+            result.line_number = first_match_line(&result.content, query);
+            if let Some(metadata) = self.document_metadata.get(&result.file_path).filter(|_| result.metadata.is_empty()) {
+                result.metadata = metadata.clone();
+            }
+        }
+    }
+
+    /// Cut the content of results about to be returned to
+    /// `FusionConfig::max_result_content_chars`. Runs after `annotate` and any filtering
+    /// on content, which need the whole chunk.
+    fn cap_content(&self, results: &mut [SearchResult]) {
+        let Some(max_chars) = self.fusion_config.max_result_content_chars else {
+            return;
+        };
+        for result in results {
+            // Cut between characters, never inside a multibyte one
+            if let Some((end, _)) = result.content.char_indices().nth(max_chars) {
+                result.content.truncate(end);
+            }
+        }
+    }
+
+    /// Indexed files most like the indexed file `path`, best first: each file is
+    /// represented by the mean of its chunk embeddings and ranked by cosine similarity
+    /// to that of `path`. Purely semantic, with no keyword search or fusion; `path`
+    /// itself is left out. Each result carries the file's chunk closest to `path`.
+    /// A `path` without stored embeddings, e.g. when indexed keyword-only, is
+    /// `SearchError::InvalidParameter`.
+    pub fn find_similar(&self, path: &str, limit: usize) -> Result<Vec<SearchResult>> {
+        let mut files: BTreeMap<String, Vec<EmbeddingRecord>> = BTreeMap::new();
+        for record in self.vector_storage.records() {
+            files.entry(record.file_path.clone()).or_default().push(record);
+        }
+        let target = files.remove(path).ok_or_else(|| SearchError::InvalidParameter {
+            parameter: "path".to_string(),
+            reason: format!("{} has no stored embeddings; index it with semantic search enabled", path),
+        })?;
+        let target = mean_embedding(&target);
+
+        let mut results: Vec<SearchResult> = files
+            .into_iter()
+            .filter_map(|(file_path, chunks)| {
+                let score = cosine_similarity(&target, &mean_embedding(&chunks));
+                let closest = chunks
+                    .into_iter()
+                    .max_by(|a, b| cosine_similarity(&target, &a.embedding).total_cmp(&cosine_similarity(&target, &b.embedding)))?;
+                Some(SearchResult {
+                    content: closest.content,
+                    file_path,
+                    score,
+                    match_type: "vector".to_string(),
+                    snippet: None,
+                    line_number: None,
+                    heading_path: None,
+                    component_scores: None,
+                    metadata: closest.metadata,
+                })
+            })
+            .collect();
+        // Stable, so equal scores stay in path order
+        results.sort_by(|a, b| b.score.total_cmp(&a.score));
+        results.truncate(limit);
+        self.cap_content(&mut results);
+        Ok(results)
+    }
+
+    /// Fuzzy text search returning the results and the edit distance actually applied.
+    /// Distances above `MAX_FUZZY_DISTANCE` are rejected with `SearchError::InvalidParameter`.
+    pub fn search_fuzzy_with(&self, query: &str, config: &FuzzyConfig, limit: usize) -> Result<(Vec<SearchResult>, u8)> {
+        config.validate()?;
+        if self.is_blank_query(query)? {
+            return Ok((Vec::new(), config.max_distance));
+        }
+        let tantivy = match &self.text_backend {
+            TextBackend::Tantivy(tantivy) => tantivy,
+            TextBackend::Bm25 { .. } => {
+                return Err(SearchError::InvalidParameter {
+                    parameter: "search_backend".to_string(),
+                    reason: "fuzzy search requires the tantivy backend".to_string(),
+                }.into());
+            }
+        };
+        let mut results = fuzzy_text_search(tantivy, query, config, limit)?;
+        self.cap_content(&mut results);
+        Ok((results, config.max_distance))
+    }
+
+    /// Simple RRF fusion - not over-engineered. Returns the full ranking after
+    /// `min_score` and deduplication; callers apply their own limit. Paths boosted
+    /// by `feedback` have their fused score multiplied by `FEEDBACK_BOOST`.
+    fn simple_rrf_fusion(&self, 
+                         query: &str,
+                         vector_results: Vec<VectorResult>, 
+                         text_results: Vec<SearchResult>,
+                         feedback: Option<&SearchFeedback>) -> Vec<SearchResult> {
+        let mut score_map: HashMap<String, (SearchResult, f32)> = HashMap::new();
+        let text_weight = if self.embedders.is_some() {
+            TEXT_RRF_WEIGHT
+        } else {
+            TEXT_RRF_WEIGHT + VECTOR_RRF_WEIGHT
+        };
+        
+        // Add vector results with RRF scoring
+        for (rank, result) in vector_results.into_iter().enumerate() {
+            let key = format!("{}:{}", result.file_path, content_preview_with_marker(&result.content, 50, ""));
+            let rrf_score = VECTOR_RRF_WEIGHT / (60.0 + rank as f32 + 1.0);
+            
+            score_map.insert(key, (SearchResult {
+                content: result.content,
+                file_path: result.file_path,
+                score: rrf_score,
+                match_type: "vector".to_string(),
+                snippet: None,
+                line_number: None,
+                heading_path: None,
+                component_scores: self.fusion_config.explain_component(&MatchType::Semantic, rrf_score),
+                metadata: result.metadata,
+            }, rrf_score));
+        }
+        
+        // Add text results with RRF scoring
+        for (rank, result) in text_results.into_iter().enumerate() {
+            let key = format!("{}:{}", result.file_path, content_preview_with_marker(&result.content, 50, ""));
+            let rrf_score = text_weight / (60.0 + rank as f32 + 1.0);
+            
+            if let Some((existing_result, existing_score)) = score_map.get_mut(&key) {
+                *existing_score += rrf_score;
+                existing_result.match_type = "hybrid".to_string();
+                existing_result.score = *existing_score;
+                if let Some(components) = existing_result.component_scores.as_mut() {
+                    components.add(&MatchType::Statistical, rrf_score);
+                }
+            } else {
+                let component_scores = self.fusion_config.explain_component(&MatchType::Statistical, rrf_score);
+                score_map.insert(key, (SearchResult { score: rrf_score, component_scores, ..result }, rrf_score));
+            }
+        }
+        
+        let mut final_results: Vec<_> = score_map.into_values().map(|(result, _)| result).collect();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        for result in &mut final_results {
+            apply_heading_context(result, query, self.fusion_config.heading_weight);
+            if feedback.is_some_and(|feedback| feedback.is_boosted(&result.file_path)) {
+                result.score *= FEEDBACK_BOOST;
+            }
+            if let Some(boost) = &self.fusion_config.recency_boost {
+                let mtime = self.document_metadata.get(&result.file_path).and_then(|tags| tags.get(MTIME_METADATA_KEY));
+                if let Some(mtime) = mtime.and_then(|mtime| mtime.parse::<u64>().ok()) {
+                    result.score *= boost.multiplier(now.saturating_sub(mtime) as f32 / SECONDS_PER_DAY);
+                }
+            }
+            result.score = self.fusion_config.round_score(result.score);
+        }
+        
+        // Sort by combined score; ties by path and content so pages are stable across calls
+        // and runs, whatever order the candidates were gathered in
+        let doc_order = |a: &SearchResult, b: &SearchResult| a.file_path.cmp(&b.file_path).then_with(|| a.content.cmp(&b.content));
+        final_results.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| doc_order(a, b)));
+        self.fusion_config.break_ties(&mut final_results, |r| r.score, |r| fused_match_type(&r.match_type), doc_order);
+        final_results.retain(|r| self.fusion_config.passes_min_score(r.score));
+        if let Some(threshold) = self.fusion_config.dedup_threshold {
+            final_results = dedup_similar(final_results, threshold, |r| {
+                self.vector_storage.embedding(&r.file_path, &r.content)
+            });
+        }
+        
+        final_results
+    }
+
+    /// Document, term and segment counts plus on-disk sizes for each component
+    pub fn stats(&self) -> Result<IndexStats> {
+        self.text_backend.stats(self.vector_storage.as_ref())
+    }
+
+    pub async fn clear(&mut self) -> Result<()> {
+        self.clear_backend(ClearTarget::All).await
+    }
+
+    /// Drop one component's data and leave the rest queryable. Clearing the keyword
+    /// index of the backend that isn't configured is an `InvalidParameter` error.
+    pub async fn clear_backend(&mut self, target: ClearTarget) -> Result<()> {
+        self.check_writable()?;
+        self.invalidate_result_cache();
+        match (target, &self.text_backend) {
+            (ClearTarget::All, _) => {
+                self.vector_storage.clear()?;
+                self.text_backend.clear()?;
+                self.document_metadata.clear();
+                self.save_document_metadata()?;
+            }
+            (ClearTarget::Tantivy, TextBackend::Tantivy(_)) | (ClearTarget::Bm25, TextBackend::Bm25 { .. }) => {
+                self.text_backend.clear()?;
+            }
+            (ClearTarget::Tantivy | ClearTarget::Bm25, _) => {
+                return Err(SearchError::InvalidParameter {
+                    parameter: "target".to_string(),
+                    reason: format!("{} is not the configured keyword backend", target),
+                }.into());
+            }
+            (ClearTarget::Vectors, _) => self.vector_storage.clear()?,
+            (ClearTarget::EmbeddingCache, _) => {
+                if let Some(embedders) = &self.embedders {
+                    embedders.text.clear_cache();
+                    embedders.code.clear_cache();
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Element-wise mean of the chunks' embeddings
+fn mean_embedding(chunks: &[EmbeddingRecord]) -> Vec<f32> {
+    let mut mean = vec![0.0; chunks.first().map_or(0, |chunk| chunk.embedding.len())];
+    for chunk in chunks {
+        for (sum, value) in mean.iter_mut().zip(&chunk.embedding) {
+            *sum += value;
+        }
+    }
+    for value in &mut mean {
+        *value /= chunks.len() as f32;
+    }
+    mean
+}
+
+/// Record the heading breadcrumb of a markdown result and boost its score by
+/// `heading_weight` times the share of query terms its headings contain
+fn apply_heading_context(result: &mut SearchResult, query: &str, heading_weight: f32) {
+    if !is_markdown_path(&result.file_path) {
+        return;
+    }
+    if let Some((path, share)) = match_heading_path(&result.content, query) {
+        if !path.is_empty() {
+            result.heading_path = Some(path.join(HEADING_BREADCRUMB_SEPARATOR));
+        }
+        result.score += heading_weight * share;
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::embedder::{Embedder, MinimalEmbedder};
+    
+    use crate::search::fusion::RecencyBoost;
+    use crate::search::text_backend::tests::numbered_files;
     use tempfile::tempdir;
 
     #[tokio::test]
@@ -240,4 +1579,1094 @@ mod tests {
         
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_hybrid_search_with_minimal_embedders() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut search = HybridSearch::with_embedders(
+            &db_path,
+            &Config::default(),
+            Box::new(MinimalEmbedder::new(64)),
+            Box::new(MinimalEmbedder::new(64)),
+        ).await?;
+
+        search.index(
+            vec!["fn authenticate(user: &User) -> bool".to_string(), "struct Template { html: String }".to_string()],
+            vec!["auth.rs".to_string(), "template.rs".to_string()],
+        ).await?;
+
+        let results = search.search("authenticate user", 5).await?;
+        assert_eq!(results[0].file_path, "auth.rs");
+        assert_eq!(results[0].match_type, "hybrid");
+        assert!(results[0].snippet.as_deref().unwrap().contains("**authenticate**"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_explain_reports_each_backends_share_of_the_score() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut search = HybridSearch::with_embedders(
+            &db_path,
+            &Config::default(),
+            Box::new(MinimalEmbedder::new(64)),
+            Box::new(MinimalEmbedder::new(64)),
+        ).await?;
+        search.index(
+            vec!["fn authenticate(user: &User) -> bool".to_string(), "struct Template { html: String }".to_string()],
+            vec!["auth.rs".to_string(), "template.rs".to_string()],
+        ).await?;
+
+        let results = search.search("authenticate user", 5).await?;
+        assert!(results.iter().all(|r| r.component_scores.is_none()));
+
+        search.set_fusion_config(FusionConfig { explain: true, ..search.fusion_config().clone() });
+        let results = search.search("authenticate user", 5).await?;
+        assert_eq!(results[0].match_type, "hybrid");
+        for result in &results {
+            let components = result.component_scores.clone().unwrap();
+            assert_eq!(components.bm25 > 0.0, result.match_type != "vector", "{:?}", result);
+            assert_eq!(components.semantic > 0.0, matches!(result.match_type.as_str(), "vector" | "hybrid"), "{:?}", result);
+            assert!((components.bm25 + components.semantic - result.score).abs() < 1e-6, "{:?}", result);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_find_similar_ranks_the_near_duplicate_first() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut search = HybridSearch::with_embedders(
+            &db_path,
+            &Config::default(),
+            Box::new(MinimalEmbedder::new(64)),
+            Box::new(MinimalEmbedder::new(64)),
+        ).await?;
+        search.index(
+            vec![
+                "fn load_settings(path: &Path) -> Settings { let text = read(path); parse_settings(&text) }".to_string(),
+                "fn load_config(path: &Path) -> Settings { let text = read(path); parse_settings(&text) }".to_string(),
+                "Release notes: the dashboard now supports dark mode and keyboard shortcuts".to_string(),
+            ],
+            vec!["settings.rs".to_string(), "config.rs".to_string(), "CHANGELOG.md".to_string()],
+        ).await?;
+
+        let similar = search.find_similar("settings.rs", 5)?;
+        let paths: Vec<&str> = similar.iter().map(|r| r.file_path.as_str()).collect();
+        assert_eq!(paths, ["config.rs", "CHANGELOG.md"]);
+        assert!(similar[0].score > similar[1].score);
+        assert_eq!(search.find_similar("settings.rs", 1)?.len(), 1);
+
+        match search.find_similar("missing.rs", 5).unwrap_err().downcast::<SearchError>()? {
+            SearchError::InvalidParameter { parameter, .. } => assert_eq!(parameter, "path"),
+            other => panic!("expected InvalidParameter, got {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_find_similar_sees_vectors_indexed_by_another_instance() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut writer = HybridSearch::with_embedders(
+            &db_path,
+            &Config::default(),
+            Box::new(MinimalEmbedder::new(64)),
+            Box::new(MinimalEmbedder::new(64)),
+        ).await?;
+        writer.index(
+            vec![
+                "fn load_settings(path: &Path) -> Settings { parse_settings(&read(path)) }".to_string(),
+                "fn load_config(path: &Path) -> Settings { parse_settings(&read(path)) }".to_string(),
+            ],
+            vec!["settings.rs".to_string(), "config.rs".to_string()],
+        ).await?;
+        writer.close().await?;
+
+        // As `embed-search similar` does: a new process, no models, the same db path
+        let mut config = Config::default();
+        config.search.semantic_mode = SemanticMode::Disabled;
+        let reader = HybridSearch::open_read_only(&db_path, &config).await?;
+        let similar = reader.find_similar("settings.rs", 5)?;
+        let paths: Vec<&str> = similar.iter().map(|r| r.file_path.as_str()).collect();
+        assert_eq!(paths, ["config.rs"]);
+        assert_eq!(reader.stats()?.vector_count, 2);
+        Ok(())
+    }
+
+    pub(crate) fn document(id: &str, text: &str, metadata: &[(&str, &str)]) -> Document {
+        Document {
+            id: id.to_string(),
+            text: text.to_string(),
+            metadata: metadata.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        }
+    }
+
+    pub(crate) async fn search_with_tagged_documents() -> Result<(tempfile::TempDir, HybridSearch)> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut search = HybridSearch::with_embedders(
+            &db_path,
+            &Config::default(),
+            Box::new(MinimalEmbedder::new(64)),
+            Box::new(MinimalEmbedder::new(64)),
+        ).await?;
+        search.index_documents(vec![
+            document("a", "parse the config file", &[("language", "rust"), ("author", "ana")]),
+            document("b", "parse the config module", &[("language", "python"), ("author", "ana")]),
+            document("c", "parse the config script", &[("language", "go"), ("author", "bo")]),
+        ]).await?;
+        search.index(vec!["fn parse_config() {}".to_string()], vec!["config.rs".to_string()]).await?;
+        Ok((temp_dir, search))
+    }
+
+    pub(crate) fn ranking(results: &[SearchResult]) -> Vec<(String, f32, Option<String>)> {
+        results.iter().map(|r| (r.file_path.clone(), r.score, r.metadata.get("language").cloned())).collect()
+    }
+
+    #[tokio::test]
+    async fn test_metadata_filter_equals() -> Result<()> {
+        let (_dir, mut search) = search_with_tagged_documents().await?;
+        let filter = MetadataFilter::new().equals("language", "rust");
+        let results = search.search_with_metadata_filter("parse config", 1, &filter).await?;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].file_path, "a");
+
+        let filter = MetadataFilter::new().equals("author", "ana").equals("language", "python");
+        let paths: Vec<String> = search.search_with_metadata_filter("parse config", 5, &filter).await?
+            .into_iter().map(|r| r.file_path).collect();
+        assert_eq!(paths, vec!["b"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_metadata_filter_one_of() -> Result<()> {
+        let (_dir, mut search) = search_with_tagged_documents().await?;
+        let filter = MetadataFilter::new().one_of("language", ["go", "rust"]);
+        let mut paths: Vec<String> = search.search_with_metadata_filter("parse config", 5, &filter).await?
+            .into_iter().map(|r| r.file_path).collect();
+        paths.sort();
+        assert_eq!(paths, vec!["a", "c"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_metadata_filter_matching_nothing() -> Result<()> {
+        let (_dir, mut search) = search_with_tagged_documents().await?;
+        for filter in [
+            MetadataFilter::new().equals("language", "haskell"),
+            MetadataFilter::new().one_of("commit", ["abc123"]),
+            MetadataFilter::new().one_of("language", Vec::<String>::new()),
+        ] {
+            assert!(search.search_with_metadata_filter("parse config", 5, &filter).await?.is_empty());
+        }
+        // An empty filter is a plain search and includes path-indexed files
+        let results = search.search_with_metadata_filter("parse config", 5, &MetadataFilter::new()).await?;
+        assert_eq!(results.len(), 4);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_search_query_routes_field_scopes() -> Result<()> {
+        let (_dir, mut search) = search_with_tagged_documents().await?;
+        let paths = |results: Vec<SearchResult>| results.into_iter().map(|r| r.file_path).collect::<Vec<_>>();
+        assert_eq!(paths(search.search_query("parse lang:rust", 5).await?), vec!["config.rs"]);
+        assert_eq!(paths(search.search_query("parse meta:author=bo", 5).await?), vec!["c"]);
+        assert_eq!(paths(search.search_query("config symbol:module", 5).await?), vec!["b"]);
+        for query in ["parse author:bo", ":parse", "lang:rust"] {
+            match search.search_query(query, 5).await.unwrap_err().downcast_ref::<SearchError>() {
+                Some(SearchError::InvalidParameter { parameter, .. }) => assert_eq!(parameter, "query"),
+                other => panic!("expected InvalidParameter for {:?}, got {:?}", query, other),
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_clearing_vectors_keeps_lexical_results() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut search = HybridSearch::with_embedders(
+            &db_path,
+            &Config::default(),
+            Box::new(MinimalEmbedder::new(64)),
+            Box::new(MinimalEmbedder::new(64)),
+        ).await?;
+        search.index(
+            vec!["fn authenticate(user: &User) -> bool".to_string(), "fn render(template: &str)".to_string()],
+            vec!["auth.rs".to_string(), "render.rs".to_string()],
+        ).await?;
+
+        search.clear_backend(ClearTarget::Vectors).await?;
+        assert_eq!(search.stats()?.vector_count, 0);
+        let results = search.search("authenticate", 5).await?;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].file_path, "auth.rs");
+        assert_eq!(results[0].match_type, "text");
+
+        // Tantivy is the configured backend, so the BM25 target is refused
+        assert!(search.clear_backend(ClearTarget::Bm25).await.is_err());
+        search.clear_backend(ClearTarget::Tantivy).await?;
+        assert!(search.search("authenticate", 5).await?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_clear_target_parses_cli_names() -> Result<()> {
+        for target in [ClearTarget::All, ClearTarget::Tantivy, ClearTarget::Bm25, ClearTarget::Vectors, ClearTarget::EmbeddingCache] {
+            assert_eq!(target.to_string().parse::<ClearTarget>()?, target);
+        }
+        assert_eq!("embedding-cache".parse::<ClearTarget>()?, ClearTarget::EmbeddingCache);
+        assert!("lancedb".parse::<ClearTarget>().is_err());
+        Ok(())
+    }
+
+    /// `MinimalEmbedder` that records the task of every call
+    struct TaskRecorder {
+        inner: MinimalEmbedder,
+        tasks: std::sync::Arc<parking_lot::Mutex<Vec<EmbeddingTask>>>,
+    }
+
+    impl Embedder for TaskRecorder {
+        fn embed(&self, text: &str, task: EmbeddingTask) -> Result<Vec<f32>> {
+            self.tasks.lock().push(task);
+            self.inner.embed(text, task)
+        }
+
+        fn dimension(&self) -> usize {
+            self.inner.dimension()
+        }
+    }
+
+    /// `MinimalEmbedder` that records every text it is given
+    struct InputRecorder {
+        inner: MinimalEmbedder,
+        inputs: std::sync::Arc<parking_lot::Mutex<Vec<String>>>,
+    }
+
+    impl Embedder for InputRecorder {
+        fn embed(&self, text: &str, task: EmbeddingTask) -> Result<Vec<f32>> {
+            self.inputs.lock().push(text.to_string());
+            self.inner.embed(text, task)
+        }
+
+        fn dimension(&self) -> usize {
+            self.inner.dimension()
+        }
+    }
+
+    /// `MinimalEmbedder` that takes `delay` to embed each query
+    struct SlowQueryEmbedder {
+        inner: MinimalEmbedder,
+        delay: Duration,
+    }
+
+    impl Embedder for SlowQueryEmbedder {
+        fn embed(&self, text: &str, task: EmbeddingTask) -> Result<Vec<f32>> {
+            if task == EmbeddingTask::SearchQuery {
+                std::thread::sleep(self.delay);
+            }
+            self.inner.embed(text, task)
+        }
+
+        fn dimension(&self) -> usize {
+            self.inner.dimension()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_with_timeout_returns_timeout_error() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let slow = |delay| Box::new(SlowQueryEmbedder { inner: MinimalEmbedder::new(64), delay });
+        let mut search = HybridSearch::with_embedders(
+            &db_path,
+            &Config::default(),
+            slow(Duration::from_secs(1)),
+            slow(Duration::from_secs(1)),
+        ).await?;
+        search.index(vec!["fn parse_config() {}".to_string()], vec!["config.rs".to_string()]).await?;
+
+        let started = std::time::Instant::now();
+        let err = search.search_with_timeout("parse config", 5, Duration::from_millis(50)).await.unwrap_err();
+        assert!(started.elapsed() < Duration::from_millis(500), "took {:?}", started.elapsed());
+        assert!(matches!(
+            err.downcast_ref::<SearchError>(),
+            Some(SearchError::Timeout { timeout }) if *timeout == Duration::from_millis(50)
+        ));
+
+        let results = search.search_with_timeout("parse config", 5, Duration::from_secs(30)).await?;
+        assert_eq!(results[0].file_path, "config.rs");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_documents_and_queries_use_their_own_tasks() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let text_tasks = std::sync::Arc::default();
+        let code_tasks = std::sync::Arc::default();
+        let mut search = HybridSearch::with_embedders(
+            &db_path,
+            &Config::default(),
+            Box::new(TaskRecorder { inner: MinimalEmbedder::new(64), tasks: std::sync::Arc::clone(&text_tasks) }),
+            Box::new(TaskRecorder { inner: MinimalEmbedder::new(64), tasks: std::sync::Arc::clone(&code_tasks) }),
+        ).await?;
+
+        search.index(
+            vec!["fn parse() {}".to_string(), "# Parsing".to_string()],
+            vec!["parse.rs".to_string(), "README.md".to_string()],
+        ).await?;
+        search.index_documents(vec![document("row-1", "parse rows", &[])]).await?;
+        assert_eq!(*code_tasks.lock(), vec![EmbeddingTask::SearchDocument]);
+        assert_eq!(*text_tasks.lock(), vec![EmbeddingTask::SearchDocument; 2]);
+
+        text_tasks.lock().clear();
+        search.search("parse", 5).await?;
+        search.search_batch(&["parse".to_string()], 5).await?;
+        assert_eq!(*text_tasks.lock(), vec![EmbeddingTask::SearchQuery; 2]);
+        assert_eq!(code_tasks.lock().len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_documents_are_formatted_for_their_language_before_embedding() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut config = Config::default();
+        config.indexing.embedding_languages.insert("rb".to_string(), "ruby".to_string());
+        let inputs = std::sync::Arc::default();
+        let recorder = |inputs| Box::new(InputRecorder { inner: MinimalEmbedder::new(64), inputs });
+        let mut search = HybridSearch::with_embedders(
+            &db_path,
+            &config,
+            recorder(std::sync::Arc::clone(&inputs)),
+            recorder(std::sync::Arc::clone(&inputs)),
+        ).await?;
+
+        let body = "add(a, b) returns a + b".to_string();
+        search.index(vec![body.clone(); 3], vec!["add.py".to_string(), "add.rs".to_string(), "add.rb".to_string()]).await?;
+        // The code model's batch (.py, .rs) comes before the text model's (.rb)
+        assert_eq!(*inputs.lock(), [format!("# Python\n{}", body), format!("// Rust\n{}", body), format!("[ruby]\n{}", body)]);
+
+        // Only the embedder sees the language header; stored content is the file's
+        let results = search.search("returns", 5).await?;
+        assert!(results.iter().all(|result| result.content == body), "{:?}", results);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_indexed_paths_lists_exactly_the_indexed_files() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut search = HybridSearch::with_semantic_mode(&db_path, SemanticMode::Disabled).await?;
+        assert!(search.indexed_paths()?.is_empty());
+        assert_eq!(search.indexed_document_count()?, 0);
+
+        search.index(
+            vec!["fn main() {}".to_string(), "fn lib() {}".to_string(), "# Readme".to_string()],
+            vec!["src/main.rs".to_string(), "src/lib.rs".to_string(), "README.md".to_string()],
+        ).await?;
+        assert_eq!(search.indexed_paths()?, vec!["README.md", "src/lib.rs", "src/main.rs"]);
+        assert_eq!(search.indexed_document_count()?, 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_search_options_override_configured_stemming() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().to_str().unwrap();
+        let mut config = Config::default();
+        config.search.backend = SearchBackend::Bm25Only;
+        config.search.semantic_mode = SemanticMode::Disabled;
+        config.search.enable_stemming = true;
+        let mut search = HybridSearch::with_config(db_path, &config).await?;
+        search.index(
+            vec!["fn parsing() {}".to_string(), "fn parse() {}".to_string()],
+            vec!["a.rs".to_string(), "b.rs".to_string()],
+        ).await?;
+
+        assert_eq!(search.search("parsing", 5).await?.len(), 2);
+        let exact = search.search_with_options("parsing", 5, SearchOptions { stemming: Some(false), ..Default::default() }).await?;
+        assert_eq!(exact.len(), 1);
+        assert_eq!(exact[0].file_path, "a.rs");
+        // Cached apart from the stemmed search
+        assert_eq!(search.search("parsing", 5).await?.len(), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bm25_search_matches_identifiers_across_naming_styles() -> Result<()> {
+        for split_identifiers in [true, false] {
+            let temp_dir = tempdir()?;
+            let db_path = temp_dir.path().to_str().unwrap();
+            let mut config = Config::default();
+            config.search.backend = SearchBackend::Bm25Only;
+            config.search.semantic_mode = SemanticMode::Disabled;
+            config.search.split_identifiers = split_identifiers;
+            let mut search = HybridSearch::with_config(db_path, &config).await?;
+            search.index(
+                vec!["fn get_user_name() {}".to_string(), "function getOrderTotal() {}".to_string()],
+                vec!["user.rs".to_string(), "order.js".to_string()],
+            ).await?;
+
+            let top = |results: Vec<SearchResult>| results.first().map(|r| r.file_path.clone());
+            let expected = |path: &str| split_identifiers.then(|| path.to_string());
+            assert_eq!(top(search.search("getUserName", 5).await?), expected("user.rs"));
+            assert_eq!(top(search.search("get_order_total", 5).await?), expected("order.js"));
+            // The whole compound is indexed either way
+            assert_eq!(top(search.search("get_user_name", 5).await?), Some("user.rs".to_string()));
+            assert_eq!(top(search.search("getOrderTotal", 5).await?), Some("order.js".to_string()));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_symbol_kinds_isolate_a_struct_from_a_function_of_the_same_name() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().to_str().unwrap();
+        let mut search = HybridSearch::with_semantic_mode(db_path, SemanticMode::Disabled).await?;
+        search.index(
+            vec![
+                "struct Widget {\n    id: u32,\n}\n\n#[allow(non_snake_case)]\nfn Widget() -> u32 {\n    0\n}\n".to_string(),
+                "// Builds a widget from its parts\nfn build() {}\n".to_string(),
+            ],
+            vec!["widget.rs".to_string(), "build.rs".to_string()],
+        ).await?;
+        let with_kinds = |kinds: Vec<SymbolKind>| SearchOptions { symbol_kinds: Some(kinds), ..Default::default() };
+
+        let structs = search.search_with_options("Widget", 5, with_kinds(vec![SymbolKind::Struct])).await?;
+        assert_eq!(structs.len(), 1);
+        assert_eq!((structs[0].file_path.as_str(), structs[0].line_number), ("widget.rs", Some(1)));
+        assert!(structs[0].snippet.as_deref().unwrap().starts_with("Widget {"));
+
+        let functions = search.search_with_options("widget", 5, with_kinds(vec![SymbolKind::Function])).await?;
+        assert_eq!(functions.len(), 1);
+        assert_eq!((functions[0].file_path.as_str(), functions[0].line_number), ("widget.rs", Some(6)));
+
+        assert!(search.search_with_options("Widget", 5, with_kinds(vec![SymbolKind::Enum])).await?.is_empty());
+        assert_eq!(search.search("widget", 5).await?.len(), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blank_queries_are_decided_before_any_backend() -> Result<()> {
+        let blank_queries = ["", " ", "\u{200B}\u{200C}\u{FEFF}"];
+        for error_on_empty_query in [false, true] {
+            let temp_dir = tempdir()?;
+            let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+            let mut config = Config::default();
+            config.search.semantic_mode = SemanticMode::Disabled;
+            config.error_on_empty_query = error_on_empty_query;
+            let mut search = HybridSearch::with_config(&db_path, &config).await?;
+            search.index(vec!["fn parse_config() {}".to_string()], vec!["config.rs".to_string()]).await?;
+
+            for query in blank_queries {
+                let outcomes = [
+                    search.search(query, 5).await,
+                    search.search_filtered(query, 5, &PathFilter { include: vec!["*.rs".to_string()], exclude: Vec::new() }).await,
+                    search.search_query(query, 5).await,
+                    search.search_fuzzy_with(query, &FuzzyConfig::default(), 5).map(|(results, _)| results),
+                    search.search_batch(&[query.to_string()], 5).await?.remove(0),
+                ];
+                for outcome in outcomes {
+                    match outcome {
+                        Ok(results) => {
+                            assert!(!error_on_empty_query, "{:?} should be rejected", query);
+                            assert!(results.is_empty(), "{:?}", query);
+                        }
+                        Err(e) => {
+                            assert!(error_on_empty_query, "{:?} failed: {}", query, e);
+                            assert!(matches!(e.downcast_ref::<SearchError>(), Some(SearchError::EmptyQuery)), "{}", e);
+                        }
+                    }
+                }
+            }
+            assert_eq!(search.search("parse_config", 5).await?.len(), 1);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_search_timings_cover_each_phase_of_the_total() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut search = HybridSearch::with_semantic_mode(&db_path, SemanticMode::Disabled).await?;
+        search.index(vec!["fn parse_config() {}".to_string()], vec!["config.rs".to_string()]).await?;
+        search.search("parse_config", 5).await?;
+        assert_eq!(search.last_search_timings(), None);
+
+        search.set_fusion_config(FusionConfig { collect_timings: true, ..search.fusion_config().clone() });
+        search.search("parse_config", 5).await?;
+        let timings = search.last_search_timings().unwrap();
+        assert!(timings.tantivy_search > Duration::ZERO, "{:?}", timings);
+        assert!(timings.fuse > Duration::ZERO, "{:?}", timings);
+        // Keyword-only on Tantivy
+        assert_eq!((timings.embed_query, timings.bm25_search, timings.semantic_search), (Duration::ZERO, Duration::ZERO, Duration::ZERO));
+        let phases: Duration = timings.phases().iter().map(|(_, duration)| *duration).sum();
+        assert!(phases <= timings.total, "{:?}", timings);
+        assert!(phases >= timings.total / 10, "phases should dominate a search: {:?}", timings);
+
+        // Answered from the result cache
+        search.search("parse_config", 5).await?;
+        let cached = search.last_search_timings().unwrap();
+        assert_eq!(cached.phases().map(|(_, duration)| duration), [Duration::ZERO; 5]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rounded_scores_give_identical_json_across_runs() -> Result<()> {
+        let mut outputs = Vec::new();
+        for _ in 0..2 {
+            let temp_dir = tempdir()?;
+            let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+            let mut search = HybridSearch::with_embedders(
+                &db_path,
+                &Config::default(),
+                Box::new(MinimalEmbedder::new(64)),
+                Box::new(MinimalEmbedder::new(64)),
+            ).await?;
+            search.set_fusion_config(FusionConfig { score_precision: Some(4), ..search.fusion_config().clone() });
+            search.index_documents(vec![
+                document("a", "refresh the session token", &[("source", "wiki"), ("lang", "en"), ("owner", "auth")]),
+                document("b", "session token expiry", &[("source", "chat"), ("lang", "en")]),
+                document("c", "token bucket rate limiting", &[]),
+            ]).await?;
+            let results = search.search("session token", 10).await?;
+            for result in &results {
+                assert_eq!(search.fusion_config().round_score(result.score), result.score, "{:?}", result);
+            }
+            outputs.push(serde_json::to_string(&results)?);
+        }
+        assert_eq!(outputs[0], outputs[1]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_search_stream_yields_what_search_returns() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut search = HybridSearch::with_embedders(
+            &db_path,
+            &Config::default(),
+            Box::new(MinimalEmbedder::new(64)),
+            Box::new(MinimalEmbedder::new(64)),
+        ).await?;
+        search.set_fusion_config(FusionConfig { max_result_content_chars: Some(20), ..search.fusion_config().clone() });
+        let paths: Vec<String> = (0..12).map(|i| format!("session_{}.rs", i)).collect();
+        let contents = (0..12).map(|i| format!("fn refresh_session_{}() {{ token_{}(); }}\n{}", i, i % 3, "// session ".repeat(i))).collect();
+        search.index(contents, paths).await?;
+
+        let expected = search.search("refresh session token", 8).await?;
+        assert!(expected.len() > 1, "{:?}", expected);
+        let streamed: Vec<SearchResult> = search.search_stream("refresh session token", 8).map(Result::unwrap).collect().await;
+        assert_eq!(serde_json::to_string(&streamed)?, serde_json::to_string(&expected)?);
+        assert!(search.search_stream("   ", 8).collect::<Vec<_>>().await.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_recency_boost_ranks_the_newer_of_two_equal_files_first() -> Result<()> {
+        let tree = tempdir()?;
+        let content = "fn refresh_token() {}\n";
+        std::fs::write(tree.path().join("a_old.rs"), content)?;
+        std::fs::write(tree.path().join("b_new.rs"), content)?;
+        let sixty_days_ago = SystemTime::now() - Duration::from_secs(60 * 86_400);
+        std::fs::File::options().write(true).open(tree.path().join("a_old.rs"))?.set_modified(sixty_days_ago)?;
+
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut config = Config::default();
+        config.search.semantic_mode = SemanticMode::Disabled;
+        let mut search = HybridSearch::with_config(&db_path, &config).await?;
+        search.index_directory(tree.path(), &config, |_| {}).await?;
+        let name = |result: &SearchResult| Path::new(&result.file_path).file_name().unwrap().to_string_lossy().into_owned();
+
+        let plain = search.search("refresh_token", 5).await?;
+        assert_eq!(plain.len(), 2);
+        assert!(plain.iter().all(|result| result.metadata.contains_key(MTIME_METADATA_KEY)), "{:?}", plain);
+        search.set_fusion_config(FusionConfig {
+            recency_boost: Some(RecencyBoost { half_life_days: 7.0, weight: 1.0 }),
+            ..search.fusion_config().clone()
+        });
+        let boosted = search.search("refresh_token", 5).await?;
+        assert_eq!(boosted.iter().map(name).collect::<Vec<_>>(), ["b_new.rs", "a_old.rs"]);
+        for result in &boosted {
+            let before = plain.iter().find(|p| p.file_path == result.file_path).unwrap().score;
+            let expected = if name(result) == "b_new.rs" { 2.0 } else { 1.0 + 0.5f32.powf(60.0 / 7.0) };
+            assert!((result.score / before - expected).abs() < 1e-3, "{:?}", result);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_result_content_is_capped_but_snippet_keeps_the_match() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut search = HybridSearch::with_semantic_mode(&db_path, SemanticMode::Disabled).await?;
+        let content = format!("{}\nfn refresh_token() {{}}\n", "// é 🦀 padding line\n".repeat(200));
+        search.index(vec![content.clone()], vec!["auth.rs".to_string()]).await?;
+        assert_eq!(search.search("refresh_token", 5).await?[0].content, content);
+
+        search.set_fusion_config(FusionConfig { max_result_content_chars: Some(100), ..search.fusion_config().clone() });
+        for results in [search.search("refresh_token", 5).await?, search.search_paged("refresh_token", 0, 5).await?.results] {
+            let result = &results[0];
+            assert_eq!(result.content.chars().count(), 100);
+            assert!(content.starts_with(&result.content));
+            assert!(result.snippet.as_deref().unwrap().contains("**refresh_token**"), "{:?}", result.snippet);
+            assert_eq!(result.line_number, Some(202));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_repeated_search_hits_result_cache_until_index_changes() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut search = HybridSearch::with_semantic_mode(&db_path, SemanticMode::Disabled).await?;
+        search.index(vec!["fn parse_config() {}".to_string()], vec!["config.rs".to_string()]).await?;
+
+        let first = search.search("parse_config", 5).await?;
+        let second = search.search("  parse_config ", 5).await?;
+        assert_eq!(first.len(), 1);
+        assert_eq!(second.len(), 1);
+        let stats = search.result_cache_stats().unwrap();
+        assert_eq!((stats.hits, stats.misses), (1, 1));
+
+        // A different limit is a different entry
+        search.search("parse_config", 1).await?;
+        assert_eq!(search.result_cache_stats().unwrap().misses, 2);
+
+        search.index(vec!["fn parse_config_file() { parse_config() }".to_string()], vec!["loader.rs".to_string()]).await?;
+        let after_index = search.search("parse_config", 5).await?;
+        assert_eq!(after_index.len(), 2);
+        let stats = search.result_cache_stats().unwrap();
+        assert_eq!((stats.hits, stats.misses), (1, 3));
+
+        search.clear().await?;
+        assert!(search.search("parse_config", 5).await?.is_empty());
+        assert_eq!(search.result_cache_stats().unwrap().misses, 4);
+        Ok(())
+    }
+
+    /// `MinimalEmbedder` that refuses any text containing "unembeddable"
+    pub(crate) struct PickyEmbedder(pub(crate) MinimalEmbedder);
+
+    impl Embedder for PickyEmbedder {
+        fn embed(&self, text: &str, task: EmbeddingTask) -> Result<Vec<f32>> {
+            anyhow::ensure!(!text.contains("unembeddable"), "model rejected the input");
+            self.0.embed(text, task)
+        }
+
+        fn dimension(&self) -> usize {
+            self.0.dimension()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_batch_keeps_input_order_and_isolates_a_failing_query() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut search = HybridSearch::with_embedders(
+            &db_path,
+            &Config::default(),
+            Box::new(PickyEmbedder(MinimalEmbedder::new(64))),
+            Box::new(PickyEmbedder(MinimalEmbedder::new(64))),
+        ).await?;
+        search.index(
+            vec!["fn parse_config() {}".to_string(), "fn render_page() {}".to_string()],
+            vec!["config.rs".to_string(), "page.rs".to_string()],
+        ).await?;
+
+        let queries = ["render page", "unembeddable parse", "parse config"].map(String::from);
+        let results = search.search_batch(&queries, 5).await?;
+        assert_eq!(results.len(), 3);
+        assert!(format!("{:#}", results[1].as_ref().unwrap_err()).contains("model rejected the input"));
+        for (i, expected) in [(0, "page.rs"), (2, "config.rs")] {
+            let batched = results[i].as_ref().unwrap();
+            assert_eq!(batched[0].file_path, expected);
+            let alone = search.search(&queries[i], 5).await?;
+            let paths = |results: &[SearchResult]| results.iter().map(|r| r.file_path.clone()).collect::<Vec<_>>();
+            assert_eq!(paths(batched), paths(&alone));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_apply_config_swaps_in_reloaded_fusion_weights() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut search = HybridSearch::with_semantic_mode(&db_path, SemanticMode::Disabled).await?;
+        search.set_fusion_config(FusionConfig { min_score: Some(0.01), ..search.fusion_config().clone() });
+
+        let mut reloaded = Config::default();
+        reloaded.search.fusion_exact_weight = 0.1;
+        reloaded.search.fusion_bm25_weight = 0.6;
+        reloaded.search.fusion_semantic_weight = 0.2;
+        reloaded.search.fusion_symbol_weight = 0.1;
+        assert_ne!(search.fusion_config().weights, reloaded.search.fusion_weights());
+        search.apply_config(&reloaded);
+
+        assert_eq!(search.fusion_config().weights, reloaded.search.fusion_weights());
+        // Settings the config doesn't carry are kept
+        assert_eq!(search.fusion_config().min_score, Some(0.01));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_search_grouped_by_file_collapses_chunk_hits() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut search = HybridSearch::with_semantic_mode(&db_path, SemanticMode::Disabled).await?;
+        search.index(
+            vec![
+                "fn parse_config() { parse config }".to_string(),
+                "fn reload() { parse config again }".to_string(),
+                "fn parse() { config config config parse parse }".to_string(),
+            ],
+            vec!["config.rs".to_string(), "config.rs".to_string(), "parser.rs".to_string()],
+        ).await?;
+
+        let ungrouped = search.search_grouped("parse config", 10, GroupBy::None).await?;
+        assert_eq!(ungrouped.len(), 3);
+
+        let groups = search.search_grouped("parse config", 10, GroupBy::File).await?;
+        assert_eq!(groups.len(), 2);
+        let config = groups.iter().find(|g| g.file_path == "config.rs").unwrap();
+        assert_eq!(config.chunk_hits.len(), 2);
+        assert!(config.chunk_hits.iter().all(|hit| hit.file_path == "config.rs"));
+        assert_eq!(config.best_score, config.chunk_hits[0].score);
+        let parser = groups.iter().find(|g| g.file_path == "parser.rs").unwrap();
+        assert_eq!(parser.chunk_hits.len(), 1);
+
+        // Summed scores put the file with two hits first
+        search.set_fusion_config(FusionConfig { group_score: GroupScore::Sum, ..FusionConfig::default() });
+        let groups = search.search_grouped("parse config", 10, GroupBy::File).await?;
+        assert_eq!(groups[0].file_path, "config.rs");
+        assert!((groups[0].score - groups[0].chunk_hits.iter().map(|h| h.score).sum::<f32>()).abs() < 1e-6);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_feedback_boosts_and_suppresses_paths() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut search = HybridSearch::with_semantic_mode(&db_path, SemanticMode::Disabled).await?;
+        search.index(
+            vec![
+                "parse config parse config".to_string(),
+                "parse config file".to_string(),
+                "parse the whole config module here".to_string(),
+            ],
+            vec!["a.rs".to_string(), "b.rs".to_string(), "c.rs".to_string()],
+        ).await?;
+        let plain: Vec<String> = search.search("parse config", 5).await?.into_iter().map(|r| r.file_path).collect();
+        assert_eq!(plain.len(), 3);
+        let (first, last) = (plain[0].clone(), plain[2].clone());
+
+        let feedback = SearchFeedback {
+            boosted_paths: vec![last.clone()],
+            suppressed_paths: vec![format!("./{}", first)],
+        };
+        let results: Vec<String> = search.with_feedback(&feedback).search("parse config", 5).await?
+            .into_iter().map(|r| r.file_path).collect();
+        assert_eq!(results[0], last);
+        assert_eq!(results.len(), 2);
+        assert!(!results.contains(&first));
+
+        // Nothing is kept for later searches
+        let again: Vec<String> = search.search("parse config", 5).await?.into_iter().map(|r| r.file_path).collect();
+        assert_eq!(again, plain);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_disabled_semantic_mode_searches_by_keyword() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut search = HybridSearch::with_semantic_mode(&db_path, SemanticMode::Disabled).await?;
+        assert!(!search.is_semantic_enabled());
+
+        search.index(
+            vec!["fn authenticate(user: &User) -> bool".to_string(), "struct Template { html: String }".to_string()],
+            vec!["auth.rs".to_string(), "template.rs".to_string()],
+        ).await?;
+        assert_eq!(search.stats()?.vector_count, 0);
+
+        let results = search.search("authenticate", 5).await?;
+        assert_eq!(results[0].file_path, "auth.rs");
+        // The text side carries the vector side's weight too
+        assert!((results[0].score - 2.0 / 61.0).abs() < 1e-6);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_optional_semantic_mode_starts_with_or_without_models() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut config = Config::default();
+        config.search.semantic_mode = SemanticMode::Optional;
+        config.search.backend = SearchBackend::Bm25Only;
+        let mut search = HybridSearch::with_config(&db_path, &config).await?;
+
+        search.index(vec!["fn authenticate() {}".to_string()], vec!["auth.rs".to_string()]).await?;
+        let results = search.search("authenticate", 5).await?;
+        assert_eq!(results[0].file_path, "auth.rs");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_search_results_carry_first_match_line() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut search = HybridSearch::with_embedders(
+            &db_path,
+            &Config::default(),
+            Box::new(MinimalEmbedder::new(64)),
+            Box::new(MinimalEmbedder::new(64)),
+        ).await?;
+        let auth = "use crate::user::User;\n\n/// Checks credentials\nfn authenticate(user: &User) -> bool {\n    user.authenticate()\n}";
+        search.index(vec![auth.to_string()], vec!["auth.rs".to_string()]).await?;
+
+        let results = search.search("authenticate", 5).await?;
+        assert_eq!(results[0].line_number, Some(4));
+        let line = auth.lines().nth(results[0].line_number.unwrap() - 1).unwrap();
+        assert!(line.contains("authenticate"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_min_score_keeps_only_hybrid_matches() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut search = HybridSearch::with_embedders(
+            &db_path,
+            &Config::default(),
+            Box::new(MinimalEmbedder::new(64)),
+            Box::new(MinimalEmbedder::new(64)),
+        ).await?;
+        search.index(
+            vec!["fn authenticate(user: &User) -> bool".to_string(), "fn render(template: &str)".to_string()],
+            vec!["auth.rs".to_string(), "render.rs".to_string()],
+        ).await?;
+
+        // A single-list rank-1 RRF score is 1/61; only results found by both lists clear it
+        search.set_fusion_config(FusionConfig { min_score: Some(1.5 / 61.0), ..FusionConfig::default() });
+        let results = search.search("authenticate", 5).await?;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].file_path, "auth.rs");
+
+        search.set_fusion_config(FusionConfig { min_score: Some(1.0), ..FusionConfig::default() });
+        assert!(search.search("authenticate", 5).await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_search_paged_reports_total_and_pages() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut search = HybridSearch::with_embedders(
+            &db_path,
+            &Config::default(),
+            Box::new(MinimalEmbedder::new(64)),
+            Box::new(MinimalEmbedder::new(64)),
+        ).await?;
+        let contents: Vec<String> = (0..7).map(|i| format!("fn handler_{}(request: Request) {{}}", i)).collect();
+        let paths: Vec<String> = (0..7).map(|i| format!("handler_{}.rs", i)).collect();
+        search.index(contents, paths).await?;
+
+        let first = search.search_paged("request", 0, 3).await?;
+        assert_eq!(first.total_matched, 7);
+        assert_eq!((first.offset, first.limit, first.results.len()), (0, 3, 3));
+
+        let last = search.search_paged("request", 6, 3).await?;
+        assert_eq!(last.total_matched, 7);
+        assert_eq!(last.results.len(), 1);
+
+        let mut seen = std::collections::HashSet::new();
+        for offset in [0, 3, 6] {
+            for result in search.search_paged("request", offset, 3).await?.results {
+                assert!(seen.insert(result.file_path));
+            }
+        }
+        assert_eq!(seen.len(), 7);
+
+        let beyond = search.search_paged("request", 50, 3).await?;
+        assert!(beyond.results.is_empty());
+        assert_eq!(beyond.total_matched, 7);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_heading_weight_boosts_matching_markdown_section() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut search = HybridSearch::with_embedders(
+            &db_path,
+            &Config::default(),
+            Box::new(MinimalEmbedder::new(64)),
+            Box::new(MinimalEmbedder::new(64)),
+        ).await?;
+        let guide = "# Guide\n\n## Installation\n\n### Linux\n\nUse the package manager.";
+        let notes = "# Notes\n\nLinux install notes: linux, linux, linux install.";
+        search.index(
+            vec![notes.to_string(), guide.to_string(), notes.to_string()],
+            vec!["notes.md".to_string(), "guide.md".to_string(), "notes.rs".to_string()],
+        ).await?;
+
+        search.set_fusion_config(FusionConfig { heading_weight: 0.0, ..FusionConfig::default() });
+        let unboosted = search.search("linux install", 5).await?;
+        let guide_score = unboosted.iter().find(|r| r.file_path == "guide.md").unwrap().score;
+
+        search.set_fusion_config(FusionConfig { heading_weight: 0.05, ..FusionConfig::default() });
+        let results = search.search("linux install", 5).await?;
+        assert_eq!(results[0].file_path, "guide.md");
+        assert!((results[0].score - guide_score - 0.05).abs() < 1e-6);
+        assert_eq!(results[0].heading_path.as_deref(), Some("Guide > Installation > Linux"));
+        let code = results.iter().find(|r| r.file_path == "notes.rs").unwrap();
+        assert!(code.heading_path.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dedup_threshold_collapses_copies() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut search = HybridSearch::with_embedders(
+            &db_path,
+            &Config::default(),
+            Box::new(MinimalEmbedder::new(64)),
+            Box::new(MinimalEmbedder::new(64)),
+        ).await?;
+        let copy = "fn parse_config(path: &str) -> Config { load(path) }";
+        search.index(
+            vec![copy.to_string(), copy.to_string(), copy.to_string(), "fn render_page(template: &Html) {}".to_string()],
+            vec!["gen/a.rs".to_string(), "gen/b.rs".to_string(), "gen/c.rs".to_string(), "page.rs".to_string()],
+        ).await?;
+        assert_eq!(search.search("parse config", 10).await?.len(), 4);
+
+        search.set_fusion_config(FusionConfig { dedup_threshold: Some(0.95), ..FusionConfig::default() });
+        let results = search.search("parse config", 10).await?;
+        assert_eq!(results.len(), 2);
+        assert!(results[0].file_path.starts_with("gen/"));
+        assert_eq!(results[1].file_path, "page.rs");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_search_with_embedding_uses_vector_for_semantic_side() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let embedder = MinimalEmbedder::new(64);
+        let mut search = HybridSearch::with_embedders(
+            &db_path,
+            &Config::default(),
+            Box::new(embedder.clone()),
+            Box::new(embedder.clone()),
+        ).await?;
+        search.index(
+            vec!["fn authenticate(user: &User) -> bool".to_string(), "fn render(template: &str)".to_string()],
+            vec!["auth.rs".to_string(), "render.rs".to_string()],
+        ).await?;
+
+        // Lexical query matches render.rs, the vector points at auth.rs
+        let vector = embedder.embed("authenticate user", EmbeddingTask::SearchQuery)?;
+        let results = search.search_with_embedding("template", &vector, 5).await?;
+        let auth = results.iter().find(|r| r.file_path == "auth.rs").unwrap();
+        let render = results.iter().find(|r| r.file_path == "render.rs").unwrap();
+        assert_eq!(auth.match_type, "vector");
+        assert_eq!(render.match_type, "hybrid");
+        assert!(render.snippet.as_deref().unwrap().contains("**template**"));
+
+        let err = search.search_with_embedding("template", &[1.0; 3], 5).await.unwrap_err();
+        match err.downcast_ref::<SearchError>() {
+            Some(SearchError::Storage(StorageError::DimensionMismatch { expected, actual })) => {
+                assert_eq!((*expected, *actual), (64, 3));
+            }
+            other => panic!("expected DimensionMismatch, got {:?}", other),
+        }
+        Ok(())
+    }
+
+    fn filter(include: &[&str], exclude: &[&str]) -> PathFilter {
+        PathFilter {
+            include: include.iter().map(|s| s.to_string()).collect(),
+            exclude: exclude.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_path_filter_include_and_exclude() -> Result<()> {
+        let matcher = filter(&["src/**"], &["**/tests/**", "*.md"]).compile()?;
+        assert!(matcher.is_match("src/lib.rs"));
+        assert!(matcher.is_match("./src/search/mod.rs"));
+        assert!(!matcher.is_match("src/tests/helpers.rs"));
+        assert!(!matcher.is_match("src/README.md"));
+        assert!(!matcher.is_match("benches/search.rs"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_path_filter_exclude_only() -> Result<()> {
+        let matcher = filter(&[], &["tests/**"]).compile()?;
+        assert!(matcher.is_match("src/lib.rs"));
+        assert!(!matcher.is_match("tests/integration.rs"));
+        assert!(PathFilter::default().is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_count_indexed_paths_matching() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db").to_str().unwrap().to_string();
+        let mut search = HybridSearch::with_embedders(
+            &db_path,
+            &Config::default(),
+            Box::new(MinimalEmbedder::new(64)),
+            Box::new(MinimalEmbedder::new(64)),
+        ).await?;
+        search.index(
+            vec!["fn a() {}".to_string(), "fn b() {}".to_string(), "# Docs".to_string()],
+            vec!["src/a.rs".to_string(), "src/b.rs".to_string(), "README.md".to_string()],
+        ).await?;
+
+        assert_eq!(search.count_indexed_paths_matching(&filter(&["src/**"], &[]))?, 2);
+        assert_eq!(search.count_indexed_paths_matching(&filter(&["src/a.rs"], &[]))?, 1);
+        assert_eq!(search.count_indexed_paths_matching(&filter(&["lib/**"], &[]))?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_path_filter_rejects_malformed_glob() {
+        match filter(&["src/[unclosed"], &[]).compile() {
+            Err(SearchError::InvalidParameter { parameter, .. }) => assert_eq!(parameter, "include"),
+            Err(other) => panic!("expected InvalidParameter, got {:?}", other),
+            Ok(_) => panic!("expected malformed glob to be rejected"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_only_hybrid_search_refuses_to_index() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().to_str().unwrap().to_string();
+        let mut config = Config::default();
+        config.search.semantic_mode = SemanticMode::Disabled;
+        let missing = HybridSearch::open_read_only(&db_path, &config).await.err().unwrap();
+        assert!(matches!(missing.downcast_ref::<SearchError>(), Some(SearchError::IndexNotReady { .. })), "{:?}", missing);
+
+        let (contents, paths) = numbered_files(3);
+        let mut writer = HybridSearch::with_config(&db_path, &config).await?;
+        writer.index(contents.clone(), paths.clone()).await?;
+
+        let mut reader = HybridSearch::open_read_only(&db_path, &config).await?;
+        assert!(!reader.search("handler_1", 5).await?.is_empty());
+        let is_read_only = |err: anyhow::Error| matches!(err.downcast_ref::<SearchError>(), Some(SearchError::ReadOnly { path }) if path == &db_path);
+        assert!(is_read_only(reader.index(contents, paths).await.unwrap_err()));
+        assert!(is_read_only(reader.index_directory(temp_dir.path(), &config, |_| {}).await.unwrap_err()));
+        assert!(is_read_only(reader.clear().await.unwrap_err()));
+        assert_eq!(reader.indexed_document_count()?, 3);
+        Ok(())
+    }
+
+}
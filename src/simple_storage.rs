@@ -1,50 +1,304 @@
-use anyhow::Result;
-use std::collections::HashMap;
+use anyhow::{bail, Result};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use serde::{Serialize, Deserialize};
 
+use crate::config::{StorageBackend, VectorQuantization};
+use crate::embedder::fnv1a;
+use crate::error::StorageError;
+
+/// Vector storage operations `HybridSearch` relies on, so the store can be swapped per config
+pub trait VectorStore: Send + Sync {
+    /// Store embeddings with their content and file paths, matched by position. The
+    /// vectors given for a file are all of its chunks: earlier ones are replaced.
+    fn insert(&mut self, contents: Vec<String>, embeddings: Vec<Vec<f32>>, file_paths: Vec<String>) -> Result<()> {
+        let metadata = vec![HashMap::new(); contents.len()];
+        self.insert_with_metadata(contents, embeddings, file_paths, metadata)
+    }
+
+    /// `insert` with a metadata map per vector, returned on its search results
+    fn insert_with_metadata(
+        &mut self,
+        contents: Vec<String>,
+        embeddings: Vec<Vec<f32>>,
+        file_paths: Vec<String>,
+        metadata: Vec<HashMap<String, String>>,
+    ) -> Result<()>;
+
+    /// The `k` stored vectors most similar to `query_embedding`, best first
+    fn search_similar(&self, query_embedding: &[f32], k: usize) -> Result<Vec<SearchResult>>;
+
+    /// Remove every stored vector of `file_path`, returning how many were removed
+    fn remove(&mut self, file_path: &str) -> Result<usize>;
+
+    /// Remove every stored vector
+    fn clear(&mut self) -> Result<()>;
+
+    /// Number of stored vectors
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Dimension of the stored vectors, if any have been stored
+    fn dimension(&self) -> Option<usize>;
+
+    /// Stored embedding of the chunk with this path and content, if there is one
+    fn embedding(&self, file_path: &str, content: &str) -> Option<Vec<f32>>;
+
+    /// Every stored vector in insertion order, e.g. to write it to an archive
+    fn records(&self) -> Vec<EmbeddingRecord>;
+}
+
+/// File under the db path the vectors of `open_vector_store` are saved to
+pub const VECTOR_STORE_FILE: &str = "vector_store.json";
+
+/// Format version of `VECTOR_STORE_FILE`; a file with another version is ignored
+pub const VECTOR_STORE_VERSION: u32 = 1;
+
+/// Open the vector store selected by `backend`, holding vectors as `quantization` says.
+/// Vectors saved under `db_path` by an earlier process are loaded, and every change
+/// is saved there again, so separate processes on the same `db_path` see one store.
+pub fn open_vector_store(backend: StorageBackend, quantization: VectorQuantization, db_path: &str) -> Result<Box<dyn VectorStore>> {
+    match backend {
+        StorageBackend::Simple => Ok(Box::new(PersistedVectorStorage::open(&Path::new(db_path).join(VECTOR_STORE_FILE), quantization)?)),
+    }
+}
+
 /// Simple in-memory vector storage for CPU-only systems
 /// Replaces LanceDB to avoid arrow dependency conflicts
 #[derive(Clone)]
 pub struct VectorStorage {
-    documents: Vec<Document>,
+    documents: Vec<StoredRecord>,
+    /// Dimension of the first stored embedding, used to validate queries
+    dimension: Option<usize>,
+    quantization: VectorQuantization,
 }
 
+/// Stable id of the `chunk_index`th chunk of `file_path`, the same every time the file
+/// is indexed
+pub fn chunk_doc_id(file_path: &str, chunk_index: usize) -> String {
+    format!("{:016x}", fnv1a(0, format!("{}#{}", file_path, chunk_index).as_bytes()))
+}
+
+/// A stored chunk together with its embedding
 #[derive(Clone, Debug, Serialize, Deserialize)]
-struct Document {
-    id: usize,
-    content: String,
-    file_path: String,
-    embedding: Vec<f32>,
+pub struct EmbeddingRecord {
+    pub id: usize,
+    /// `chunk_doc_id` of the chunk; empty in archives written before it existed
+    #[serde(default)]
+    pub doc_id: String,
+    pub content: String,
+    pub file_path: String,
+    pub embedding: Vec<f32>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub metadata: HashMap<String, String>,
+}
+
+/// An embedding held as `scale * values`, see `VectorQuantization::Int8`
+#[derive(Clone, Debug, PartialEq)]
+pub struct QuantizedVector {
+    pub scale: f32,
+    pub values: Vec<i8>,
+}
+
+impl QuantizedVector {
+    /// `vector` with its largest magnitude mapped to ±127 and the rest rounded to match
+    pub fn quantize(vector: &[f32]) -> Self {
+        let max = vector.iter().fold(0.0f32, |max, x| max.max(x.abs()));
+        let scale = if max > 0.0 { max / 127.0 } else { 1.0 };
+        Self { scale, values: vector.iter().map(|x| (x / scale).round() as i8).collect() }
+    }
+
+    pub fn dequantize(&self) -> Vec<f32> {
+        self.values.iter().map(|&v| v as f32 * self.scale).collect()
+    }
+
+    /// Cosine similarity with `query`, computed on the quantized values directly since
+    /// the scale cancels out; mismatched lengths score 0.0
+    pub fn cosine_similarity(&self, query: &[f32]) -> f32 {
+        if query.len() != self.values.len() {
+            return 0.0;
+        }
+        let (mut dot, mut norm_q, mut norm_v) = (0.0f32, 0.0f32, 0.0f32);
+        for (&q, &v) in query.iter().zip(&self.values) {
+            let v = v as f32;
+            dot += q * v;
+            norm_q += q * q;
+            norm_v += v * v;
+        }
+        if norm_q == 0.0 || norm_v == 0.0 {
+            return 0.0;
+        }
+        dot / (norm_q.sqrt() * norm_v.sqrt())
+    }
+}
+
+/// On-disk form of a `VectorStorage`, written by `VectorStorage::save`
+#[derive(Serialize, Deserialize)]
+struct VectorSnapshot {
+    version: u32,
+    records: Vec<EmbeddingRecord>,
+}
+
+/// A record as `VectorStorage` holds it: when quantized, `record.embedding` is empty
+/// and `quantized` has the vector instead
+#[derive(Clone)]
+struct StoredRecord {
+    record: EmbeddingRecord,
+    quantized: Option<QuantizedVector>,
+}
+
+impl StoredRecord {
+    fn new(mut record: EmbeddingRecord, quantization: VectorQuantization) -> Self {
+        let quantized = match quantization {
+            VectorQuantization::None => None,
+            VectorQuantization::Int8 => Some(QuantizedVector::quantize(&std::mem::take(&mut record.embedding))),
+        };
+        Self { record, quantized }
+    }
+
+    fn similarity(&self, query: &[f32]) -> f32 {
+        match &self.quantized {
+            Some(quantized) => quantized.cosine_similarity(query),
+            None => cosine_similarity(query, &self.record.embedding),
+        }
+    }
+
+    fn embedding(&self) -> Vec<f32> {
+        match &self.quantized {
+            Some(quantized) => quantized.dequantize(),
+            None => self.record.embedding.clone(),
+        }
+    }
+
+    /// The record with its embedding at full width again
+    fn to_record(&self) -> EmbeddingRecord {
+        match &self.quantized {
+            Some(quantized) => EmbeddingRecord { embedding: quantized.dequantize(), ..self.record.clone() },
+            None => self.record.clone(),
+        }
+    }
 }
 
 impl VectorStorage {
     pub fn new(_db_path: &str) -> Result<Self> {
         Ok(Self {
             documents: Vec::new(),
+            dimension: None,
+            quantization: VectorQuantization::None,
         })
     }
 
+    /// Hold embeddings as `quantization` says, converting any already stored
+    pub fn with_quantization(mut self, quantization: VectorQuantization) -> Self {
+        if quantization != self.quantization {
+            self.documents = self.documents.iter().map(|doc| StoredRecord::new(doc.to_record(), quantization)).collect();
+            self.quantization = quantization;
+        }
+        self
+    }
+
+    pub fn quantization(&self) -> VectorQuantization {
+        self.quantization
+    }
+
+    /// Write every record, at full width, to `path`
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let snapshot = VectorSnapshot {
+            version: VECTOR_STORE_VERSION,
+            records: self.documents.iter().map(StoredRecord::to_record).collect(),
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Write to a temporary file first so a crash never leaves a truncated store
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, serde_json::to_vec(&snapshot)?)?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Load a store written by `save`, unquantized. Fails on a version mismatch or
+    /// when the records disagree on their dimension.
+    pub fn load(path: &Path) -> Result<Self> {
+        let snapshot: VectorSnapshot = serde_json::from_slice(&std::fs::read(path)?)?;
+        if snapshot.version != VECTOR_STORE_VERSION {
+            bail!("Vector store version {} does not match expected version {}", snapshot.version, VECTOR_STORE_VERSION);
+        }
+        let dimension = snapshot.records.first().map(|record| record.embedding.len());
+        if snapshot.records.iter().any(|record| Some(record.embedding.len()) != dimension) {
+            bail!("Vector store at {} mixes embedding dimensions", path.display());
+        }
+        Ok(Self {
+            documents: snapshot.records.into_iter().map(|record| StoredRecord::new(record, VectorQuantization::None)).collect(),
+            dimension,
+            quantization: VectorQuantization::None,
+        })
+    }
+
+    /// `load`, or an empty store when `path` is missing or can't be loaded
+    pub fn load_or_new(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Self::new("");
+        }
+        match Self::load(path) {
+            Ok(storage) => Ok(storage),
+            Err(e) => {
+                log::warn!("Ignoring vector store at {}: {}; index again to restore embeddings", path.display(), e);
+                Self::new("")
+            }
+        }
+    }
+
     /// Store embeddings with metadata
     pub fn store(&mut self, 
                 contents: Vec<String>, 
                 embeddings: Vec<Vec<f32>>, 
                 file_paths: Vec<String>) -> Result<()> {
+        let metadata = vec![HashMap::new(); contents.len()];
+        self.store_with_metadata(contents, embeddings, file_paths, metadata)
+    }
+
+    /// `store` with a metadata map per embedding, matched by position. A call holds
+    /// every chunk of the files it names, so their earlier chunks are replaced and
+    /// chunk `i` of a file gets `chunk_doc_id(file_path, i)`.
+    pub fn store_with_metadata(&mut self,
+                contents: Vec<String>,
+                embeddings: Vec<Vec<f32>>,
+                file_paths: Vec<String>,
+                metadata: Vec<HashMap<String, String>>) -> Result<()> {
         
-        let start_id = self.documents.len();
+        let replaced: HashSet<&String> = file_paths.iter().collect();
+        self.documents.retain(|doc| !replaced.contains(&doc.record.file_path));
+        let start_id = self.documents.iter().map(|doc| doc.record.id + 1).max().unwrap_or(0);
+        let mut chunk_counts: HashMap<String, usize> = HashMap::new();
         
-        for (i, ((content, embedding), file_path)) in contents.into_iter()
+        for (i, (((content, embedding), file_path), metadata)) in contents.into_iter()
             .zip(embeddings.into_iter())
             .zip(file_paths.into_iter())
+            .zip(metadata)
             .enumerate() {
             
-            let document = Document {
+            if self.dimension.is_none() {
+                self.dimension = Some(embedding.len());
+            }
+            
+            let chunk_index = chunk_counts.entry(file_path.clone()).or_default();
+            let doc_id = chunk_doc_id(&file_path, *chunk_index);
+            *chunk_index += 1;
+            
+            let document = EmbeddingRecord {
                 id: start_id + i,
+                doc_id,
                 content,
                 file_path,
                 embedding,
+                metadata,
             };
             
-            self.documents.push(document);
+            self.documents.push(StoredRecord::new(document, self.quantization));
         }
         
         Ok(())
@@ -55,7 +309,7 @@ impl VectorStorage {
         let mut results: Vec<(usize, f32)> = Vec::new();
         
         for (idx, doc) in self.documents.iter().enumerate() {
-            let similarity = cosine_similarity(&query_embedding, &doc.embedding);
+            let similarity = doc.similarity(&query_embedding);
             results.push((idx, similarity));
         }
         
@@ -66,11 +320,12 @@ impl VectorStorage {
         let search_results = results.into_iter()
             .take(limit)
             .map(|(idx, similarity)| {
-                let doc = &self.documents[idx];
+                let doc = &self.documents[idx].record;
                 SearchResult {
                     content: doc.content.clone(),
                     file_path: doc.file_path.clone(),
                     score: similarity,
+                    metadata: doc.metadata.clone(),
                 }
             })
             .collect();
@@ -78,9 +333,56 @@ impl VectorStorage {
         Ok(search_results)
     }
 
+    /// Nearest-neighbour query over the stored vectors by cosine similarity.
+    /// Fails with `StorageError::DimensionMismatch` if the query doesn't match the stored dimension.
+    pub fn search_similar(&self, query_embedding: &[f32], k: usize) -> Result<Vec<(EmbeddingRecord, f32)>> {
+        if let Some(expected) = self.dimension {
+            if query_embedding.len() != expected {
+                return Err(StorageError::DimensionMismatch {
+                    expected,
+                    actual: query_embedding.len(),
+                }.into());
+            }
+        }
+        
+        let mut scored: Vec<(&StoredRecord, f32)> = self.documents
+            .iter()
+            .map(|doc| (doc, doc.similarity(query_embedding)))
+            .collect();
+        scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        
+        Ok(scored
+            .into_iter()
+            .take(k)
+            .map(|(doc, score)| (doc.to_record(), score))
+            .collect())
+    }
+    
+    /// Embedding dimension of the stored vectors, if any have been stored
+    pub fn dimension(&self) -> Option<usize> {
+        self.dimension
+    }
+    
+    /// Stored embedding of the chunk with this path and content (linear scan),
+    /// dequantized when quantization is on
+    pub fn embedding(&self, file_path: &str, content: &str) -> Option<Vec<f32>> {
+        self.documents
+            .iter()
+            .find(|doc| doc.record.file_path == file_path && doc.record.content == content)
+            .map(StoredRecord::embedding)
+    }
+    
+    /// Remove every stored chunk belonging to `file_path`, returning how many were removed
+    pub fn remove_file(&mut self, file_path: &str) -> usize {
+        let before = self.documents.len();
+        self.documents.retain(|doc| doc.record.file_path != file_path);
+        before - self.documents.len()
+    }
+
     /// Clear all data
     pub fn clear(&mut self) -> Result<()> {
         self.documents.clear();
+        self.dimension = None;
         Ok(())
     }
     
@@ -95,28 +397,128 @@ impl VectorStorage {
     }
 }
 
+impl VectorStore for VectorStorage {
+    fn insert_with_metadata(
+        &mut self,
+        contents: Vec<String>,
+        embeddings: Vec<Vec<f32>>,
+        file_paths: Vec<String>,
+        metadata: Vec<HashMap<String, String>>,
+    ) -> Result<()> {
+        self.store_with_metadata(contents, embeddings, file_paths, metadata)
+    }
+
+    fn search_similar(&self, query_embedding: &[f32], k: usize) -> Result<Vec<SearchResult>> {
+        self.search(query_embedding.to_vec(), k)
+    }
+
+    fn remove(&mut self, file_path: &str) -> Result<usize> {
+        Ok(self.remove_file(file_path))
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        VectorStorage::clear(self)
+    }
+
+    fn len(&self) -> usize {
+        VectorStorage::len(self)
+    }
+
+    fn dimension(&self) -> Option<usize> {
+        VectorStorage::dimension(self)
+    }
+
+    fn embedding(&self, file_path: &str, content: &str) -> Option<Vec<f32>> {
+        VectorStorage::embedding(self, file_path, content)
+    }
+
+    fn records(&self) -> Vec<EmbeddingRecord> {
+        self.documents.iter().map(StoredRecord::to_record).collect()
+    }
+}
+
+/// `VectorStorage` saved to `path` after every change, so it outlives the process
+pub struct PersistedVectorStorage {
+    storage: VectorStorage,
+    path: PathBuf,
+}
+
+impl PersistedVectorStorage {
+    /// Load the store at `path` (empty when there is none yet), holding vectors as
+    /// `quantization` says. Nothing is written until the first change.
+    pub fn open(path: &Path, quantization: VectorQuantization) -> Result<Self> {
+        Ok(Self {
+            storage: VectorStorage::load_or_new(path)?.with_quantization(quantization),
+            path: path.to_path_buf(),
+        })
+    }
+}
+
+impl VectorStore for PersistedVectorStorage {
+    fn insert_with_metadata(
+        &mut self,
+        contents: Vec<String>,
+        embeddings: Vec<Vec<f32>>,
+        file_paths: Vec<String>,
+        metadata: Vec<HashMap<String, String>>,
+    ) -> Result<()> {
+        self.storage.store_with_metadata(contents, embeddings, file_paths, metadata)?;
+        self.storage.save(&self.path)
+    }
+
+    fn search_similar(&self, query_embedding: &[f32], k: usize) -> Result<Vec<SearchResult>> {
+        VectorStore::search_similar(&self.storage, query_embedding, k)
+    }
+
+    fn remove(&mut self, file_path: &str) -> Result<usize> {
+        let removed = self.storage.remove_file(file_path);
+        if removed > 0 {
+            self.storage.save(&self.path)?;
+        }
+        Ok(removed)
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        self.storage.clear()?;
+        if self.path.exists() {
+            self.storage.save(&self.path)?;
+        }
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.storage.len()
+    }
+
+    fn dimension(&self) -> Option<usize> {
+        self.storage.dimension()
+    }
+
+    fn embedding(&self, file_path: &str, content: &str) -> Option<Vec<f32>> {
+        self.storage.embedding(file_path, content)
+    }
+
+    fn records(&self) -> Vec<EmbeddingRecord> {
+        VectorStore::records(&self.storage)
+    }
+}
+
 #[derive(Debug)]
 pub struct SearchResult {
     pub content: String,
     pub file_path: String,
     pub score: f32,
+    pub metadata: HashMap<String, String>,
 }
 
-/// Calculate cosine similarity between two vectors
-fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+/// Calculate cosine similarity between two vectors; mismatched lengths score 0.0.
+/// See `utils::math::cosine_similarity` for the checked version.
+pub(crate) fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
     if a.len() != b.len() {
         return 0.0;
     }
     
-    let dot_product: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
-    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
-    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
-    
-    if norm_a == 0.0 || norm_b == 0.0 {
-        return 0.0;
-    }
-    
-    dot_product / (norm_a * norm_b)
+    crate::utils::math::cosine_similarity_unchecked(a, b)
 }
 
 #[cfg(test)]
@@ -142,6 +544,220 @@ mod tests {
         Ok(())
     }
     
+    #[test]
+    fn test_remove_file() -> Result<()> {
+        let mut storage = VectorStorage::new("test.db")?;
+        storage.store(
+            vec!["a1".to_string(), "b".to_string(), "a2".to_string()],
+            vec![vec![1.0, 0.0], vec![0.0, 1.0], vec![1.0, 1.0]],
+            vec!["a.rs".to_string(), "b.rs".to_string(), "a.rs".to_string()],
+        )?;
+        
+        assert_eq!(storage.remove_file("a.rs"), 2);
+        assert_eq!(storage.len(), 1);
+        assert_eq!(storage.remove_file("missing.rs"), 0);
+        
+        Ok(())
+    }
+    
+    #[test]
+    fn test_restoring_a_file_replaces_its_chunks() -> Result<()> {
+        let mut storage = VectorStorage::new("test.db")?;
+        storage.store(
+            vec!["a1".to_string(), "b".to_string(), "a2".to_string(), "a3".to_string()],
+            vec![vec![1.0, 0.0], vec![0.0, 1.0], vec![1.0, 1.0], vec![1.0, 0.5]],
+            vec!["a.rs".to_string(), "b.rs".to_string(), "a.rs".to_string(), "a.rs".to_string()],
+        )?;
+        let first_ids: Vec<String> = storage.records().into_iter().filter(|r| r.file_path == "a.rs").map(|r| r.doc_id).collect();
+        assert_eq!(first_ids, (0..3).map(|i| chunk_doc_id("a.rs", i)).collect::<Vec<_>>());
+
+        // a.rs shrank to two chunks
+        storage.store(
+            vec!["a1 edited".to_string(), "a2".to_string()],
+            vec![vec![1.0, 0.1], vec![1.0, 1.0]],
+            vec!["a.rs".to_string(), "a.rs".to_string()],
+        )?;
+        assert_eq!(storage.len(), 3);
+        let records = storage.records();
+        let a: Vec<(&str, &str)> = records.iter().filter(|r| r.file_path == "a.rs").map(|r| (r.doc_id.as_str(), r.content.as_str())).collect();
+        assert_eq!(a, [(first_ids[0].as_str(), "a1 edited"), (first_ids[1].as_str(), "a2")]);
+        assert!(records.iter().any(|r| r.file_path == "b.rs" && r.doc_id == chunk_doc_id("b.rs", 0)));
+        assert_eq!(records.iter().map(|r| r.id).collect::<HashSet<_>>().len(), 3);
+        
+        Ok(())
+    }
+    
+    #[test]
+    fn test_search_similar_returns_top_k_with_scores() -> Result<()> {
+        let mut storage = VectorStorage::new("test.db")?;
+        storage.store(
+            vec!["x".to_string(), "y".to_string(), "xy".to_string()],
+            vec![vec![1.0, 0.0], vec![0.0, 1.0], vec![1.0, 1.0]],
+            vec!["x.rs".to_string(), "y.rs".to_string(), "xy.rs".to_string()],
+        )?;
+        
+        let results = storage.search_similar(&[1.0, 0.0], 2)?;
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0.file_path, "x.rs");
+        assert!((results[0].1 - 1.0).abs() < 1e-6);
+        assert_eq!(results[1].0.file_path, "xy.rs");
+        assert_eq!(results[1].0.embedding, vec![1.0, 1.0]);
+        
+        Ok(())
+    }
+    
+    #[test]
+    fn test_metadata_is_returned_with_results() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut store = open_vector_store(StorageBackend::Simple, VectorQuantization::None, dir.path().to_str().unwrap())?;
+        let metadata = HashMap::from([("language".to_string(), "rust".to_string())]);
+        store.insert_with_metadata(
+            vec!["x".to_string(), "y".to_string()],
+            vec![vec![1.0, 0.0], vec![0.0, 1.0]],
+            vec!["x.rs".to_string(), "y.rs".to_string()],
+            vec![metadata.clone(), HashMap::new()],
+        )?;
+        
+        let results = store.search_similar(&[1.0, 0.0], 2)?;
+        assert_eq!(results[0].metadata, metadata);
+        assert!(results[1].metadata.is_empty());
+        
+        Ok(())
+    }
+    
+    #[test]
+    fn test_search_similar_rejects_dimension_mismatch() -> Result<()> {
+        let mut storage = VectorStorage::new("test.db")?;
+        storage.store(vec!["x".to_string()], vec![vec![1.0, 0.0, 0.0]], vec!["x.rs".to_string()])?;
+        
+        let err = storage.search_similar(&[1.0, 0.0], 1).unwrap_err();
+        match err.downcast_ref::<StorageError>() {
+            Some(StorageError::DimensionMismatch { expected, actual }) => {
+                assert_eq!(*expected, 3);
+                assert_eq!(*actual, 2);
+            }
+            other => panic!("expected DimensionMismatch, got {:?}", other),
+        }
+        
+        Ok(())
+    }
+    
+    #[test]
+    fn test_open_vector_store_simple_matches_concrete_storage() -> Result<()> {
+        let contents = vec!["x".to_string(), "y".to_string(), "xy".to_string()];
+        let embeddings = vec![vec![1.0, 0.0], vec![0.0, 1.0], vec![1.0, 1.0]];
+        let paths = vec!["x.rs".to_string(), "y.rs".to_string(), "xy.rs".to_string()];
+
+        let mut concrete = VectorStorage::new("test.db")?;
+        concrete.store(contents.clone(), embeddings.clone(), paths.clone())?;
+        let dir = tempfile::tempdir()?;
+        let mut store = open_vector_store(StorageBackend::Simple, VectorQuantization::None, dir.path().to_str().unwrap())?;
+        store.insert(contents, embeddings, paths)?;
+        assert_eq!(store.len(), 3);
+        assert_eq!(store.dimension(), Some(2));
+
+        for query in [[1.0, 0.0], [0.0, 1.0], [0.7, 0.7]] {
+            let expected: Vec<String> = concrete.search(query.to_vec(), 2)?.into_iter().map(|r| r.file_path).collect();
+            let actual: Vec<String> = store.search_similar(&query, 2)?.into_iter().map(|r| r.file_path).collect();
+            assert_eq!(actual, expected);
+        }
+
+        store.clear()?;
+        assert!(store.is_empty());
+        assert_eq!(store.dimension(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_vector_store_reloads_what_an_earlier_store_saved() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let db_path = dir.path().join("test.db");
+        let db_path = db_path.to_str().unwrap();
+        for quantization in [VectorQuantization::None, VectorQuantization::Int8] {
+            let mut store = open_vector_store(StorageBackend::Simple, quantization, db_path)?;
+            store.insert(
+                vec!["x".to_string(), "y".to_string(), "z".to_string()],
+                vec![vec![1.0, 0.0], vec![0.0, 1.0], vec![0.5, 0.5]],
+                vec!["x.rs".to_string(), "y.rs".to_string(), "z.rs".to_string()],
+            )?;
+            assert_eq!(store.remove("z.rs")?, 1);
+            drop(store);
+
+            let reopened = open_vector_store(StorageBackend::Simple, quantization, db_path)?;
+            assert_eq!(reopened.len(), 2);
+            assert_eq!(reopened.dimension(), Some(2));
+            let best: Vec<String> = reopened.search_similar(&[0.0, 1.0], 1)?.into_iter().map(|r| r.file_path).collect();
+            assert_eq!(best, ["y.rs"]);
+            assert_eq!(reopened.records()[0].doc_id, chunk_doc_id("x.rs", 0));
+
+            let mut reopened = reopened;
+            reopened.clear()?;
+            assert!(open_vector_store(StorageBackend::Simple, quantization, db_path)?.is_empty());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_unreadable_vector_store_is_ignored() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(VECTOR_STORE_FILE);
+        let mut storage = VectorStorage::new("unused")?;
+        storage.store(vec!["x".to_string()], vec![vec![1.0, 0.0]], vec!["x.rs".to_string()])?;
+        storage.save(&path)?;
+        assert_eq!(VectorStorage::load(&path)?.len(), 1);
+
+        let stale = std::fs::read_to_string(&path)?
+            .replace(&format!("\"version\":{}", VECTOR_STORE_VERSION), "\"version\":0");
+        std::fs::write(&path, stale)?;
+        assert!(VectorStorage::load(&path).is_err());
+        assert!(VectorStorage::load_or_new(&path)?.is_empty());
+
+        std::fs::write(&path, b"not json")?;
+        assert!(VectorStorage::load_or_new(&path)?.is_empty());
+        assert!(VectorStorage::load_or_new(&dir.path().join("missing.json"))?.is_empty());
+        Ok(())
+    }
+
+    /// Deterministic pseudo-random vectors in `-1.0..1.0`
+    fn random_vectors(count: usize, dimension: usize, seed: u64) -> Vec<Vec<f32>> {
+        let mut state = seed;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 40) as f32 / (1u64 << 23) as f32 - 1.0
+        };
+        (0..count).map(|_| (0..dimension).map(|_| next()).collect()).collect()
+    }
+
+    #[test]
+    fn test_int8_quantization_keeps_recall_at_10() -> Result<()> {
+        let vectors = random_vectors(2000, 128, 0x9E37_79B9_7F4A_7C15);
+        let paths: Vec<String> = (0..vectors.len()).map(|i| format!("{}.rs", i)).collect();
+        let mut full = VectorStorage::new("test.db")?;
+        full.store(paths.clone(), vectors.clone(), paths.clone())?;
+        let mut quantized = VectorStorage::new("test.db")?.with_quantization(VectorQuantization::Int8);
+        quantized.store(paths.clone(), vectors, paths)?;
+
+        let top_10 = |storage: &VectorStorage, query: &[f32]| -> Result<HashSet<String>> {
+            Ok(storage.search_similar(query, 10)?.into_iter().map(|(record, _)| record.file_path).collect())
+        };
+        let queries = random_vectors(50, 128, 42);
+        let mut found = 0;
+        for query in &queries {
+            found += top_10(&quantized, query)?.intersection(&top_10(&full, query)?).count();
+        }
+        let recall = found as f32 / (10 * queries.len()) as f32;
+        assert!(recall >= 0.9, "recall@10 {}", recall);
+
+        // Records come back at full width, close to what was stored
+        let original = &full.records()[7].embedding;
+        let restored = &quantized.records()[7].embedding;
+        let scale = original.iter().fold(0.0f32, |max, x| max.max(x.abs())) / 127.0;
+        assert!(original.iter().zip(restored).all(|(a, b)| (a - b).abs() <= scale / 2.0 + 1e-6));
+        Ok(())
+    }
+
     #[test]
     fn test_cosine_similarity() {
         let a = vec![1.0, 0.0, 0.0];
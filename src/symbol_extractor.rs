@@ -4,15 +4,49 @@ use anyhow::Result;
 use tree_sitter::{Parser, Query, QueryCursor};
 use std::collections::HashMap;
 
+/// Symbols shared by the JavaScript and TypeScript grammars
+const JS_QUERY: &str = r#"
+    (function_declaration name: (identifier) @function.name)
+    (generator_function_declaration name: (identifier) @function.name)
+    (class_declaration name: (identifier) @class.name)
+    (method_definition name: (property_identifier) @method.name)
+    (field_definition property: (property_identifier) @method.name value: [(arrow_function) (function_expression)])
+    (variable_declarator name: (identifier) @variable.name)
+"#;
+
+/// TypeScript names classes with `type_identifier` and adds interfaces
+const TS_QUERY: &str = r#"
+    (function_declaration name: (identifier) @function.name)
+    (generator_function_declaration name: (identifier) @function.name)
+    (class_declaration name: (type_identifier) @class.name)
+    (abstract_class_declaration name: (type_identifier) @class.name)
+    (interface_declaration name: (type_identifier) @interface.name)
+    (method_definition name: (property_identifier) @method.name)
+    (method_signature name: (property_identifier) @method.name)
+    (public_field_definition name: (property_identifier) @method.name value: [(arrow_function) (function_expression)])
+    (variable_declarator name: (identifier) @variable.name)
+"#;
+
+/// Declarations whose methods are qualified as `Owner.method`
+const METHOD_OWNER_KINDS: &[&str] = &["class_declaration", "class", "abstract_class_declaration", "interface_declaration", "class_definition"];
+
+/// Function bodies end the search for an owning class: a function nested in a method is not a method
+const SCOPE_BOUNDARY_KINDS: &[&str] = &["function_definition"];
+
+/// Initializers that turn a variable into a function (`const f = () => {}`)
+const FUNCTION_VALUE_KINDS: &[&str] = &["arrow_function", "function_expression", "generator_function"];
+
 #[derive(Debug, Clone)]
 pub struct Symbol {
     pub name: String,
     pub kind: SymbolKind,
     pub line: usize,
     pub definition: String,
+    /// Decorator expressions without the leading `@`, e.g. `property` or `dataclass(frozen=True)`
+    pub decorators: Vec<String>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum SymbolKind {
     Function,
     Class,
@@ -70,39 +104,26 @@ impl SymbolExtractor {
         parsers.insert("py".to_string(), python_parser);
         queries.insert("py".to_string(), python_query);
         
-        // Initialize JavaScript/TypeScript parser and query
-        let mut js_parser = Parser::new();
-        js_parser.set_language(tree_sitter_javascript::language())?;
-        
-        let js_query = Query::new(
-            tree_sitter_javascript::language(),
-            r#"
-            (function_declaration name: (identifier) @function.name)
-            (class_declaration name: (identifier) @class.name)
-            (method_definition name: (property_identifier) @method.name)
-            (variable_declarator name: (identifier) @variable.name)
-            "#
-        )?;
-        
-        // Create separate parser for TypeScript (can't clone Parser)
-        let mut ts_parser = Parser::new();
-        ts_parser.set_language(tree_sitter_javascript::language())?;
-        
-        // Create separate query for TypeScript (can't clone Query)
-        let ts_query = Query::new(
-            tree_sitter_javascript::language(),
-            r#"
-            (function_declaration name: (identifier) @function.name)
-            (class_declaration name: (identifier) @class.name)
-            (method_definition name: (property_identifier) @method.name)
-            (variable_declarator name: (identifier) @variable.name)
-            "#
-        )?;
+        // Initialize JavaScript parsers and queries (the grammar covers JSX too).
+        // Separate instances per extension since Parser and Query can't be cloned.
+        for ext in ["js", "jsx"] {
+            let mut js_parser = Parser::new();
+            js_parser.set_language(tree_sitter_javascript::language())?;
+            parsers.insert(ext.to_string(), js_parser);
+            queries.insert(ext.to_string(), Query::new(tree_sitter_javascript::language(), JS_QUERY)?);
+        }
         
-        parsers.insert("js".to_string(), js_parser);
-        parsers.insert("ts".to_string(), ts_parser);
-        queries.insert("js".to_string(), js_query);
-        queries.insert("ts".to_string(), ts_query);
+        // TypeScript needs its own grammar for interfaces and type annotations
+        let ts_languages = [
+            ("ts", tree_sitter_typescript::language_typescript()),
+            ("tsx", tree_sitter_typescript::language_tsx()),
+        ];
+        for (ext, language) in ts_languages {
+            let mut ts_parser = Parser::new();
+            ts_parser.set_language(language)?;
+            parsers.insert(ext.to_string(), ts_parser);
+            queries.insert(ext.to_string(), Query::new(language, TS_QUERY)?);
+        }
         
         Ok(Self { parsers, queries })
     }
@@ -127,8 +148,28 @@ impl SymbolExtractor {
         for match_ in matches {
             for capture in match_.captures {
                 let node = capture.node;
-                let name = node.utf8_text(code.as_bytes())?;
-                let kind = self.determine_kind(&query.capture_names()[capture.index as usize]);
+                let mut name = node.utf8_text(code.as_bytes())?.to_string();
+                let mut kind = self.determine_kind(&query.capture_names()[capture.index as usize]);
+                
+                if kind == SymbolKind::Variable && Self::has_function_value(node) {
+                    kind = SymbolKind::Function;
+                }
+                // Python only records module-level assignments, not locals or class attributes
+                if Self::parent_kind(node) == Some("assignment") && !Self::is_module_level(node) {
+                    continue;
+                }
+                // A Python `def` directly inside a class body is a method
+                if kind == SymbolKind::Function
+                    && Self::parent_kind(node) == Some("function_definition")
+                    && Self::method_owner(node, code)?.is_some()
+                {
+                    kind = SymbolKind::Method;
+                }
+                if kind == SymbolKind::Method {
+                    if let Some(owner) = Self::method_owner(node, code)? {
+                        name = format!("{}.{}", owner, name);
+                    }
+                }
                 
                 // Get the full definition line
                 let start_byte = node.start_byte();
@@ -136,10 +177,11 @@ impl SymbolExtractor {
                 let definition = &code[start_byte..end_byte];
                 
                 symbols.push(Symbol {
-                    name: name.to_string(),
+                    name,
                     kind,
                     line: node.start_position().row + 1,
                     definition: definition.to_string(),
+                    decorators: Self::decorators(node, code)?,
                 });
             }
         }
@@ -162,6 +204,74 @@ impl SymbolExtractor {
         }
     }
     
+    /// Whether a `variable_declarator` name is bound to an arrow function or function expression
+    fn has_function_value(name_node: tree_sitter::Node) -> bool {
+        name_node
+            .parent()
+            .and_then(|declarator| declarator.child_by_field_name("value"))
+            .is_some_and(|value| FUNCTION_VALUE_KINDS.contains(&value.kind()))
+    }
+    
+    fn parent_kind(node: tree_sitter::Node) -> Option<&'static str> {
+        node.parent().map(|parent| parent.kind())
+    }
+    
+    /// Whether an assignment target sits directly in the module body
+    fn is_module_level(name_node: tree_sitter::Node) -> bool {
+        name_node
+            .parent()
+            .and_then(|assignment| assignment.parent())
+            .filter(|statement| statement.kind() == "expression_statement")
+            .and_then(|statement| statement.parent())
+            .is_some_and(|scope| scope.kind() == "module")
+    }
+    
+    /// Decorators applied to the definition named by `name_node`: Python's
+    /// `decorated_definition` wrapper or `decorator` children (TS/JS classes)
+    fn decorators(name_node: tree_sitter::Node, code: &str) -> Result<Vec<String>> {
+        let definition = match name_node.parent() {
+            Some(definition) => definition,
+            None => return Ok(Vec::new()),
+        };
+        
+        let mut holders = vec![definition];
+        if let Some(wrapper) = definition.parent().filter(|p| p.kind() == "decorated_definition") {
+            holders.push(wrapper);
+        }
+        
+        let mut decorators = Vec::new();
+        for holder in holders {
+            let mut cursor = holder.walk();
+            for child in holder.named_children(&mut cursor) {
+                if child.kind() == "decorator" {
+                    let text = child.utf8_text(code.as_bytes())?.trim();
+                    decorators.push(text.trim_start_matches('@').trim().to_string());
+                }
+            }
+        }
+        Ok(decorators)
+    }
+    
+    /// Name of the class or interface enclosing a method, if any
+    fn method_owner(name_node: tree_sitter::Node, code: &str) -> Result<Option<String>> {
+        // Skip the definition itself so a Python `def` doesn't stop the walk at its own node
+        let mut current = name_node.parent().and_then(|definition| definition.parent());
+        while let Some(node) = current {
+            if SCOPE_BOUNDARY_KINDS.contains(&node.kind()) {
+                return Ok(None);
+            }
+            if METHOD_OWNER_KINDS.contains(&node.kind()) {
+                return match node.child_by_field_name("name") {
+                    Some(owner) => Ok(Some(owner.utf8_text(code.as_bytes())?.to_string())),
+                    // Anonymous class expression
+                    None => Ok(None),
+                };
+            }
+            current = node.parent();
+        }
+        Ok(None)
+    }
+    
     fn find_line_end(&self, code: &str, start: usize) -> usize {
         code[start..]
             .find('\n')
@@ -202,4 +312,189 @@ impl SymbolExtractor {
     pub fn extract_typescript(&mut self, code: &str) -> Result<Vec<Symbol>> {
         self.extract(code, "ts")
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TS_FIXTURE: &str = r#"
+export interface Repository {
+    find(id: string): Promise<User>;
+}
+
+interface Internal {}
+
+export class UserService {
+    private cache = new Map<string, User>();
+
+    constructor(private repo: Repository) {}
+
+    async getUser(id: string): Promise<User> {
+        return this.repo.find(id);
+    }
+
+    handleClick = (event: Event) => {
+        console.log(event);
+    };
+}
+
+class Helper {
+    static format(name: string): string {
+        return name.trim();
+    }
+}
+
+export function createService(repo: Repository): UserService {
+    return new UserService(repo);
+}
+
+function internalHelper() {}
+
+export const formatName = (name: string): string => name.trim();
+const parseId = function (raw: string): number { return Number(raw); };
+const MAX_USERS = 100;
+"#;
+
+    const JS_FIXTURE: &str = r#"
+export class Cart {
+    total() {
+        return 0;
+    }
+    onAdd = () => this.total();
+}
+
+export const addItem = (cart, item) => cart.push(item);
+function removeItem(cart, item) {}
+let count = 0;
+"#;
+
+    fn find<'a>(symbols: &'a [Symbol], name: &str) -> &'a Symbol {
+        symbols.iter().find(|s| s.name == name)
+            .unwrap_or_else(|| panic!("missing symbol {}; got {:?}", name, symbols.iter().map(|s| &s.name).collect::<Vec<_>>()))
+    }
+
+    #[test]
+    fn test_typescript_classes_interfaces_and_functions() -> Result<()> {
+        let mut extractor = SymbolExtractor::new()?;
+        let symbols = extractor.extract_typescript(TS_FIXTURE)?;
+
+        assert_eq!(find(&symbols, "Repository").kind, SymbolKind::Interface);
+        assert_eq!(find(&symbols, "Internal").kind, SymbolKind::Interface);
+        assert_eq!(find(&symbols, "UserService").kind, SymbolKind::Class);
+        assert_eq!(find(&symbols, "Helper").kind, SymbolKind::Class);
+        assert_eq!(find(&symbols, "createService").kind, SymbolKind::Function);
+        assert_eq!(find(&symbols, "internalHelper").kind, SymbolKind::Function);
+        Ok(())
+    }
+
+    #[test]
+    fn test_typescript_methods_are_qualified() -> Result<()> {
+        let mut extractor = SymbolExtractor::new()?;
+        let symbols = extractor.extract_typescript(TS_FIXTURE)?;
+
+        assert_eq!(find(&symbols, "UserService.getUser").kind, SymbolKind::Method);
+        assert_eq!(find(&symbols, "UserService.constructor").kind, SymbolKind::Method);
+        assert_eq!(find(&symbols, "UserService.handleClick").kind, SymbolKind::Method);
+        assert_eq!(find(&symbols, "Helper.format").kind, SymbolKind::Method);
+        assert_eq!(find(&symbols, "Repository.find").kind, SymbolKind::Method);
+        // Plain fields are not methods
+        assert!(!symbols.iter().any(|s| s.name.ends_with("cache")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_typescript_const_arrow_functions() -> Result<()> {
+        let mut extractor = SymbolExtractor::new()?;
+        let symbols = extractor.extract_typescript(TS_FIXTURE)?;
+
+        assert_eq!(find(&symbols, "formatName").kind, SymbolKind::Function);
+        assert_eq!(find(&symbols, "parseId").kind, SymbolKind::Function);
+        assert_eq!(find(&symbols, "MAX_USERS").kind, SymbolKind::Variable);
+        Ok(())
+    }
+
+    const PY_FIXTURE: &str = r#"
+from dataclasses import dataclass
+
+DEFAULT_TIMEOUT = 30
+
+@dataclass(frozen=True)
+class Account:
+    owner: str
+    balance: int = 0
+
+    @property
+    def display_name(self):
+        label = self.owner.title()
+        return label
+
+    @staticmethod
+    def validate(owner):
+        def check(value):
+            return bool(value)
+        return check(owner)
+
+    async def refresh(self, client):
+        return await client.fetch(self.owner)
+
+def display_name(account):
+    return account.owner
+
+async def sync_all(accounts):
+    pass
+"#;
+
+    #[test]
+    fn test_python_methods_are_distinguished_from_functions() -> Result<()> {
+        let mut extractor = SymbolExtractor::new()?;
+        let symbols = extractor.extract_python(PY_FIXTURE)?;
+
+        assert_eq!(find(&symbols, "Account.display_name").kind, SymbolKind::Method);
+        assert_eq!(find(&symbols, "display_name").kind, SymbolKind::Function);
+        assert_eq!(find(&symbols, "Account.refresh").kind, SymbolKind::Method);
+        assert_eq!(find(&symbols, "sync_all").kind, SymbolKind::Function);
+        // Nested helper inside a method is a plain function
+        assert_eq!(find(&symbols, "check").kind, SymbolKind::Function);
+        Ok(())
+    }
+
+    #[test]
+    fn test_python_decorators_are_attached() -> Result<()> {
+        let mut extractor = SymbolExtractor::new()?;
+        let symbols = extractor.extract_python(PY_FIXTURE)?;
+
+        assert_eq!(find(&symbols, "Account").decorators, vec!["dataclass(frozen=True)".to_string()]);
+        assert_eq!(find(&symbols, "Account.display_name").decorators, vec!["property".to_string()]);
+        assert_eq!(find(&symbols, "Account.validate").decorators, vec!["staticmethod".to_string()]);
+        assert!(find(&symbols, "Account.refresh").decorators.is_empty());
+        assert!(find(&symbols, "display_name").decorators.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_python_only_module_level_assignments() -> Result<()> {
+        let mut extractor = SymbolExtractor::new()?;
+        let symbols = extractor.extract_python(PY_FIXTURE)?;
+
+        assert_eq!(find(&symbols, "DEFAULT_TIMEOUT").kind, SymbolKind::Variable);
+        assert!(!symbols.iter().any(|s| s.name == "label"));
+        assert!(!symbols.iter().any(|s| s.name == "balance"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_javascript_classes_and_arrow_functions() -> Result<()> {
+        let mut extractor = SymbolExtractor::new()?;
+        for ext in ["js", "jsx"] {
+            let symbols = extractor.extract(JS_FIXTURE, ext)?;
+            assert_eq!(find(&symbols, "Cart").kind, SymbolKind::Class);
+            assert_eq!(find(&symbols, "Cart.total").kind, SymbolKind::Method);
+            assert_eq!(find(&symbols, "Cart.onAdd").kind, SymbolKind::Method);
+            assert_eq!(find(&symbols, "addItem").kind, SymbolKind::Function);
+            assert_eq!(find(&symbols, "removeItem").kind, SymbolKind::Function);
+            assert_eq!(find(&symbols, "count").kind, SymbolKind::Variable);
+        }
+        Ok(())
+    }
 }
\ No newline at end of file
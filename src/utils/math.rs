@@ -0,0 +1,89 @@
+// Vector math for semantic ranking
+
+use crate::error::SearchError;
+
+/// Independent accumulators per pass, enough for the compiler to keep a full
+/// AVX register of partial sums instead of one serial add chain
+const LANES: usize = 8;
+
+/// Cosine similarity of two equal-length vectors. Returns 0.0 when either vector
+/// has zero norm, and `SearchError::InvalidParameter` when the lengths differ.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> Result<f32, SearchError> {
+    if a.len() != b.len() {
+        return Err(SearchError::InvalidParameter {
+            parameter: "vector".to_string(),
+            reason: format!("length {} does not match {}", b.len(), a.len()),
+        });
+    }
+    Ok(cosine_similarity_unchecked(a, b))
+}
+
+/// `cosine_similarity` without the length check; extra elements of the longer
+/// vector are ignored
+pub(crate) fn cosine_similarity_unchecked(a: &[f32], b: &[f32]) -> f32 {
+    let (dot, norm_a, norm_b) = dot_and_norms(a, b);
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a.sqrt() * norm_b.sqrt())
+}
+
+/// Dot product and squared norms in one pass over fixed-width chunks, which the
+/// compiler turns into packed multiply-adds without `unsafe` or nightly SIMD
+fn dot_and_norms(a: &[f32], b: &[f32]) -> (f32, f32, f32) {
+    let mut dot = [0.0f32; LANES];
+    let mut norm_a = [0.0f32; LANES];
+    let mut norm_b = [0.0f32; LANES];
+
+    let chunks_a = a.chunks_exact(LANES);
+    let chunks_b = b.chunks_exact(LANES);
+    let (tail_a, tail_b) = (chunks_a.remainder(), chunks_b.remainder());
+    for (x, y) in chunks_a.zip(chunks_b) {
+        for i in 0..LANES {
+            dot[i] += x[i] * y[i];
+            norm_a[i] += x[i] * x[i];
+            norm_b[i] += y[i] * y[i];
+        }
+    }
+
+    let mut sums = (dot.iter().sum::<f32>(), norm_a.iter().sum::<f32>(), norm_b.iter().sum::<f32>());
+    for (x, y) in tail_a.iter().zip(tail_b) {
+        sums.0 += x * y;
+        sums.1 += x * x;
+        sums.2 += y * y;
+    }
+    sums
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn naive(a: &[f32], b: &[f32]) -> f32 {
+        let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+        let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+        let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+        dot / (norm_a * norm_b)
+    }
+
+    #[test]
+    fn test_cosine_similarity_matches_naive() {
+        // 771 exercises both the chunked body and the remainder
+        let a: Vec<f32> = (0..771).map(|i| ((i * 7) % 13) as f32 - 6.0).collect();
+        let b: Vec<f32> = (0..771).map(|i| ((i * 5) % 11) as f32 - 5.0).collect();
+        let fast = cosine_similarity(&a, &b).unwrap();
+        assert!((fast - naive(&a, &b)).abs() < 1e-5);
+        assert!((cosine_similarity(&a, &a).unwrap() - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_cosine_similarity_edge_cases() {
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]).unwrap(), 0.0);
+        assert_eq!(cosine_similarity(&[], &[]).unwrap(), 0.0);
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]).unwrap(), 0.0);
+        match cosine_similarity(&[1.0; 3], &[1.0; 4]) {
+            Err(SearchError::InvalidParameter { parameter, .. }) => assert_eq!(parameter, "vector"),
+            other => panic!("expected InvalidParameter, got {:?}", other),
+        }
+    }
+}
@@ -1,7 +1,11 @@
 pub mod retry;
+pub mod math;
 pub mod memory;
 pub mod memory_monitor;
+pub mod text;
 
-pub use retry::{RetryConfig, RetryableOperation, retry_with_backoff};
+pub use retry::{RetryConfig, RetryableOperation, retry_with_backoff, retry_blocking_with_backoff};
+pub use math::cosine_similarity;
 pub use memory::{MemoryInfo, check_memory_available};
-pub use memory_monitor::{MemoryMonitor, SystemMemoryInfo, get_system_memory_info};
\ No newline at end of file
+pub use memory_monitor::{MemoryMonitor, SystemMemoryInfo, get_system_memory_info};
+pub use text::{content_preview, content_preview_with_marker, is_blank};
\ No newline at end of file
@@ -75,18 +75,7 @@ where
     Op: RetryableOperation<T, E>,
     E: fmt::Debug + fmt::Display,
 {
-    let mut backoff = ExponentialBackoff {
-        initial_interval: config.initial_delay,
-        max_interval: config.max_delay,
-        multiplier: config.multiplier,
-        max_elapsed_time: None,
-        // Explicit configuration - no defaults
-        current_interval: config.initial_delay,
-        start_time: std::time::Instant::now(),
-        randomization_factor: if config.jitter { 0.5 } else { 0.0 },
-        // Note: reset_interval field doesn't exist in backoff crate - configuration is explicit via other fields
-        clock: backoff::SystemClock {},
-    };
+    let mut backoff = exponential_backoff(&config);
 
     let mut attempt = 0;
     let operation_name = operation.operation_name().to_string();
@@ -127,6 +116,70 @@ where
     }
 }
 
+/// Blocking counterpart of `retry_with_backoff` for synchronous call sites.
+/// Sleeps the current thread between attempts; errors for which `is_retryable`
+/// returns false are returned immediately.
+pub fn retry_blocking_with_backoff<T, E, F, R>(
+    operation_name: &str,
+    config: &RetryConfig,
+    mut operation: F,
+    is_retryable: R,
+) -> Result<T, E>
+where
+    F: FnMut() -> Result<T, E>,
+    R: Fn(&E) -> bool,
+    E: fmt::Display,
+{
+    let mut backoff = exponential_backoff(config);
+    let mut attempt = 0;
+
+    loop {
+        attempt += 1;
+        match operation() {
+            Ok(result) => {
+                if attempt > 1 {
+                    debug!("Operation {} succeeded after {} attempts", operation_name, attempt);
+                }
+                return Ok(result);
+            }
+            Err(error) => {
+                if attempt > config.max_retries || !is_retryable(&error) {
+                    return Err(error);
+                }
+                match backoff.next_backoff() {
+                    Some(delay) => {
+                        warn!(
+                            "Operation {} failed (attempt {}/{}), retrying in {:?}: {}",
+                            operation_name,
+                            attempt,
+                            config.max_retries + 1,
+                            delay,
+                            error
+                        );
+                        std::thread::sleep(delay);
+                    }
+                    None => return Err(error),
+                }
+            }
+        }
+    }
+}
+
+fn exponential_backoff(config: &RetryConfig) -> ExponentialBackoff {
+    ExponentialBackoff {
+        initial_interval: config.initial_delay,
+        max_interval: config.max_delay,
+        multiplier: config.multiplier,
+        max_elapsed_time: None,
+        // Explicit configuration - no defaults
+        current_interval: config.initial_delay,
+        start_time: std::time::Instant::now(),
+        randomization_factor: if config.jitter { 0.5 } else { 0.0 },
+        // Note: reset_interval field doesn't exist in backoff crate - configuration is explicit via other fields
+        clock: backoff::SystemClock {},
+    }
+}
+
 /// Wrapper for database operations
 pub struct DatabaseOperation<F, T>
 where
@@ -325,6 +378,40 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_blocking_retry_stops_on_non_retryable_error() {
+        let config = RetryConfig::new(3, Duration::from_millis(1), Duration::from_millis(5), 2.0, false);
+        let mut attempts = 0;
+        let result: Result<(), String> = retry_blocking_with_backoff(
+            "test_op",
+            &config,
+            || {
+                attempts += 1;
+                Err("malformed".to_string())
+            },
+            |error| error != "malformed",
+        );
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn test_blocking_retry_respects_max_retries() {
+        let config = RetryConfig::new(2, Duration::from_millis(1), Duration::from_millis(5), 2.0, true);
+        let mut attempts = 0;
+        let result: Result<(), String> = retry_blocking_with_backoff(
+            "test_op",
+            &config,
+            || {
+                attempts += 1;
+                Err("busy".to_string())
+            },
+            |_| true,
+        );
+        assert!(result.is_err());
+        assert_eq!(attempts, 3);
+    }
+
     #[tokio::test]
     async fn test_immediate_success() {
         let operation = TestOperation::new("test_op", 0);
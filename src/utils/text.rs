@@ -0,0 +1,54 @@
+// Display helpers for indexed content
+
+/// Marker `content_preview` appends to truncated content
+pub const DEFAULT_TRUNCATION_MARKER: &str = "...";
+
+/// The first `max_chars` characters of `content`, followed by `DEFAULT_TRUNCATION_MARKER`
+/// when anything was cut. Cuts between characters, never inside a multibyte one.
+pub fn content_preview(content: &str, max_chars: usize) -> String {
+    content_preview_with_marker(content, max_chars, DEFAULT_TRUNCATION_MARKER)
+}
+
+/// `content_preview` appending `marker` (e.g. "…", or "" for none) to truncated content
+pub fn content_preview_with_marker(content: &str, max_chars: usize, marker: &str) -> String {
+    match content.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}{}", &content[..end], marker),
+        None => content.to_string(),
+    }
+}
+
+/// `text` has nothing but whitespace and zero-width characters (zero-width space,
+/// joiners, word joiner, byte order mark), so it is empty as a query
+pub fn is_blank(text: &str) -> bool {
+    text.chars().all(|c| c.is_whitespace() || matches!(c, '\u{200B}' | '\u{200C}' | '\u{200D}' | '\u{2060}' | '\u{FEFF}'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preview_never_splits_a_codepoint() {
+        let content = "🦀é漢🦀 parse_config 🦀🦀";
+        let chars = content.chars().count();
+        for max_chars in 0..=chars + 2 {
+            let preview = content_preview(content, max_chars);
+            let kept: String = content.chars().take(max_chars).collect();
+            if max_chars < chars {
+                assert_eq!(preview, format!("{}...", kept));
+            } else {
+                assert_eq!(preview, content);
+            }
+        }
+        assert_eq!(content_preview_with_marker(content, 2, "…"), "🦀é…");
+        assert_eq!(content_preview_with_marker("short", 10, "…"), "short");
+    }
+
+    #[test]
+    fn test_is_blank() {
+        for text in ["", " ", "\t\n", "\u{200B}\u{200D}\u{FEFF}", " \u{2060} "] {
+            assert!(is_blank(text), "{:?}", text);
+        }
+        assert!(!is_blank("\u{200B}a"));
+    }
+}
@@ -0,0 +1,251 @@
+// Change polling for `embed-search watch`
+
+use anyhow::{anyhow, Result};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use walkdir::WalkDir;
+
+use crate::config::WatchConfig;
+
+/// How a `Watcher` notices that files under its root may have changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeSource {
+    /// `HEAD` plus `git status`, with the modification time of every dirty path
+    Git,
+    /// Paths, sizes and modification times of every file outside `.git`
+    Mtime,
+}
+
+impl fmt::Display for ChangeSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChangeSource::Git => write!(f, "git"),
+            ChangeSource::Mtime => write!(f, "mtime"),
+        }
+    }
+}
+
+/// Polls a directory for changes. It only answers "did anything change since the
+/// last poll"; `IncrementalIndexer::reconcile` works out which files to re-index.
+pub struct Watcher {
+    root: PathBuf,
+    source: ChangeSource,
+    fingerprint: u64,
+}
+
+impl Watcher {
+    /// Watch `root` through git when `enable_git_watch` is set and `root` is inside a
+    /// work tree, otherwise by modification time. The current state is the baseline.
+    pub fn new(root: &Path, config: &WatchConfig) -> Result<Self> {
+        let source = if config.enable_git_watch && is_git_work_tree(root) {
+            ChangeSource::Git
+        } else {
+            ChangeSource::Mtime
+        };
+        let mut watcher = Self {
+            root: root.to_path_buf(),
+            source,
+            fingerprint: 0,
+        };
+        watcher.fingerprint = watcher.snapshot()?;
+        Ok(watcher)
+    }
+
+    pub fn source(&self) -> ChangeSource {
+        self.source
+    }
+
+    /// Whether the watched tree differs from the previous poll (or from `new`)
+    pub fn poll(&mut self) -> Result<bool> {
+        let fingerprint = self.snapshot()?;
+        let changed = fingerprint != self.fingerprint;
+        self.fingerprint = fingerprint;
+        Ok(changed)
+    }
+
+    fn snapshot(&self) -> Result<u64> {
+        let mut hasher = DefaultHasher::new();
+        match self.source {
+            ChangeSource::Git => {
+                // An unborn branch has no HEAD yet; the status still covers its files
+                git(&self.root, &["rev-parse", "HEAD"]).unwrap_or_default().hash(&mut hasher);
+                let status = git(&self.root, &["status", "--porcelain", "--untracked-files=all"])?;
+                for line in status.lines() {
+                    line.hash(&mut hasher);
+                    // Editing an already-modified file leaves its status line unchanged
+                    let path = line.get(3..).unwrap_or("").rsplit(" -> ").next().unwrap_or("");
+                    file_stamp(&self.root.join(path.trim_matches('"'))).hash(&mut hasher);
+                }
+            }
+            ChangeSource::Mtime => {
+                let entries = WalkDir::new(&self.root)
+                    .sort_by_file_name()
+                    .into_iter()
+                    .filter_entry(|e| e.file_name() != ".git")
+                    .filter_map(|e| e.ok())
+                    .filter(|e| e.file_type().is_file());
+                for entry in entries {
+                    entry.path().hash(&mut hasher);
+                    file_stamp(entry.path()).hash(&mut hasher);
+                }
+            }
+        }
+        Ok(hasher.finish())
+    }
+}
+
+/// Filesystem events under a root, coalesced into batches of changed paths.
+/// Both ends of a rename are reported, so an editor's write-temp-then-rename save
+/// yields the vanished temp path and the target; `reconcile_paths` skips the first.
+pub struct NotifyWatcher {
+    // Dropping the watcher stops the events
+    _watcher: RecommendedWatcher,
+    events: mpsc::UnboundedReceiver<Vec<PathBuf>>,
+    debounce: Duration,
+}
+
+impl NotifyWatcher {
+    /// Watch `root` recursively, ignoring `.git` and anything under `exclude`
+    /// (typically the index directory, which the watch loop itself writes to)
+    pub fn new(root: &Path, debounce: Duration, exclude: &[PathBuf]) -> Result<Self> {
+        let (sender, events) = mpsc::unbounded_channel();
+        let exclude = exclude.to_vec();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| match event {
+            Ok(event) if !matches!(event.kind, EventKind::Access(_)) => {
+                let paths: Vec<PathBuf> = event
+                    .paths
+                    .into_iter()
+                    .filter(|p| !p.components().any(|c| c == Component::Normal(".git".as_ref())))
+                    .filter(|p| !exclude.iter().any(|excluded| p.starts_with(excluded)))
+                    .collect();
+                if !paths.is_empty() {
+                    let _ = sender.send(paths);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => log::warn!("File watch error: {}", e),
+        })?;
+        watcher.watch(root, RecursiveMode::Recursive)?;
+
+        Ok(Self {
+            _watcher: watcher,
+            events,
+            debounce,
+        })
+    }
+
+    /// Wait for the next change, then keep collecting until no event has arrived
+    /// for the debounce period. Paths are deduplicated and sorted; `None` means
+    /// the watcher has shut down.
+    pub async fn next_batch(&mut self) -> Option<Vec<PathBuf>> {
+        let mut batch: BTreeSet<PathBuf> = self.events.recv().await?.into_iter().collect();
+        while let Ok(Some(paths)) = tokio::time::timeout(self.debounce, self.events.recv()).await {
+            batch.extend(paths);
+        }
+        Some(batch.into_iter().collect())
+    }
+}
+
+fn is_git_work_tree(root: &Path) -> bool {
+    git(root, &["rev-parse", "--is-inside-work-tree"]).is_ok_and(|out| out.trim() == "true")
+}
+
+fn git(root: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git").arg("-C").arg(root).args(args).output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Size and modification time, or `None` for a path that no longer exists
+fn file_stamp(path: &Path) -> Option<(u64, std::time::SystemTime)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.len(), metadata.modified().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_mtime_watcher_sees_new_and_changed_files() -> Result<()> {
+        let dir = tempdir()?;
+        std::fs::write(dir.path().join("lib.rs"), "fn a() {}")?;
+        let mut watcher = Watcher::new(dir.path(), &WatchConfig { enable_git_watch: false, ..Default::default() })?;
+        assert_eq!(watcher.source(), ChangeSource::Mtime);
+        assert!(!watcher.poll()?);
+
+        std::fs::write(dir.path().join("lib.rs"), "fn a() { 1 }")?;
+        assert!(watcher.poll()?);
+        assert!(!watcher.poll()?);
+
+        std::fs::write(dir.path().join("new.rs"), "fn b() {}")?;
+        assert!(watcher.poll()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_git_watcher_sees_edits_to_dirty_files() -> Result<()> {
+        let dir = tempdir()?;
+        if git(dir.path(), &["init", "-q"]).is_err() {
+            return Ok(()); // git not installed
+        }
+        std::fs::write(dir.path().join("lib.rs"), "fn a() {}")?;
+        let mut watcher = Watcher::new(dir.path(), &WatchConfig::default())?;
+        assert_eq!(watcher.source(), ChangeSource::Git);
+        assert!(!watcher.poll()?);
+
+        // Still "?? lib.rs" in git status, only the contents differ
+        std::fs::write(dir.path().join("lib.rs"), "fn a() { 1 }")?;
+        assert!(watcher.poll()?);
+        assert!(!watcher.poll()?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_notify_watcher_coalesces_atomic_save() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path().canonicalize()?;
+        let index_dir = root.join(".embed_db");
+        std::fs::create_dir(&index_dir)?;
+        let mut watcher = NotifyWatcher::new(&root, Duration::from_millis(200), std::slice::from_ref(&index_dir))?;
+
+        let temp = root.join(".lib.rs.tmp");
+        let target = root.join("lib.rs");
+        std::fs::write(&temp, "fn a() {}")?;
+        std::fs::rename(&temp, &target)?;
+        std::fs::write(index_dir.join("bm25_index.json"), "{}")?;
+
+        let batch = tokio::time::timeout(Duration::from_secs(5), watcher.next_batch())
+            .await?
+            .expect("watcher closed");
+        assert!(batch.contains(&target), "{:?}", batch);
+        assert!(batch.iter().all(|p| !p.starts_with(&index_dir)));
+        assert!(tokio::time::timeout(Duration::from_millis(300), watcher.next_batch()).await.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_falls_back_to_mtime_outside_git() -> Result<()> {
+        let dir = tempdir()?;
+        let watcher = Watcher::new(dir.path(), &WatchConfig::default())?;
+        // The temp dir may itself sit inside a work tree on some machines
+        if !is_git_work_tree(dir.path()) {
+            assert_eq!(watcher.source(), ChangeSource::Mtime);
+        }
+        Ok(())
+    }
+}
@@ -563,6 +563,7 @@ fn test_end_to_end_functionality() -> Result<()> {
         normalize: true,
         threads: 2,
         gpu_layers: 0,
+        ..Default::default()
     };
     
     let embedder = GGUFEmbedder::new(config)?;
@@ -570,6 +570,7 @@ mod concurrent_access_edge_cases {
             max_file_size: 10_000_000,
             supported_extensions: vec!["md".to_string()],
             enable_incremental: true,
+            ..Default::default()
         };
         let mut indexer = IncrementalIndexer::new(config).expect("Failed to create indexer");
         
@@ -680,6 +681,7 @@ mod filesystem_edge_cases {
             max_file_size: 10_000_000,
             supported_extensions: vec!["md".to_string()],
             enable_incremental: true,
+            ..Default::default()
         };
         let mut indexer = IncrementalIndexer::new(config).expect("Failed to create indexer");
         let mut storage = VectorStorage::new("test.db").expect("Failed to create storage");
@@ -1024,6 +1026,7 @@ fn create_test_embedder_config() -> GGUFEmbedderConfig {
         cache_size: 100,
         normalize: true,
         threads: 2,
+        ..Default::default()
     }
 }
 
@@ -122,7 +122,7 @@ async fn verify_core_technologies_work() {
             let paths = vec!["main.rs".to_string(), "user.rs".to_string(), "engine.rs".to_string()];
             
             match search.index(contents, paths).await {
-                Ok(()) => {
+                Ok(_) => {
                     println!("   ✅ Successfully indexed documents");
                     
                     match search.search("main function", 5).await {
@@ -28,6 +28,7 @@ async fn test_gguf_embedder_integration_with_indexer() -> Result<()> {
         max_file_size: 10000,
         supported_extensions: vec!["rs".to_string(), "py".to_string(), "md".to_string()],
         enable_incremental: true,
+        ..Default::default()
     };
     
     let mut indexer = IncrementalIndexer::new(config)?;